@@ -0,0 +1,52 @@
+-- Baseline schema for the H1B customer table.
+--
+-- Objects are created unqualified: the migrator sets `search_path` to the
+-- application schema before running, so the same files work for any schema.
+-- Everything is guarded so this is a no-op against databases that were
+-- provisioned by hand before migrations existed.
+
+DO $$ BEGIN
+    CREATE TYPE sex_enum AS ENUM ('MALE', 'FEMALE', 'OTHER');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE marital_status_enum AS ENUM ('SINGLE', 'MARRIED', 'DIVORCED', 'WIDOWED');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE h1b_status_enum AS ENUM ('Active', 'Inactive');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS h1bcustomer (
+    customer_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    email TEXT NOT NULL,
+    login_email TEXT NOT NULL,
+    first_name TEXT NOT NULL,
+    last_name TEXT NOT NULL,
+    dob DATE NOT NULL,
+    sex sex_enum NOT NULL,
+    marital_status marital_status_enum NOT NULL,
+    phone TEXT NOT NULL,
+    emergency_contact_name TEXT NOT NULL,
+    emergency_contact_phone TEXT NOT NULL,
+    employment_start_date DATE NOT NULL,
+    street_name TEXT NOT NULL,
+    city TEXT NOT NULL,
+    state TEXT NOT NULL,
+    zip TEXT NOT NULL,
+    client_name TEXT NOT NULL,
+    client_street_name TEXT NOT NULL,
+    client_city TEXT NOT NULL,
+    client_state TEXT NOT NULL,
+    client_zip TEXT NOT NULL,
+    lca_title TEXT NOT NULL,
+    lca_salary NUMERIC(12, 2) NOT NULL,
+    lca_code TEXT NOT NULL,
+    receipt_number TEXT NOT NULL,
+    h1b_start_date DATE NOT NULL,
+    h1b_end_date DATE NOT NULL,
+    h1b_status h1b_status_enum NOT NULL DEFAULT 'Active'
+);
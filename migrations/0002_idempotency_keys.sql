@@ -0,0 +1,18 @@
+-- Responses to mutating requests sent with an `Idempotency-Key` header, so a
+-- client retrying after a timeout gets the original response back instead of
+-- repeating the write. A row with a NULL status_code is a request still in flight.
+
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    principal TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    status_code SMALLINT,
+    content_type TEXT,
+    location TEXT,
+    response_body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (principal, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_expires_at_idx ON idempotency_keys (expires_at);
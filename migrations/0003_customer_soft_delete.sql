@@ -0,0 +1,16 @@
+-- Record lifecycle is tracked separately from the visa's h1b_status.
+-- A customer is soft-deleted when deleted_at is set; reads exclude such rows.
+
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS deleted_by TEXT;
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS delete_reason TEXT;
+
+-- Until now soft delete was recorded as h1b_status = 'Inactive', so existing
+-- inactive rows are treated as deleted. h1b_status is kept as it was.
+UPDATE h1bcustomer
+SET deleted_at = now(),
+    deleted_by = 'migration',
+    delete_reason = 'Backfilled: was h1b_status = Inactive before soft delete had its own columns'
+WHERE h1b_status = 'Inactive' AND deleted_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS h1bcustomer_not_deleted_idx ON h1bcustomer (customer_id) WHERE deleted_at IS NULL;
@@ -0,0 +1,37 @@
+-- h1b_status becomes a lifecycle (see src/lifecycle.rs) instead of an
+-- Active/Inactive toggle, and every change after creation is recorded with a
+-- reason and effective date.
+--
+-- Enum values can't be removed in place, so the type is rebuilt and the column
+-- converted. Inactive only ever meant "soft deleted", which 0003 moved to its
+-- own columns, and said nothing about the petition; those rows become Active
+-- like every other customer. No transition is recorded for them since nothing
+-- happened to their status. New customers start as Draft.
+
+ALTER TYPE h1b_status_enum RENAME TO h1b_status_enum_legacy;
+
+CREATE TYPE h1b_status_enum AS ENUM (
+    'Draft', 'Filed', 'RFE', 'Approved', 'Active',
+    'Transferred', 'Expired', 'Terminated', 'Denied', 'Withdrawn'
+);
+
+ALTER TABLE h1bcustomer ALTER COLUMN h1b_status DROP DEFAULT;
+ALTER TABLE h1bcustomer ALTER COLUMN h1b_status TYPE h1b_status_enum
+    USING (CASE h1b_status::text WHEN 'Inactive' THEN 'Active' ELSE h1b_status::text END)::h1b_status_enum;
+ALTER TABLE h1bcustomer ALTER COLUMN h1b_status SET DEFAULT 'Draft';
+
+DROP TYPE h1b_status_enum_legacy;
+
+CREATE TABLE IF NOT EXISTS h1b_status_transitions (
+    transition_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_id UUID NOT NULL REFERENCES h1bcustomer (customer_id) ON DELETE CASCADE,
+    from_status h1b_status_enum NOT NULL,
+    to_status h1b_status_enum NOT NULL,
+    reason TEXT NOT NULL CHECK (btrim(reason) <> ''),
+    effective_date DATE NOT NULL,
+    transitioned_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS h1b_status_transitions_customer_idx
+    ON h1b_status_transitions (customer_id, created_at);
@@ -0,0 +1,35 @@
+-- Receipt numbers are stored normalized (uppercase, no spaces or dashes; see
+-- src/receipt.rs) and no two live customers may share one. The format itself
+-- is checked by the API, so legacy rows that don't match it stay editable.
+
+UPDATE h1bcustomer
+SET receipt_number = upper(regexp_replace(receipt_number, '[-[:space:]‐‑‒–—−]', '', 'g'))
+WHERE receipt_number <> upper(regexp_replace(receipt_number, '[-[:space:]‐‑‒–—−]', '', 'g'));
+
+-- A Draft customer has no receipt number until the petition is filed; moving
+-- to Filed requires one. Blank legacy values mean the same thing.
+ALTER TABLE h1bcustomer ALTER COLUMN receipt_number DROP NOT NULL;
+UPDATE h1bcustomer SET receipt_number = NULL WHERE receipt_number = '';
+
+-- Live customers already sharing a receipt number would block the index.
+-- Nothing records which row came first, so keep the one with the latest
+-- h1b_end_date (then the lowest customer_id) and soft delete the others with a
+-- reason naming the one kept; merge them into it later with
+-- POST /customers/{id}/merge to move their history over.
+WITH ranked AS (
+    SELECT customer_id,
+           first_value(customer_id) OVER w AS kept,
+           row_number() OVER w AS rank
+    FROM h1bcustomer
+    WHERE deleted_at IS NULL AND receipt_number IS NOT NULL
+    WINDOW w AS (PARTITION BY receipt_number ORDER BY h1b_end_date DESC, customer_id)
+)
+UPDATE h1bcustomer c
+SET deleted_at = now(),
+    deleted_by = 'migration',
+    delete_reason = 'Duplicate receipt number of customer ' || r.kept || '; merge it with POST /customers/' || r.kept || '/merge'
+FROM ranked r
+WHERE c.customer_id = r.customer_id AND r.rank > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS h1bcustomer_receipt_number_key
+    ON h1bcustomer (receipt_number) WHERE deleted_at IS NULL;
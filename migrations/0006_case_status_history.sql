@@ -0,0 +1,20 @@
+-- USCIS case status timeline per receipt number, filled by the case status
+-- tracker. A row is added only when the status text changes; last_checked_at
+-- records the most recent poll that still saw it.
+
+CREATE TABLE IF NOT EXISTS case_status_history (
+    entry_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    receipt_number TEXT NOT NULL,
+    customer_id UUID REFERENCES h1bcustomer (customer_id) ON DELETE SET NULL,
+    status TEXT NOT NULL,
+    description TEXT,
+    previous_status TEXT,
+    provider TEXT NOT NULL,
+    observed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_checked_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS case_status_history_receipt_idx
+    ON case_status_history (receipt_number, observed_at);
+CREATE INDEX IF NOT EXISTS case_status_history_changes_idx
+    ON case_status_history (observed_at) WHERE previous_status IS NOT NULL;
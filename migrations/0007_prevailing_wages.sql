@@ -0,0 +1,47 @@
+-- OFLC prevailing wage data for the LCA wage check, replaced wholesale on
+-- every import (see src/prevailing_wage). Wages are hourly, as OFLC publishes
+-- them; a level OFLC leaves blank is NULL.
+
+DO $$ BEGIN
+    CREATE TYPE wage_level_enum AS ENUM ('I', 'II', 'III', 'IV');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- The wage level the employer declared on the LCA. NULL is checked as Level I.
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS lca_wage_level wage_level_enum;
+
+-- ALC_Export.csv: one row per OFLC area and SOC code.
+CREATE TABLE IF NOT EXISTS prevailing_wages (
+    area TEXT NOT NULL,
+    soc_code TEXT NOT NULL,
+    geo_level TEXT,
+    level_1 NUMERIC(10, 2),
+    level_2 NUMERIC(10, 2),
+    level_3 NUMERIC(10, 2),
+    level_4 NUMERIC(10, 2),
+    average NUMERIC(10, 2),
+    PRIMARY KEY (area, soc_code)
+);
+
+-- Geography.csv, one row per area (OFLC lists each county of an area separately).
+CREATE TABLE IF NOT EXISTS wage_areas (
+    area TEXT PRIMARY KEY,
+    area_name TEXT NOT NULL,
+    state TEXT
+);
+
+-- ZIP code to OFLC area, from a crosswalk such as HUD's ZIP-CBSA file.
+CREATE TABLE IF NOT EXISTS wage_area_zips (
+    zip TEXT PRIMARY KEY,
+    area TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS prevailing_wage_imports (
+    import_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source TEXT NOT NULL,
+    wages INTEGER NOT NULL,
+    areas INTEGER NOT NULL,
+    zips INTEGER NOT NULL,
+    imported_by TEXT NOT NULL,
+    imported_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
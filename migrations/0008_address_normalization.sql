@@ -0,0 +1,7 @@
+-- County and metropolitan area (CBSA code) derived from the home and worksite
+-- ZIP codes (see src/address). NULL when the ZIP isn't in the ZIP dataset, and
+-- for customers saved before addresses were normalized.
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS county TEXT;
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS msa TEXT;
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS client_county TEXT;
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS client_msa TEXT;
@@ -0,0 +1,5 @@
+-- phone and emergency_contact_phone hold E.164 numbers (see src/phone.rs);
+-- these hold the same numbers formatted for display. NULL until a stored number
+-- has been normalized.
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS phone_display TEXT;
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS emergency_contact_phone_display TEXT;
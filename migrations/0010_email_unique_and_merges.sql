@@ -0,0 +1,51 @@
+-- No two live customers may share an email or a login_email, ignoring case.
+-- Emails keep the case they were entered in; lookups compare lower().
+
+UPDATE h1bcustomer SET email = btrim(email) WHERE email <> btrim(email);
+UPDATE h1bcustomer SET login_email = btrim(login_email) WHERE login_email <> btrim(login_email);
+
+-- As with receipt numbers (see 0005), soft delete all but one of the live
+-- customers sharing an address, keeping the one with the latest h1b_end_date,
+-- so they can be merged into it later.
+WITH ranked AS (
+    SELECT customer_id,
+           first_value(customer_id) OVER w AS kept,
+           row_number() OVER w AS rank
+    FROM h1bcustomer
+    WHERE deleted_at IS NULL
+    WINDOW w AS (PARTITION BY lower(email) ORDER BY h1b_end_date DESC, customer_id)
+)
+UPDATE h1bcustomer c
+SET deleted_at = now(),
+    deleted_by = 'migration',
+    delete_reason = 'Duplicate email of customer ' || r.kept || '; merge it with POST /customers/' || r.kept || '/merge'
+FROM ranked r
+WHERE c.customer_id = r.customer_id AND r.rank > 1;
+
+WITH ranked AS (
+    SELECT customer_id,
+           first_value(customer_id) OVER w AS kept,
+           row_number() OVER w AS rank
+    FROM h1bcustomer
+    WHERE deleted_at IS NULL
+    WINDOW w AS (PARTITION BY lower(login_email) ORDER BY h1b_end_date DESC, customer_id)
+)
+UPDATE h1bcustomer c
+SET deleted_at = now(),
+    deleted_by = 'migration',
+    delete_reason = 'Duplicate login_email of customer ' || r.kept || '; merge it with POST /customers/' || r.kept || '/merge'
+FROM ranked r
+WHERE c.customer_id = r.customer_id AND r.rank > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS h1bcustomer_email_key
+    ON h1bcustomer (lower(email)) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS h1bcustomer_login_email_key
+    ON h1bcustomer (lower(login_email)) WHERE deleted_at IS NULL;
+
+-- Set on a customer merged into another; it stays soft-deleted and its
+-- history now belongs to the customer it points at.
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS merged_into UUID REFERENCES h1bcustomer (customer_id);
+
+-- Set on status transitions moved over by a merge: the customer they were
+-- recorded on, since they don't continue the kept customer's own chain.
+ALTER TABLE h1b_status_transitions ADD COLUMN IF NOT EXISTS merged_from UUID REFERENCES h1bcustomer (customer_id);
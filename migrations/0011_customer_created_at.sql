@@ -0,0 +1,5 @@
+-- When each customer was created, for the dashboard's new customers per month.
+-- Nothing recorded this before, so existing customers keep NULL rather than
+-- a made-up date; the dashboard counts them separately.
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ;
+ALTER TABLE h1bcustomer ALTER COLUMN created_at SET DEFAULT now();
@@ -0,0 +1,38 @@
+-- Inputs to the six-year maximum stay calculator (src/max_stay.rs): earlier
+-- H-1B petitions, trips outside the US, and the green card steps that make a
+-- customer eligible for AC21 extensions beyond six years.
+
+-- Approved petitions other than the one on the customer record, e.g. with a
+-- previous employer. Receipt numbers are normalized like the customer's; the
+-- API keeps them unique across live customers and their petitions.
+CREATE TABLE IF NOT EXISTS h1b_petitions (
+    petition_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_id UUID NOT NULL REFERENCES h1bcustomer (customer_id) ON DELETE CASCADE,
+    receipt_number TEXT,
+    employer TEXT,
+    valid_from DATE NOT NULL,
+    valid_to DATE NOT NULL CHECK (valid_to >= valid_from),
+    recorded_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS h1b_petitions_customer_idx ON h1b_petitions (customer_id, valid_from);
+CREATE INDEX IF NOT EXISTS h1b_petitions_receipt_idx ON h1b_petitions (receipt_number);
+
+-- Time outside the US. returned_on is NULL while the customer is still abroad.
+CREATE TABLE IF NOT EXISTS h1b_trips (
+    trip_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_id UUID NOT NULL REFERENCES h1bcustomer (customer_id) ON DELETE CASCADE,
+    departed_on DATE NOT NULL,
+    returned_on DATE CHECK (returned_on >= departed_on),
+    recorded_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS h1b_trips_customer_idx ON h1b_trips (customer_id, departed_on);
+
+-- AC21 §106(a): a PERM labor certification or I-140 filed at least 365 days
+-- earlier allows one-year extensions. §104(c): an approved I-140 allows
+-- three-year extensions.
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS labor_certification_filed_on DATE;
+ALTER TABLE h1bcustomer ADD COLUMN IF NOT EXISTS i140_approved_on DATE;
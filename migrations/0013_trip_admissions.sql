@@ -0,0 +1,5 @@
+-- What CBP recorded when the customer came back from a trip, for I-94
+-- reconciliation. All NULL while the customer is still abroad.
+ALTER TABLE h1b_trips ADD COLUMN IF NOT EXISTS port_of_entry TEXT;
+ALTER TABLE h1b_trips ADD COLUMN IF NOT EXISTS admission_class TEXT;
+ALTER TABLE h1b_trips ADD COLUMN IF NOT EXISTS i94_number TEXT;
@@ -0,0 +1,16 @@
+-- Follow-ups raised for a customer, e.g. a new LCA after the worksite moved
+-- to another MSA (src/worksite.rs). Open until someone marks them done.
+CREATE TABLE IF NOT EXISTS compliance_tasks (
+    task_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_id UUID NOT NULL REFERENCES h1bcustomer (customer_id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    due_on DATE NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ,
+    completed_by TEXT
+);
+
+CREATE INDEX IF NOT EXISTS compliance_tasks_customer_idx ON compliance_tasks (customer_id);
+CREATE INDEX IF NOT EXISTS compliance_tasks_open_idx ON compliance_tasks (due_on) WHERE completed_at IS NULL;
@@ -0,0 +1,36 @@
+-- Documents uploaded for a customer's LCA public access file, and a record
+-- of each time the file was generated (src/public_access.rs). The PDF itself
+-- is generated on request and not kept.
+CREATE TABLE IF NOT EXISTS lca_documents (
+    document_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_id UUID NOT NULL REFERENCES h1bcustomer (customer_id) ON DELETE CASCADE,
+    category TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    page_count INTEGER NOT NULL,
+    content BYTEA NOT NULL,
+    uploaded_by TEXT NOT NULL,
+    uploaded_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS lca_documents_customer_idx ON lca_documents (customer_id);
+
+CREATE TABLE IF NOT EXISTS public_access_files (
+    file_id UUID PRIMARY KEY,
+    customer_id UUID NOT NULL REFERENCES h1bcustomer (customer_id) ON DELETE CASCADE,
+    lca_case_number TEXT,
+    lca_code TEXT NOT NULL,
+    lca_title TEXT NOT NULL,
+    lca_salary NUMERIC(12, 2) NOT NULL,
+    lca_wage_level TEXT,
+    worksite TEXT NOT NULL,
+    document_ids UUID[] NOT NULL,
+    missing TEXT[] NOT NULL,
+    page_count INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    generated_by TEXT NOT NULL,
+    generated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS public_access_files_customer_idx ON public_access_files (customer_id, generated_at);
@@ -0,0 +1,5 @@
+-- The startup pass in src/main.rs only reads customers whose phone numbers
+-- haven't been normalized yet; once they all have, this index is empty and
+-- the pass costs one index lookup instead of a scan of every customer.
+CREATE INDEX IF NOT EXISTS h1bcustomer_phones_not_normalized_idx ON h1bcustomer (customer_id)
+    WHERE phone_display IS NULL OR emergency_contact_phone_display IS NULL;
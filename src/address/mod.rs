@@ -0,0 +1,451 @@
+//! US postal address normalization for the home (`street_name`, `city`,
+//! `state`, `zip`) and worksite (`client_*`) addresses.
+//!
+//! States become USPS codes and ZIP codes `12345` or `12345-6789`. The ZIP
+//! dataset gives the city a ZIP belongs to, its county and its metropolitan
+//! area (CBSA). The embedded dataset (`zip_codes.csv`) is a sample of a few
+//! hundred ZIP codes in the metro areas most customers live and work in, not
+//! a substitute for a complete one (`address.zip_file`).
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use utoipa::ToSchema;
+
+const EMBEDDED_DATASET: &str = include_str!("zip_codes.csv");
+
+/// USPS codes and names of the states, DC, territories and military "states".
+const STATES: &[(&str, &str)] = &[
+    ("AL", "Alabama"), ("AK", "Alaska"), ("AZ", "Arizona"), ("AR", "Arkansas"), ("CA", "California"),
+    ("CO", "Colorado"), ("CT", "Connecticut"), ("DE", "Delaware"), ("DC", "District of Columbia"),
+    ("FL", "Florida"), ("GA", "Georgia"), ("HI", "Hawaii"), ("ID", "Idaho"), ("IL", "Illinois"),
+    ("IN", "Indiana"), ("IA", "Iowa"), ("KS", "Kansas"), ("KY", "Kentucky"), ("LA", "Louisiana"),
+    ("ME", "Maine"), ("MD", "Maryland"), ("MA", "Massachusetts"), ("MI", "Michigan"), ("MN", "Minnesota"),
+    ("MS", "Mississippi"), ("MO", "Missouri"), ("MT", "Montana"), ("NE", "Nebraska"), ("NV", "Nevada"),
+    ("NH", "New Hampshire"), ("NJ", "New Jersey"), ("NM", "New Mexico"), ("NY", "New York"),
+    ("NC", "North Carolina"), ("ND", "North Dakota"), ("OH", "Ohio"), ("OK", "Oklahoma"), ("OR", "Oregon"),
+    ("PA", "Pennsylvania"), ("RI", "Rhode Island"), ("SC", "South Carolina"), ("SD", "South Dakota"),
+    ("TN", "Tennessee"), ("TX", "Texas"), ("UT", "Utah"), ("VT", "Vermont"), ("VA", "Virginia"),
+    ("WA", "Washington"), ("WV", "West Virginia"), ("WI", "Wisconsin"), ("WY", "Wyoming"),
+    ("AS", "American Samoa"), ("GU", "Guam"), ("MP", "Northern Mariana Islands"), ("PR", "Puerto Rico"),
+    ("VI", "Virgin Islands"), ("AA", "Armed Forces Americas"), ("AE", "Armed Forces Europe"),
+    ("AP", "Armed Forces Pacific"),
+];
+
+/// USPS street suffix and unit abbreviations (Publication 28).
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("avenue", "Ave"), ("ave", "Ave"), ("boulevard", "Blvd"), ("blvd", "Blvd"), ("circle", "Cir"), ("cir", "Cir"),
+    ("court", "Ct"), ("ct", "Ct"), ("drive", "Dr"), ("dr", "Dr"), ("expressway", "Expy"), ("expy", "Expy"),
+    ("highway", "Hwy"), ("hwy", "Hwy"), ("lane", "Ln"), ("ln", "Ln"), ("parkway", "Pkwy"), ("pkwy", "Pkwy"),
+    ("place", "Pl"), ("pl", "Pl"), ("plaza", "Plz"), ("plz", "Plz"), ("road", "Rd"), ("rd", "Rd"),
+    ("square", "Sq"), ("sq", "Sq"), ("street", "St"), ("st", "St"), ("terrace", "Ter"), ("ter", "Ter"),
+    ("trail", "Trl"), ("trl", "Trl"),
+];
+
+/// Words starting the unit part of a street line (`Suite 200`); the street
+/// suffix is the word before it.
+const UNIT_DESIGNATORS: &[(&str, &str)] = &[
+    ("apartment", "Apt"), ("apt", "Apt"), ("suite", "Ste"), ("ste", "Ste"), ("unit", "Unit"),
+    ("floor", "Fl"), ("fl", "Fl"), ("room", "Rm"), ("rm", "Rm"), ("#", "#"),
+];
+
+/// One ZIP code of the dataset.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ZipCode {
+    pub zip: String,
+    /// The USPS preferred city name.
+    pub city: String,
+    pub state: String,
+    pub county: String,
+    /// CBSA code of the metropolitan area, `None` outside one.
+    pub msa: Option<String>,
+    pub msa_name: Option<String>,
+    /// Other names accepted for the city, such as `NYC`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl ZipCode {
+    /// Whether `city` names this ZIP's city, ignoring case, punctuation and
+    /// `St`/`Saint` style abbreviations.
+    pub fn has_city(&self, city: &str) -> bool {
+        let key = city_key(city);
+        city_key(&self.city) == key || self.aliases.iter().any(|alias| city_key(alias) == key)
+    }
+}
+
+#[derive(Debug)]
+pub struct DatasetError(String);
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid ZIP dataset: {}", self.0)
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+/// ZIP codes by five-digit ZIP.
+#[derive(Debug, Clone)]
+pub struct ZipDirectory {
+    zips: BTreeMap<String, ZipCode>,
+    /// Where the dataset came from, for logs.
+    pub source: String,
+}
+
+impl ZipDirectory {
+    pub fn embedded() -> Self {
+        Self::from_csv(EMBEDDED_DATASET.as_bytes(), "embedded")
+            .expect("the embedded ZIP dataset is valid")
+    }
+
+    /// Reads a CSV with `zip`, `city`, `state` and `county` columns, and
+    /// optionally `msa`, `msa_name` and `aliases` (separated by `|`).
+    pub fn from_file(path: &str) -> Result<Self, DatasetError> {
+        let file = std::fs::File::open(path).map_err(|e| DatasetError(format!("{}: {}", path, e)))?;
+        Self::from_csv(file, path)
+    }
+
+    pub(crate) fn from_csv(reader: impl std::io::Read, source: &str) -> Result<Self, DatasetError> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| DatasetError(e.to_string()))?
+            .iter()
+            .map(|h| h.trim_start_matches('\u{feff}').trim().to_ascii_lowercase())
+            .collect();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let required = |name: &str| column(name).ok_or_else(|| DatasetError(format!("missing column `{}`", name)));
+        let (zip, city, state, county) = (required("zip")?, required("city")?, required("state")?, required("county")?);
+        let (msa, msa_name, aliases) = (column("msa"), column("msa_name"), column("aliases"));
+
+        let mut zips = BTreeMap::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| DatasetError(e.to_string()))?;
+            let field = |i: usize| record.get(i).unwrap_or_default().trim().to_string();
+            let optional = |i: Option<usize>| i.map(field).filter(|v| !v.is_empty());
+            // Spreadsheets drop leading zeros, so `7102` is New Jersey's `07102`.
+            let code = format!("{:0>5}", field(zip));
+            let Some(code) = zip5(&code) else {
+                return Err(DatasetError(format!("`{}` is not a ZIP code", field(zip))));
+            };
+            zips.insert(code.clone(), ZipCode {
+                zip: code,
+                city: field(city),
+                state: normalize_state(&field(state)).map_err(DatasetError)?,
+                county: field(county),
+                msa: optional(msa),
+                msa_name: optional(msa_name),
+                aliases: optional(aliases)
+                    .map(|a| a.split('|').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                    .unwrap_or_default(),
+            });
+        }
+        if zips.is_empty() {
+            return Err(DatasetError("no ZIP codes".to_string()));
+        }
+        Ok(Self { zips, source: source.to_string() })
+    }
+
+    /// Whether this is the embedded sample rather than a loaded dataset.
+    pub fn is_embedded(&self) -> bool {
+        self.source == "embedded"
+    }
+
+    pub fn len(&self) -> usize {
+        self.zips.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zips.is_empty()
+    }
+
+    /// Looks up a ZIP or ZIP+4.
+    pub fn get(&self, zip: &str) -> Option<&ZipCode> {
+        zip5(zip).and_then(|zip| self.zips.get(&zip))
+    }
+
+    /// Normalizes `address` in place and checks the city and state against the
+    /// ZIP. Malformed states and ZIP codes are errors; a city or state that
+    /// doesn't go with a known ZIP is returned as a mismatch.
+    pub fn normalize(&self, address: &mut Address) -> Result<NormalizedAddress, AddressError> {
+        address.state = normalize_state(&address.state).map_err(|e| AddressError { field: "state", message: e })?;
+        address.zip = normalize_zip(&address.zip).map_err(|e| AddressError { field: "zip", message: e })?;
+        address.street_name = normalize_street(&address.street_name);
+        address.city = normalize_city(&address.city);
+
+        let mut result = NormalizedAddress::default();
+        let Some(zip) = self.get(&address.zip) else {
+            return Ok(result);
+        };
+        if zip.has_city(&address.city) {
+            address.city = zip.city.clone();
+        } else if !address.city.is_empty() {
+            result.mismatches.push(AddressError {
+                field: "city",
+                message: format!("\"{}\" is not the city of ZIP {} ({}, {})", address.city, zip.zip, zip.city, zip.state),
+            });
+        }
+        if zip.state != address.state {
+            result.mismatches.push(AddressError {
+                field: "state",
+                message: format!("ZIP {} is in {}, not {}", zip.zip, zip.state, address.state),
+            });
+        }
+        result.county = Some(zip.county.clone());
+        result.msa = zip.msa.clone();
+        Ok(result)
+    }
+}
+
+/// The parts of a postal address that get normalized.
+#[derive(Debug, Clone, Default)]
+pub struct Address {
+    pub street_name: String,
+    pub city: String,
+    pub state: String,
+    pub zip: String,
+}
+
+/// What the ZIP dataset says about a normalized address.
+#[derive(Debug, Default)]
+pub struct NormalizedAddress {
+    /// `None` when the ZIP isn't in the dataset.
+    pub county: Option<String>,
+    pub msa: Option<String>,
+    pub mismatches: Vec<AddressError>,
+}
+
+/// A problem with one field of an address (`city`, `state` or `zip`).
+#[derive(Debug)]
+pub struct AddressError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// USPS code for a state given as a code or a name, in any case and with or
+/// without periods (`N.Y.`).
+pub fn normalize_state(raw: &str) -> Result<String, String> {
+    let cleaned: String = raw.trim().chars().filter(|c| *c != '.').collect();
+    let cleaned = collapse_whitespace(&cleaned);
+    STATES
+        .iter()
+        .find(|(code, name)| code.eq_ignore_ascii_case(&cleaned) || name.eq_ignore_ascii_case(&cleaned))
+        .map(|(code, _)| code.to_string())
+        .ok_or_else(|| format!("invalid state \"{}\": expected a USPS state code such as NY", raw.trim()))
+}
+
+/// `12345` or `12345-6789`. The plus-four part may also be separated by a space
+/// or not at all.
+pub fn normalize_zip(raw: &str) -> Result<String, String> {
+    let trimmed = raw.trim();
+    let parts = match trimmed.len() {
+        5 => Some((trimmed, None)),
+        9 if trimmed.is_ascii() => Some((&trimmed[..5], Some(&trimmed[5..]))),
+        10 if matches!(trimmed.as_bytes()[5], b'-' | b' ') && trimmed.is_ascii() => Some((&trimmed[..5], Some(&trimmed[6..]))),
+        _ => None,
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    match parts {
+        Some((zip, None)) if digits(zip) => Ok(zip.to_string()),
+        Some((zip, Some(plus4))) if digits(zip) && digits(plus4) => Ok(format!("{}-{}", zip, plus4)),
+        _ => Err(format!("invalid ZIP code \"{}\": expected 12345 or 12345-6789", trimmed)),
+    }
+}
+
+/// The five-digit part of a ZIP or ZIP+4.
+pub fn zip5(zip: &str) -> Option<String> {
+    let digits: String = zip.trim().chars().take_while(|c| c.is_ascii_digit()).collect();
+    (digits.len() >= 5).then(|| digits[..5].to_string())
+}
+
+/// Collapses whitespace, fixes single-case input and abbreviates the street
+/// suffix and unit designator the way USPS does (`123 main street suite 200`
+/// becomes `123 Main St Ste 200`).
+pub fn normalize_street(raw: &str) -> String {
+    let mut words: Vec<String> = fix_case(&collapse_whitespace(raw.trim().trim_end_matches(',')))
+        .split(' ')
+        .map(str::to_string)
+        .collect();
+    let lookup = |table: &[(&str, &'static str)], word: &str| {
+        let key = word.trim_end_matches(['.', ',']).to_lowercase();
+        table.iter().find(|(name, _)| *name == key).map(|(_, abbreviation)| *abbreviation)
+    };
+
+    // The house number comes first, so the unit can't start before the second word.
+    let unit = words.iter().skip(2).position(|w| lookup(UNIT_DESIGNATORS, w).is_some()).map(|i| i + 2);
+    if let Some(i) = unit {
+        let abbreviation = lookup(UNIT_DESIGNATORS, &words[i]).unwrap_or_default();
+        words[i] = abbreviation.to_string();
+    }
+    let suffix = unit.unwrap_or(words.len()).checked_sub(1).filter(|i| *i > 0);
+    if let Some(i) = suffix {
+        if let Some(abbreviation) = lookup(ABBREVIATIONS, &words[i]) {
+            let comma = if words[i].ends_with(',') { "," } else { "" };
+            words[i] = format!("{}{}", abbreviation, comma);
+        }
+    }
+    words.join(" ")
+}
+
+/// Collapses whitespace, drops a trailing state (`new york, ny`) and fixes the
+/// case of all-lowercase or all-uppercase names.
+pub fn normalize_city(raw: &str) -> String {
+    let mut city = collapse_whitespace(raw.trim());
+    if let Some((name, state)) = city.rsplit_once(',') {
+        if normalize_state(state).is_ok() {
+            city = name.trim().to_string();
+        }
+    }
+    fix_case(city.trim_end_matches(','))
+}
+
+/// Capitalizes each word of text typed all in lowercase or all in uppercase;
+/// mixed case is left as entered.
+fn fix_case(text: &str) -> String {
+    if text.chars().any(char::is_lowercase) && text.chars().any(char::is_uppercase) {
+        return text.to_string();
+    }
+    text.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Lowercase words without punctuation, with `St`, `Ft` and `Mt` spelled out.
+fn city_key(city: &str) -> String {
+    city.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| match w {
+            "st" => "saint",
+            "ft" => "fort",
+            "mt" => "mount",
+            w => w,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(street_name: &str, city: &str, state: &str, zip: &str) -> Address {
+        Address { street_name: street_name.into(), city: city.into(), state: state.into(), zip: zip.into() }
+    }
+
+    #[test]
+    fn states_by_code_or_name() {
+        assert_eq!(normalize_state("ny").unwrap(), "NY");
+        assert_eq!(normalize_state(" N.Y. ").unwrap(), "NY");
+        assert_eq!(normalize_state("new  york").unwrap(), "NY");
+        assert_eq!(normalize_state("District of Columbia").unwrap(), "DC");
+        assert!(normalize_state("XY").is_err());
+    }
+
+    #[test]
+    fn zip_and_zip_plus_four() {
+        assert_eq!(normalize_zip("10001").unwrap(), "10001");
+        assert_eq!(normalize_zip("100011234").unwrap(), "10001-1234");
+        assert_eq!(normalize_zip("10001 1234").unwrap(), "10001-1234");
+        assert_eq!(normalize_zip("10001-1234").unwrap(), "10001-1234");
+        assert!(normalize_zip("1001").is_err());
+        assert!(normalize_zip("10001-12").is_err());
+        assert!(normalize_zip("1000é123").is_err());
+        assert_eq!(zip5("10001-1234").as_deref(), Some("10001"));
+    }
+
+    #[test]
+    fn streets_get_usps_abbreviations() {
+        assert_eq!(normalize_street("123 main street suite 400"), "123 Main St Ste 400");
+        assert_eq!(normalize_street("  1  Market   Avenue, "), "1 Market Ave");
+        assert_eq!(normalize_street("500 Park Place apartment 3"), "500 Park Pl Apt 3");
+        // Mixed case is kept; only the suffix changes.
+        assert_eq!(normalize_street("10 McDonald Road"), "10 McDonald Rd");
+        // The first word is the house number, never the suffix.
+        assert_eq!(normalize_street("Suite"), "Suite");
+    }
+
+    #[test]
+    fn cities_drop_a_trailing_state() {
+        assert_eq!(normalize_city("new york, ny"), "New York");
+        assert_eq!(normalize_city("SAN FRANCISCO"), "San Francisco");
+        assert_eq!(normalize_city("McAllen"), "McAllen");
+    }
+
+    #[test]
+    fn known_zip_fills_county_and_msa() {
+        let zips = ZipDirectory::embedded();
+        assert!(zips.is_embedded());
+        let mut a = address("1 penn plaza", "nyc", "new york", "10001");
+        let result = zips.normalize(&mut a).unwrap();
+        assert_eq!(a.city, "New York");
+        assert_eq!(a.state, "NY");
+        assert_eq!(a.street_name, "1 Penn Plz");
+        assert_eq!(result.county.as_deref(), Some("New York County"));
+        assert_eq!(result.msa.as_deref(), Some("35620"));
+        assert!(result.mismatches.is_empty());
+    }
+
+    #[test]
+    fn mismatched_city_and_state_are_reported_not_rejected() {
+        let zips = ZipDirectory::embedded();
+        let mut a = address("1 Main St", "Boston", "CA", "10001");
+        let result = zips.normalize(&mut a).unwrap();
+        let fields: Vec<_> = result.mismatches.iter().map(|m| m.field).collect();
+        assert_eq!(fields, vec!["city", "state"]);
+        assert_eq!(a.city, "Boston");
+    }
+
+    #[test]
+    fn unknown_zip_is_accepted_without_derived_fields() {
+        let zips = ZipDirectory::embedded();
+        let mut a = address("1 Main St", "Nowhere", "WY", "82001");
+        let result = zips.normalize(&mut a).unwrap();
+        assert!(result.county.is_none() && result.msa.is_none() && result.mismatches.is_empty());
+
+        let mut bad = address("1 Main St", "Nowhere", "WY", "820");
+        assert_eq!(zips.normalize(&mut bad).unwrap_err().field, "zip");
+    }
+
+    #[test]
+    fn csv_restores_leading_zeros_and_reads_aliases() {
+        let csv = "zip,city,state,county,msa,aliases\n7102,Newark,NJ,Essex County,35620,Brick City | \n";
+        let zips = ZipDirectory::from_csv(csv.as_bytes(), "test").unwrap();
+        let newark = zips.get("07102-1234").unwrap();
+        assert_eq!(newark.aliases, vec!["Brick City"]);
+        assert!(newark.has_city("brick city"));
+        assert!(newark.msa_name.is_none());
+        assert!(!zips.is_embedded());
+
+        assert!(ZipDirectory::from_csv("zip,city,state\n".as_bytes(), "test").is_err());
+        assert!(ZipDirectory::from_csv("zip,city,state,county\n".as_bytes(), "test").is_err());
+    }
+
+    #[test]
+    fn city_match_spells_out_saint() {
+        let zip = ZipCode {
+            zip: "63101".into(),
+            city: "Saint Louis".into(),
+            state: "MO".into(),
+            county: "St. Louis city".into(),
+            msa: None,
+            msa_name: None,
+            aliases: Vec::new(),
+        };
+        assert!(zip.has_city("St. Louis"));
+        assert!(zip.has_city("ST LOUIS"));
+        assert!(!zip.has_city("Louisville"));
+    }
+}
@@ -0,0 +1,113 @@
+use axum::{
+    extract::DefaultBodyLimit,
+    http::Method,
+    middleware::from_fn_with_state,
+    routing::{get, post, put, patch},
+    Router,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::config::settings::CorsConfig;
+use crate::handlers::*;
+use crate::middleware::circuit_breaker::fail_fast;
+use crate::middleware::idempotency::{idempotency, IDEMPOTENCY_KEY};
+use crate::openapi;
+use crate::public_access::MAX_UPLOAD_BYTES;
+use crate::state::AppState;
+
+/// Builds the full application router around the given state.
+///
+/// `main` calls this with the production pool; integration tests call it with a
+/// pool pointing at their own database, so several routers can run side by side.
+pub fn build_router(state: AppState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::PATCH, Method::OPTIONS])
+        .allow_origin(allowed_origins(&state.config.cors))
+        .allow_headers(vec![
+            axum::http::header::AUTHORIZATION,
+            axum::http::header::CONTENT_TYPE,
+            IDEMPOTENCY_KEY,
+        ]);
+
+    // Customer routes depend on the store, so they fail fast while the circuit breaker is open.
+    // Mutations among them honour `Idempotency-Key`.
+    let customers = Router::new()
+        .route("/h1b_customer/create", post(create_visa_details))
+        .route("/customers", get(get_all_customers_with_status))
+        .route("/customers/duplicates", get(get_duplicate_customers))
+        .route("/customers/:id", get(get_customer))
+        .route("/customers/by_receipt/:receipt", get(get_customer_by_receipt))
+        .route("/get_customer_by_id/:id", get(get_customer_by_id))
+        .route("/get_customer_by_email/:email", get(get_customer_by_email))
+        .route("/soft_delete_customer_via_id/:id", patch(soft_delete_customer_by_id))
+        .route("/update_customer_by_id/:id", put(update_customer_by_id))
+        .route("/h1b_customer/by_login_email/:login_email", get(get_customer_by_login_email))
+        .route("/h1b_customer/all", get(get_all_customers_no_filter))
+        .route("/h1b_customer/activate/:customer_id", patch(activate_customer_by_id))
+        .route("/customers/:id/restore", patch(restore_customer))
+        .route("/customers/:id/transitions", get(get_customer_transitions).post(transition_customer))
+        .route("/customers/:id/duplicates", get(get_customer_duplicates))
+        .route("/customers/:id/merge", post(merge_customers))
+        .route("/customers/:id/petitions", get(get_customer_petitions).post(add_customer_petition))
+        .route("/customers/:id/trips", get(get_customer_trips).post(add_customer_trip))
+        .route("/customers/:id/trips/import", post(import_customer_trips))
+        .route("/customers/:id/trips/:trip_id", put(update_customer_trip))
+        .route("/customers/:id/compliance_tasks", get(get_customer_compliance_tasks))
+        .route("/compliance/tasks", get(get_open_compliance_tasks))
+        .route("/compliance/tasks/:task_id/complete", post(complete_compliance_task))
+        .route(
+            "/customers/:id/lca_documents",
+            get(get_customer_lca_documents)
+                .post(upload_lca_document)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/customers/:id/lca_documents/:document_id", get(download_lca_document).delete(delete_lca_document))
+        .route("/customers/:id/public_access_file", post(generate_public_access_file))
+        .route("/customers/:id/public_access_files", get(get_customer_public_access_files))
+        .route("/customers/:id/max_stay", get(get_customer_max_stay))
+        .route("/compliance/max_stay", get(get_max_stay_report))
+        .route("/customers/:id/summary.pdf", get(get_customer_summary_pdf))
+        .route("/customers/:id/i129", get(get_customer_i129))
+        .route("/customers/:id/i129.xfdf", get(get_customer_i129_xfdf))
+        .route("/case_status/changes", get(get_case_status_changes))
+        .route("/case_status/events", get(case_status_events))
+        .route("/case_status/runs", post(run_case_status_check))
+        .route("/case_status/:receipt", get(get_case_status))
+        .route("/customers/:id/prevailing_wage", get(get_customer_prevailing_wage))
+        .route("/compliance/prevailing_wage", get(get_prevailing_wage_report))
+        .route("/dashboard", get(get_dashboard))
+        .route("/prevailing_wages/import", post(import_prevailing_wages))
+        .route_layer(from_fn_with_state(state.clone(), idempotency))
+        .route_layer(from_fn_with_state(state.clone(), fail_fast));
+
+    Router::new()
+        // Health and Info Routes
+        .route("/health", get(health_check))
+        .route("/hello", get(test_connection))
+        .route("/livez", get(livez))
+        .route("/readyz", get(readyz))
+
+        // Reference data, served from memory
+        .route("/soc/search", get(search_soc))
+        .route("/soc/:code", get(get_soc_occupation))
+        .route("/zip_codes/:zip", get(get_zip_code))
+
+        // New API structure as per README
+        .merge(customers)
+
+        // API documentation
+        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", openapi::ApiDoc::openapi()))
+        .layer(cors)
+        .with_state(state)
+}
+
+/// `["*"]` allows any origin; otherwise only the listed origins (validated at startup).
+fn allowed_origins(cors: &CorsConfig) -> AllowOrigin {
+    if cors.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(cors.allowed_origins.iter().filter_map(|origin| origin.parse().ok()))
+    }
+}
@@ -0,0 +1,70 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header, request::Parts, StatusCode},
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::state::AppState;
+
+/// Who is making the request, as far as the API can tell.
+///
+/// There is no user authentication yet: a request is from an admin, identified
+/// by a bearer token listed in `auth.admin_tokens`, from a client application
+/// with an API key listed in `auth.client_tokens`, or anonymous.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    /// Recorded in audit columns such as `deleted_by`.
+    pub name: String,
+    pub is_admin: bool,
+    /// Presented a known admin token or client API key.
+    pub is_authenticated: bool,
+}
+
+impl Caller {
+    fn anonymous() -> Self {
+        Self { name: "anonymous".to_string(), is_admin: false, is_authenticated: false }
+    }
+
+    /// Rejects non-admins with 403.
+    pub fn require_admin(&self) -> Result<(), StatusCode> {
+        if self.is_admin { Ok(()) } else { Err(StatusCode::FORBIDDEN) }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Caller
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = AppState::from_ref(state);
+        let Some(token) = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return Ok(Caller::anonymous());
+        };
+
+        // Compare digests so the comparison time doesn't depend on how much of the token matched.
+        let presented = Sha256::digest(token.trim().as_bytes());
+        let find = |tokens: &HashMap<String, String>| {
+            tokens.iter().find(|(_, known)| Sha256::digest(known.as_bytes()) == presented).map(|(name, _)| name.clone())
+        };
+
+        let auth = &state.config.auth;
+        Ok(if let Some(name) = find(&auth.admin_tokens) {
+            Caller { name: format!("admin:{}", name), is_admin: true, is_authenticated: true }
+        } else if let Some(name) = find(&auth.client_tokens) {
+            Caller { name: format!("client:{}", name), is_admin: false, is_authenticated: true }
+        } else {
+            Caller::anonymous()
+        })
+    }
+}
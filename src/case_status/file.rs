@@ -0,0 +1,55 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::{CaseStatus, CaseStatusProvider, ProviderError};
+
+/// Reads statuses from a JSON file, for offline use or when statuses are
+/// collected by hand:
+///
+/// ```json
+/// { "EAC2412345678": { "status": "Case Was Approved", "description": "..." } }
+/// ```
+///
+/// The file is reread on every lookup, so edits are picked up by the next run.
+pub struct FileCaseStatusProvider {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct FileEntry {
+    status: String,
+    description: Option<String>,
+}
+
+impl FileCaseStatusProvider {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl CaseStatusProvider for FileCaseStatusProvider {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn fetch(&self, receipt_number: &str) -> Result<CaseStatus, ProviderError> {
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| ProviderError::Unavailable(format!("cannot read {}: {}", self.path, e)))?;
+        let mut entries: HashMap<String, FileEntry> = serde_json::from_str(&contents)
+            .map_err(|e| ProviderError::Invalid(format!("{}: {}", self.path, e)))?;
+
+        // Keys are matched after the same normalization the customer records get.
+        let entry = entries
+            .drain()
+            .find(|(receipt, _)| crate::receipt::normalize(receipt).is_ok_and(|receipt| receipt == receipt_number))
+            .map(|(_, entry)| entry)
+            .ok_or(ProviderError::NotFound)?;
+        Ok(CaseStatus {
+            status: entry.status,
+            description: entry.description,
+        })
+    }
+}
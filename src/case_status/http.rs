@@ -0,0 +1,138 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::{CaseStatus, CaseStatusProvider, ProviderError};
+
+/// Queries a case status API shaped like the USCIS developer Case Status API:
+/// `GET {base_url}/case-status/{receipt_number}` answering
+/// `{"case_status": {"current_case_status_text_en": ..., "current_case_status_desc_en": ...}}`.
+///
+/// Point `base_url` at a local mock server to test without USCIS credentials.
+pub struct HttpCaseStatusProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CaseStatusResponse {
+    case_status: CaseStatusBody,
+}
+
+#[derive(Deserialize)]
+struct CaseStatusBody {
+    current_case_status_text_en: Option<String>,
+    current_case_status_desc_en: Option<String>,
+}
+
+impl HttpCaseStatusProvider {
+    pub fn new(base_url: &str, api_token: Option<String>, timeout: Duration) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_token,
+        })
+    }
+}
+
+#[async_trait]
+impl CaseStatusProvider for HttpCaseStatusProvider {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    async fn fetch(&self, receipt_number: &str) -> Result<CaseStatus, ProviderError> {
+        let url = format!("{}/case-status/{}", self.base_url, receipt_number);
+        let mut request = self.client.get(&url);
+        if let Some(token) = &self.api_token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.map_err(|e| ProviderError::Unavailable(e.to_string()))?;
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::NOT_FOUND => return Err(ProviderError::NotFound),
+            status if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() => {
+                return Err(ProviderError::Unavailable(format!("HTTP {}", status)));
+            }
+            status => return Err(ProviderError::Invalid(format!("HTTP {}", status))),
+        }
+
+        let body: CaseStatusResponse = response.json().await.map_err(|e| ProviderError::Invalid(e.to_string()))?;
+        let status = body
+            .case_status
+            .current_case_status_text_en
+            .map(|status| status.trim().to_string())
+            .filter(|status| !status.is_empty())
+            .ok_or_else(|| ProviderError::Invalid("missing current_case_status_text_en".to_string()))?;
+        Ok(CaseStatus {
+            status,
+            description: body.case_status.current_case_status_desc_en,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use axum::http::HeaderMap;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    /// Answers like the USCIS API for a few fixed receipt numbers.
+    async fn case_status(Path(receipt): Path<String>, headers: HeaderMap) -> (StatusCode, Json<Value>) {
+        if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some("Bearer test-token") {
+            return (StatusCode::UNAUTHORIZED, Json(json!({})));
+        }
+        match receipt.as_str() {
+            "EAC2412345678" => (StatusCode::OK, Json(json!({ "case_status": {
+                "current_case_status_text_en": " Case Was Approved ",
+                "current_case_status_desc_en": "We approved your Form I-129."
+            }}))),
+            "EAC2412345679" => (StatusCode::OK, Json(json!({ "case_status": { "current_case_status_text_en": "" } }))),
+            "EAC2412345670" => (StatusCode::TOO_MANY_REQUESTS, Json(json!({}))),
+            _ => (StatusCode::NOT_FOUND, Json(json!({}))),
+        }
+    }
+
+    async fn provider(api_token: Option<&str>) -> HttpCaseStatusProvider {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        let app = Router::new().route("/case-status/:receipt", get(case_status));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        HttpCaseStatusProvider::new(&base_url, api_token.map(str::to_string), Duration::from_secs(5)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_the_current_status() {
+        let provider = provider(Some("test-token")).await;
+        let status = provider.fetch("EAC2412345678").await.unwrap();
+        assert_eq!(status.status, "Case Was Approved");
+        assert_eq!(status.description.as_deref(), Some("We approved your Form I-129."));
+    }
+
+    #[tokio::test]
+    async fn maps_http_errors() {
+        let authenticated = provider(Some("test-token")).await;
+        assert!(matches!(authenticated.fetch("EAC2499999999").await, Err(ProviderError::NotFound)));
+        assert!(matches!(authenticated.fetch("EAC2412345670").await, Err(ProviderError::Unavailable(_))));
+        assert!(matches!(authenticated.fetch("EAC2412345679").await, Err(ProviderError::Invalid(_))));
+
+        let unauthenticated = provider(None).await;
+        assert!(matches!(unauthenticated.fetch("EAC2412345678").await, Err(ProviderError::Invalid(m)) if m.contains("401")));
+    }
+
+    #[tokio::test]
+    async fn unreachable_provider_is_unavailable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let provider = HttpCaseStatusProvider::new(&base_url, None, Duration::from_secs(5)).unwrap();
+        assert!(matches!(provider.fetch("EAC2412345678").await, Err(ProviderError::Unavailable(_))));
+    }
+}
@@ -0,0 +1,74 @@
+//! Polling USCIS case status for pending receipts.
+//!
+//! A `CaseStatusProvider` answers "what is the status of this receipt?". The
+//! `CaseStatusTracker` asks it about every customer whose petition is pending,
+//! records changes in the status history and publishes them as events.
+
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::config::settings::{CaseStatusConfig, CaseStatusProviderKind};
+
+pub mod file;
+pub mod http;
+pub mod tracker;
+
+pub use file::FileCaseStatusProvider;
+pub use http::HttpCaseStatusProvider;
+pub use tracker::CaseStatusTracker;
+
+/// A receipt's current status as the provider reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseStatus {
+    /// Status title, e.g. "Case Was Approved".
+    pub status: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ProviderError {
+    /// The provider doesn't know this receipt number.
+    NotFound,
+    /// The provider is down, rate limiting or timing out; try again next run.
+    Unavailable(String),
+    /// The provider answered with something we can't use.
+    Invalid(String),
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::NotFound => write!(f, "receipt number not found"),
+            ProviderError::Unavailable(message) => write!(f, "provider unavailable: {}", message),
+            ProviderError::Invalid(message) => write!(f, "invalid provider response: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+#[async_trait]
+pub trait CaseStatusProvider: Send + Sync {
+    /// Short name recorded with every history entry, e.g. `http`.
+    fn name(&self) -> &str;
+
+    /// Looks up a normalized receipt number.
+    async fn fetch(&self, receipt_number: &str) -> Result<CaseStatus, ProviderError>;
+}
+
+/// Builds the provider selected in the config, or `None` when tracking is off.
+/// Only called after validation, so the required settings are present.
+pub fn provider_from_config(config: &CaseStatusConfig) -> Result<Option<Arc<dyn CaseStatusProvider>>, Box<dyn std::error::Error>> {
+    Ok(match config.provider {
+        CaseStatusProviderKind::None => None,
+        CaseStatusProviderKind::Http => {
+            let base_url = config.base_url.as_deref().expect("case_status.base_url validated at startup");
+            Some(Arc::new(HttpCaseStatusProvider::new(base_url, config.api_token.clone(), config.timeout())?))
+        }
+        CaseStatusProviderKind::File => {
+            let path = config.file.as_deref().expect("case_status.file validated at startup");
+            Some(Arc::new(FileCaseStatusProvider::new(path)))
+        }
+    })
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+
+use super::{CaseStatusProvider, ProviderError};
+use crate::lifecycle::H1bStatus;
+use crate::models::{CaseStatusEntry, CaseStatusRunSummary};
+use crate::store::{CaseStatusObservation, CaseStatusStore, CustomerFilter, CustomerStore, Deleted, StoreError};
+
+/// Changes buffered per subscriber; a subscriber that falls further behind skips ahead.
+const EVENT_BUFFER: usize = 256;
+
+/// Polls the provider for every pending receipt and records what changed.
+///
+/// Pending means a non-deleted customer in `Filed` or `RFE`. Each status change
+/// is written to the history and published to subscribers (see `subscribe`);
+/// the first status seen for a receipt is recorded but isn't an event.
+pub struct CaseStatusTracker {
+    provider: Arc<dyn CaseStatusProvider>,
+    customers: Arc<dyn CustomerStore>,
+    history: Arc<dyn CaseStatusStore>,
+    events: broadcast::Sender<CaseStatusEntry>,
+    request_delay: Duration,
+    /// Held for the length of a run so a manual run can't overlap the scheduled one.
+    running: Mutex<()>,
+}
+
+impl CaseStatusTracker {
+    pub fn new(
+        provider: Arc<dyn CaseStatusProvider>,
+        customers: Arc<dyn CustomerStore>,
+        history: Arc<dyn CaseStatusStore>,
+        request_delay: Duration,
+    ) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self { provider, customers, history, events, request_delay, running: Mutex::new(()) }
+    }
+
+    /// Status changes from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<CaseStatusEntry> {
+        self.events.subscribe()
+    }
+
+    /// Runs every `interval`, starting immediately.
+    pub async fn run_forever(self: Arc<Self>, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match self.run_once().await {
+                Ok(Some(summary)) => println!(
+                    "🔎 Case status run: {} checked, {} changed, {} not found, {} failed",
+                    summary.checked, summary.changed, summary.not_found, summary.failed
+                ),
+                Ok(None) => println!("⚠️  Case status run skipped; the previous one is still going"),
+                Err(e) => eprintln!("❌ Case status run failed: {}", e),
+            }
+        }
+    }
+
+    /// One pass over the pending receipts. Returns `None` if a run is already in progress.
+    pub async fn run_once(&self) -> Result<Option<CaseStatusRunSummary>, StoreError> {
+        let Ok(_running) = self.running.try_lock() else {
+            return Ok(None);
+        };
+
+        let customers = self.customers.list(CustomerFilter::All, Deleted::Exclude).await?;
+        let pending = customers.into_iter().filter(|c| {
+            c.h1b_status.parse::<H1bStatus>().is_ok_and(H1bStatus::is_pending_with_uscis)
+        });
+
+        let mut summary = CaseStatusRunSummary::default();
+        for (i, customer) in pending.enumerate() {
+            if i > 0 && !self.request_delay.is_zero() {
+                tokio::time::sleep(self.request_delay).await;
+            }
+            summary.checked += 1;
+
+            // Rows from before receipt validation may not normalize, and a customer
+            // created as already filed may have none; there's nothing to ask about.
+            let Some(Ok(receipt_number)) = customer.receipt_number.as_deref().map(crate::receipt::normalize) else {
+                eprintln!("❌ Skipping invalid receipt number {:?} for customer {}", customer.receipt_number, customer.customer_id);
+                summary.failed += 1;
+                continue;
+            };
+
+            let status = match self.provider.fetch(&receipt_number).await {
+                Ok(status) => status,
+                Err(ProviderError::NotFound) => {
+                    summary.not_found += 1;
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("❌ Case status lookup for {} failed: {}", receipt_number, e);
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+
+            let observation = CaseStatusObservation {
+                receipt_number,
+                customer_id: customer.customer_id,
+                status: status.status,
+                description: status.description,
+                provider: self.provider.name().to_string(),
+            };
+            match self.history.record(&observation).await {
+                Ok(Some(entry)) if entry.previous_status.is_some() => {
+                    println!(
+                        "📣 {} ({}): {} -> {}",
+                        entry.receipt_number,
+                        customer.customer_id,
+                        entry.previous_status.as_deref().unwrap_or_default(),
+                        entry.status
+                    );
+                    summary.changed += 1;
+                    // No subscribers is fine; the history has it.
+                    let _ = self.events.send(entry);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("❌ Failed to record case status for {}: {}", observation.receipt_number, e);
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        Ok(Some(summary))
+    }
+}
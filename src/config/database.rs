@@ -1,28 +1,54 @@
-use sqlx::{PgPool, postgres::PgPoolOptions};
-use std::sync::OnceLock;
+use sqlx::{Connection, PgConnection, PgPool, postgres::PgPoolOptions, migrate::Migrator};
+use crate::config::settings::DatabaseConfig;
 
-static DB_POOL: OnceLock<PgPool> = OnceLock::new();
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
-pub async fn initialize_database() -> Result<(), Box<dyn std::error::Error>> {
-    let db_host = std::env::var("DB_HOST")?;
-    let db_port = std::env::var("DB_PORT")?;
-    let db_user = std::env::var("DB_USER")?;
-    let db_password = std::env::var("DB_PASSWORD")?;
-    let db_name = std::env::var("DB_NAME")?;
-    
-    let database_url = format!("postgresql://{}:{}@{}:{}/{}", db_user, db_password, db_host, db_port, db_name);
-                                        
-    
+/// Opens the connection pool and, unless disabled, brings the schema up to date.
+pub async fn initialize_database(config: &DatabaseConfig) -> Result<PgPool, Box<dyn std::error::Error>> {
+    println!("🔌 Connecting to Postgres ({:?})", config.connection_mode);
     let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&database_url)
+        .max_connections(config.pool.max_connections)
+        .min_connections(config.pool.min_connections)
+        .acquire_timeout(config.pool.acquire_timeout())
+        .idle_timeout(config.pool.idle_timeout())
+        .max_lifetime(config.pool.max_lifetime())
+        .connect_with(config.connect_options())
         .await?;
-    
-    DB_POOL.set(pool).map_err(|_| "Failed to set database pool")?;
-    
-    Ok(())
+
+    if config.run_migrations {
+        match config.migration_connect_options() {
+            Some(options) => {
+                let mut conn = PgConnection::connect_with(&options).await?;
+                run_migrations(&mut conn, &config.schema).await?;
+                conn.close().await?;
+            }
+            None => {
+                // Detached so the altered search_path never serves a request.
+                let mut conn = pool.acquire().await?.detach();
+                run_migrations(&mut conn, &config.schema).await?;
+            }
+        }
+    }
+
+    Ok(pool)
 }
 
-pub fn get_db_pool() -> PgPool {
-    DB_POOL.get().expect("Database not initialized").clone()
-}
\ No newline at end of file
+/// Applies pending migrations inside the application schema.
+///
+/// The migration files are unqualified, so they run with the connection's
+/// `search_path` pointing at the schema. That is session state, which is why
+/// callers must hand over a connection that no request will reuse, and why
+/// this can't go through a transaction pooler.
+pub async fn run_migrations(conn: &mut PgConnection, schema: &str) -> Result<(), Box<dyn std::error::Error>> {
+    sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS {}", schema))
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(&format!("SET search_path TO {}", schema))
+        .execute(&mut *conn)
+        .await?;
+
+    MIGRATOR.run(&mut *conn).await?;
+    println!("✅ Database migrations are up to date");
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Figures for the admin dashboard. The Postgres store computes them with SQL
+//! aggregates; this holds the windows and limits both stores use and the
+//! helpers that shape their results the same way.
+
+use chrono::{Datelike, Months, NaiveDate};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+
+use crate::lifecycle::H1bStatus;
+use crate::models::{MonthCount, StatusCount};
+
+/// Days ahead counted in `Dashboard::expiring`.
+pub const EXPIRY_WINDOWS: [i32; 4] = [30, 60, 90, 180];
+
+/// Months covered by `Dashboard::new_per_month`, the current one included.
+pub const MONTHS: u32 = 12;
+
+pub const TOP_CLIENTS: i64 = 10;
+
+/// First day of each month in `new_per_month`, oldest first.
+pub fn month_starts(today: NaiveDate) -> Vec<NaiveDate> {
+    let current = today.with_day(1).expect("every month has a first day");
+    (0..MONTHS).rev().filter_map(|back| current.checked_sub_months(Months::new(back))).collect()
+}
+
+pub fn month_label(date: NaiveDate) -> String {
+    date.format("%Y-%m").to_string()
+}
+
+/// One entry per month, zero where `counts` (keyed by `month_label`) has none.
+pub fn fill_months(today: NaiveDate, counts: &HashMap<String, i64>) -> Vec<MonthCount> {
+    month_starts(today)
+        .into_iter()
+        .map(|start| {
+            let month = month_label(start);
+            let customers = counts.get(&month).copied().unwrap_or(0);
+            MonthCount { month, customers }
+        })
+        .collect()
+}
+
+/// Every status in lifecycle order, zero where `counts` (keyed by label) has none.
+pub fn fill_statuses(counts: &HashMap<String, i64>) -> Vec<StatusCount> {
+    H1bStatus::ALL
+        .into_iter()
+        .map(|status| StatusCount { h1b_status: status, customers: counts.get(status.as_str()).copied().unwrap_or(0) })
+        .collect()
+}
+
+/// Labels of the statuses whose customers count as expiring.
+pub fn approved_statuses() -> Vec<String> {
+    H1bStatus::ALL.into_iter().filter(|status| status.is_approved()).map(|status| status.as_str().to_string()).collect()
+}
+
+/// Interpolates between the two nearest of the ascending `sorted` values, as
+/// Postgres' `percentile_cont` does. `sorted` must not be empty.
+pub fn percentile(sorted: &[Decimal], fraction: Decimal) -> Decimal {
+    let position = fraction * Decimal::from(sorted.len() - 1);
+    let lower = position.floor();
+    let i = lower.to_usize().unwrap_or(0);
+    match sorted.get(i + 1) {
+        Some(next) => sorted[i] + (next - sorted[i]) * (position - lower),
+        None => sorted[i],
+    }
+}
+
+/// Cents, rounding half away from zero like Postgres' `round(numeric, 2)`.
+pub fn round_cents(value: Decimal) -> Decimal {
+    value.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+}
@@ -0,0 +1,269 @@
+//! Finds customers that look like the same person entered twice, so an admin
+//! can merge them.
+//!
+//! Each signal adds to a pair's score, capped at 1: a shared receipt number is
+//! nearly conclusive, name and date of birth together are strong, a shared or
+//! near-identical email and the same phone number back the rest up. Only pairs
+//! sharing a blocking key (date of birth, receipt, name, email or phone) are
+//! compared, so the whole customer list doesn't have to be scored pairwise.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::models::{CreateCustomer, DuplicatePair};
+
+/// Pairs scoring below this are left out unless the caller asks for them.
+pub const DEFAULT_MIN_SCORE: f64 = 0.5;
+
+const SAME_RECEIPT: f64 = 0.9;
+const SAME_NAME_AND_DOB: f64 = 0.7;
+const SIMILAR_NAME_AND_DOB: f64 = 0.5;
+const SAME_NAME: f64 = 0.2;
+const SHARED_EMAIL: f64 = 0.6;
+const SIMILAR_EMAIL: f64 = 0.3;
+const SAME_PHONE: f64 = 0.3;
+
+/// Jaro-Winkler similarity above which two full names count as the same
+/// person with a typo.
+const SIMILAR_NAME: f64 = 0.9;
+
+/// Likely duplicates among `customers`, best first.
+pub fn find_pairs(customers: &[CreateCustomer], min_score: f64) -> Vec<DuplicatePair> {
+    let mut blocks: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, customer) in customers.iter().enumerate() {
+        for key in blocking_keys(customer) {
+            blocks.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut candidates = BTreeSet::new();
+    for members in blocks.values() {
+        for (n, &i) in members.iter().enumerate() {
+            for &j in &members[n + 1..] {
+                candidates.insert((i.min(j), i.max(j)));
+            }
+        }
+    }
+
+    let mut pairs: Vec<DuplicatePair> = candidates
+        .into_iter()
+        .filter_map(|(i, j)| pair(&customers[i], &customers[j], min_score))
+        .collect();
+    sort(&mut pairs);
+    pairs
+}
+
+/// Likely duplicates of `customer` among `others`, best first.
+pub fn find_for(customer: &CreateCustomer, others: &[CreateCustomer], min_score: f64) -> Vec<DuplicatePair> {
+    let mut pairs: Vec<DuplicatePair> = others
+        .iter()
+        .filter(|other| other.customer_id != customer.customer_id)
+        .filter_map(|other| pair(customer, other, min_score))
+        .collect();
+    sort(&mut pairs);
+    pairs
+}
+
+/// How likely `a` and `b` are the same person, from 0 to 1, with the evidence.
+pub fn score(a: &CreateCustomer, b: &CreateCustomer) -> (f64, Vec<String>) {
+    let mut score = 0.0;
+    let mut reasons = Vec::new();
+    let mut add = |weight: f64, reason: &str| {
+        score += weight;
+        reasons.push(reason.to_string());
+    };
+
+    if a.receipt_number.is_some() && a.receipt_number == b.receipt_number {
+        add(SAME_RECEIPT, "same receipt number");
+    }
+
+    let same_name = name_key(a) == name_key(b);
+    if a.dob == b.dob && same_name {
+        add(SAME_NAME_AND_DOB, "same name and date of birth");
+    } else if a.dob == b.dob && similar_names(a, b) {
+        add(SIMILAR_NAME_AND_DOB, "same date of birth and similar name");
+    } else if same_name {
+        add(SAME_NAME, "same name");
+    }
+
+    let addresses = |c: &CreateCustomer| [c.email.to_lowercase(), c.login_email.to_lowercase()];
+    let (a_addresses, b_addresses) = (addresses(a), addresses(b));
+    if a_addresses.iter().any(|address| b_addresses.contains(address)) {
+        add(SHARED_EMAIL, "shared email");
+    } else {
+        let (a_mailboxes, b_mailboxes) = (a_addresses.map(|e| canonical_email(&e)), b_addresses.map(|e| canonical_email(&e)));
+        if a_mailboxes.iter().any(|mailbox| b_mailboxes.contains(mailbox)) {
+            add(SIMILAR_EMAIL, "similar email");
+        }
+    }
+
+    if !a.phone.is_empty() && a.phone == b.phone {
+        add(SAME_PHONE, "same phone number");
+    }
+
+    (score.min(1.0), reasons)
+}
+
+fn pair(a: &CreateCustomer, b: &CreateCustomer, min_score: f64) -> Option<DuplicatePair> {
+    let (score, reasons) = score(a, b);
+    if reasons.is_empty() || score < min_score {
+        return None;
+    }
+    Some(DuplicatePair {
+        score: (score * 100.0).round() / 100.0,
+        reasons,
+        customers: vec![a.clone(), b.clone()],
+    })
+}
+
+fn sort(pairs: &mut [DuplicatePair]) {
+    pairs.sort_by(|a, b| b.score.total_cmp(&a.score));
+}
+
+/// A pair is only scored if it shares one of these. Similar names are only
+/// considered with the same date of birth, so the name keys are exact.
+fn blocking_keys(c: &CreateCustomer) -> Vec<String> {
+    let mut keys = vec![format!("dob:{}", c.dob), format!("name:{}", name_key(c))];
+    if let Some(receipt_number) = &c.receipt_number {
+        keys.push(format!("receipt:{}", receipt_number));
+    }
+    if !c.phone.is_empty() {
+        keys.push(format!("phone:{}", c.phone));
+    }
+    let mut mailboxes = vec![canonical_email(&c.email), canonical_email(&c.login_email)];
+    mailboxes.dedup();
+    keys.extend(mailboxes.into_iter().map(|mailbox| format!("email:{}", mailbox)));
+    keys
+}
+
+fn name_key(c: &CreateCustomer) -> String {
+    format!("{}|{}", simplify(&c.last_name), simplify(&c.first_name))
+}
+
+/// A typo in either name, or first and last name swapped.
+fn similar_names(a: &CreateCustomer, b: &CreateCustomer) -> bool {
+    let (a_first, a_last) = (simplify(&a.first_name), simplify(&a.last_name));
+    let (b_first, b_last) = (simplify(&b.first_name), simplify(&b.last_name));
+    let swapped = a_first == b_last && a_last == b_first;
+    swapped || strsim::jaro_winkler(&format!("{} {}", a_first, a_last), &format!("{} {}", b_first, b_last)) >= SIMILAR_NAME
+}
+
+/// Lowercase letters and digits only, so "O'Brien" and "obrien" compare equal.
+fn simplify(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// The mailbox an address delivers to, ignoring case, dots and a `+tag` in
+/// the local part: `Jane.Doe+h1b@gmail.com` and `janedoe@gmail.com` match.
+fn canonical_email(email: &str) -> String {
+    let email = email.trim().to_lowercase();
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return email;
+    };
+    let local = local.split('+').next().unwrap_or(local).replace('.', "");
+    format!("{}@{}", local, domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{customer, date};
+
+    /// Someone else entirely: nothing in common with `customer()`.
+    fn stranger() -> CreateCustomer {
+        let mut c = customer();
+        c.first_name = "Raj".to_string();
+        c.last_name = "Patel".to_string();
+        c.dob = date("1985-07-30");
+        c.email = "raj@example.org".to_string();
+        c.login_email = "raj@example.org".to_string();
+        c.phone = "+14155550123".to_string();
+        c.receipt_number = None;
+        c
+    }
+
+    #[test]
+    fn reentered_customer_scores_the_maximum() {
+        let (a, b) = (customer(), customer());
+        let (score, reasons) = score(&a, &b);
+        assert_eq!(score, 1.0);
+        assert_eq!(reasons, ["same receipt number", "same name and date of birth", "shared email", "same phone number"]);
+    }
+
+    #[test]
+    fn strangers_share_nothing() {
+        let (score, reasons) = score(&customer(), &stranger());
+        assert_eq!(score, 0.0);
+        assert!(reasons.is_empty());
+    }
+
+    #[test]
+    fn missing_receipt_numbers_do_not_match() {
+        let (mut a, mut b) = (stranger(), stranger());
+        (a.email, a.login_email, a.phone) = ("a@example.org".into(), "a@example.org".into(), String::new());
+        (b.email, b.login_email, b.first_name) = ("b@example.org".into(), "b@example.org".into(), "Ravi".into());
+        b.dob = date("1990-01-01");
+        let (score, reasons) = score(&a, &b);
+        assert_eq!(score, 0.0, "{:?}", reasons);
+    }
+
+    #[test]
+    fn typos_and_swapped_names_with_the_same_birthday() {
+        let a = customer();
+        let mut typo = stranger();
+        (typo.first_name, typo.last_name, typo.dob) = ("Anne".into(), "Lee".into(), a.dob);
+        assert_eq!(score(&a, &typo), (SIMILAR_NAME_AND_DOB, vec!["same date of birth and similar name".to_string()]));
+
+        let mut swapped = stranger();
+        (swapped.first_name, swapped.last_name, swapped.dob) = ("Lee".into(), "Ann".into(), a.dob);
+        assert_eq!(score(&a, &swapped).0, SIMILAR_NAME_AND_DOB);
+
+        // Without the birthday only an exact name counts, and only a little.
+        let mut namesake = stranger();
+        (namesake.first_name, namesake.last_name) = ("ann".into(), "LEE".into());
+        assert_eq!(score(&a, &namesake), (SAME_NAME, vec!["same name".to_string()]));
+    }
+
+    #[test]
+    fn emails_match_ignoring_dots_tags_and_case() {
+        let mut a = customer();
+        a.email = "Jane.Doe+h1b@gmail.com".to_string();
+        let mut b = stranger();
+        b.email = "janedoe@gmail.com".to_string();
+        assert_eq!(score(&a, &b), (SIMILAR_EMAIL, vec!["similar email".to_string()]));
+
+        b.login_email = "ANN.LEE@example.com".to_string();
+        assert_eq!(score(&a, &b), (SHARED_EMAIL, vec!["shared email".to_string()]));
+    }
+
+    #[test]
+    fn find_pairs_only_returns_likely_duplicates() {
+        let original = customer();
+        let mut reentered = customer();
+        reentered.receipt_number = None;
+        let mut same_phone = stranger();
+        (same_phone.first_name, same_phone.dob, same_phone.email) = ("Mei".into(), date("1979-02-02"), "mei@example.net".into());
+        same_phone.login_email = same_phone.email.clone();
+        same_phone.phone = original.phone.clone();
+        let customers = [original.clone(), stranger(), reentered.clone(), same_phone];
+
+        let pairs = find_pairs(&customers, DEFAULT_MIN_SCORE);
+        assert_eq!(pairs.len(), 1);
+        let ids: Vec<_> = pairs[0].customers.iter().map(|c| c.customer_id).collect();
+        assert_eq!(ids, [original.customer_id, reentered.customer_id]);
+
+        // Lower the bar and the shared phone shows up too, after the strong pair.
+        let pairs = find_pairs(&customers, 0.0);
+        let scores: Vec<f64> = pairs.iter().map(|p| p.score).collect();
+        assert_eq!(scores, [1.0, 0.3, 0.3]);
+    }
+
+    #[test]
+    fn find_for_skips_the_customer_itself() {
+        let a = customer();
+        let mut b = customer();
+        b.receipt_number = None;
+        let pairs = find_for(&a, &[a.clone(), b.clone(), stranger()], DEFAULT_MIN_SCORE);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].customers[1].customer_id, b.customer_id);
+    }
+}
@@ -1,17 +1,36 @@
 use axum::{
-    extract::Path,
-    http::StatusCode,
-    response::Json,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderName, StatusCode},
+    response::{sse::{Event, KeepAlive, Sse}, Json},
 };
-use sqlx::{Row, Executor};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use sqlx::PgPool;
+use uuid::Uuid;
 use crate::models::*;
-use crate::config::database::get_db_pool;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::database::MIGRATOR;
+use crate::state::AppState;
+use crate::auth::Caller;
+use crate::lifecycle::H1bStatus;
+use crate::soc::SocOccupation;
+use crate::address::{normalize_zip, ZipCode};
+use crate::store::{CustomerFilter, Deleted, MergeOutcome, RestoreOutcome, SoftDeleteOutcome, StoreError, TransitionOutcome, UpsertOutcome};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-fn get_timestamp() -> u128 {
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
-}
+const PUBLIC_ACCESS_FILE_ID: HeaderName = HeaderName::from_static("x-public-access-file-id");
+const MISSING_SECTIONS: HeaderName = HeaderName::from_static("x-missing-sections");
+const MISSING_FIELDS: HeaderName = HeaderName::from_static("x-missing-fields");
 
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (status = 200, description = "API process is running", example = json!({"status": "OK", "message": "API is running"}))
+    )
+)]
 pub async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
     Ok(Json(serde_json::json!({
         "status": "OK",
@@ -19,10 +38,169 @@ pub async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
     })))
 }
 
-pub async fn test_connection() -> Result<Json<serde_json::Value>, StatusCode> {
-    let pool = get_db_pool();
+/// Liveness probe: the process is up and serving requests. Never touches the database,
+/// so an outage there doesn't get healthy instances restarted.
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    responses(
+        (status = 200, description = "Process is alive", example = json!({"status": "alive"}))
+    )
+)]
+pub async fn livez() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "alive"
+    }))
+}
+
+/// Readiness probe: reports whether this instance can actually serve traffic.
+/// Returns 503 when any dependency check fails so the orchestrator stops routing here.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "All dependency checks passed"),
+        (status = 503, description = "At least one dependency check failed or the circuit breaker is open; body lists per-check status and latency")
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    // While the breaker is open every store call is rejected with 503, so the
+    // instance can't serve traffic whatever the other checks say. Half-open is
+    // ready: the trial request has to come from somewhere.
+    let breaker = match state.circuit_breaker.retry_after() {
+        Some(retry_after) => serde_json::json!({ "status": "open", "retry_after_secs": retry_after.as_secs().max(1) }),
+        None if state.circuit_breaker.is_open() => serde_json::json!({ "status": "half_open" }),
+        None => serde_json::json!({ "status": "closed" }),
+    };
+    let breaker_ready = breaker["status"] != "open";
+
+    let Some(pool) = &state.pool else {
+        // In-memory store: there is no external dependency to wait for.
+        let status = if breaker_ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        return (status, Json(serde_json::json!({
+            "status": if breaker_ready { "ready" } else { "not_ready" },
+            "checks": {
+                "store": { "status": "up", "backend": "memory" },
+                "circuit_breaker": breaker
+            }
+        })));
+    };
+
+    let database = check_database(pool).await;
+    let migrations = if database["status"] == "up" {
+        check_migrations(pool, state.schema()).await
+    } else {
+        serde_json::json!({
+            "status": "skipped",
+            "detail": "database unreachable"
+        })
+    };
+    let pool_check = check_pool(pool);
+
+    let ready = breaker_ready
+        && [&database, &migrations, &pool_check]
+            .iter()
+            .all(|check| check["status"] == "up");
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (status, Json(serde_json::json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": {
+            "database": database,
+            "migrations": migrations,
+            "pool": pool_check,
+            "circuit_breaker": breaker
+        }
+    })))
+}
+
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn check_database(pool: &PgPool) -> serde_json::Value {
+    let start = Instant::now();
+    let result = tokio::time::timeout(READINESS_TIMEOUT, sqlx::query("SELECT 1").execute(pool)).await;
+    let latency_ms = start.elapsed().as_millis();
+
+    match result {
+        Ok(Ok(_)) => serde_json::json!({ "status": "up", "latency_ms": latency_ms }),
+        Ok(Err(e)) => {
+            eprintln!("❌ Readiness database check failed: {}", e);
+            serde_json::json!({ "status": "down", "latency_ms": latency_ms, "detail": e.to_string() })
+        },
+        Err(_) => serde_json::json!({
+            "status": "down",
+            "latency_ms": latency_ms,
+            "detail": format!("timed out after {}ms", READINESS_TIMEOUT.as_millis())
+        }),
+    }
+}
+
+async fn check_migrations(pool: &PgPool, schema: &str) -> serde_json::Value {
+    let start = Instant::now();
+    let sql = format!("SELECT version FROM {}._sqlx_migrations WHERE success", schema);
+    let result = tokio::time::timeout(READINESS_TIMEOUT, sqlx::query_scalar::<_, i64>(&sql).fetch_all(pool)).await;
+    let latency_ms = start.elapsed().as_millis();
+
+    match result {
+        Ok(Ok(applied)) => {
+            let pending: Vec<String> = MIGRATOR
+                .iter()
+                .filter(|m| !applied.contains(&m.version))
+                .map(|m| format!("{}_{}", m.version, m.description))
+                .collect();
+            serde_json::json!({
+                "status": if pending.is_empty() { "up" } else { "down" },
+                "latency_ms": latency_ms,
+                "applied": applied.len(),
+                "pending": pending
+            })
+        },
+        Ok(Err(e)) => {
+            eprintln!("❌ Readiness migration check failed: {}", e);
+            serde_json::json!({ "status": "down", "latency_ms": latency_ms, "detail": e.to_string() })
+        },
+        Err(_) => serde_json::json!({
+            "status": "down",
+            "latency_ms": latency_ms,
+            "detail": format!("timed out after {}ms", READINESS_TIMEOUT.as_millis())
+        }),
+    }
+}
+
+fn check_pool(pool: &PgPool) -> serde_json::Value {
+    let size = pool.size();
+    let idle = pool.num_idle();
+    let max = pool.options().get_max_connections();
+    // Every connection is open and checked out: new requests would queue on acquire.
+    let exhausted = size >= max && idle == 0;
+
+    serde_json::json!({
+        "status": if exhausted { "down" } else { "up" },
+        "size": size,
+        "idle": idle,
+        "max": max
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/hello",
+    tag = "health",
+    responses(
+        (status = 200, description = "Database answered `SELECT 1`", example = json!({"status": "Database connected successfully"})),
+        (status = 500, description = "Database unreachable")
+    )
+)]
+pub async fn test_connection(State(state): State<AppState>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Some(pool) = &state.pool else {
+        return Ok(Json(serde_json::json!({
+            "status": "No database configured (in-memory store)"
+        })));
+    };
     match sqlx::query("SELECT 1 as test")
-        .fetch_one(&pool)
+        .fetch_one(pool)
         .await {
         Ok(_) => Ok(Json(serde_json::json!({
             "status": "Database connected successfully"
@@ -34,1009 +212,1920 @@ pub async fn test_connection() -> Result<Json<serde_json::Value>, StatusCode> {
     }
 }
 
-pub async fn get_all_customers() -> Result<Json<Vec<CreateCustomer>>, StatusCode> {
-    println!("🔥 get_all_customers function called");
-    let pool = get_db_pool();
-    
-    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
-    let raw_sql = format!("SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone, 
-        emergency_contact_name, emergency_contact_phone, employment_start_date,
-        street_name, city, state, zip,
-        client_name, client_street_name, client_city, client_state, client_zip,
-        lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, h1b_status::text
-        FROM visa_db.h1bcustomer -- {}", timestamp);
-    
-    let rows = pool.fetch_all(raw_sql.as_str())
-    .await
-    .map_err(|e| {
-        eprintln!("❌ Database error in get_all_customers: {}", e);
-        eprintln!("❌ Error details: {:?}", e);
-        eprintln!("❌ SQL Query: {}", raw_sql);
-        StatusCode::INTERNAL_SERVER_ERROR
+/// Creates a customer and returns it with its generated `customer_id`.
+/// With `?upsert=true` an existing customer with the same `email` is updated instead.
+#[utoipa::path(
+    post,
+    path = "/h1b_customer/create",
+    tag = "customers",
+    params(
+        CreateCustomerParams,
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body = CreateCompleteCustomerRequest,
+    responses(
+        (status = 201, description = "Customer created, with `warnings` about doubtful input such as an `lca_title` that doesn't match the SOC code", body = CreateCustomerResponse,
+            headers(("Location" = String, description = "`/customers/{customer_id}`"))),
+        (status = 200, description = "Upsert matched an existing customer by email and replaced its details", body = CreateCustomerResponse,
+            headers(("Location" = String, description = "`/customers/{customer_id}`"))),
+        (status = 400, description = "Rejected input, e.g. an unknown enum value, an `h1b_status` other than `Draft`, a `receipt_number` for a customer that hasn't filed, a malformed receipt number, SOC code, state or ZIP code"),
+        (status = 409, description = "Receipt number, email or login_email already used by another customer, upsert would change `h1b_status`, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn create_visa_details(
+    State(state): State<AppState>,
+    Query(params): Query<CreateCustomerParams>,
+    Json(mut payload): Json<CreateCompleteCustomerRequest>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Json<CreateCustomerResponse>), StatusCode> {
+    println!("🔥 create_visa_details function called");
+
+    // Every later status is reached through a transition, which records it.
+    if payload.h1b_status.as_deref().is_some_and(|status| status != H1bStatus::Draft.as_str()) {
+        eprintln!("❌ Rejected customer {}: new customers start as Draft", payload.email);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let warnings = state.validator().check_new(&mut payload).map_err(|e| {
+        eprintln!("❌ Rejected customer {}: {}", payload.email, e);
+        StatusCode::BAD_REQUEST
     })?;
 
-    let customers: Vec<CreateCustomer> = rows.into_iter().map(|row| CreateCustomer {
-        customer_id: row.get("customer_id"),
-        email: row.get("email"),
-        first_name: row.get("first_name"),
-        last_name: row.get("last_name"),
-        dob: row.get("dob"),
-        sex: row.get("sex"),
-        marital_status: row.get("marital_status"),
-        phone: row.get("phone"),
-        emergency_contact_name: row.get("emergency_contact_name"),
-        emergency_contact_phone: row.get("emergency_contact_phone"),
-        employment_start_date: row.get("employment_start_date"),
-        street_name: row.get("street_name"),
-        city: row.get("city"),
-        state: row.get("state"),
-        zip: row.get("zip"),
-        client_name: row.get("client_name"),
-        client_street_name: row.get("client_street_name"),
-        client_city: row.get("client_city"),
-        client_state: row.get("client_state"),
-        client_zip: row.get("client_zip"),
-        lca_title: row.get("lca_title"),
-        lca_salary: row.get("lca_salary"),
-        lca_code: row.get("lca_code"),
-        receipt_number: row.get("receipt_number"),
-        h1b_start_date: row.get("h1b_start_date"),
-        h1b_end_date: row.get("h1b_end_date"),
-        h1b_status: row.get("h1b_status"),
-    }).collect();
-
-    Ok(Json(customers))
-}
+    let result = if params.upsert.unwrap_or(false) {
+        state.customers.upsert_by_email(&payload).await.map(|outcome| match outcome {
+            UpsertOutcome::Created(customer) => (StatusCode::CREATED, *customer),
+            UpsertOutcome::Updated(customer) => (StatusCode::OK, *customer),
+        })
+    } else {
+        state.customers.create(&payload).await.map(|customer| (StatusCode::CREATED, customer))
+    };
 
-pub async fn create_visa_details(
-    Json(payload): Json<CreateCompleteCustomerRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    println!("🔥 create_visa_details function called");
-    let pool = get_db_pool();
-    let h1b_status = payload.h1b_status.as_deref().unwrap_or("Active");
-    
-    let _query_sql = "INSERT INTO visa_db.h1bcustomer (
-            email, first_name, last_name, dob, sex, marital_status, phone,
-            emergency_contact_name, emergency_contact_phone, employment_start_date,
-            street_name, city, state, zip,
-            client_name, client_street_name, client_city, client_state, client_zip,
-            lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, h1b_status
-        ) VALUES (
-            $1, $2, $3, $4, $5::visa_db.sex_enum, $6::visa_db.marital_status_enum, $7,
-            $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26::visa_db.h1b_status_enum
-        )";
-    
-    let raw_sql = format!("INSERT INTO global_visa_mgmt.h1bcustomer (
-            email, first_name, last_name, dob, sex, marital_status, phone,
-            emergency_contact_name, emergency_contact_phone, employment_start_date,
-            street_name, city, state, zip,
-            client_name, client_street_name, client_city, client_state, client_zip,
-            lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status
-        ) VALUES (
-            '{}', '{}', '{}', '{}', '{}'::global_visa_mgmt.sex_enum, '{}'::global_visa_mgmt.marital_status_enum, '{}',
-            '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', {}, '{}', '{}', '{}', '{}', '{}', '{}'::global_visa_mgmt.h1b_status_enum
-        )",
-        payload.email.replace("'", "''"), payload.first_name.replace("'", "''"), payload.last_name.replace("'", "''"), 
-        payload.dob, payload.sex, payload.marital_status, payload.phone.replace("'", "''"),
-        payload.emergency_contact_name.replace("'", "''"), payload.emergency_contact_phone.replace("'", "''"), payload.employment_start_date,
-        payload.street_name.replace("'", "''"), payload.city.replace("'", "''"), payload.state.replace("'", "''"), payload.zip.replace("'", "''"),
-        payload.client_name.replace("'", "''"), payload.client_street_name.replace("'", "''"), payload.client_city.replace("'", "''"), 
-        payload.client_state.replace("'", "''"), payload.client_zip.replace("'", "''"),
-        payload.lca_title.replace("'", "''"), payload.lca_salary, payload.lca_code.replace("'", "''"), 
-        payload.receipt_number.replace("'", "''"), payload.h1b_start_date, payload.h1b_end_date, payload.login_email.replace("'", "''"), h1b_status
-    );
-    
-    match pool.execute(raw_sql.as_str()).await {
-        Ok(result) => {
-            Ok(Json(serde_json::json!({
-                "message": "Visa details created successfully",
-                "email": payload.email,
-                "rows_affected": result.rows_affected()
-            })))
+    match result {
+        Ok((status, customer)) => {
+            let location = format!("/customers/{}", customer.customer_id);
+            Ok((status, [(header::LOCATION, location)], Json(CreateCustomerResponse { customer, warnings })))
         },
         Err(e) => {
-            eprintln!("❌ Database error in create_visa_details: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ SQL Query: {}", raw_sql);
             eprintln!("❌ Email: {}", payload.email);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(store_error("create_visa_details", e))
         }
     }
 }
 
-pub async fn soft_delete_customer(
-    Path(email): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    println!("🔥 soft_delete_customer function called for email: {}", email);
-    let pool = get_db_pool();
-
-    // Use the pool directly, SQLx will manage connections automatically
-    match sqlx::query("UPDATE visa_db.h1bcustomer SET h1b_status = 'Inactive' WHERE email = $1")
-        .bind(&email)
-        .execute(&pool) // <-- pass the pool, not manually acquired conn
-        .await 
-    {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                Ok(Json(serde_json::json!({
-                    "status": 404,
-                    "message": "Record not found in the database",
-                    "email": email
-                })))
-            } else {
-                Ok(Json(serde_json::json!({
-                    "message": "Customer soft deleted successfully",
-                    "email": email,
-                    "rows_affected": result.rows_affected()
-                })))
-            }
-        },
-        Err(e) => {
-            eprintln!("❌ Database error in soft_delete_customer: {}", e);
-            eprintln!("❌ Soft delete error details: {:?}", e);
-            eprintln!("❌ Email: {}", email);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+/// Canonical customer resource, the target of the `Location` header returned by create.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}",
+    tag = "customers",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Customer", body = CreateCustomer),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<CreateCustomer>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    match state.customers.get_by_id(id, deleted).await {
+        Ok(Some(customer)) => Ok(Json(customer)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("get_customer", e)),
     }
 }
 
-pub async fn get_customer_personal(
-    Path(id): Path<String>,
+/// Looks a customer up by USCIS receipt number. Spaces, dashes and case are ignored.
+#[utoipa::path(
+    get,
+    path = "/customers/by_receipt/{receipt}",
+    tag = "customers",
+    params(("receipt" = String, Path, description = "Receipt number, e.g. `EAC2412345678` or `eac-24-123-45678`"), ReadParams),
+    responses(
+        (status = 200, description = "Customer holding this receipt number", body = CreateCustomer),
+        (status = 400, description = "Not a valid receipt number"),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this receipt number, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_by_receipt(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(receipt): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<CreateCustomer>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    match state.customers.get_by_receipt(&receipt, deleted).await {
+        Ok(Some(customer)) => Ok(Json(customer)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("get_customer_by_receipt", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_customer_by_id/{id}",
+    tag = "customers",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Customer, or `{\"message\": \"Data not found\"}` when no customer has this id or it is deleted", body = CreateCustomer),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_by_id(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let query_sql = "SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone 
-        FROM visa_db.h1bcustomer WHERE customer_id = $1";
-    
-    match sqlx::query(query_sql)
-        .bind(&id)
-        .fetch_optional(&pool)
-        .await {
-        Ok(Some(row)) => {
+    let deleted = deleted_scope(&params, &caller)?;
+    let Some(id) = parse_customer_id(&customer_id) else {
+        return Ok(Json(serde_json::json!({
+            "message": "Data not found"
+        })));
+    };
+
+    match state.customers.get_by_id(id, deleted).await {
+        Ok(Some(customer)) => Ok(Json(serde_json::json!(customer))),
+        Ok(None) => {
             Ok(Json(serde_json::json!({
-                "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-                "email": row.get::<String, _>("email"),
-                "first_name": row.get::<String, _>("first_name"),
-                "last_name": row.get::<String, _>("last_name"),
-                "dob": row.get::<chrono::NaiveDate, _>("dob"),
-                "sex": row.get::<String, _>("sex"),
-                "marital_status": row.get::<String, _>("marital_status"),
-                "phone": row.get::<String, _>("phone")
+                "message": "Data not found"
             })))
         },
-        Ok(None) => {
-            eprintln!("❌ Customer not found in get_customer_personal: {}", id);
-            Err(StatusCode::NOT_FOUND)
-        },
         Err(e) => {
-            eprintln!("❌ Database error in get_customer_personal: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            eprintln!("❌ Customer ID: {}", customer_id);
+            Err(store_error("get_customer_by_id", e))
         }
     }
 }
 
-pub async fn get_customer_address(
-    Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let query_sql = "SELECT customer_id, street_name, city, state, zip 
-        FROM visa_db.h1bcustomer WHERE customer_id = $1";
-    
-    match sqlx::query(query_sql)
-        .bind(&id)
-        .fetch_optional(&pool)
-        .await {
-        Ok(Some(row)) => {
-            Ok(Json(serde_json::json!({
-                "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-                "street_name": row.get::<String, _>("street_name"),
-                "city": row.get::<String, _>("city"),
-                "state": row.get::<String, _>("state"),
-                "zip": row.get::<String, _>("zip")
-            })))
-        },
-        Ok(None) => {
-            eprintln!("❌ Customer not found in get_customer_address: {}", id);
-            Err(StatusCode::NOT_FOUND)
-        },
+#[utoipa::path(
+    get,
+    path = "/get_customer_by_email/{email}",
+    tag = "customers",
+    params(("email" = String, Path, description = "Matched against both `email` and `login_email`"), ReadParams),
+    responses(
+        (status = 200, description = "Matching customers, or a single `{\"message\": \"Data not found\"}` entry", body = [CreateCustomer]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_by_email(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(email): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    match state.customers.find_by_email(&email, deleted).await {
+        Ok(customers) => Ok(Json(customers_or_not_found(customers))),
         Err(e) => {
-            eprintln!("❌ Database error in get_customer_address: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            eprintln!("❌ Email: {}", email);
+            Err(store_error("get_customer_by_email", e))
         }
     }
 }
 
-pub async fn get_customer_h1b(
-    Path(id): Path<String>,
+#[utoipa::path(
+    patch,
+    path = "/soft_delete_customer_via_id/{id}",
+    tag = "customers",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body(content = Option<SoftDeleteOptions>, description = "Optional reason recorded with the deletion"),
+    responses(
+        (status = 200, description = "Customer soft deleted, or `{\"message\": \"Data not found\"}` if missing or already deleted", example = json!({
+            "message": "Customer soft deleted successfully",
+            "customer_id": "6f1c2b1e-2d7a-4a59-9a8e-0c1d2e3f4a5b",
+            "rows_affected": 1
+        })),
+        (status = 409, description = "A request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn soft_delete_customer_by_id(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    options: Option<Json<SoftDeleteOptions>>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let query_sql = "SELECT customer_id, client_name, client_street_name, client_city, client_state, client_zip,
-        lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, h1b_status::text 
-        FROM visa_db.h1bcustomer WHERE customer_id = $1";
-    
-    match sqlx::query(query_sql)
-        .bind(&id)
-        .fetch_optional(&pool)
-        .await {
-        Ok(Some(row)) => {
+    println!("🔥 soft_delete_customer_by_id function called for customer_id: {}", customer_id);
+    let Some(id) = parse_customer_id(&customer_id) else {
+        return Ok(Json(serde_json::json!({
+            "message": "Data not found"
+        })));
+    };
+
+    let options = options.map(|Json(options)| options).unwrap_or_default();
+    match state.customers.soft_delete(id, &caller.name, options.reason.as_deref()).await {
+        Ok(SoftDeleteOutcome::Deleted) => {
             Ok(Json(serde_json::json!({
-                "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-                "client_name": row.get::<String, _>("client_name"),
-                "client_street_name": row.get::<String, _>("client_street_name"),
-                "client_city": row.get::<String, _>("client_city"),
-                "client_state": row.get::<String, _>("client_state"),
-                "client_zip": row.get::<String, _>("client_zip"),
-                "lca_title": row.get::<String, _>("lca_title"),
-                "lca_salary": row.get::<rust_decimal::Decimal, _>("lca_salary"),
-                "lca_code": row.get::<String, _>("lca_code"),
-                "receipt_number": row.get::<String, _>("receipt_number"),
-                "h1b_start_date": row.get::<chrono::NaiveDate, _>("h1b_start_date"),
-                "h1b_end_date": row.get::<chrono::NaiveDate, _>("h1b_end_date"),
-                "h1b_status": row.get::<String, _>("h1b_status")
+                "message": "Customer soft deleted successfully",
+                "customer_id": customer_id,
+                "rows_affected": 1
             })))
         },
-        Ok(None) => {
-            eprintln!("❌ Customer not found in get_customer_h1b: {}", id);
-            Err(StatusCode::NOT_FOUND)
+        Ok(SoftDeleteOutcome::NotFound) => {
+            Ok(Json(serde_json::json!({
+                "message": "Data not found"
+            })))
         },
         Err(e) => {
-            eprintln!("❌ Database error in get_customer_h1b: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            eprintln!("❌ Customer ID: {}", customer_id);
+            Err(store_error("soft_delete_customer_by_id", e))
         }
     }
 }
 
-pub async fn update_customer_address(
-    Path(id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
+/// Partial update. A change of the worksite address is classified by MSA and
+/// raises a compliance task with the reason and deadline, returned as
+/// `worksite_change`.
+#[utoipa::path(
+    put,
+    path = "/update_customer_by_id/{id}",
+    tag = "customers",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body(content = UpdateVisaDetailsRequest, description = "Partial update: only the fields present are changed"),
+    responses(
+        (status = 200, description = "Customer updated, or `{\"message\": \"Customer not found\"}`. A worksite change comes back as `worksite_change` (see `WorksiteChange`)", example = json!({
+            "message": "Customer updated successfully",
+            "customer_id": "6f1c2b1e-2d7a-4a59-9a8e-0c1d2e3f4a5b",
+            "rows_affected": 1
+        })),
+        (status = 400, description = "Rejected input, e.g. an unknown enum value, a malformed receipt number, SOC code, state or ZIP code, or a `receipt_number` for a customer that hasn't filed"),
+        (status = 409, description = "Receipt number, email or login_email already used by another customer, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn update_customer_by_id(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Json(mut payload): Json<UpdateVisaDetailsRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let query_sql = "UPDATE visa_db.h1bcustomer SET 
-        street_name = $2, city = $3, state = $4, zip = $5 
-        WHERE customer_id = $1";
-    
-    match sqlx::query(query_sql)
-        .bind(&id)
-        .bind(payload["street_name"].as_str().unwrap_or(""))
-        .bind(payload["city"].as_str().unwrap_or(""))
-        .bind(payload["state"].as_str().unwrap_or(""))
-        .bind(payload["zip"].as_str().unwrap_or(""))
-        .execute(&pool)
-        .await {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(Json(serde_json::json!({
-                    "message": "Address updated successfully",
-                    "customer_id": id
-                })))
-            } else {
-                eprintln!("❌ Customer not found in update_customer_address: {}", id);
-                Err(StatusCode::NOT_FOUND)
+    println!("🔥 update_customer_by_id function called for customer_id: {}", customer_id);
+    let not_found = || Json(serde_json::json!({
+        "message": "Customer not found",
+        "customer_id": customer_id
+    }));
+    let Some(id) = parse_customer_id(&customer_id) else {
+        return Ok(not_found());
+    };
+
+    let current = state.customers.get_by_id(id, Deleted::Exclude).await
+        .map_err(|e| store_error("update_customer_by_id", e))?;
+    let warnings = state.validator().check_update(&mut payload, current.as_ref()).map_err(|e| {
+        eprintln!("❌ Rejected update for customer {}: {}", customer_id, e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let worksite_change = current.as_ref().and_then(|current| {
+        crate::worksite::classify(current, &payload, &state.zips, chrono::Utc::now().date_naive())
+    });
+    let task = worksite_change.as_ref().map(|change| crate::worksite::task(change, &caller.name));
+
+    match state.customers.update(id, &payload, task.as_ref()).await {
+        Ok(true) => {
+            let mut body = serde_json::json!({
+                "message": "Customer updated successfully",
+                "customer_id": customer_id,
+                "rows_affected": 1
+            });
+            if !warnings.is_empty() {
+                body["warnings"] = serde_json::json!(warnings);
+            }
+            if let Some(change) = worksite_change {
+                body["worksite_change"] = serde_json::json!(change);
             }
+            Ok(Json(body))
         },
+        Ok(false) => Ok(not_found()),
         Err(e) => {
-            eprintln!("❌ Database error in update_customer_address: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            eprintln!("❌ Customer ID: {}", customer_id);
+            Err(store_error("update_customer_by_id", e))
         }
     }
 }
 
-pub async fn update_customer_h1b(
-    Path(id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let query_sql = "UPDATE visa_db.h1bcustomer SET 
-        client_name = $2, client_street_name = $3, client_city = $4, client_state = $5, client_zip = $6,
-        lca_title = $7, lca_salary = $8, lca_code = $9, receipt_number = $10, 
-        h1b_start_date = $11, h1b_end_date = $12, h1b_status = $13::visa_db.h1b_status_enum 
-        WHERE customer_id = $1";
-    
-    match sqlx::query(query_sql)
-        .bind(&id)
-        .bind(payload["client_name"].as_str().unwrap_or(""))
-        .bind(payload["client_street_name"].as_str().unwrap_or(""))
-        .bind(payload["client_city"].as_str().unwrap_or(""))
-        .bind(payload["client_state"].as_str().unwrap_or(""))
-        .bind(payload["client_zip"].as_str().unwrap_or(""))
-        .bind(payload["lca_title"].as_str().unwrap_or(""))
-        .bind(payload["lca_salary"].as_f64().unwrap_or(0.0))
-        .bind(payload["lca_code"].as_str().unwrap_or(""))
-        .bind(payload["receipt_number"].as_str().unwrap_or(""))
-        .bind(payload["h1b_start_date"].as_str().unwrap_or(""))
-        .bind(payload["h1b_end_date"].as_str().unwrap_or(""))
-        .bind(payload["h1b_status"].as_str().unwrap_or("Active"))
-        .execute(&pool)
-        .await {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(Json(serde_json::json!({
-                    "message": "H1B details updated successfully",
-                    "customer_id": id
-                })))
-            } else {
-                eprintln!("❌ Customer not found in update_customer_h1b: {}", id);
-                Err(StatusCode::NOT_FOUND)
-            }
-        },
+#[utoipa::path(
+    get,
+    path = "/customers",
+    tag = "customers",
+    params(ReadParams),
+    responses(
+        (status = 200, description = "All customers with `h1b_status = Active`", body = [CreateCustomer]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_all_customers_with_status(
+    State(state): State<AppState>,
+    caller: Caller,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<CreateCustomer>>, StatusCode> {
+    println!("🔥 get_all_customers_with_status function called");
+    let deleted = deleted_scope(&params, &caller)?;
+    state.customers.list(CustomerFilter::ActiveOnly, deleted).await
+        .map(Json)
+        .map_err(|e| store_error("get_all_customers_with_status", e))
+}
+
+#[utoipa::path(
+    get,
+    path = "/h1b_customer/by_login_email/{login_email}",
+    tag = "customers",
+    params(("login_email" = String, Path, description = "Login email of the customer"), ReadParams),
+    responses(
+        (status = 200, description = "Customers with this login email, or a single `{\"message\": \"Data not found\"}` entry", body = [CreateCustomer]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_by_login_email(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(login_email): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    match state.customers.find_by_login_email(&login_email, deleted).await {
+        Ok(customers) => Ok(Json(customers_or_not_found(customers))),
         Err(e) => {
-            eprintln!("❌ Database error in update_customer_h1b: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            eprintln!("❌ Login Email: {}", login_email);
+            Err(store_error("get_customer_by_login_email", e))
         }
     }
 }
 
-pub async fn update_visa_details_by_id(
-    Path(customer_id): Path<String>,
-    Json(payload): Json<UpdateVisaDetailsRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    println!("🔥 update_visa_details_by_id function called for customer_id: {}", customer_id);
-    let pool = get_db_pool();
-    let mut tx = pool.begin().await.map_err(|e| {
-        eprintln!("❌ Failed to begin transaction in update_visa_details_by_id: {}", e);
-        eprintln!("❌ Transaction error details: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    
-    let select_sql = "SELECT * FROM visa_db.h1bcustomer WHERE customer_id = $1";
-    
-    let current_row = sqlx::query(&select_sql)
-        .bind(&customer_id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|e| {
-            eprintln!("❌ Database error in update_visa_details_by_id select: {}", e);
-            eprintln!("❌ Select error details: {:?}", e);
-            eprintln!("❌ SQL Query: {}", select_sql);
-            eprintln!("❌ Customer ID: {}", customer_id);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    
-    let current = match current_row {
-        Some(row) => row,
-        None => {
-            return Ok(Json(serde_json::json!({
-                "status": 404,
-                "message": "Record not found in the database",
-                "customer_id": customer_id
-            })));
-        }
-    };
-    
-    let first_name = payload.first_name.unwrap_or_else(|| current.get("first_name"));
-    let last_name = payload.last_name.unwrap_or_else(|| current.get("last_name"));
-    let dob = payload.dob.unwrap_or_else(|| current.get("dob"));
-    let sex = payload.sex.unwrap_or_else(|| current.get("sex"));
-    let marital_status = payload.marital_status.unwrap_or_else(|| current.get("marital_status"));
-    let phone = payload.phone.unwrap_or_else(|| current.get("phone"));
-    let emergency_contact_name = payload.emergency_contact_name.unwrap_or_else(|| current.get("emergency_contact_name"));
-    let emergency_contact_phone = payload.emergency_contact_phone.unwrap_or_else(|| current.get("emergency_contact_phone"));
-    let employment_start_date = payload.employment_start_date.unwrap_or_else(|| current.get("employment_start_date"));
-    let street_name = payload.street_name.unwrap_or_else(|| current.get("street_name"));
-    let city = payload.city.unwrap_or_else(|| current.get("city"));
-    let state = payload.state.unwrap_or_else(|| current.get("state"));
-    let zip = payload.zip.unwrap_or_else(|| current.get("zip"));
-    let client_name = payload.client_name.unwrap_or_else(|| current.get("client_name"));
-    let client_street_name = payload.client_street_name.unwrap_or_else(|| current.get("client_street_name"));
-    let client_city = payload.client_city.unwrap_or_else(|| current.get("client_city"));
-    let client_state = payload.client_state.unwrap_or_else(|| current.get("client_state"));
-    let client_zip = payload.client_zip.unwrap_or_else(|| current.get("client_zip"));
-    let lca_title = payload.lca_title.unwrap_or_else(|| current.get("lca_title"));
-    let lca_salary = payload.lca_salary.unwrap_or_else(|| current.get("lca_salary"));
-    let lca_code = payload.lca_code.unwrap_or_else(|| current.get("lca_code"));
-    let receipt_number = payload.receipt_number.unwrap_or_else(|| current.get("receipt_number"));
-    let h1b_start_date = payload.h1b_start_date.unwrap_or_else(|| current.get("h1b_start_date"));
-    let h1b_end_date = payload.h1b_end_date.unwrap_or_else(|| current.get("h1b_end_date"));
-    let h1b_status = payload.h1b_status.unwrap_or_else(|| current.get("h1b_status"));
-    
-    let update_sql = "UPDATE visa_db.h1bcustomer SET
-            first_name = $2, last_name = $3, dob = $4, sex = $5::visa_db.sex_enum,
-            marital_status = $6::visa_db.marital_status_enum, phone = $7,
-            emergency_contact_name = $8, emergency_contact_phone = $9, employment_start_date = $10,
-            street_name = $11, city = $12, state = $13, zip = $14,
-            client_name = $15, client_street_name = $16, client_city = $17, client_state = $18, client_zip = $19,
-            lca_title = $20, lca_salary = $21, lca_code = $22, receipt_number = $23,
-            h1b_start_date = $24, h1b_end_date = $25, h1b_status = $26::visa_db.h1b_status_enum
-         WHERE customer_id = $1";
-    
-    let result = sqlx::query(&update_sql)
-        .bind(&customer_id)
-        .bind(&first_name)
-        .bind(&last_name)
-        .bind(&dob)
-        .bind(&sex)
-        .bind(&marital_status)
-        .bind(&phone)
-        .bind(&emergency_contact_name)
-        .bind(&emergency_contact_phone)
-        .bind(&employment_start_date)
-        .bind(&street_name)
-        .bind(&city)
-        .bind(&state)
-        .bind(&zip)
-        .bind(&client_name)
-        .bind(&client_street_name)
-        .bind(&client_city)
-        .bind(&client_state)
-        .bind(&client_zip)
-        .bind(&lca_title)
-        .bind(&lca_salary)
-        .bind(&lca_code)
-        .bind(&receipt_number)
-        .bind(&h1b_start_date)
-        .bind(&h1b_end_date)
-        .bind(&h1b_status)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            eprintln!("❌ Database error in update_visa_details_by_id: {}", e);
-            eprintln!("❌ Update error details: {:?}", e);
-            eprintln!("❌ SQL Query: {}", update_sql);
-            eprintln!("❌ Customer ID: {}", customer_id);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    
-    tx.commit().await.map_err(|e| {
-        eprintln!("❌ Failed to commit transaction in update_visa_details_by_id: {}", e);
-        eprintln!("❌ Commit error details: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    Ok(Json(serde_json::json!({
-        "message": "Visa details updated successfully",
-        "customer_id": customer_id,
-        "rows_affected": result.rows_affected()
-    })))
+#[utoipa::path(
+    get,
+    path = "/h1b_customer/all",
+    tag = "customers",
+    params(ReadParams),
+    responses(
+        (status = 200, description = "Every customer regardless of h1b status", body = [CreateCustomer]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_all_customers_no_filter(
+    State(state): State<AppState>,
+    caller: Caller,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<CreateCustomer>>, StatusCode> {
+    println!("🔥 get_all_customers_no_filter function called");
+    let deleted = deleted_scope(&params, &caller)?;
+    state.customers.list(CustomerFilter::All, deleted).await
+        .map(Json)
+        .map_err(|e| store_error("get_all_customers_no_filter", e))
 }
 
-pub async fn get_customer_by_id(
+/// Legacy shortcut for the `Approved -> Active` transition, kept for existing
+/// clients. New code should use `POST /customers/{id}/transitions`.
+#[utoipa::path(
+    patch,
+    path = "/h1b_customer/activate/{customer_id}",
+    tag = "customers",
+    params(
+        ("customer_id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    responses(
+        (status = 200, description = "Customer moved from Approved to Active; the body includes the `updated_record`. Returns a message instead if already active, not Approved, not found or deleted"),
+        (status = 409, description = "A request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn activate_customer_by_id(
+    State(state): State<AppState>,
+    caller: Caller,
     Path(customer_id): Path<String>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let raw_sql = format!("SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone, 
-        emergency_contact_name, emergency_contact_phone, employment_start_date,
-        street_name, city, state, zip,
-        client_name, client_street_name, client_city, client_state, client_zip,
-        lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status::text
-        FROM global_visa_mgmt.h1bcustomer WHERE customer_id::text = '{}' AND h1b_status = 'Active'", customer_id.replace("'", "''"));
-    
-    match pool.fetch_optional(raw_sql.as_str())
-        .await {
-        Ok(Some(row)) => {
-            let mut response = serde_json::Map::new();
-            response.insert("customer_id".to_string(), serde_json::json!(row.get::<uuid::Uuid, _>("customer_id")));
-            response.insert("email".to_string(), serde_json::json!(row.get::<String, _>("email")));
-            response.insert("first_name".to_string(), serde_json::json!(row.get::<String, _>("first_name")));
-            response.insert("last_name".to_string(), serde_json::json!(row.get::<String, _>("last_name")));
-            response.insert("dob".to_string(), serde_json::json!(row.get::<chrono::NaiveDate, _>("dob")));
-            response.insert("sex".to_string(), serde_json::json!(row.get::<String, _>("sex")));
-            response.insert("marital_status".to_string(), serde_json::json!(row.get::<String, _>("marital_status")));
-            response.insert("phone".to_string(), serde_json::json!(row.get::<String, _>("phone")));
-            response.insert("emergency_contact_name".to_string(), serde_json::json!(row.get::<String, _>("emergency_contact_name")));
-            response.insert("emergency_contact_phone".to_string(), serde_json::json!(row.get::<String, _>("emergency_contact_phone")));
-            response.insert("employment_start_date".to_string(), serde_json::json!(row.get::<chrono::NaiveDate, _>("employment_start_date")));
-            response.insert("street_name".to_string(), serde_json::json!(row.get::<String, _>("street_name")));
-            response.insert("city".to_string(), serde_json::json!(row.get::<String, _>("city")));
-            response.insert("state".to_string(), serde_json::json!(row.get::<String, _>("state")));
-            response.insert("zip".to_string(), serde_json::json!(row.get::<String, _>("zip")));
-            response.insert("client_name".to_string(), serde_json::json!(row.get::<String, _>("client_name")));
-            response.insert("client_street_name".to_string(), serde_json::json!(row.get::<String, _>("client_street_name")));
-            response.insert("client_city".to_string(), serde_json::json!(row.get::<String, _>("client_city")));
-            response.insert("client_state".to_string(), serde_json::json!(row.get::<String, _>("client_state")));
-            response.insert("client_zip".to_string(), serde_json::json!(row.get::<String, _>("client_zip")));
-            response.insert("lca_title".to_string(), serde_json::json!(row.get::<String, _>("lca_title")));
-            response.insert("lca_salary".to_string(), serde_json::json!(row.get::<rust_decimal::Decimal, _>("lca_salary")));
-            response.insert("lca_code".to_string(), serde_json::json!(row.get::<String, _>("lca_code")));
-            response.insert("receipt_number".to_string(), serde_json::json!(row.get::<String, _>("receipt_number")));
-            response.insert("h1b_start_date".to_string(), serde_json::json!(row.get::<chrono::NaiveDate, _>("h1b_start_date")));
-            response.insert("h1b_end_date".to_string(), serde_json::json!(row.get::<chrono::NaiveDate, _>("h1b_end_date")));
-            response.insert("login_email".to_string(), serde_json::json!(row.get::<String, _>("login_email")));
-            response.insert("h1b_status".to_string(), serde_json::json!(row.get::<String, _>("h1b_status")));
-            Ok(Json(serde_json::Value::Object(response)))
+    println!("🔥 activate_customer_by_id function called for customer_id: {}", customer_id);
+    let not_found = || Json(serde_json::json!({
+        "status": 404,
+        "message": "Record not found in the database",
+        "customer_id": customer_id
+    }));
+    let Some(id) = parse_customer_id(&customer_id) else {
+        return Ok(not_found());
+    };
+
+    let request = TransitionRequest {
+        to_status: H1bStatus::Active,
+        reason: "Activated via /h1b_customer/activate".to_string(),
+        effective_date: chrono::Utc::now().date_naive(),
+        receipt_number: None,
+    };
+    match state.customers.transition(id, &request, &caller.name).await {
+        Ok(TransitionOutcome::Applied(customer, _)) => {
+            Ok(Json(serde_json::json!({
+                "message": "Customer activated successfully",
+                "customer_id": customer_id,
+                "rows_affected": 1,
+                "updated_record": customer
+            })))
         },
-        Ok(None) => {
+        Ok(TransitionOutcome::NotAllowed { from: H1bStatus::Active }) => {
             Ok(Json(serde_json::json!({
-                "message": "Data not found"
+                "message": "This customer is already active."
             })))
         },
+        Ok(TransitionOutcome::NotAllowed { from }) => {
+            Ok(Json(serde_json::json!({
+                "status": 409,
+                "message": format!("A customer in status {} can't be activated.", from),
+                "customer_id": customer_id,
+                "allowed_next": from.allowed_next()
+            })))
+        },
+        Ok(TransitionOutcome::NotFound) => Ok(not_found()),
         Err(e) => {
-            eprintln!("❌ Database error in get_customer_by_id: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
             eprintln!("❌ Customer ID: {}", customer_id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(store_error("activate_customer_by_id", e))
         }
     }
 }
 
-pub async fn get_customer_by_email(
-    Path(email): Path<String>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let raw_sql = format!("SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone, 
-        emergency_contact_name, emergency_contact_phone, employment_start_date,
-        street_name, city, state, zip,
-        client_name, client_street_name, client_city, client_state, client_zip,
-        lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status::text
-        FROM global_visa_mgmt.h1bcustomer WHERE (email = '{}' OR login_email = '{}')", email.replace("'", "''"), email.replace("'", "''"));
-    
-    match pool.fetch_all(raw_sql.as_str())
-        .await {
-        Ok(rows) => {
-            if rows.is_empty() {
-                Ok(Json(vec![serde_json::json!({
-                    "message": "Data not found"
-                })]))
-            } else {
-                let customers: Vec<serde_json::Value> = rows.into_iter().map(|row| {
-                    serde_json::json!({
-                        "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-                        "email": row.get::<String, _>("email"),
-                        "first_name": row.get::<String, _>("first_name"),
-                        "last_name": row.get::<String, _>("last_name"),
-                        "dob": row.get::<chrono::NaiveDate, _>("dob"),
-                        "sex": row.get::<String, _>("sex"),
-                        "marital_status": row.get::<String, _>("marital_status"),
-                        "phone": row.get::<String, _>("phone"),
-                        "emergency_contact_name": row.get::<String, _>("emergency_contact_name"),
-                        "emergency_contact_phone": row.get::<String, _>("emergency_contact_phone"),
-                        "employment_start_date": row.get::<chrono::NaiveDate, _>("employment_start_date"),
-                        "street_name": row.get::<String, _>("street_name"),
-                        "city": row.get::<String, _>("city"),
-                        "state": row.get::<String, _>("state"),
-                        "zip": row.get::<String, _>("zip"),
-                        "client_name": row.get::<String, _>("client_name"),
-                        "client_street_name": row.get::<String, _>("client_street_name"),
-                        "client_city": row.get::<String, _>("client_city"),
-                        "client_state": row.get::<String, _>("client_state"),
-                        "client_zip": row.get::<String, _>("client_zip"),
-                        "lca_title": row.get::<String, _>("lca_title"),
-                        "lca_salary": row.get::<rust_decimal::Decimal, _>("lca_salary"),
-                        "lca_code": row.get::<String, _>("lca_code"),
-                        "receipt_number": row.get::<String, _>("receipt_number"),
-                        "h1b_start_date": row.get::<chrono::NaiveDate, _>("h1b_start_date"),
-                        "h1b_end_date": row.get::<chrono::NaiveDate, _>("h1b_end_date"),
-                        "login_email": row.get::<String, _>("login_email"),
-                        "h1b_status": row.get::<String, _>("h1b_status")
-                    })
-                }).collect();
-                Ok(Json(customers))
-            }
+/// Current status, the states the UI may offer next, and the change history.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/transitions",
+    tag = "customers",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Status, allowed next states and history", body = CustomerTransitions),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_transitions(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<CustomerTransitions>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let customer = state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_transitions", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let history = state.customers.transitions(id).await
+        .map_err(|e| store_error("get_customer_transitions", e))?;
+
+    // Deleted customers can't transition until restored.
+    let allowed_next = if customer.deleted_at.is_some() {
+        Vec::new()
+    } else {
+        allowed_next(&customer.h1b_status)
+    };
+    Ok(Json(CustomerTransitions {
+        customer_id: id,
+        h1b_status: customer.h1b_status,
+        allowed_next,
+        history,
+    }))
+}
+
+/// Moves a customer to another status if the lifecycle allows it. Moving to
+/// `Filed` needs a receipt number, already on the customer or sent along.
+#[utoipa::path(
+    post,
+    path = "/customers/{id}/transitions",
+    tag = "customers",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body = TransitionRequest,
+    responses(
+        (status = 200, description = "Status changed and the transition recorded", body = TransitionResponse),
+        (status = 400, description = "Empty `reason`; moving to `Filed` without a receipt number on the customer or in `receipt_number`; `receipt_number` with any other `to_status`; or a malformed receipt number"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 409, description = "The current status can't move to `to_status` (see `GET /customers/{id}/transitions`), another live customer holds `receipt_number`, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Unknown `to_status`, malformed body, or Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn transition_customer(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Json(mut request): Json<TransitionRequest>,
+) -> Result<Json<TransitionResponse>, StatusCode> {
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    request.reason = request.reason.trim().to_string();
+    if request.reason.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match state.customers.transition(id, &request, &caller.name).await {
+        Ok(TransitionOutcome::Applied(customer, transition)) => Ok(Json(TransitionResponse {
+            allowed_next: request.to_status.allowed_next().to_vec(),
+            customer: *customer,
+            transition: *transition,
+        })),
+        Ok(TransitionOutcome::NotAllowed { from }) => {
+            eprintln!("❌ Transition {} -> {} not allowed for {}", from, request.to_status, customer_id);
+            Err(StatusCode::CONFLICT)
         },
-        Err(e) => {
-            eprintln!("❌ Database error in get_customer_by_email: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Email: {}", email);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Ok(TransitionOutcome::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("transition_customer", e)),
     }
 }
 
-pub async fn soft_delete_customer_by_id(
+/// Next states for a stored `h1b_status` label.
+fn allowed_next(h1b_status: &str) -> Vec<H1bStatus> {
+    h1b_status.parse::<H1bStatus>().map(|status| status.allowed_next().to_vec()).unwrap_or_default()
+}
+
+#[utoipa::path(
+    patch,
+    path = "/customers/{id}/restore",
+    tag = "customers",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    responses(
+        (status = 200, description = "Deletion cleared; the body includes the `restored_record`. Returns a message instead if the customer isn't deleted or doesn't exist"),
+        (status = 409, description = "Another live customer now holds this receipt number or email, the customer was merged into another, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn restore_customer(
+    State(state): State<AppState>,
     Path(customer_id): Path<String>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    println!("🔥 soft_delete_customer_by_id function called for customer_id: {}", customer_id);
-    let pool = get_db_pool();
-
-    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
-    let check_sql = format!("SELECT h1b_status::text FROM global_visa_mgmt.h1bcustomer WHERE customer_id = '{}'::uuid -- {}", customer_id.replace("'", "''"), timestamp);
-    
-    match pool.fetch_optional(check_sql.as_str()).await {
-        Ok(Some(row)) => {
-            let current_status: String = row.get("h1b_status");
-            if current_status == "Inactive" {
-                return Ok(Json(serde_json::json!({
-                    "message": "Data not found"
-                })));
-            }
+    let Some(id) = parse_customer_id(&customer_id) else {
+        return Ok(Json(serde_json::json!({
+            "message": "Data not found"
+        })));
+    };
+
+    match state.customers.restore(id).await {
+        Ok(RestoreOutcome::Restored(customer)) => {
+            Ok(Json(serde_json::json!({
+                "message": "Customer restored successfully",
+                "customer_id": customer_id,
+                "rows_affected": 1,
+                "restored_record": customer
+            })))
         },
-        Ok(None) => {
-            return Ok(Json(serde_json::json!({
+        Ok(RestoreOutcome::NotDeleted) => {
+            Ok(Json(serde_json::json!({
+                "message": "This customer is not deleted."
+            })))
+        },
+        Ok(RestoreOutcome::NotFound) => {
+            Ok(Json(serde_json::json!({
                 "message": "Data not found"
-            })));
+            })))
         },
         Err(e) => {
-            eprintln!("❌ Database error checking status: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            eprintln!("❌ Customer ID: {}", customer_id);
+            Err(store_error("restore_customer", e))
         }
     }
+}
 
-    let raw_sql = format!("UPDATE global_visa_mgmt.h1bcustomer SET h1b_status = 'Inactive' WHERE customer_id = '{}'::uuid -- {}", customer_id.replace("'", "''"), timestamp);
-
-    match pool.execute(raw_sql.as_str()).await 
-    {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                Ok(Json(serde_json::json!({
-                    "status": 404,
-                    "message": "Record not found in the database",
-                    "customer_id": customer_id
-                })))
-            } else {
-                Ok(Json(serde_json::json!({
-                    "message": "Customer soft deleted successfully",
-                    "customer_id": customer_id,
-                    "rows_affected": result.rows_affected()
-                })))
-            }
-        },
-        Err(e) => {
-            eprintln!("❌ Database error in soft_delete_customer_by_id: {}", e);
-            eprintln!("❌ Soft delete error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", customer_id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+/// Pairs of live customers that look like the same person, best first.
+#[utoipa::path(
+    get,
+    path = "/customers/duplicates",
+    tag = "customers",
+    params(DuplicateParams),
+    responses(
+        (status = 200, description = "Likely duplicates with their score and the evidence", body = [DuplicatePair]),
+        (status = 400, description = "`min_score` outside 0 to 1"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_duplicate_customers(
+    State(state): State<AppState>,
+    Query(params): Query<DuplicateParams>,
+) -> Result<Json<Vec<DuplicatePair>>, StatusCode> {
+    let min_score = min_score(&params)?;
+    let customers = state.customers.list(CustomerFilter::All, Deleted::Exclude).await
+        .map_err(|e| store_error("get_duplicate_customers", e))?;
+    Ok(Json(crate::duplicates::find_pairs(&customers, min_score)))
+}
+
+/// Live customers that look like the same person as this one, best first.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/duplicates",
+    tag = "customers",
+    params(("id" = String, Path, description = "Customer UUID"), DuplicateParams),
+    responses(
+        (status = 200, description = "Likely duplicates; the customer itself comes first in each pair", body = [DuplicatePair]),
+        (status = 400, description = "`min_score` outside 0 to 1"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_duplicates(
+    State(state): State<AppState>,
+    Path(customer_id): Path<String>,
+    Query(params): Query<DuplicateParams>,
+) -> Result<Json<Vec<DuplicatePair>>, StatusCode> {
+    let min_score = min_score(&params)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let customer = state.customers.get_by_id(id, Deleted::Exclude).await
+        .map_err(|e| store_error("get_customer_duplicates", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let customers = state.customers.list(CustomerFilter::All, Deleted::Exclude).await
+        .map_err(|e| store_error("get_customer_duplicates", e))?;
+    Ok(Json(crate::duplicates::find_for(&customer, &customers, min_score)))
+}
+
+fn min_score(params: &DuplicateParams) -> Result<f64, StatusCode> {
+    match params.min_score {
+        None => Ok(crate::duplicates::DEFAULT_MIN_SCORE),
+        Some(score) if (0.0..=1.0).contains(&score) => Ok(score),
+        Some(_) => Err(StatusCode::BAD_REQUEST),
     }
 }
-pub async fn update_customer_by_id(
+
+/// Folds a duplicate into this customer: its status transitions, petitions,
+/// trips, compliance tasks, case status history and LCA documents move over, and it is soft-deleted with `merged_into` set.
+/// This customer's own details are left as they are. Admin only.
+#[utoipa::path(
+    post,
+    path = "/customers/{id}/merge",
+    tag = "customers",
+    params(
+        ("id" = String, Path, description = "UUID of the customer that stays"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body = MergeRequest,
+    responses(
+        (status = 200, description = "Duplicate merged", body = MergeResponse),
+        (status = 400, description = "`duplicate_id` is the customer itself"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Either customer doesn't exist, or the one that stays is deleted"),
+        (status = 409, description = "The duplicate was already merged, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Malformed body, or Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn merge_customers(
+    State(state): State<AppState>,
+    caller: Caller,
     Path(customer_id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    println!("🔥 update_customer_by_id function called for customer_id: {}", customer_id);
-    let pool = get_db_pool();
-
-    let raw_sql = format!("UPDATE global_visa_mgmt.h1bcustomer SET 
-        email = '{}', first_name = '{}', last_name = '{}', dob = '{}', 
-        sex = '{}'::global_visa_mgmt.sex_enum, marital_status = '{}'::global_visa_mgmt.marital_status_enum, 
-        phone = '{}', emergency_contact_name = '{}', emergency_contact_phone = '{}', 
-        employment_start_date = '{}', street_name = '{}', city = '{}', state = '{}', 
-        zip = '{}', client_name = '{}', client_street_name = '{}', client_city = '{}', 
-        client_state = '{}', client_zip = '{}', lca_title = '{}', lca_salary = {}, 
-        lca_code = '{}', receipt_number = '{}', h1b_start_date = '{}', h1b_end_date = '{}', login_email = '{}'
-        WHERE customer_id = '{}'::uuid",
-        payload["email"].as_str().unwrap_or("").replace("'", "''"),
-        payload["first_name"].as_str().unwrap_or("").replace("'", "''"),
-        payload["last_name"].as_str().unwrap_or("").replace("'", "''"),
-        payload["dob"].as_str().unwrap_or(""),
-        payload["sex"].as_str().unwrap_or(""),
-        payload["marital_status"].as_str().unwrap_or(""),
-        payload["phone"].as_str().unwrap_or("").replace("'", "''"),
-        payload["emergency_contact_name"].as_str().unwrap_or("").replace("'", "''"),
-        payload["emergency_contact_phone"].as_str().unwrap_or("").replace("'", "''"),
-        payload["employment_start_date"].as_str().unwrap_or(""),
-        payload["street_name"].as_str().unwrap_or("").replace("'", "''"),
-        payload["city"].as_str().unwrap_or("").replace("'", "''"),
-        payload["state"].as_str().unwrap_or("").replace("'", "''"),
-        payload["zip"].as_str().unwrap_or("").replace("'", "''"),
-        payload["client_name"].as_str().unwrap_or("").replace("'", "''"),
-        payload["client_street_name"].as_str().unwrap_or("").replace("'", "''"),
-        payload["client_city"].as_str().unwrap_or("").replace("'", "''"),
-        payload["client_state"].as_str().unwrap_or("").replace("'", "''"),
-        payload["client_zip"].as_str().unwrap_or("").replace("'", "''"),
-        payload["lca_title"].as_str().unwrap_or("").replace("'", "''"),
-        payload["lca_salary"].as_str().unwrap_or("0"),
-        payload["lca_code"].as_str().unwrap_or("").replace("'", "''"),
-        payload["receipt_number"].as_str().unwrap_or("").replace("'", "''"),
-        payload["h1b_start_date"].as_str().unwrap_or(""),
-        payload["h1b_end_date"].as_str().unwrap_or(""),
-        payload["login_email"].as_str().unwrap_or("").replace("'", "''"),
-        customer_id.replace("'", "''")
-    );
-
-    match pool.execute(raw_sql.as_str()).await {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                Ok(Json(serde_json::json!({
-                    "message": "Customer updated successfully",
-                    "customer_id": customer_id,
-                    "rows_affected": result.rows_affected()
-                })))
-            } else {
-                Ok(Json(serde_json::json!({
-                    "message": "Customer not found",
-                    "customer_id": customer_id
-                })))
-            }
-        },
-        Err(e) => {
-            eprintln!("❌ Database error in update_customer_by_id: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", customer_id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    Json(request): Json<MergeRequest>,
+) -> Result<Json<MergeResponse>, StatusCode> {
+    caller.require_admin()?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let reason = request.reason.as_deref().map(str::trim).filter(|reason| !reason.is_empty());
+
+    let (customer, moved) = match state.customers.merge(id, request.duplicate_id, &caller.name, reason).await {
+        Ok(MergeOutcome::Merged { customer, moved }) => (*customer, moved),
+        Ok(MergeOutcome::NotFound) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => return Err(store_error("merge_customers", e)),
+    };
+    Ok(Json(MergeResponse {
+        customer,
+        merged_customer_id: request.duplicate_id,
+        transitions_moved: moved.transitions,
+        petitions_moved: moved.petitions,
+        trips_moved: moved.trips,
+        compliance_tasks_moved: moved.compliance_tasks,
+        case_status_entries_moved: moved.case_status_entries,
+        lca_documents_moved: moved.lca_documents,
+        public_access_files_moved: moved.public_access_files,
+    }))
+}
+
+/// Earlier H-1B petitions, oldest first. The one on the customer record isn't repeated here.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/petitions",
+    tag = "max_stay",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Petitions", body = [Petition]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_petitions(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<Petition>>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_petitions", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.petitions(id).await
+        .map(Json)
+        .map_err(|e| store_error("get_customer_petitions", e))
+}
+
+/// Records an earlier petition, e.g. with a previous employer, so its validity
+/// counts towards the six-year limit.
+#[utoipa::path(
+    post,
+    path = "/customers/{id}/petitions",
+    tag = "max_stay",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body = PetitionRequest,
+    responses(
+        (status = 201, description = "Petition recorded", body = Petition),
+        (status = 400, description = "Malformed receipt number, or `valid_to` before `valid_from`"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 409, description = "Receipt number already used by a customer or petition, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Malformed body, or Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn add_customer_petition(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Json(request): Json<PetitionRequest>,
+) -> Result<(StatusCode, Json<Petition>), StatusCode> {
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    match state.customers.add_petition(id, &request, &caller.name).await {
+        Ok(Some(petition)) => Ok((StatusCode::CREATED, Json(petition))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("add_customer_petition", e)),
+    }
+}
+
+/// Trips outside the US recorded for a customer, oldest first.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/trips",
+    tag = "max_stay",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Trips, oldest first", body = [Trip]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_trips(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<Trip>>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_trips", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.trips(id).await
+        .map(Json)
+        .map_err(|e| store_error("get_customer_trips", e))
+}
+
+/// Records a trip outside the US. It must touch one of the customer's H-1B
+/// validity periods and not overlap another trip.
+#[utoipa::path(
+    post,
+    path = "/customers/{id}/trips",
+    tag = "max_stay",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body = TripRequest,
+    responses(
+        (status = 201, description = "Trip recorded", body = Trip),
+        (status = 400, description = "Malformed I-94 number, `returned_on` before `departed_on`, return details without `returned_on`, or the trip is outside the customer's H-1B validity"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 409, description = "Trip overlaps another or repeats its I-94 number, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Malformed body, or Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn add_customer_trip(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Json(request): Json<TripRequest>,
+) -> Result<(StatusCode, Json<Trip>), StatusCode> {
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let periods = validity_periods(&state, id).await
+        .map_err(|e| store_error("add_customer_trip", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if crate::travel::outside_validity(&periods, &request) {
+        return Err(store_error("add_customer_trip", StoreError::Invalid(crate::travel::outside_validity_reason(&request))));
+    }
+    match state.customers.add_trip(id, &request, &caller.name).await {
+        Ok(Some(trip)) => Ok((StatusCode::CREATED, Json(trip))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("add_customer_trip", e)),
+    }
+}
+
+/// Replaces a trip's details, e.g. to record the return of a trip entered
+/// while the customer was still abroad. Checked like a new trip.
+#[utoipa::path(
+    put,
+    path = "/customers/{id}/trips/{trip_id}",
+    tag = "max_stay",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("trip_id" = Uuid, Path, description = "Trip UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body = TripRequest,
+    responses(
+        (status = 200, description = "Trip updated", body = Trip),
+        (status = 400, description = "Malformed I-94 number, `returned_on` before `departed_on`, return details without `returned_on`, or the trip is outside the customer's H-1B validity"),
+        (status = 404, description = "No customer has this id, it is deleted, or it has no such trip"),
+        (status = 409, description = "Trip overlaps another or repeats its I-94 number, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Malformed body, or Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn update_customer_trip(
+    State(state): State<AppState>,
+    Path((customer_id, trip_id)): Path<(String, Uuid)>,
+    Json(request): Json<TripRequest>,
+) -> Result<Json<Trip>, StatusCode> {
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let periods = validity_periods(&state, id).await
+        .map_err(|e| store_error("update_customer_trip", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if crate::travel::outside_validity(&periods, &request) {
+        return Err(store_error("update_customer_trip", StoreError::Invalid(crate::travel::outside_validity_reason(&request))));
+    }
+    match state.customers.update_trip(id, trip_id, &request).await {
+        Ok(Some(trip)) => Ok(Json(trip)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("update_customer_trip", e)),
+    }
+}
+
+/// Records the trips in an I-94 travel history downloaded from CBP
+/// (i94.cbp.dhs.gov, "Get Travel History"), saved as CSV with `Date`, `Type`
+/// and `Location` columns plus, optionally, `Class of Admission` and
+/// `I-94 Number`. Trips already recorded, overlapping one, or outside the
+/// customer's H-1B validity are skipped with the reason, so the same history
+/// can be imported again after a new trip.
+#[utoipa::path(
+    post,
+    path = "/customers/{id}/trips/import",
+    tag = "max_stay",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body(content = String, content_type = "text/csv", description = "I-94 travel history"),
+    responses(
+        (status = 200, description = "Import finished", body = TripImport),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 409, description = "A request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Not a CSV, a `Date` or `Type` column is missing, or Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn import_customer_trips(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    body: String,
+) -> Result<Json<TripImport>, StatusCode> {
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let history = crate::travel::parse_i94_history(&body).map_err(|e| {
+        eprintln!("❌ I-94 travel history import failed: {}", e);
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+    let periods = validity_periods(&state, id).await
+        .map_err(|e| store_error("import_customer_trips", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let recorded = state.customers.trips(id).await
+        .map_err(|e| store_error("import_customer_trips", e))?;
+
+    let mut import = TripImport { imported: Vec::new(), skipped: history.skipped };
+    for (line, trip) in history.trips {
+        let skip = |reason: String| SkippedTrip { line, departed_on: Some(trip.departed_on), returned_on: trip.returned_on, reason };
+        if recorded.iter().any(|r| r.departed_on == trip.departed_on && r.returned_on == trip.returned_on) {
+            import.skipped.push(skip("already recorded".to_string()));
+            continue;
+        }
+        if crate::travel::outside_validity(&periods, &trip) {
+            import.skipped.push(skip(crate::travel::outside_validity_reason(&trip)));
+            continue;
+        }
+        match state.customers.add_trip(id, &trip, &caller.name).await {
+            Ok(Some(trip)) => import.imported.push(trip),
+            Ok(None) => return Err(StatusCode::NOT_FOUND),
+            Err(StoreError::Invalid(reason)) | Err(StoreError::Conflict(reason)) => import.skipped.push(skip(reason)),
+            Err(e) => return Err(store_error("import_customer_trips", e)),
         }
     }
+    import.skipped.sort_by_key(|skipped| skipped.line);
+    Ok(Json(import))
 }
-pub async fn get_all_customers_with_status() -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    println!("🔥 get_all_customers_with_status function called");
-    let pool = get_db_pool();
-    
-    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
-    let raw_sql = format!("SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone, 
-        emergency_contact_name, emergency_contact_phone, employment_start_date,
-        street_name, city, state, zip,
-        client_name, client_street_name, client_city, client_state, client_zip,
-        lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status::text
-        FROM global_visa_mgmt.h1bcustomer WHERE h1b_status = 'Active' -- {}", timestamp);
-    
-    let rows = pool.fetch_all(raw_sql.as_str())
+
+/// The validity periods trips are checked against; `None` if the customer
+/// doesn't exist or is deleted.
+async fn validity_periods(state: &AppState, customer_id: Uuid) -> Result<Option<Vec<ValidityPeriod>>, StoreError> {
+    let Some(customer) = state.customers.get_by_id(customer_id, Deleted::Exclude).await? else {
+        return Ok(None);
+    };
+    let petitions = state.customers.petitions(customer_id).await?;
+    Ok(Some(crate::max_stay::validity_periods(&customer, &petitions)))
+}
+
+/// Compliance tasks raised for a customer, e.g. by a worksite change: open
+/// ones first, each by due date.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/compliance_tasks",
+    tag = "compliance_tasks",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Tasks, open ones first", body = [ComplianceTask]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_compliance_tasks(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<ComplianceTask>>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_compliance_tasks", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.compliance_tasks(id).await
+        .map(Json)
+        .map_err(|e| store_error("get_customer_compliance_tasks", e))
+}
+
+/// Open compliance tasks of every live customer, soonest due first.
+#[utoipa::path(
+    get,
+    path = "/compliance/tasks",
+    tag = "compliance_tasks",
+    responses(
+        (status = 200, description = "Open tasks by due date", body = [ComplianceTask]),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_open_compliance_tasks(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ComplianceTask>>, StatusCode> {
+    state.customers.open_compliance_tasks().await
+        .map(Json)
+        .map_err(|e| store_error("get_open_compliance_tasks", e))
+}
+
+/// Marks a compliance task done, recording who did it.
+#[utoipa::path(
+    post,
+    path = "/compliance/tasks/{task_id}/complete",
+    tag = "compliance_tasks",
+    params(
+        ("task_id" = Uuid, Path, description = "Task UUID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    responses(
+        (status = 200, description = "Task completed", body = ComplianceTask),
+        (status = 404, description = "No task has this id"),
+        (status = 409, description = "The task is already done, or a request with the same Idempotency-Key is still in progress"),
+        (status = 422, description = "Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn complete_compliance_task(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<ComplianceTask>, StatusCode> {
+    match state.customers.complete_compliance_task(task_id, &caller.name).await {
+        Ok(Some(task)) => Ok(Json(task)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("complete_compliance_task", e)),
+    }
+}
+
+/// Documents on file for the customer's LCA public access file, oldest
+/// first, without their content.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/lca_documents",
+    tag = "public_access",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Documents", body = [LcaDocument]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_lca_documents(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<LcaDocument>>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_lca_documents", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    state.lca_documents.list(id).await
+        .map(Json)
+        .map_err(|e| store_error("get_customer_lca_documents", e))
+}
+
+/// Uploads a document for the customer's LCA public access file. The body is
+/// the file itself, a PDF or a JPEG scan, up to 20 MB.
+#[utoipa::path(
+    post,
+    path = "/customers/{id}/lca_documents",
+    tag = "public_access",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        LcaDocumentParams,
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a repeat with the same key and body replays the first response. Needs an admin token or client API key; anyone else gets 400")
+    ),
+    request_body(content = Vec<u8>, content_type = "application/pdf", description = "The document, as a PDF or `image/jpeg`"),
+    responses(
+        (status = 201, description = "Document stored", body = LcaDocument),
+        (status = 400, description = "Unknown `category`, or the body isn't a readable PDF or JPEG, or the PDF is encrypted"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 409, description = "A request with the same Idempotency-Key is still in progress"),
+        (status = 413, description = "Larger than 20 MB"),
+        (status = 422, description = "Idempotency-Key reused with a different request"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn upload_lca_document(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<LcaDocumentParams>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<LcaDocument>), StatusCode> {
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.get_by_id(id, Deleted::Exclude).await
+        .map_err(|e| store_error("upload_lca_document", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let document = crate::public_access::check_upload(body.to_vec(), params.category, params.filename.as_deref(), &caller.name)
+        .map_err(|e| store_error("upload_lca_document", StoreError::Invalid(e)))?;
+    state.lca_documents.add(id, &document).await
+        .map(|document| (StatusCode::CREATED, Json(document)))
+        .map_err(|e| store_error("upload_lca_document", e))
+}
+
+/// The document as uploaded.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/lca_documents/{document_id}",
+    tag = "public_access",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("document_id" = Uuid, Path, description = "Document UUID"),
+        ReadParams
+    ),
+    responses(
+        (status = 200, description = "The file", content_type = "application/pdf"),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, it is deleted, or it has no such document"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn download_lca_document(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path((customer_id, document_id)): Path<(String, Uuid)>,
+    Query(params): Query<ReadParams>,
+) -> Result<([(HeaderName, String); 2], Vec<u8>), StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("download_lca_document", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let (document, content) = state.lca_documents.get(id, document_id).await
+        .map_err(|e| store_error("download_lca_document", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, document.content_type),
+            (header::CONTENT_DISPOSITION, format!("inline; filename=\"{}\"", header_filename(&document.filename))),
+        ],
+        content,
+    ))
+}
+
+/// Removes a document from the customer's public access file. Admin only:
+/// the file has to be kept for a year after the LCA ends.
+#[utoipa::path(
+    delete,
+    path = "/customers/{id}/lca_documents/{document_id}",
+    tag = "public_access",
+    params(
+        ("id" = String, Path, description = "Customer UUID"),
+        ("document_id" = Uuid, Path, description = "Document UUID")
+    ),
+    responses(
+        (status = 204, description = "Document deleted"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "No customer has this id, or it has no such document"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn delete_lca_document(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path((customer_id, document_id)): Path<(String, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    caller.require_admin()?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    match state.lca_documents.delete(id, document_id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(store_error("delete_lca_document", e)),
+    }
+}
+
+/// Generates the customer's LCA public access file as one PDF: a cover memo
+/// from the LCA fields, a table of contents, generated wage rate and
+/// prevailing wage pages, and the documents on file. Required sections with
+/// nothing on file are listed in the memo and in `X-Missing-Sections`.
+/// Each generation is recorded; see `/customers/{id}/public_access_files`.
+#[utoipa::path(
+    post,
+    path = "/customers/{id}/public_access_file",
+    tag = "public_access",
+    params(("id" = String, Path, description = "Customer UUID"), PublicAccessFileParams),
+    responses(
+        (status = 200, description = "The public access file; `X-Public-Access-File-Id` is its generation record", content_type = "application/pdf"),
+        (status = 400, description = "Malformed `lca_case_number`"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error, or a stored document couldn't be added"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn generate_public_access_file(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<PublicAccessFileParams>,
+) -> Result<([(HeaderName, String); 4], Vec<u8>), StatusCode> {
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let lca_case_number = match params.lca_case_number.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        Some(number) => Some(crate::public_access::normalize_case_number(number)
+            .map_err(|e| store_error("generate_public_access_file", StoreError::Invalid(e)))?),
+        None => None,
+    };
+    let customer = state.customers.get_by_id(id, Deleted::Exclude).await
+        .map_err(|e| store_error("generate_public_access_file", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let wage_check = wage_check(&state, &customer).await
+        .map_err(|e| store_error("generate_public_access_file", e))?;
+    let wage_data = state.prevailing_wages.latest_import().await
+        .map_err(|e| store_error("generate_public_access_file", e))?;
+    let documents = state.lca_documents.contents(id).await
+        .map_err(|e| store_error("generate_public_access_file", e))?;
+
+    let contents = crate::public_access::Contents {
+        customer,
+        wage_check,
+        wage_data,
+        lca_case_number,
+        documents,
+        generated_by: caller.name.clone(),
+        generated_at: chrono::Utc::now(),
+    };
+    let (contents, generated) = tokio::task::spawn_blocking(move || {
+        let generated = crate::public_access::assemble(&contents);
+        (contents, generated)
+    })
     .await
-    .map_err(|e| {
-        eprintln!("❌ Database error in get_all_customers_with_status: {}", e);
-        eprintln!("❌ Error details: {:?}", e);
-        eprintln!("❌ SQL Query: {}", raw_sql);
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let generated = generated.map_err(|e| {
+        eprintln!("❌ Generating the public access file of {} failed: {}", id, e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let customers: Vec<serde_json::Value> = rows.into_iter().map(|row| {
-        serde_json::json!({
-            "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-            "email": row.get::<String, _>("email"),
-            "first_name": row.get::<String, _>("first_name"),
-            "last_name": row.get::<String, _>("last_name"),
-            "dob": row.get::<chrono::NaiveDate, _>("dob"),
-            "sex": row.get::<String, _>("sex"),
-            "marital_status": row.get::<String, _>("marital_status"),
-            "phone": row.get::<String, _>("phone"),
-            "emergency_contact_name": row.get::<String, _>("emergency_contact_name"),
-            "emergency_contact_phone": row.get::<String, _>("emergency_contact_phone"),
-            "employment_start_date": row.get::<chrono::NaiveDate, _>("employment_start_date"),
-            "street_name": row.get::<String, _>("street_name"),
-            "city": row.get::<String, _>("city"),
-            "state": row.get::<String, _>("state"),
-            "zip": row.get::<String, _>("zip"),
-            "client_name": row.get::<String, _>("client_name"),
-            "client_street_name": row.get::<String, _>("client_street_name"),
-            "client_city": row.get::<String, _>("client_city"),
-            "client_state": row.get::<String, _>("client_state"),
-            "client_zip": row.get::<String, _>("client_zip"),
-            "lca_title": row.get::<String, _>("lca_title"),
-            "lca_salary": row.get::<rust_decimal::Decimal, _>("lca_salary"),
-            "lca_code": row.get::<String, _>("lca_code"),
-            "receipt_number": row.get::<String, _>("receipt_number"),
-            "h1b_start_date": row.get::<chrono::NaiveDate, _>("h1b_start_date"),
-            "h1b_end_date": row.get::<chrono::NaiveDate, _>("h1b_end_date"),
-            "login_email": row.get::<String, _>("login_email"),
-            "h1b_status": row.get::<String, _>("h1b_status")
-        })
-    }).collect();
+    let customer = &contents.customer;
+    let file = PublicAccessFile {
+        file_id: Uuid::new_v4(),
+        customer_id: id,
+        lca_case_number: contents.lca_case_number.clone(),
+        lca_code: customer.lca_code.clone(),
+        lca_title: customer.lca_title.clone(),
+        lca_salary: customer.lca_salary,
+        lca_wage_level: customer.lca_wage_level.clone(),
+        worksite: crate::public_access::worksite(customer),
+        document_ids: generated.document_ids,
+        missing: generated.missing.iter().map(|category| category.as_str().to_string()).collect(),
+        page_count: generated.page_count as i32,
+        sha256: hex::encode(Sha256::digest(&generated.pdf)),
+        generated_by: caller.name.clone(),
+        generated_at: contents.generated_at,
+    };
+    state.lca_documents.record_generation(&file).await
+        .map_err(|e| store_error("generate_public_access_file", e))?;
 
-    Ok(Json(customers))
+    let filename = format!("public-access-file-{}-{}-{}.pdf", customer.first_name, customer.last_name, file.generated_at.format("%Y-%m-%d"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", header_filename(&filename))),
+            (PUBLIC_ACCESS_FILE_ID, file.file_id.to_string()),
+            (MISSING_SECTIONS, file.missing.join(",")),
+        ],
+        generated.pdf,
+    ))
 }
-pub async fn get_customer_by_login_email(
-    Path(login_email): Path<String>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    let pool = get_db_pool();
-    
-    let raw_sql = format!("SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone, 
-        emergency_contact_name, emergency_contact_phone, employment_start_date,
-        street_name, city, state, zip,
-        client_name, client_street_name, client_city, client_state, client_zip,
-        lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status::text
-        FROM global_visa_mgmt.h1bcustomer WHERE login_email = '{}' AND h1b_status = 'Active'", login_email.replace("'", "''"));
-    
-    match pool.fetch_all(raw_sql.as_str())
-        .await {
-        Ok(rows) => {
-            if rows.is_empty() {
-                Ok(Json(vec![serde_json::json!({
-                    "message": "Data not found"
-                })]))
-            } else {
-                let customers: Vec<serde_json::Value> = rows.into_iter().map(|row| {
-                    serde_json::json!({
-                        "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-                        "email": row.get::<String, _>("email"),
-                        "first_name": row.get::<String, _>("first_name"),
-                        "last_name": row.get::<String, _>("last_name"),
-                        "dob": row.get::<chrono::NaiveDate, _>("dob"),
-                        "sex": row.get::<String, _>("sex"),
-                        "marital_status": row.get::<String, _>("marital_status"),
-                        "phone": row.get::<String, _>("phone"),
-                        "emergency_contact_name": row.get::<String, _>("emergency_contact_name"),
-                        "emergency_contact_phone": row.get::<String, _>("emergency_contact_phone"),
-                        "employment_start_date": row.get::<chrono::NaiveDate, _>("employment_start_date"),
-                        "street_name": row.get::<String, _>("street_name"),
-                        "city": row.get::<String, _>("city"),
-                        "state": row.get::<String, _>("state"),
-                        "zip": row.get::<String, _>("zip"),
-                        "client_name": row.get::<String, _>("client_name"),
-                        "client_street_name": row.get::<String, _>("client_street_name"),
-                        "client_city": row.get::<String, _>("client_city"),
-                        "client_state": row.get::<String, _>("client_state"),
-                        "client_zip": row.get::<String, _>("client_zip"),
-                        "lca_title": row.get::<String, _>("lca_title"),
-                        "lca_salary": row.get::<rust_decimal::Decimal, _>("lca_salary"),
-                        "lca_code": row.get::<String, _>("lca_code"),
-                        "receipt_number": row.get::<String, _>("receipt_number"),
-                        "h1b_start_date": row.get::<chrono::NaiveDate, _>("h1b_start_date"),
-                        "h1b_end_date": row.get::<chrono::NaiveDate, _>("h1b_end_date"),
-                        "login_email": row.get::<String, _>("login_email"),
-                        "h1b_status": row.get::<String, _>("h1b_status")
-                    })
-                }).collect();
-                Ok(Json(customers))
-            }
-        },
-        Err(e) => {
-            eprintln!("❌ Database error in get_customer_by_login_email: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Login Email: {}", login_email);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+
+/// When the customer's public access file was generated, newest first, with
+/// the LCA details and documents each one was generated from.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/public_access_files",
+    tag = "public_access",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Generation records", body = [PublicAccessFile]),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_public_access_files(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<Vec<PublicAccessFile>>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_public_access_files", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    state.lca_documents.generations(id).await
+        .map(Json)
+        .map_err(|e| store_error("get_customer_public_access_files", e))
 }
-pub async fn get_all_customers_no_filter() -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    println!("🔥 get_all_customers_no_filter function called");
-    let pool = get_db_pool();
-    
-    let timestamp = get_timestamp();
-    let raw_sql = format!("SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone, 
-        emergency_contact_name, emergency_contact_phone, employment_start_date,
-        street_name, city, state, zip,
-        client_name, client_street_name, client_city, client_state, client_zip,
-        lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status::text
-        FROM global_visa_mgmt.h1bcustomer -- {}", timestamp);
-    
-    let rows = pool.fetch_all(raw_sql.as_str())
-    .await
-    .map_err(|e| {
-        eprintln!("❌ Database error in get_all_customers_no_filter: {}", e);
-        eprintln!("❌ Error details: {:?}", e);
-        eprintln!("❌ SQL Query: {}", raw_sql);
+
+/// `name` reduced to characters safe in a quoted `Content-Disposition` filename.
+fn header_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ' ') { c } else { '_' })
+        .collect()
+}
+
+/// A printable case summary of the customer: personal details, home address,
+/// client worksite, LCA, current petition, petition and status history,
+/// upcoming deadlines and documents on file. Every page has a footer with
+/// the generation time and, if asked for, a watermark.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/summary.pdf",
+    tag = "customers",
+    params(("id" = String, Path, description = "Customer UUID"), SummaryParams),
+    responses(
+        (status = 200, description = "The case summary", content_type = "application/pdf"),
+        (status = 400, description = "`watermark` longer than 40 characters"),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_summary_pdf(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<SummaryParams>,
+) -> Result<([(HeaderName, String); 2], Vec<u8>), StatusCode> {
+    let deleted = deleted_scope(&ReadParams { include_deleted: params.include_deleted }, &caller)?;
+    let watermark = params.watermark.as_deref().map(str::trim).filter(|w| !w.is_empty());
+    if watermark.is_some_and(|w| w.chars().count() > crate::summary::MAX_WATERMARK_LENGTH) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let customer = state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let generated_at = chrono::Utc::now();
+    let wage_check = wage_check(&state, &customer).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?;
+    let petitions = state.customers.petitions(id).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?;
+    let transitions = state.customers.transitions(id).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?;
+    let case_status = match customer.receipt_number.as_deref().map(crate::receipt::normalize) {
+        Some(Ok(receipt_number)) => state.case_status.history(&receipt_number).await
+            .map_err(|e| store_error("get_customer_summary_pdf", e))?
+            .pop(),
+        _ => None,
+    };
+    let max_stay = max_stay(&state, &customer, generated_at.date_naive()).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?;
+    let compliance_tasks = state.customers.compliance_tasks(id).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?;
+    let documents = state.lca_documents.list(id).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?;
+    let public_access_files = state.lca_documents.generations(id).await
+        .map_err(|e| store_error("get_customer_summary_pdf", e))?;
+
+    let summary = crate::summary::CaseSummary {
+        soc_title: state.soc.get(&customer.lca_code).map(|occupation| occupation.title.clone()),
+        customer,
+        wage_check,
+        petitions,
+        transitions,
+        case_status,
+        max_stay,
+        compliance_tasks,
+        documents,
+        public_access_files,
+        generated_by: caller.name.clone(),
+        generated_at,
+    };
+    let pdf = crate::summary::render(&summary, watermark).map_err(|e| {
+        eprintln!("❌ Rendering the case summary of {} failed: {}", id, e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let customers: Vec<serde_json::Value> = rows.into_iter().map(|row| {
-        serde_json::json!({
-            "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-            "email": row.get::<String, _>("email"),
-            "first_name": row.get::<String, _>("first_name"),
-            "last_name": row.get::<String, _>("last_name"),
-            "dob": row.get::<chrono::NaiveDate, _>("dob"),
-            "sex": row.get::<String, _>("sex"),
-            "marital_status": row.get::<String, _>("marital_status"),
-            "phone": row.get::<String, _>("phone"),
-            "emergency_contact_name": row.get::<String, _>("emergency_contact_name"),
-            "emergency_contact_phone": row.get::<String, _>("emergency_contact_phone"),
-            "employment_start_date": row.get::<chrono::NaiveDate, _>("employment_start_date"),
-            "street_name": row.get::<String, _>("street_name"),
-            "city": row.get::<String, _>("city"),
-            "state": row.get::<String, _>("state"),
-            "zip": row.get::<String, _>("zip"),
-            "client_name": row.get::<String, _>("client_name"),
-            "client_street_name": row.get::<String, _>("client_street_name"),
-            "client_city": row.get::<String, _>("client_city"),
-            "client_state": row.get::<String, _>("client_state"),
-            "client_zip": row.get::<String, _>("client_zip"),
-            "lca_title": row.get::<String, _>("lca_title"),
-            "lca_salary": row.get::<rust_decimal::Decimal, _>("lca_salary"),
-            "lca_code": row.get::<String, _>("lca_code"),
-            "receipt_number": row.get::<String, _>("receipt_number"),
-            "h1b_start_date": row.get::<chrono::NaiveDate, _>("h1b_start_date"),
-            "h1b_end_date": row.get::<chrono::NaiveDate, _>("h1b_end_date"),
-            "login_email": row.get::<String, _>("login_email"),
-            "h1b_status": row.get::<String, _>("h1b_status")
-        })
-    }).collect();
+    let customer = &summary.customer;
+    let filename = format!("case-summary-{}-{}-{}.pdf", customer.first_name, customer.last_name, generated_at.format("%Y-%m-%d"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("inline; filename=\"{}\"", header_filename(&filename))),
+        ],
+        pdf,
+    ))
+}
 
-    Ok(Json(customers))
+/// The customer's stored fields mapped onto Form I-129, the H Classification
+/// Supplement and the H-1B Data Collection Supplement, with the required
+/// fields that couldn't be filled and why.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/i129",
+    tag = "i129",
+    params(("id" = String, Path, description = "Customer UUID"), I129Params),
+    responses(
+        (status = 200, description = "Form fields in form order, and the missing ones", body = I129Export),
+        (status = 400, description = "Unknown `basis` or malformed `lca_case_number`"),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_i129(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<I129Params>,
+) -> Result<Json<I129Export>, StatusCode> {
+    i129_export(&state, &caller, &customer_id, params).await.map(Json)
 }
 
-pub async fn activate_customer_by_id(
+/// The same fields as XFDF, to import into the fillable I-129 PDF. Field
+/// names come from `i129.field_names_file`; blank fields are left out.
+/// `X-Missing-Fields` lists the keys of the missing ones.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/i129.xfdf",
+    tag = "i129",
+    params(("id" = String, Path, description = "Customer UUID"), I129Params),
+    responses(
+        (status = 200, description = "XFDF form data", content_type = "application/vnd.adobe.xfdf"),
+        (status = 400, description = "Unknown `basis` or malformed `lca_case_number`"),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_i129_xfdf(
+    State(state): State<AppState>,
+    caller: Caller,
     Path(customer_id): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    println!("🔥 activate_customer_by_id function called for customer_id: {}", customer_id);
-    let pool = get_db_pool();
-
-    let timestamp = get_timestamp();
-    let check_sql = format!("SELECT h1b_status::text FROM global_visa_mgmt.h1bcustomer WHERE customer_id = '{}'::uuid -- {}", customer_id.replace("'", "''"), timestamp);
-    
-    match pool.fetch_optional(check_sql.as_str()).await {
-        Ok(Some(row)) => {
-            let current_status: String = row.get("h1b_status");
-            if current_status == "Active" {
-                return Ok(Json(serde_json::json!({
-                    "message": "This customer is already active."
-                })));
-            }
-        },
-        Ok(None) => {
-            return Ok(Json(serde_json::json!({
-                "status": 404,
-                "message": "Record not found in the database",
-                "customer_id": customer_id
-            })));
-        },
-        Err(e) => {
-            eprintln!("❌ Database error checking status: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    Query(params): Query<I129Params>,
+) -> Result<([(HeaderName, String); 3], String), StatusCode> {
+    let export = i129_export(&state, &caller, &customer_id, params).await?;
+    let missing: Vec<&str> = export.missing.iter().map(|field| field.key.as_str()).collect();
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/vnd.adobe.xfdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"i129-{}.xfdf\"", export.customer_id)),
+            (MISSING_FIELDS, missing.join(",")),
+        ],
+        crate::i129::xfdf(&export, &state.i129_field_names),
+    ))
+}
+
+async fn i129_export(state: &AppState, caller: &Caller, customer_id: &str, params: I129Params) -> Result<I129Export, StatusCode> {
+    let deleted = deleted_scope(&ReadParams { include_deleted: params.include_deleted }, caller)?;
+    let id = parse_customer_id(customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let requested_case_number = match params.lca_case_number.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        Some(number) => Some(crate::public_access::normalize_case_number(number)
+            .map_err(|e| store_error("i129_export", StoreError::Invalid(e)))?),
+        None => None,
+    };
+    let customer = state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("i129_export", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let lca_case_number = match requested_case_number {
+        Some(number) => Some(number),
+        None => state.lca_documents.generations(id).await
+            .map_err(|e| store_error("i129_export", e))?
+            .into_iter()
+            .find_map(|file| file.lca_case_number),
+    };
+    let petitions = state.customers.petitions(id).await
+        .map_err(|e| store_error("i129_export", e))?;
+    let max_stay = max_stay(state, &customer, chrono::Utc::now().date_naive()).await
+        .map_err(|e| store_error("i129_export", e))?;
+
+    let input = crate::i129::Input {
+        customer: &customer,
+        basis: params.basis.unwrap_or_else(|| crate::i129::I129Basis::default_for(&customer)),
+        lca_case_number,
+        petitions: &petitions,
+        max_stay: &max_stay,
+    };
+    Ok(crate::i129::export(&input, &state.i129_field_names))
+}
+
+/// Days used of the six-year H-1B limit, days recaptured from trips abroad,
+/// days remaining, the projected max-out date and AC21 eligibility.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/max_stay",
+    tag = "max_stay",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Maximum stay as of today", body = MaxStay),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_max_stay(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<MaxStay>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let customer = state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_max_stay", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    max_stay(&state, &customer, chrono::Utc::now().date_naive()).await
+        .map(Json)
+        .map_err(|e| store_error("get_customer_max_stay", e))
+}
+
+/// Maximum stay for every live customer, soonest max-out first.
+#[utoipa::path(
+    get,
+    path = "/compliance/max_stay",
+    tag = "max_stay",
+    params(MaxStayReportParams),
+    responses(
+        (status = 200, description = "Customers by projected max-out date", body = MaxStayReport),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_max_stay_report(
+    State(state): State<AppState>,
+    Query(params): Query<MaxStayReportParams>,
+) -> Result<Json<MaxStayReport>, StatusCode> {
+    let today = chrono::Utc::now().date_naive();
+    let customers = state.customers.list(CustomerFilter::All, Deleted::Exclude).await
+        .map_err(|e| store_error("get_max_stay_report", e))?;
+    let ids: Vec<Uuid> = customers.iter().map(|c| c.customer_id).collect();
+    let mut petitions: HashMap<Uuid, Vec<Petition>> = HashMap::new();
+    for petition in state.customers.petitions_for(&ids).await.map_err(|e| store_error("get_max_stay_report", e))? {
+        petitions.entry(petition.customer_id).or_default().push(petition);
+    }
+    let mut trips: HashMap<Uuid, Vec<Trip>> = HashMap::new();
+    for trip in state.customers.trips_for(&ids).await.map_err(|e| store_error("get_max_stay_report", e))? {
+        trips.entry(trip.customer_id).or_default().push(trip);
+    }
+
+    let mut report = MaxStayReport { as_of: today, customers: Vec::new() };
+    for customer in &customers {
+        let max_stay = crate::max_stay::calculate(
+            customer,
+            petitions.get(&customer.customer_id).map_or(&[], Vec::as_slice),
+            trips.get(&customer.customer_id).map_or(&[], Vec::as_slice),
+            today,
+        );
+        if params.within_days.is_none_or(|days| max_stay.days_remaining <= days) {
+            report.customers.push(max_stay);
         }
     }
+    report.customers.sort_by_key(|max_stay| max_stay.projected_max_out_date);
+    Ok(Json(report))
+}
 
-    let raw_sql = format!("UPDATE global_visa_mgmt.h1bcustomer SET h1b_status = 'Active' WHERE customer_id = '{}'::uuid -- {}", customer_id.replace("'", "''"), timestamp);
-
-    match pool.execute(raw_sql.as_str()).await {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                Ok(Json(serde_json::json!({
-                    "status": 404,
-                    "message": "Record not found in the database",
-                    "customer_id": customer_id
-                })))
-            } else {
-                let select_sql = format!("SELECT customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone, 
-                    emergency_contact_name, emergency_contact_phone, employment_start_date,
-                    street_name, city, state, zip,
-                    client_name, client_street_name, client_city, client_state, client_zip,
-                    lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status::text
-                    FROM global_visa_mgmt.h1bcustomer WHERE customer_id = '{}'::uuid -- {}", customer_id.replace("'", "''"), timestamp);
-                
-                match pool.fetch_optional(select_sql.as_str()).await {
-                    Ok(Some(row)) => {
-                        Ok(Json(serde_json::json!({
-                            "message": "Customer activated successfully",
-                            "customer_id": customer_id,
-                            "rows_affected": result.rows_affected(),
-                            "updated_record": {
-                                "customer_id": row.get::<uuid::Uuid, _>("customer_id"),
-                                "email": row.get::<String, _>("email"),
-                                "first_name": row.get::<String, _>("first_name"),
-                                "last_name": row.get::<String, _>("last_name"),
-                                "dob": row.get::<chrono::NaiveDate, _>("dob"),
-                                "sex": row.get::<String, _>("sex"),
-                                "marital_status": row.get::<String, _>("marital_status"),
-                                "phone": row.get::<String, _>("phone"),
-                                "emergency_contact_name": row.get::<String, _>("emergency_contact_name"),
-                                "emergency_contact_phone": row.get::<String, _>("emergency_contact_phone"),
-                                "employment_start_date": row.get::<chrono::NaiveDate, _>("employment_start_date"),
-                                "street_name": row.get::<String, _>("street_name"),
-                                "city": row.get::<String, _>("city"),
-                                "state": row.get::<String, _>("state"),
-                                "zip": row.get::<String, _>("zip"),
-                                "client_name": row.get::<String, _>("client_name"),
-                                "client_street_name": row.get::<String, _>("client_street_name"),
-                                "client_city": row.get::<String, _>("client_city"),
-                                "client_state": row.get::<String, _>("client_state"),
-                                "client_zip": row.get::<String, _>("client_zip"),
-                                "lca_title": row.get::<String, _>("lca_title"),
-                                "lca_salary": row.get::<rust_decimal::Decimal, _>("lca_salary"),
-                                "lca_code": row.get::<String, _>("lca_code"),
-                                "receipt_number": row.get::<String, _>("receipt_number"),
-                                "h1b_start_date": row.get::<chrono::NaiveDate, _>("h1b_start_date"),
-                                "h1b_end_date": row.get::<chrono::NaiveDate, _>("h1b_end_date"),
-                                "login_email": row.get::<String, _>("login_email"),
-                                "h1b_status": row.get::<String, _>("h1b_status")
-                            }
-                        })))
-                    },
-                    Ok(None) => {
-                        Ok(Json(serde_json::json!({
-                            "message": "Customer activated successfully",
-                            "customer_id": customer_id,
-                            "rows_affected": result.rows_affected()
-                        })))
-                    },
-                    Err(e) => {
-                        eprintln!("❌ Database error fetching updated record: {}", e);
-                        Ok(Json(serde_json::json!({
-                            "message": "Customer activated successfully",
-                            "customer_id": customer_id,
-                            "rows_affected": result.rows_affected()
-                        })))
-                    }
-                }
-            }
-        },
-        Err(e) => {
-            eprintln!("❌ Database error in activate_customer_by_id: {}", e);
-            eprintln!("❌ Error details: {:?}", e);
-            eprintln!("❌ Customer ID: {}", customer_id);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+async fn max_stay(state: &AppState, customer: &CreateCustomer, today: chrono::NaiveDate) -> Result<MaxStay, StoreError> {
+    let petitions = state.customers.petitions(customer.customer_id).await?;
+    let trips = state.customers.trips(customer.customer_id).await?;
+    Ok(crate::max_stay::calculate(customer, &petitions, &trips, today))
+}
+
+/// Status timeline for a receipt as recorded by the case status tracker.
+#[utoipa::path(
+    get,
+    path = "/case_status/{receipt}",
+    tag = "case_status",
+    params(("receipt" = String, Path, description = "Receipt number; spaces, dashes and case are ignored")),
+    responses(
+        (status = 200, description = "Current status and history, oldest first", body = CaseStatusTimeline),
+        (status = 400, description = "Not a valid receipt number"),
+        (status = 404, description = "No status has been recorded for this receipt"),
+        (status = 500, description = "Database error")
+    )
+)]
+pub async fn get_case_status(
+    State(state): State<AppState>,
+    Path(receipt): Path<String>,
+) -> Result<Json<CaseStatusTimeline>, StatusCode> {
+    let receipt_number = crate::receipt::normalize(&receipt).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let history = state.case_status.history(&receipt_number).await
+        .map_err(|e| store_error("get_case_status", e))?;
+    if history.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(CaseStatusTimeline {
+        receipt_number,
+        current: history.last().cloned(),
+        history,
+    }))
+}
+
+/// Status changes across all receipts, newest first; the list paralegals review.
+#[utoipa::path(
+    get,
+    path = "/case_status/changes",
+    tag = "case_status",
+    params(CaseStatusChangesParams),
+    responses(
+        (status = 200, description = "Entries that replaced an earlier status", body = [CaseStatusEntry]),
+        (status = 500, description = "Database error")
+    )
+)]
+pub async fn get_case_status_changes(
+    State(state): State<AppState>,
+    Query(params): Query<CaseStatusChangesParams>,
+) -> Result<Json<Vec<CaseStatusEntry>>, StatusCode> {
+    let since = params.since.unwrap_or_else(|| chrono::Utc::now() - chrono::Duration::days(7));
+    state.case_status.changes_since(since).await
+        .map(Json)
+        .map_err(|e| store_error("get_case_status_changes", e))
+}
+
+/// Server-sent events, one `case_status_changed` event per status change as the
+/// tracker finds it. The data is a `CaseStatusEntry`.
+#[utoipa::path(
+    get,
+    path = "/case_status/events",
+    tag = "case_status",
+    responses(
+        (status = 200, description = "`text/event-stream` of `case_status_changed` events", content_type = "text/event-stream"),
+        (status = 404, description = "Case status tracking is not configured")
+    )
+)]
+pub async fn case_status_events(
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
+    let tracker = state.case_status_tracker.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    // A subscriber that lags past the buffer skips the missed events; `/case_status/changes` has them.
+    let events = BroadcastStream::new(tracker.subscribe())
+        .filter_map(|entry| entry.ok())
+        .map(|entry| Event::default().event("case_status_changed").json_data(entry));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Runs the tracker now instead of waiting for the next scheduled run. Admin only.
+#[utoipa::path(
+    post,
+    path = "/case_status/runs",
+    tag = "case_status",
+    responses(
+        (status = 200, description = "Run finished", body = CaseStatusRunSummary),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Case status tracking is not configured"),
+        (status = 409, description = "A run is already in progress"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn run_case_status_check(
+    State(state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<CaseStatusRunSummary>, StatusCode> {
+    caller.require_admin()?;
+    let tracker = state.case_status_tracker.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    match tracker.run_once().await {
+        Ok(Some(summary)) => Ok(Json(summary)),
+        Ok(None) => Err(StatusCode::CONFLICT),
+        Err(e) => Err(store_error("run_case_status_check", e)),
+    }
+}
+
+/// Aggregate figures over live customers, computed by the database. Admin only.
+#[utoipa::path(
+    get,
+    path = "/dashboard",
+    tag = "dashboard",
+    responses(
+        (status = 200, description = "Counts by status, upcoming expiries, new customers per month, top clients, salaries per occupation and customers per work state", body = Dashboard),
+        (status = 403, description = "Not an admin"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_dashboard(
+    State(state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<Dashboard>, StatusCode> {
+    caller.require_admin()?;
+    let mut dashboard = state.customers.dashboard(chrono::Utc::now().date_naive()).await
+        .map_err(|e| store_error("get_dashboard", e))?;
+    for salaries in &mut dashboard.salary_by_lca_code {
+        salaries.title = state.soc.get(&salaries.lca_code).map(|occupation| occupation.title.clone());
+    }
+    Ok(Json(dashboard))
+}
+
+/// Compares one customer's LCA salary with the prevailing wage for their worksite and level.
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/prevailing_wage",
+    tag = "prevailing_wage",
+    params(("id" = String, Path, description = "Customer UUID"), ReadParams),
+    responses(
+        (status = 200, description = "Wage levels for the worksite and occupation, and whether the salary meets the required one", body = LcaWageCheck),
+        (status = 403, description = "`include_deleted=true` without an admin token"),
+        (status = 404, description = "No customer has this id, or it is deleted"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_customer_prevailing_wage(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(customer_id): Path<String>,
+    Query(params): Query<ReadParams>,
+) -> Result<Json<LcaWageCheck>, StatusCode> {
+    let deleted = deleted_scope(&params, &caller)?;
+    let id = parse_customer_id(&customer_id).ok_or(StatusCode::NOT_FOUND)?;
+    let customer = state.customers.get_by_id(id, deleted).await
+        .map_err(|e| store_error("get_customer_prevailing_wage", e))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    wage_check(&state, &customer).await
+        .map(Json)
+        .map_err(|e| store_error("get_customer_prevailing_wage", e))
+}
+
+/// Prevailing wage check for every customer, with underpaid LCAs flagged.
+#[utoipa::path(
+    get,
+    path = "/compliance/prevailing_wage",
+    tag = "prevailing_wage",
+    params(LcaWageReportParams),
+    responses(
+        (status = 200, description = "Counts per status and the checked customers", body = LcaWageReport),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn get_prevailing_wage_report(
+    State(state): State<AppState>,
+    Query(params): Query<LcaWageReportParams>,
+) -> Result<Json<LcaWageReport>, StatusCode> {
+    let wage_data = state.prevailing_wages.latest_import().await
+        .map_err(|e| store_error("get_prevailing_wage_report", e))?;
+    let customers = state.customers.list(CustomerFilter::All, Deleted::Exclude).await
+        .map_err(|e| store_error("get_prevailing_wage_report", e))?;
+    let key = |customer: &CreateCustomer| {
+        crate::prevailing_wage::zip5(&customer.client_zip).map(|zip| (zip, crate::prevailing_wage::soc_key(&customer.lca_code)))
+    };
+    let mut keys: Vec<(String, String)> = customers.iter().filter_map(key).collect();
+    keys.sort();
+    keys.dedup();
+    let lookups = state.prevailing_wages.lookup_many(&keys).await
+        .map_err(|e| store_error("get_prevailing_wage_report", e))?;
+
+    let mut report = LcaWageReport { wage_data, compliant: 0, underpaid: 0, unknown: 0, customers: Vec::new() };
+    for customer in &customers {
+        let check = crate::prevailing_wage::check(customer, key(customer).and_then(|k| lookups.get(&k)));
+        match check.status {
+            LcaWageStatus::Compliant => report.compliant += 1,
+            LcaWageStatus::Underpaid => report.underpaid += 1,
+            LcaWageStatus::Unknown => report.unknown += 1,
         }
+        if params.status.is_none() || params.status == Some(check.status) {
+            report.customers.push(check);
+        }
+    }
+    Ok(Json(report))
+}
+
+/// Reloads the OFLC wage files from `prevailing_wage.data_dir`, replacing the
+/// current data. Admin only.
+#[utoipa::path(
+    post,
+    path = "/prevailing_wages/import",
+    tag = "prevailing_wage",
+    responses(
+        (status = 200, description = "Import finished", body = PrevailingWageImport),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "`prevailing_wage.data_dir` is not configured"),
+        (status = 422, description = "A wage file is missing or malformed; the previous data is kept"),
+        (status = 500, description = "Database error"),
+        (status = 503, description = "Database unavailable or circuit breaker open; see `Retry-After`")
+    )
+)]
+pub async fn import_prevailing_wages(
+    State(state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<PrevailingWageImport>, StatusCode> {
+    caller.require_admin()?;
+    let dir = state.config.prevailing_wage.data_dir.clone().ok_or(StatusCode::NOT_FOUND)?;
+
+    let data = tokio::task::spawn_blocking(move || crate::prevailing_wage::import::load_dir(&dir))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            eprintln!("❌ Prevailing wage import failed: {}", e);
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+    state.prevailing_wages.replace(&data, &caller.name).await
+        .map(Json)
+        .map_err(|e| store_error("import_prevailing_wages", e))
+}
+
+async fn wage_check(state: &AppState, customer: &CreateCustomer) -> Result<LcaWageCheck, StoreError> {
+    let lookup = match crate::prevailing_wage::zip5(&customer.client_zip) {
+        Some(zip) => Some(state.prevailing_wages.lookup(&zip, &crate::prevailing_wage::soc_key(&customer.lca_code)).await?),
+        None => None,
+    };
+    Ok(crate::prevailing_wage::check(customer, lookup.as_ref()))
+}
+
+/// SOC occupations for `lca_code` autocomplete.
+#[utoipa::path(
+    get,
+    path = "/soc/search",
+    tag = "soc",
+    params(SocSearchParams),
+    responses(
+        (status = 200, description = "Matching occupations, best first", body = [SocOccupation])
+    )
+)]
+pub async fn search_soc(
+    State(state): State<AppState>,
+    Query(params): Query<SocSearchParams>,
+) -> Json<Vec<SocOccupation>> {
+    let limit = params.limit.unwrap_or(10).clamp(1, 50);
+    Json(state.soc.search(&params.q, limit))
+}
+
+#[utoipa::path(
+    get,
+    path = "/soc/{code}",
+    tag = "soc",
+    params(("code" = String, Path, description = "SOC code, e.g. `15-1252` or `15-1252.00`")),
+    responses(
+        (status = 200, description = "Occupation", body = SocOccupation),
+        (status = 404, description = "Not in the SOC catalog")
+    )
+)]
+pub async fn get_soc_occupation(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<Json<SocOccupation>, StatusCode> {
+    state.soc.get(&code).cloned().map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    get,
+    path = "/zip_codes/{zip}",
+    tag = "addresses",
+    params(("zip" = String, Path, description = "ZIP or ZIP+4, e.g. `10001` or `10001-2062`")),
+    responses(
+        (status = 200, description = "City, state, county and metropolitan area of the ZIP", body = ZipCode),
+        (status = 400, description = "Not a ZIP code"),
+        (status = 404, description = "Not in the ZIP dataset")
+    )
+)]
+pub async fn get_zip_code(
+    State(state): State<AppState>,
+    Path(zip): Path<String>,
+) -> Result<Json<ZipCode>, StatusCode> {
+    let zip = normalize_zip(&zip).map_err(|_| StatusCode::BAD_REQUEST)?;
+    state.zips.get(&zip).cloned().map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// `include_deleted=true` is honoured for admins only.
+fn deleted_scope(params: &ReadParams, caller: &Caller) -> Result<Deleted, StatusCode> {
+    if params.include_deleted.unwrap_or(false) {
+        caller.require_admin()?;
+        Ok(Deleted::Include)
+    } else {
+        Ok(Deleted::Exclude)
     }
-}
\ No newline at end of file
+}
+
+/// Malformed ids can't match any customer, so callers treat them as not found.
+fn parse_customer_id(customer_id: &str) -> Option<Uuid> {
+    Uuid::parse_str(customer_id).ok()
+}
+
+/// Lookup endpoints answer an empty result with a single "Data not found" entry.
+fn customers_or_not_found(customers: Vec<CreateCustomer>) -> Vec<serde_json::Value> {
+    if customers.is_empty() {
+        vec![serde_json::json!({
+            "message": "Data not found"
+        })]
+    } else {
+        customers.into_iter().map(|customer| serde_json::json!(customer)).collect()
+    }
+}
+
+fn store_error(context: &str, e: StoreError) -> StatusCode {
+    eprintln!("❌ Store error in {}: {}", context, e);
+    match e {
+        StoreError::Invalid(_) => StatusCode::BAD_REQUEST,
+        StoreError::Conflict(_) => StatusCode::CONFLICT,
+        StoreError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        // `Retry-After` is added by the circuit breaker middleware.
+        StoreError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
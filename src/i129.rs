@@ -0,0 +1,560 @@
+//! Form I-129 export: the customer's stored fields mapped onto the I-129, the
+//! H Classification Supplement and the H-1B Data Collection Supplement, as
+//! JSON or as XFDF to merge into the fillable PDF.
+//!
+//! Fields are keyed by part and item (`part3.family_name`). The AcroForm
+//! field names of the fillable PDF change between form editions, so they come
+//! from a mapping file (`i129.field_names_file`); without one, the keys are
+//! used as field names. Required fields that can't be filled from what is
+//! stored are reported as missing, with the reason.
+
+use chrono::{Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+
+use crate::lifecycle::H1bStatus;
+use crate::models::{CreateCustomer, I129Export, I129Field, I129MissingField, MaxStay, Petition};
+
+const PART1: &str = "Part 1. Petitioner Information";
+const PART2: &str = "Part 2. Information About This Petition";
+const PART3: &str = "Part 3. Beneficiary Information";
+const PART5: &str = "Part 5. Basic Information About the Proposed Employment and Employer";
+const H_SUPPLEMENT: &str = "H Classification Supplement";
+const DATA_COLLECTION: &str = "H-1B Data Collection and Filing Fee Exemption Supplement";
+
+/// Part 2, Item 2: the basis for classification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum I129Basis {
+    NewEmployment,
+    /// Continuation of previously approved employment without change with the
+    /// same employer: an extension.
+    Continuation,
+    ChangeInEmployment,
+    NewConcurrentEmployment,
+    ChangeOfEmployer,
+    Amended,
+}
+
+impl I129Basis {
+    pub const ALL: [I129Basis; 6] = [
+        I129Basis::NewEmployment,
+        I129Basis::Continuation,
+        I129Basis::ChangeInEmployment,
+        I129Basis::NewConcurrentEmployment,
+        I129Basis::ChangeOfEmployer,
+        I129Basis::Amended,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            I129Basis::NewEmployment => "new_employment",
+            I129Basis::Continuation => "continuation",
+            I129Basis::ChangeInEmployment => "change_in_employment",
+            I129Basis::NewConcurrentEmployment => "new_concurrent_employment",
+            I129Basis::ChangeOfEmployer => "change_of_employer",
+            I129Basis::Amended => "amended",
+        }
+    }
+
+    /// As worded on the form.
+    fn label(self) -> &'static str {
+        match self {
+            I129Basis::NewEmployment => "New employment",
+            I129Basis::Continuation => "Continuation of previously approved employment without change with the same employer",
+            I129Basis::ChangeInEmployment => "Change in previously approved employment",
+            I129Basis::NewConcurrentEmployment => "New concurrent employment",
+            I129Basis::ChangeOfEmployer => "Change of employer",
+            I129Basis::Amended => "Amended petition",
+        }
+    }
+
+    /// An extension for customers whose petition is approved, new employment
+    /// for the rest.
+    pub fn default_for(customer: &CreateCustomer) -> Self {
+        match customer.h1b_status.parse::<H1bStatus>() {
+            Ok(H1bStatus::Approved | H1bStatus::Active) => I129Basis::Continuation,
+            _ => I129Basis::NewEmployment,
+        }
+    }
+}
+
+impl fmt::Display for I129Basis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for I129Basis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        I129Basis::ALL
+            .into_iter()
+            .find(|basis| basis.as_str() == s)
+            .ok_or_else(|| format!("unknown basis for classification `{}`", s))
+    }
+}
+
+#[derive(Debug)]
+pub struct FieldNamesError(String);
+
+impl fmt::Display for FieldNamesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid I-129 field names: {}", self.0)
+    }
+}
+
+impl std::error::Error for FieldNamesError {}
+
+/// A field of the fillable PDF.
+#[derive(Debug, Clone)]
+struct PdfField {
+    name: String,
+    /// Export value of a checkbox when checked.
+    on_value: Option<String>,
+}
+
+/// AcroForm field names by export key.
+#[derive(Debug, Clone)]
+pub struct FieldNames {
+    fields: HashMap<String, PdfField>,
+    /// Where the names came from, for logs.
+    pub source: String,
+}
+
+impl FieldNames {
+    /// Every field named by its key.
+    pub fn keys() -> Self {
+        Self { fields: HashMap::new(), source: "export keys".to_string() }
+    }
+
+    /// Reads a CSV with `field` and `pdf_field` columns, and optionally
+    /// `on_value` for checkboxes whose export value isn't `Yes`. Keys not in
+    /// the file keep their key as name.
+    pub fn from_file(path: &str) -> Result<Self, FieldNamesError> {
+        let file = std::fs::File::open(path).map_err(|e| FieldNamesError(format!("{}: {}", path, e)))?;
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(file);
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| FieldNamesError(e.to_string()))?
+            .iter()
+            .map(|h| h.trim_start_matches('\u{feff}').trim().to_ascii_lowercase())
+            .collect();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let key = column("field").ok_or_else(|| FieldNamesError("missing column `field`".to_string()))?;
+        let name = column("pdf_field").ok_or_else(|| FieldNamesError("missing column `pdf_field`".to_string()))?;
+        let on_value = column("on_value");
+
+        let mut fields = HashMap::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| FieldNamesError(e.to_string()))?;
+            let field = |i: usize| record.get(i).unwrap_or_default().trim().to_string();
+            if field(key).is_empty() || field(name).is_empty() {
+                continue;
+            }
+            let on_value = on_value.map(field).filter(|v| !v.is_empty());
+            fields.insert(field(key), PdfField { name: field(name), on_value });
+        }
+        Ok(Self { fields, source: path.to_string() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    fn name<'a>(&'a self, key: &'a str) -> &'a str {
+        self.fields.get(key).map_or(key, |field| &field.name)
+    }
+}
+
+/// What the export is built from, loaded by the caller.
+pub struct Input<'a> {
+    pub customer: &'a CreateCustomer,
+    pub basis: I129Basis,
+    pub lca_case_number: Option<String>,
+    /// Earlier petitions, oldest first.
+    pub petitions: &'a [Petition],
+    pub max_stay: &'a MaxStay,
+}
+
+/// Collects fields in form order.
+struct Form<'a> {
+    names: &'a FieldNames,
+    section: &'static str,
+    fields: Vec<I129Field>,
+    missing: Vec<I129MissingField>,
+}
+
+impl Form<'_> {
+    fn field(&mut self, key: &str, label: &str, value: Option<String>, source: Option<&str>) {
+        self.fields.push(I129Field {
+            key: key.to_string(),
+            pdf_field: self.names.name(key).to_string(),
+            section: self.section.to_string(),
+            label: label.to_string(),
+            value,
+            source: source.map(str::to_string),
+        });
+    }
+
+    /// A field the form can't go without; reported with `reason` if `value` is `None`.
+    fn required(&mut self, key: &str, label: &str, value: Option<String>, source: Option<&str>, reason: &str) {
+        if value.is_none() {
+            self.missing(key, label, reason);
+        }
+        self.field(key, label, value, source);
+    }
+
+    fn checkbox(&mut self, key: &str, label: &str, checked: bool, source: Option<&str>) {
+        self.field(key, label, checked.then(|| "Yes".to_string()), source);
+    }
+
+    fn missing(&mut self, key: &str, label: &str, reason: &str) {
+        self.missing.push(I129MissingField {
+            key: key.to_string(),
+            section: self.section.to_string(),
+            label: label.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+pub fn export(input: &Input, names: &FieldNames) -> I129Export {
+    let c = input.customer;
+    let basis = input.basis;
+    let in_us = basis != I129Basis::NewEmployment;
+    let mut form = Form { names, section: PART1, fields: Vec::new(), missing: Vec::new() };
+    let not_stored = "Not stored for customers";
+
+    form.missing("part1", "Petitioner information", "The petitioning employer isn't stored; fill in Part 1 by hand");
+
+    form.section = PART2;
+    form.field("part2.classification", "Requested nonimmigrant classification", Some("H-1B".to_string()), None);
+    for option in I129Basis::ALL {
+        form.checkbox(&format!("part2.basis.{}", option.as_str()), option.label(), option == basis, None);
+    }
+    if in_us {
+        let receipt = c.receipt_number.as_deref().map(str::trim).filter(|r| !r.is_empty()).map(str::to_string);
+        form.required(
+            "part2.receipt_number",
+            "Most recent petition or application receipt number",
+            receipt,
+            Some("receipt_number"),
+            "No receipt number recorded",
+        );
+    }
+    // Part 2, Item 4. For new employment it depends on whether the
+    // beneficiary is abroad or in the US in another status.
+    let action = match basis {
+        I129Basis::NewEmployment => None,
+        I129Basis::Amended => Some("amend_stay"),
+        _ => Some("extend_stay"),
+    };
+    for (option, label) in [
+        ("notify_office", "Notify the office in Part 4 so the beneficiary can obtain a visa or be admitted"),
+        ("change_status_and_extend", "Change the status and extend the stay of the beneficiary"),
+        ("extend_stay", "Extend the stay of the beneficiary, who now holds this status"),
+        ("amend_stay", "Amend the stay of the beneficiary, who now holds this status"),
+    ] {
+        form.checkbox(&format!("part2.requested_action.{}", option), label, action == Some(option), None);
+    }
+    if action.is_none() {
+        form.missing(
+            "part2.requested_action",
+            "Requested action",
+            "For new employment, choose consular notification or a change of status; where the beneficiary is isn't stored",
+        );
+    }
+    form.field("part2.total_workers", "Total number of workers in petition", Some("1".to_string()), None);
+
+    form.section = PART3;
+    form.required("part3.family_name", "Family name", non_empty(&c.last_name), Some("last_name"), "No last name recorded");
+    form.required("part3.given_name", "Given name", non_empty(&c.first_name), Some("first_name"), "No first name recorded");
+    form.field("part3.date_of_birth", "Date of birth", Some(date(c.dob)), Some("dob"));
+    let sex = c.sex.trim().to_ascii_lowercase();
+    form.checkbox("part3.gender.male", "Male", sex == "male", Some("sex"));
+    form.checkbox("part3.gender.female", "Female", sex == "female", Some("sex"));
+    if sex != "male" && sex != "female" {
+        form.missing("part3.gender", "Gender", "The form only offers male or female");
+    }
+    form.required("part3.country_of_birth", "Country of birth", None, None, not_stored);
+    form.required("part3.country_of_citizenship", "Country of citizenship or nationality", None, None, not_stored);
+    form.required("part3.passport_number", "Passport or travel document number", None, None, not_stored);
+    if in_us {
+        form.field("part3.current_status", "Current nonimmigrant status", Some("H-1B".to_string()), Some("h1b_status"));
+        form.field("part3.status_expires", "Date status expires", Some(date(c.h1b_end_date)), Some("h1b_end_date"));
+        form.field("part3.us_address.street", "Current residential U.S. address: street number and name", non_empty(&c.street_name), Some("street_name"));
+        form.field("part3.us_address.city", "Current residential U.S. address: city or town", non_empty(&c.city), Some("city"));
+        form.field("part3.us_address.state", "Current residential U.S. address: state", non_empty(&c.state), Some("state"));
+        form.field("part3.us_address.zip", "Current residential U.S. address: ZIP code", non_empty(&c.zip), Some("zip"));
+    }
+
+    form.section = PART5;
+    form.required("part5.job_title", "Job title", non_empty(&c.lca_title), Some("lca_title"), "No LCA job title recorded");
+    form.required(
+        "part5.lca_case_number",
+        "LCA or ETA case number",
+        input.lca_case_number.clone(),
+        None,
+        "No LCA case number recorded; pass `lca_case_number` or generate the public access file with one",
+    );
+    form.required("part5.worksite.street", "Address where the beneficiary will work: street number and name", non_empty(&c.client_street_name), Some("client_street_name"), "No client street recorded");
+    form.required("part5.worksite.city", "Address where the beneficiary will work: city or town", non_empty(&c.client_city), Some("client_city"), "No client city recorded");
+    form.required("part5.worksite.state", "Address where the beneficiary will work: state", non_empty(&c.client_state), Some("client_state"), "No client state recorded");
+    form.required("part5.worksite.zip", "Address where the beneficiary will work: ZIP code", non_empty(&c.client_zip), Some("client_zip"), "No client ZIP code recorded");
+    form.checkbox("part5.third_party_worksite.yes", "The beneficiary will work at a third-party worksite", true, Some("client_name"));
+    form.required("part5.third_party_name", "Name of the third party", non_empty(&c.client_name), Some("client_name"), "No client name recorded");
+    form.field("part5.wages", "Wages", Some(c.lca_salary.round_dp(2).to_string()), Some("lca_salary"));
+    form.field("part5.wages_per", "Wages per", Some("year".to_string()), None);
+    match employment_dates(input) {
+        Ok((from, to)) => {
+            form.field("part5.dates.from", "Dates of intended employment: from", Some(date(from)), Some(dates_source(basis).0));
+            form.field("part5.dates.to", "Dates of intended employment: to", Some(date(to)), Some(dates_source(basis).1));
+        }
+        Err(reason) => {
+            form.required("part5.dates.from", "Dates of intended employment: from", None, None, reason);
+            form.field("part5.dates.to", "Dates of intended employment: to", None, None);
+        }
+    }
+
+    form.section = H_SUPPLEMENT;
+    form.field("h_supplement.beneficiary_name", "Name of the beneficiary", Some(format!("{} {}", c.first_name, c.last_name)), Some("first_name, last_name"));
+    // Prior periods of stay in H or L status in the last seven years.
+    let since = input.max_stay.as_of.checked_sub_months(Months::new(84)).unwrap_or(NaiveDate::MIN);
+    let mut prior: Vec<(NaiveDate, NaiveDate, &str)> =
+        input.petitions.iter().filter(|p| p.valid_to >= since).map(|p| (p.valid_from, p.valid_to, "petitions")).collect();
+    if in_us {
+        prior.push((c.h1b_start_date, c.h1b_end_date.min(input.max_stay.as_of), "h1b_start_date, h1b_end_date"));
+    }
+    for (i, (from, to, source)) in prior.into_iter().enumerate() {
+        form.field(&format!("h_supplement.prior_stays.{}.from", i + 1), "Prior period of stay in H status: from", Some(date(from)), Some(source));
+        form.field(&format!("h_supplement.prior_stays.{}.to", i + 1), "Prior period of stay in H status: to", Some(date(to)), Some(source));
+    }
+
+    form.section = DATA_COLLECTION;
+    form.required("h1b_data.soc_code", "LCA occupational classification (SOC code)", non_empty(&c.lca_code), Some("lca_code"), "No SOC code recorded");
+    form.field("h1b_data.rate_of_pay", "Rate of pay per year", Some(c.lca_salary.round_dp(2).to_string()), Some("lca_salary"));
+    form.required("h1b_data.highest_education", "Beneficiary's highest level of education", None, None, not_stored);
+    form.required("h1b_data.field_of_study", "Major or primary field of study", None, None, not_stored);
+
+    I129Export {
+        customer_id: c.customer_id,
+        basis,
+        field_names: names.source.clone(),
+        fields: form.fields,
+        missing: form.missing,
+    }
+}
+
+/// Stored fields the employment dates come from.
+fn dates_source(basis: I129Basis) -> (&'static str, &'static str) {
+    match basis {
+        I129Basis::Continuation => ("h1b_end_date", "h1b_end_date"),
+        _ => ("h1b_start_date", "h1b_end_date"),
+    }
+}
+
+/// The validity dates stored, or for an extension, three years from the day
+/// after the current validity ends, up to the six-year limit unless AC21
+/// allows more.
+fn employment_dates(input: &Input) -> Result<(NaiveDate, NaiveDate), &'static str> {
+    let c = input.customer;
+    if input.basis != I129Basis::Continuation {
+        return Ok((c.h1b_start_date, c.h1b_end_date));
+    }
+    let from = c.h1b_end_date.succ_opt().ok_or("h1b_end_date is out of range")?;
+    let mut to = from.checked_add_months(Months::new(36)).and_then(|d| d.pred_opt()).ok_or("h1b_end_date is out of range")?;
+    let ac21 = &input.max_stay.ac21;
+    if !ac21.three_year_extensions && !ac21.one_year_extensions {
+        let max_out = input.max_stay.projected_max_out_date;
+        if from > max_out {
+            return Err("The six-year limit is reached and no AC21 extension is available");
+        }
+        to = to.min(max_out);
+    }
+    Ok((from, to))
+}
+
+/// As USCIS forms take dates.
+fn date(date: NaiveDate) -> String {
+    date.format("%m/%d/%Y").to_string()
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// The filled fields as XFDF, to import into the fillable PDF.
+pub fn xfdf(export: &I129Export, names: &FieldNames) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<xfdf xmlns=\"http://ns.adobe.com/xfdf/\" xml:space=\"preserve\">\n<fields>\n");
+    for field in &export.fields {
+        let Some(value) = &field.value else {
+            continue;
+        };
+        let value = match names.fields.get(&field.key).and_then(|f| f.on_value.as_ref()) {
+            Some(on_value) => on_value,
+            None => value,
+        };
+        out.push_str(&format!("<field name=\"{}\"><value>{}</value></field>\n", escape(&field.pdf_field), escape(value)));
+    }
+    out.push_str("</fields>\n</xfdf>\n");
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{customer, date as day, petition};
+
+    const TODAY: &str = "2025-01-01";
+
+    fn export_for(customer: &CreateCustomer, basis: I129Basis, petitions: &[Petition]) -> I129Export {
+        let max_stay = crate::max_stay::calculate(customer, petitions, &[], day(TODAY));
+        let input = Input { customer, basis, lca_case_number: Some("I-200-24001-123456".to_string()), petitions, max_stay: &max_stay };
+        export(&input, &FieldNames::keys())
+    }
+
+    fn value(export: &I129Export, key: &str) -> Option<String> {
+        export.fields.iter().find(|f| f.key == key).unwrap_or_else(|| panic!("no field {}", key)).value.clone()
+    }
+
+    fn missing(export: &I129Export) -> Vec<&str> {
+        export.missing.iter().map(|m| m.key.as_str()).collect()
+    }
+
+    #[test]
+    fn basis_defaults_to_an_extension_once_approved() {
+        let mut c = customer();
+        assert_eq!(I129Basis::default_for(&c), I129Basis::Continuation);
+        c.h1b_status = "Draft".to_string();
+        assert_eq!(I129Basis::default_for(&c), I129Basis::NewEmployment);
+        for basis in I129Basis::ALL {
+            assert_eq!(basis.as_str().parse::<I129Basis>(), Ok(basis));
+        }
+        assert!("extension".parse::<I129Basis>().is_err());
+    }
+
+    #[test]
+    fn extension_fills_the_receipt_and_the_next_three_years() {
+        let mut c = customer();
+        c.i140_approved_on = Some(day("2024-05-01"));
+        let export = export_for(&c, I129Basis::Continuation, &[]);
+        assert_eq!(value(&export, "part2.receipt_number").as_deref(), Some("EAC2312345678"));
+        assert_eq!(value(&export, "part2.basis.continuation").as_deref(), Some("Yes"));
+        assert_eq!(value(&export, "part2.basis.new_employment"), None);
+        assert_eq!(value(&export, "part2.requested_action.extend_stay").as_deref(), Some("Yes"));
+        assert_eq!(value(&export, "part3.family_name").as_deref(), Some("Lee"));
+        assert_eq!(value(&export, "part3.date_of_birth").as_deref(), Some("04/12/1990"));
+        assert_eq!(value(&export, "part3.gender.female").as_deref(), Some("Yes"));
+        assert_eq!(value(&export, "part5.wages").as_deref(), Some("150000.00"));
+        assert_eq!(value(&export, "part5.dates.from").as_deref(), Some("10/01/2026"));
+        assert_eq!(value(&export, "part5.dates.to").as_deref(), Some("09/30/2029"));
+        assert!(!missing(&export).contains(&"part2.receipt_number"));
+        assert!(missing(&export).contains(&"part1"));
+    }
+
+    #[test]
+    fn extension_ends_at_the_projected_max_out_date() {
+        let c = customer();
+        let export = export_for(&c, I129Basis::Continuation, &[]);
+        let max_out = crate::max_stay::calculate(&c, &[], &[], day(TODAY)).projected_max_out_date;
+        assert_eq!(max_out, day("2029-09-28"));
+        assert_eq!(value(&export, "part5.dates.to").as_deref(), Some("09/28/2029"));
+    }
+
+    #[test]
+    fn extension_stops_at_the_six_year_limit_without_ac21() {
+        let c = customer();
+        let petitions = [petition(c.customer_id, "2019-01-01", "2023-09-30")];
+        let export = export_for(&c, I129Basis::Continuation, &petitions);
+        assert!(missing(&export).contains(&"part5.dates.from"));
+        assert_eq!(value(&export, "part5.dates.to"), None);
+
+        let mut c = c;
+        c.i140_approved_on = Some(day("2024-05-01"));
+        let export = export_for(&c, I129Basis::Continuation, &petitions);
+        assert_eq!(value(&export, "part5.dates.to").as_deref(), Some("09/30/2029"));
+    }
+
+    #[test]
+    fn extension_without_a_receipt_reports_it_missing() {
+        let mut c = customer();
+        c.receipt_number = None;
+        let export = export_for(&c, I129Basis::Continuation, &[]);
+        assert!(missing(&export).contains(&"part2.receipt_number"));
+    }
+
+    #[test]
+    fn new_employment_leaves_out_the_current_stay() {
+        let mut c = customer();
+        c.h1b_status = "Draft".to_string();
+        let export = export_for(&c, I129Basis::NewEmployment, &[]);
+        assert!(export.fields.iter().all(|f| f.key != "part2.receipt_number" && f.key != "part3.current_status"));
+        assert!(missing(&export).contains(&"part2.requested_action"));
+        assert_eq!(value(&export, "part5.dates.from").as_deref(), Some("10/01/2023"));
+        assert!(export.fields.iter().all(|f| !f.key.starts_with("h_supplement.prior_stays")));
+    }
+
+    #[test]
+    fn prior_stays_cover_the_last_seven_years() {
+        let c = customer();
+        let petitions = [petition(c.customer_id, "2015-01-01", "2017-12-31"), petition(c.customer_id, "2019-01-01", "2021-12-31")];
+        let export = export_for(&c, I129Basis::Continuation, &petitions);
+        assert_eq!(value(&export, "h_supplement.prior_stays.1.from").as_deref(), Some("01/01/2019"));
+        // The current stay, up to today.
+        assert_eq!(value(&export, "h_supplement.prior_stays.2.to").as_deref(), Some("01/01/2025"));
+        assert!(export.fields.iter().all(|f| !f.key.starts_with("h_supplement.prior_stays.3")));
+    }
+
+    #[test]
+    fn gender_outside_the_form_is_reported() {
+        let mut c = customer();
+        c.sex = "OTHER".to_string();
+        let export = export_for(&c, I129Basis::Continuation, &[]);
+        assert!(missing(&export).contains(&"part3.gender"));
+        assert_eq!(value(&export, "part3.gender.female"), None);
+    }
+
+    #[test]
+    fn xfdf_uses_mapped_names_and_checkbox_values() {
+        let mut c = customer();
+        c.client_name = "Smith & \"Sons\"\u{7}".to_string();
+        let mut names = FieldNames::keys();
+        names.fields.insert(
+            "part2.basis.continuation".to_string(),
+            PdfField { name: "form1[0].CheckBox2b[0]".to_string(), on_value: Some("B".to_string()) },
+        );
+        let max_stay = crate::max_stay::calculate(&c, &[], &[], day(TODAY));
+        let input = Input { customer: &c, basis: I129Basis::Continuation, lca_case_number: None, petitions: &[], max_stay: &max_stay };
+        let export = export(&input, &names);
+        let xml = xfdf(&export, &names);
+        assert!(xml.contains("<field name=\"form1[0].CheckBox2b[0]\"><value>B</value></field>"));
+        assert!(xml.contains("<field name=\"part5.third_party_name\"><value>Smith &amp; &quot;Sons&quot;</value></field>"));
+        // Fields without a value are left out.
+        assert!(!xml.contains("part2.basis.new_employment"));
+        assert!(!xml.contains("part5.lca_case_number"));
+    }
+}
@@ -0,0 +1,28 @@
+pub mod address;
+pub mod app;
+pub mod auth;
+pub mod case_status;
+pub mod config;
+pub mod dashboard;
+pub mod duplicates;
+pub mod handlers;
+pub mod i129;
+pub mod lifecycle;
+pub mod max_stay;
+pub mod middleware;
+pub mod models;
+pub mod phone;
+pub mod openapi;
+pub mod pdf;
+pub mod prevailing_wage;
+pub mod public_access;
+pub mod receipt;
+pub mod soc;
+pub mod state;
+pub mod store;
+pub mod summary;
+pub mod travel;
+pub mod validation;
+pub mod worksite;
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+
+/// Where a customer's H-1B case stands. Stored as `h1b_status_enum`.
+///
+/// Changes after creation go through `CustomerStore::transition`, which only
+/// accepts the moves listed in `allowed_next`. A record can be created in any
+/// state, since imports often start mid-lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum H1bStatus {
+    /// Being prepared; nothing filed with USCIS yet.
+    Draft,
+    /// Petition filed, awaiting a decision.
+    Filed,
+    /// USCIS asked for more evidence.
+    #[serde(rename = "RFE")]
+    Rfe,
+    Approved,
+    /// The beneficiary is working in H-1B status.
+    Active,
+    /// Moved to another employer's petition.
+    Transferred,
+    Expired,
+    /// Employment ended before the validity period did.
+    Terminated,
+    Denied,
+    /// Withdrawn by the employer before or after approval.
+    Withdrawn,
+}
+
+impl H1bStatus {
+    pub const ALL: [H1bStatus; 10] = [
+        H1bStatus::Draft,
+        H1bStatus::Filed,
+        H1bStatus::Rfe,
+        H1bStatus::Approved,
+        H1bStatus::Active,
+        H1bStatus::Transferred,
+        H1bStatus::Expired,
+        H1bStatus::Terminated,
+        H1bStatus::Denied,
+        H1bStatus::Withdrawn,
+    ];
+
+    /// The enum label in the database and the API.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            H1bStatus::Draft => "Draft",
+            H1bStatus::Filed => "Filed",
+            H1bStatus::Rfe => "RFE",
+            H1bStatus::Approved => "Approved",
+            H1bStatus::Active => "Active",
+            H1bStatus::Transferred => "Transferred",
+            H1bStatus::Expired => "Expired",
+            H1bStatus::Terminated => "Terminated",
+            H1bStatus::Denied => "Denied",
+            H1bStatus::Withdrawn => "Withdrawn",
+        }
+    }
+
+    /// States reachable from this one. Terminal states return an empty slice.
+    pub fn allowed_next(self) -> &'static [H1bStatus] {
+        use H1bStatus::*;
+        match self {
+            Draft => &[Filed, Withdrawn],
+            Filed => &[Rfe, Approved, Denied, Withdrawn],
+            Rfe => &[Approved, Denied, Withdrawn],
+            Approved => &[Active, Withdrawn],
+            Active => &[Transferred, Expired, Terminated],
+            Transferred | Expired | Terminated | Denied | Withdrawn => &[],
+        }
+    }
+
+    pub fn can_transition_to(self, next: H1bStatus) -> bool {
+        self.allowed_next().contains(&next)
+    }
+
+    /// Moving here means a petition was filed, so the customer needs the
+    /// receipt number USCIS issued for it.
+    pub fn requires_receipt_number(self) -> bool {
+        self == H1bStatus::Filed
+    }
+
+    /// A petition has been filed by the time a customer is here, so a receipt
+    /// number can be on record. `Withdrawn` is left out: it can be reached
+    /// straight from `Draft`.
+    pub fn has_filed(self) -> bool {
+        !matches!(self, H1bStatus::Draft | H1bStatus::Withdrawn)
+    }
+
+    /// Filed with USCIS and waiting on a decision, so the receipt is worth polling.
+    pub fn is_pending_with_uscis(self) -> bool {
+        matches!(self, H1bStatus::Filed | H1bStatus::Rfe)
+    }
+
+    /// Approved and not yet ended, so `h1b_end_date` is a real expiry date.
+    pub fn is_approved(self) -> bool {
+        matches!(self, H1bStatus::Approved | H1bStatus::Active)
+    }
+
+    /// The petition was approved at some point, so `h1b_start_date` to
+    /// `h1b_end_date` was a validity period, even if it has since ended.
+    pub fn was_approved(self) -> bool {
+        matches!(
+            self,
+            H1bStatus::Approved | H1bStatus::Active | H1bStatus::Transferred | H1bStatus::Expired | H1bStatus::Terminated
+        )
+    }
+}
+
+impl fmt::Display for H1bStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for H1bStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        H1bStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| format!("invalid input value for h1b_status_enum: \"{}\"", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_round_trip_through_from_str_and_serde() {
+        for status in H1bStatus::ALL {
+            assert_eq!(status.as_str().parse::<H1bStatus>(), Ok(status));
+            assert_eq!(serde_json::to_string(&status).unwrap(), format!("\"{}\"", status.as_str()));
+        }
+        assert!("Inactive".parse::<H1bStatus>().is_err());
+        assert!("rfe".parse::<H1bStatus>().is_err());
+    }
+
+    #[test]
+    fn labels_match_the_database_enum() {
+        let migration = include_str!("../migrations/0004_h1b_status_lifecycle.sql");
+        for status in H1bStatus::ALL {
+            assert!(migration.contains(&format!("'{}'", status.as_str())), "{} missing from h1b_status_enum", status);
+        }
+    }
+
+    #[test]
+    fn follows_the_petition_lifecycle() {
+        use H1bStatus::*;
+        assert!(Draft.can_transition_to(Filed));
+        assert!(Filed.can_transition_to(Rfe));
+        assert!(Rfe.can_transition_to(Approved));
+        assert!(Approved.can_transition_to(Active));
+        assert!(Active.can_transition_to(Terminated));
+        assert!(!Draft.can_transition_to(Approved));
+        assert!(!Active.can_transition_to(Draft));
+        assert!(!Filed.can_transition_to(Filed));
+    }
+
+    #[test]
+    fn terminal_states_go_nowhere_and_nothing_returns_to_draft() {
+        use H1bStatus::*;
+        for status in [Transferred, Expired, Terminated, Denied, Withdrawn] {
+            assert!(status.allowed_next().is_empty(), "{} should be terminal", status);
+        }
+        assert!(H1bStatus::ALL.iter().all(|s| !s.can_transition_to(Draft)));
+    }
+
+    #[test]
+    fn only_filing_requires_a_receipt_number() {
+        let requiring: Vec<_> = H1bStatus::ALL.into_iter().filter(|s| s.requires_receipt_number()).collect();
+        assert_eq!(requiring, vec![H1bStatus::Filed]);
+    }
+}
@@ -1,60 +1,209 @@
-use axum::{
-    http::Method,
-    routing::{get, post, put, delete, patch},
-    Router,
+use visa_api::address::ZipDirectory;
+use visa_api::app::build_router;
+use visa_api::case_status::{provider_from_config, CaseStatusTracker};
+use visa_api::config::database::initialize_database;
+use visa_api::config::settings::{Config, StorageBackend};
+use visa_api::prevailing_wage::import::load_dir;
+use visa_api::soc::SocCatalog;
+use visa_api::i129::FieldNames;
+use visa_api::models::{CreateCompleteCustomerRequest, UpdateVisaDetailsRequest};
+use visa_api::phone::{self, Region};
+use visa_api::state::{AppState, ReferenceData, Stores};
+use visa_api::store::{
+    CircuitBreaker, CustomerFilter, CustomerStore, Deleted, IdempotencyStore, MemoryCaseStatusStore, MemoryCustomerStore,
+    MemoryIdempotencyStore, MemoryLcaDocumentStore, MemoryPrevailingWageStore, PgCaseStatusStore, PgCustomerStore,
+    PgIdempotencyStore, PgLcaDocumentStore, PgPrevailingWageStore, PrevailingWageStore, Resilience,
 };
-use tower_http::cors::{Any, CorsLayer};
+use visa_api::validation::Validator;
+use std::sync::Arc;
+use std::time::Duration;
 
-mod models;
-mod handlers;
-mod middleware;
-mod config;
-
-
-
-use handlers::*;
-use config::database::{initialize_database, get_db_pool};
+/// How often expired idempotency keys are deleted.
+const IDEMPOTENCY_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv::dotenv().ok();
-    run_local_server().await?;
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    run_local_server(config).await?;
     Ok(())
 }
 
-async fn run_local_server() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize database connection
-    initialize_database().await?;
-    let pool = get_db_pool();
-
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::PATCH, Method::OPTIONS])
-        .allow_origin(Any)
-        .allow_headers(vec![
-            axum::http::header::AUTHORIZATION,
-            axum::http::header::CONTENT_TYPE,
-        ]);
-
-    let app = Router::new()
-        // Health and Info Routes
-        .route("/health", get(health_check))
-        .route("/hello", get(test_connection))
-        
-        // New API structure as per README
-        .route("/h1b_customer/create", post(create_visa_details))
-        .route("/customers", get(get_all_customers_with_status))
-        .route("/get_customer_by_id/:id", get(get_customer_by_id))
-        .route("/get_customer_by_email/:email", get(get_customer_by_email))
-        .route("/soft_delete_customer_via_id/:id", patch(soft_delete_customer_by_id))
-        .route("/update_customer_by_id/:id", put(update_customer_by_id))
-        .route("/h1b_customer/by_login_email/:login_email", get(get_customer_by_login_email))
-        .route("/h1b_customer/all", get(get_all_customers_no_filter))
-        .route("/h1b_customer/activate/:customer_id", patch(activate_customer_by_id))
-        .layer(cors);
-
-    println!("Starting local server on http://localhost:3000");
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+async fn run_local_server(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let soc = match &config.soc.catalog_file {
+        Some(path) => SocCatalog::from_file(path)?,
+        None => SocCatalog::embedded(),
+    };
+    println!("📚 SOC catalog: {} occupations ({})", soc.len(), soc.source);
+    let zips = match &config.address.zip_file {
+        Some(path) => ZipDirectory::from_file(path)?,
+        None => ZipDirectory::embedded(),
+    };
+    println!("📚 ZIP dataset: {} ZIP codes ({})", zips.len(), zips.source);
+    if zips.is_embedded() {
+        eprintln!(
+            "⚠️  Only the embedded sample of {} ZIP codes is loaded: addresses outside it get no county or MSA, \
+             and worksite moves involving them are classified `unknown`. Set address.zip_file (ADDRESS_ZIP_FILE) \
+             to a complete dataset, and address.require_zip_file to refuse to start without one.",
+            zips.len()
+        );
+    }
+    let i129_field_names = match &config.i129.field_names_file {
+        Some(path) => FieldNames::from_file(path)?,
+        None => FieldNames::keys(),
+    };
+    println!("📚 I-129 field names: {} mapped ({})", i129_field_names.len(), i129_field_names.source);
+    let reference = ReferenceData { soc, zips, i129_field_names };
+
+    let idempotency_ttl = config.idempotency.ttl();
+    let (pool, stores) = match config.storage.backend {
+        StorageBackend::Postgres => {
+            // Initialize database connection
+            let pool = initialize_database(&config.database).await?;
+            let schema = &config.database.schema;
+            let timeout = config.database.statement_timeout();
+            let stores = Stores {
+                customers: Arc::new(PgCustomerStore::new(pool.clone(), schema.clone()).with_statement_timeout(timeout)),
+                idempotency: Arc::new(
+                    PgIdempotencyStore::new(pool.clone(), schema.clone(), idempotency_ttl).with_statement_timeout(timeout),
+                ),
+                case_status: Arc::new(PgCaseStatusStore::new(pool.clone(), schema.clone()).with_statement_timeout(timeout)),
+                prevailing_wages: Arc::new(PgPrevailingWageStore::new(pool.clone(), schema.clone()).with_statement_timeout(timeout)),
+                lca_documents: Arc::new(PgLcaDocumentStore::new(pool.clone(), schema.clone()).with_statement_timeout(timeout)),
+            };
+            (Some(pool), stores)
+        }
+        StorageBackend::Memory => {
+            let store = match &config.storage.seed_file {
+                Some(path) => {
+                    let validator = Validator {
+                        soc: &reference.soc,
+                        zips: &reference.zips,
+                        reject_unknown_soc_codes: config.soc.reject_unknown_codes,
+                        default_phone_region: config.phone.default_region,
+                    };
+                    MemoryCustomerStore::with_customers(&load_seed_file(path, &validator)?)?
+                }
+                None => MemoryCustomerStore::new(),
+            };
+            println!("⚠️  Using in-memory customer store; data is lost on restart");
+            let case_status = Arc::new(MemoryCaseStatusStore::new());
+            let lca_documents = Arc::new(MemoryLcaDocumentStore::new());
+            let stores = Stores {
+                customers: Arc::new(store.with_history_stores(case_status.clone(), lca_documents.clone())),
+                idempotency: Arc::new(MemoryIdempotencyStore::new(idempotency_ttl)),
+                case_status,
+                prevailing_wages: Arc::new(MemoryPrevailingWageStore::new()),
+                lca_documents,
+            };
+            (None, stores)
+        }
+    };
+
+    let breaker = Arc::new(CircuitBreaker::new(&config.database.circuit_breaker));
+    let stores = stores.resilient(Arc::new(Resilience::new(config.database.retry.clone(), breaker.clone())));
+
+    normalize_stored_phones(stores.customers.as_ref(), config.phone.default_region).await?;
+
+    if let Some(dir) = &config.prevailing_wage.data_dir {
+        if stores.prevailing_wages.latest_import().await?.is_none() {
+            import_prevailing_wages(stores.prevailing_wages.as_ref(), dir).await?;
+        }
+    }
+
+    let case_status_tracker = provider_from_config(&config.case_status)?.map(|provider| {
+        println!("🔎 Tracking USCIS case status via the {} provider every {:?}", provider.name(), config.case_status.interval());
+        let tracker = Arc::new(CaseStatusTracker::new(
+            provider,
+            stores.customers.clone(),
+            stores.case_status.clone(),
+            config.case_status.request_delay(),
+        ));
+        tokio::spawn(tracker.clone().run_forever(config.case_status.interval()));
+        tracker
+    });
+
+    let bind_addr = config.server.bind_addr.clone();
+    tokio::spawn(purge_idempotency_keys(stores.idempotency.clone()));
+    let app = build_router(AppState::new(pool, config, stores, breaker, case_status_tracker, reference));
+
+    println!("Starting local server on http://{}", bind_addr);
+    let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
     axum::serve(listener, app).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Reads a JSON array of create requests, normalized the same way as the create endpoint.
+fn load_seed_file(path: &str, validator: &Validator) -> Result<Vec<CreateCompleteCustomerRequest>, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut seed: Vec<CreateCompleteCustomerRequest> = serde_json::from_str(&contents)?;
+    for customer in &mut seed {
+        let warnings = validator.check_new(customer).map_err(|e| format!("{}: customer {}: {}", path, customer.email, e))?;
+        for warning in warnings {
+            println!("⚠️  {}: customer {}: {}: {}", path, customer.email, warning.field, warning.message);
+        }
+    }
+    Ok(seed)
+}
+
+/// Converts numbers saved before phone normalization to E.164. Only rows
+/// without a display form are read, so once every number is converted this
+/// is a single empty query. Numbers that don't parse are left as entered, to
+/// be fixed by hand, and are retried on the next start.
+async fn normalize_stored_phones(store: &dyn CustomerStore, region: Region) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut updated, mut unparseable) = (0, 0);
+    for customer in store.list(CustomerFilter::PhonesNotNormalized, Deleted::Exclude).await? {
+        let mut update = UpdateVisaDetailsRequest::default();
+        if customer.phone_display.is_none() {
+            match phone::normalize(&customer.phone, region, region) {
+                Ok(p) => (update.phone, update.phone_display) = (Some(p.e164), Some(p.display)),
+                Err(_) => unparseable += 1,
+            }
+        }
+        if customer.emergency_contact_phone_display.is_none() {
+            match phone::normalize(&customer.emergency_contact_phone, region, region) {
+                Ok(p) => (update.emergency_contact_phone, update.emergency_contact_phone_display) = (Some(p.e164), Some(p.display)),
+                Err(_) => unparseable += 1,
+            }
+        }
+        if update.phone.is_some() || update.emergency_contact_phone.is_some() {
+            store.update(customer.customer_id, &update, None).await?;
+            updated += 1;
+        }
+    }
+    if updated > 0 || unparseable > 0 {
+        println!("📞 Normalized phone numbers of {} customers; {} numbers couldn't be parsed and were left as entered", updated, unparseable);
+    }
+    Ok(())
+}
+
+/// First load of the wage data; later imports go through `POST /prevailing_wages/import`.
+async fn import_prevailing_wages(store: &dyn PrevailingWageStore, dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let data = tokio::task::spawn_blocking({
+        let dir = dir.to_string();
+        move || load_dir(&dir)
+    })
+    .await??;
+    let import = store.replace(&data, "startup").await?;
+    println!("💵 Imported prevailing wages from {}: {} wages, {} areas, {} ZIP codes", import.source, import.wages, import.areas, import.zips);
+    Ok(())
+}
+
+async fn purge_idempotency_keys(store: Arc<dyn IdempotencyStore>) {
+    let mut interval = tokio::time::interval(IDEMPOTENCY_PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match store.purge_expired().await {
+            Ok(0) => {}
+            Ok(purged) => println!("🧹 Purged {} expired idempotency keys", purged),
+            Err(e) => eprintln!("❌ Failed to purge idempotency keys: {}", e),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! The six-year H-1B maximum stay.
+//!
+//! Every day inside an approved validity period counts: the one on the
+//! customer record plus any earlier petitions. Full days spent abroad during
+//! those periods don't count and can be recaptured; the departure and return
+//! days themselves were spent in the US. AC21 extensions beyond six years are
+//! reported as eligibility only and never change the count.
+
+use chrono::{Days, NaiveDate};
+
+use crate::lifecycle::H1bStatus;
+use crate::models::{Ac21Eligibility, CreateCustomer, MaxStay, Petition, RecapturedTrip, Trip, ValidityPeriod};
+
+/// Six years of 365 days. A leap day inside the period leaves a day of slack,
+/// which is the safe side to err on.
+pub const LIMIT_DAYS: i64 = 6 * 365;
+
+/// AC21 §106(a) needs the labor certification or I-140 filed this long before
+/// the six-year limit.
+const ONE_YEAR_EXTENSION_LEAD_DAYS: u64 = 365;
+
+pub fn calculate(customer: &CreateCustomer, petitions: &[Petition], trips: &[Trip], today: NaiveDate) -> MaxStay {
+    let periods = validity_periods(customer, petitions);
+
+    let validity_days: i64 = periods.iter().map(|p| days_between(p.from, p.to.min(today))).sum();
+    let abroad: Vec<(&Trip, NaiveDate, NaiveDate)> =
+        trips.iter().filter_map(|trip| abroad(trip, today).map(|(first, last)| (trip, first, last))).collect();
+    let trips: Vec<RecapturedTrip> = abroad
+        .iter()
+        .filter_map(|&(trip, first, last)| {
+            let days: i64 = periods.iter().map(|p| days_between(first.max(p.from), last.min(p.to).min(today))).sum();
+            (days > 0).then_some(RecapturedTrip {
+                trip_id: trip.trip_id,
+                departed_on: trip.departed_on,
+                returned_on: trip.returned_on,
+                days,
+            })
+        })
+        .collect();
+    let recapturable_days: i64 = trips.iter().map(|trip| trip.days).sum();
+
+    let days_used = validity_days - recapturable_days;
+    let days_remaining = (LIMIT_DAYS - days_used).max(0);
+    let projected_max_out_date = match reached_on(&periods, &abroad, today) {
+        Some(day) => day,
+        None => max_out_date(&periods, today, days_remaining),
+    };
+
+    let one_year_extensions_from = customer
+        .labor_certification_filed_on
+        .and_then(|filed| filed.checked_add_days(Days::new(ONE_YEAR_EXTENSION_LEAD_DAYS)));
+    let ac21 = Ac21Eligibility {
+        one_year_extensions: one_year_extensions_from.is_some_and(|from| from <= projected_max_out_date),
+        one_year_extensions_from,
+        three_year_extensions: customer.i140_approved_on.is_some(),
+    };
+
+    MaxStay {
+        customer_id: customer.customer_id,
+        first_name: customer.first_name.clone(),
+        last_name: customer.last_name.clone(),
+        as_of: today,
+        limit_days: LIMIT_DAYS,
+        validity_days,
+        recapturable_days,
+        days_used,
+        days_remaining,
+        projected_max_out_date,
+        ac21,
+        periods,
+        trips,
+    }
+}
+
+/// The customer's own dates count once the petition was approved. Overlapping
+/// and back-to-back periods are merged so no day counts twice.
+pub fn validity_periods(customer: &CreateCustomer, petitions: &[Petition]) -> Vec<ValidityPeriod> {
+    let mut periods: Vec<ValidityPeriod> = petitions
+        .iter()
+        .map(|p| ValidityPeriod { from: p.valid_from, to: p.valid_to, receipt_numbers: p.receipt_number.iter().cloned().collect() })
+        .collect();
+    let approved = customer.h1b_status.parse::<H1bStatus>().is_ok_and(H1bStatus::was_approved);
+    if approved && customer.h1b_start_date <= customer.h1b_end_date {
+        periods.push(ValidityPeriod {
+            from: customer.h1b_start_date,
+            to: customer.h1b_end_date,
+            receipt_numbers: customer.receipt_number.iter().cloned().collect(),
+        });
+    }
+    periods.sort_by_key(|p| p.from);
+
+    let mut merged: Vec<ValidityPeriod> = Vec::new();
+    for period in periods {
+        match merged.last_mut() {
+            Some(last) if period.from <= last.to.succ_opt().unwrap_or(last.to) => {
+                last.to = last.to.max(period.to);
+                for receipt in period.receipt_numbers {
+                    if !last.receipt_numbers.contains(&receipt) {
+                        last.receipt_numbers.push(receipt);
+                    }
+                }
+            }
+            _ => merged.push(period),
+        }
+    }
+    merged
+}
+
+/// First and last full day abroad up to `today`, if there is one.
+fn abroad(trip: &Trip, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let first = trip.departed_on.succ_opt()?;
+    let last = match trip.returned_on {
+        Some(returned) => returned.pred_opt()?,
+        None => today,
+    };
+    (first <= last).then_some((first, last))
+}
+
+/// Days from `from` to `to`, both included; zero if `to` is earlier.
+fn days_between(from: NaiveDate, to: NaiveDate) -> i64 {
+    ((to - from).num_days() + 1).max(0)
+}
+
+/// The day the limit was reached, if it was by `today`: the 2,190th day in a
+/// period that wasn't spent abroad.
+fn reached_on(periods: &[ValidityPeriod], abroad: &[(&Trip, NaiveDate, NaiveDate)], today: NaiveDate) -> Option<NaiveDate> {
+    let mut used = 0;
+    for period in periods {
+        for day in period.from.iter_days().take_while(|day| *day <= period.to.min(today)) {
+            if !abroad.iter().any(|&(_, first, last)| first <= day && day <= last) {
+                used += 1;
+                if used == LIMIT_DAYS {
+                    return Some(day);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The last day of H-1B time if the customer stays in the US: counting on
+/// from today inside a period, or from the start of the next one otherwise.
+fn max_out_date(periods: &[ValidityPeriod], today: NaiveDate, days_remaining: i64) -> NaiveDate {
+    let remaining = Days::new(days_remaining as u64);
+    let in_period = periods.iter().any(|p| p.from <= today && today <= p.to);
+    let next_start = periods.iter().map(|p| p.from).find(|from| *from > today);
+    match next_start {
+        Some(start) if !in_period && days_remaining > 0 => start + remaining - Days::new(1),
+        _ => today + remaining,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{customer, date, petition, trip};
+
+    #[test]
+    fn counts_the_approved_validity_period_up_to_today() {
+        let c = customer();
+        let stay = calculate(&c, &[], &[], date("2024-09-30"));
+        // 2023-10-01 to 2024-09-30 with 2024-02-29 in it.
+        assert_eq!(stay.validity_days, 366);
+        assert_eq!(stay.days_used, 366);
+        assert_eq!(stay.days_remaining, LIMIT_DAYS - 366);
+        assert_eq!(stay.projected_max_out_date, date("2024-09-30") + Days::new((LIMIT_DAYS - 366) as u64));
+    }
+
+    #[test]
+    fn unapproved_petition_does_not_count() {
+        let mut c = customer();
+        c.h1b_status = "Filed".to_string();
+        let stay = calculate(&c, &[], &[], date("2024-09-30"));
+        assert!(stay.periods.is_empty());
+        assert_eq!(stay.days_used, 0);
+        assert_eq!(stay.days_remaining, LIMIT_DAYS);
+    }
+
+    #[test]
+    fn full_days_abroad_are_recaptured() {
+        let c = customer();
+        let trips = [
+            trip(c.customer_id, "2024-03-01", Some("2024-03-11")),
+            // Out and back the next day: no full day abroad.
+            trip(c.customer_id, "2024-05-01", Some("2024-05-02")),
+            // Before any validity period.
+            trip(c.customer_id, "2022-01-01", Some("2022-02-01")),
+        ];
+        let stay = calculate(&c, &[], &trips, date("2024-09-30"));
+        assert_eq!(stay.recapturable_days, 9);
+        assert_eq!(stay.trips.len(), 1);
+        assert_eq!(stay.days_used, 366 - 9);
+    }
+
+    #[test]
+    fn trip_without_a_return_counts_through_today() {
+        let c = customer();
+        let trips = [trip(c.customer_id, "2024-09-20", None)];
+        let stay = calculate(&c, &[], &trips, date("2024-09-30"));
+        assert_eq!(stay.recapturable_days, 10);
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_periods_merge() {
+        let c = customer();
+        let mut earlier = petition(c.customer_id, "2020-09-01", "2023-09-30");
+        earlier.receipt_number = Some("WAC2012345678".to_string());
+        let petitions = [petition(c.customer_id, "2017-10-01", "2020-09-30"), earlier];
+        let periods = validity_periods(&c, &petitions);
+        assert_eq!(periods.len(), 1);
+        assert_eq!((periods[0].from, periods[0].to), (date("2017-10-01"), date("2026-09-30")));
+        assert_eq!(periods[0].receipt_numbers, vec!["WAC2012345678", "EAC2312345678"]);
+    }
+
+    #[test]
+    fn limit_reached_in_the_past_reports_the_day() {
+        let c = customer();
+        let petitions = [petition(c.customer_id, "2017-10-01", "2023-09-30")];
+        let stay = calculate(&c, &petitions, &[], date("2026-01-01"));
+        assert_eq!(stay.days_remaining, 0);
+        // Six years of 365 days from 2017-10-01, plus 2020-02-29.
+        assert_eq!(stay.projected_max_out_date, date("2023-09-29"));
+
+        // Ten days abroad push it back ten days.
+        let trips = [trip(c.customer_id, "2019-06-01", Some("2019-06-12"))];
+        let stay = calculate(&c, &petitions, &trips, date("2026-01-01"));
+        assert_eq!(stay.projected_max_out_date, date("2023-10-09"));
+    }
+
+    #[test]
+    fn counts_on_from_the_next_period_when_between_petitions() {
+        let mut c = customer();
+        c.h1b_start_date = date("2025-01-01");
+        c.h1b_end_date = date("2027-12-31");
+        c.h1b_status = "Approved".to_string();
+        let petitions = [petition(c.customer_id, "2022-01-01", "2022-12-31")];
+        let stay = calculate(&c, &petitions, &[], date("2024-06-01"));
+        assert_eq!(stay.days_used, 365);
+        assert_eq!(stay.projected_max_out_date, date("2025-01-01") + Days::new((LIMIT_DAYS - 365 - 1) as u64));
+    }
+
+    #[test]
+    fn ac21_eligibility() {
+        let mut c = customer();
+        let stay = calculate(&c, &[], &[], date("2024-09-30"));
+        assert!(!stay.ac21.one_year_extensions && !stay.ac21.three_year_extensions);
+        assert!(stay.ac21.one_year_extensions_from.is_none());
+
+        c.labor_certification_filed_on = Some(date("2024-01-15"));
+        c.i140_approved_on = Some(date("2024-06-01"));
+        let stay = calculate(&c, &[], &[], date("2024-09-30"));
+        assert_eq!(stay.ac21.one_year_extensions_from, Some(date("2025-01-14")));
+        assert!(stay.ac21.one_year_extensions);
+        assert!(stay.ac21.three_year_extensions);
+    }
+}
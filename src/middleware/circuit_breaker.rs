@@ -0,0 +1,42 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use std::time::Duration;
+
+use crate::state::AppState;
+
+/// Rejects database-backed requests with 503 while the circuit breaker is open,
+/// instead of letting each one wait on a failing database.
+///
+/// Also adds `Retry-After` to any 503 a handler returns, since handlers only
+/// produce a status code.
+pub async fn fail_fast(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if let Some(retry_after) = state.circuit_breaker.retry_after() {
+        println!("⛔ Circuit open, rejecting {} {}", request.method(), request.uri());
+        let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+            "message": "Database temporarily unavailable",
+            "retry_after_secs": retry_after_secs(retry_after)
+        }))).into_response();
+        set_retry_after(&mut response, retry_after);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::SERVICE_UNAVAILABLE && !response.headers().contains_key(header::RETRY_AFTER) {
+        let retry_after = state.circuit_breaker.retry_after().unwrap_or(Duration::from_secs(1));
+        set_retry_after(&mut response, retry_after);
+    }
+    response
+}
+
+/// Whole seconds, rounded up so clients never come back early.
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+}
+
+fn set_retry_after(response: &mut Response, retry_after: Duration) {
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs(retry_after)));
+}
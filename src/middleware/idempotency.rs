@@ -0,0 +1,149 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{FromRequestParts, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use sha2::{Digest, Sha256};
+
+use crate::auth::Caller;
+use crate::state::AppState;
+use crate::store::idempotency::{IdempotencyClaim, StoredResponse};
+use crate::store::{FailureKind, StoreError};
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+/// Set on responses served from the idempotency store rather than the handler.
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// The largest body any route accepts, a public access file upload; JSON
+/// bodies are still held to axum's 2 MB default by the handlers.
+const MAX_BODY_BYTES: usize = crate::public_access::MAX_UPLOAD_BYTES;
+const MAX_KEY_LEN: usize = 255;
+
+/// Makes mutating requests that carry an `Idempotency-Key` header safe to retry.
+///
+/// The first request with a key runs normally and its response is stored under
+/// (caller, key). Retries with the same payload get that response back;
+/// reusing the key with a different payload is rejected with 422. Server errors
+/// aren't stored, so a retry after a 5xx runs the request again.
+///
+/// Keys are only accepted from callers with an admin token or a client API key
+/// (`auth.client_tokens`), and are rejected with 400 otherwise: anonymous
+/// callers would all share one namespace, so one client could replay
+/// another's response.
+pub async fn idempotency(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(&IDEMPOTENCY_KEY) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => key.to_string(),
+        _ => return error(StatusCode::BAD_REQUEST, "Idempotency-Key must be 1-255 visible ASCII characters"),
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let principal = match Caller::from_request_parts(&mut parts, &state).await {
+        Ok(caller) if caller.is_authenticated => caller.name,
+        Ok(_) => return error(StatusCode::BAD_REQUEST, "Idempotency-Key requires an admin token or client API key"),
+        Err(status) => return status.into_response(),
+    };
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => return error(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large"),
+    };
+    let target = parts.uri.path_and_query().map_or(parts.uri.path(), |target| target.as_str());
+    let request_hash = request_hash(&parts.method, target, &body);
+
+    let store = &state.idempotency;
+    match store.claim(&principal, &key, &request_hash).await {
+        Ok(IdempotencyClaim::Claimed) => {}
+        Ok(IdempotencyClaim::Replay(stored)) => {
+            println!("🔁 Replaying response for Idempotency-Key {}", key);
+            return replay(stored);
+        }
+        Ok(IdempotencyClaim::InProgress) => {
+            return error(StatusCode::CONFLICT, "A request with this Idempotency-Key is still being processed");
+        }
+        Ok(IdempotencyClaim::PayloadMismatch) => {
+            return error(StatusCode::UNPROCESSABLE_ENTITY, "Idempotency-Key was already used with a different request");
+        }
+        Err(e) => return store_failure(e),
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    if response.status().is_server_error() {
+        if let Err(e) = store.release(&principal, &key).await {
+            eprintln!("❌ Failed to release Idempotency-Key {}: {}", key, e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            eprintln!("❌ Failed to buffer response for Idempotency-Key {}: {}", key, e);
+            let _ = store.release(&principal, &key).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let stored = StoredResponse {
+        status_code: parts.status.as_u16(),
+        content_type: header_string(&parts.headers, header::CONTENT_TYPE),
+        location: header_string(&parts.headers, header::LOCATION),
+        body: body.to_vec(),
+    };
+    if let Err(e) = store.complete(&principal, &key, &stored).await {
+        // The write already happened; the client still gets its response.
+        eprintln!("❌ Failed to store response for Idempotency-Key {}: {}", key, e);
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Covers the query string too: `?upsert=true` turns a create into a different request.
+fn request_hash(method: &Method, path_and_query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update(b"\n");
+    hasher.update(path_and_query);
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string)
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status_code).unwrap_or(StatusCode::OK);
+    let mut response = (status, stored.body).into_response();
+    let headers = response.headers_mut();
+    for (name, value) in [(header::CONTENT_TYPE, stored.content_type), (header::LOCATION, stored.location)] {
+        if let Some(value) = value.and_then(|value| HeaderValue::from_str(&value).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+fn store_failure(e: StoreError) -> Response {
+    eprintln!("❌ Idempotency store error: {}", e);
+    if e.kind() == FailureKind::Permanent {
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({
+        "message": message
+    }))).into_response()
+}
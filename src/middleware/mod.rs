@@ -1 +1,3 @@
+pub mod circuit_breaker;
+pub mod idempotency;
 pub mod request_logging;
\ No newline at end of file
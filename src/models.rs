@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use chrono::NaiveDate;
-use utoipa::ToSchema;
+use chrono::{DateTime, NaiveDate, Utc};
+use utoipa::{IntoParams, ToSchema};
 use rust_decimal::Decimal;
 use uuid::Uuid;
 
-#[derive(Debug, Deserialize, ToSchema)]
+use crate::i129::I129Basis;
+use crate::lifecycle::H1bStatus;
+use crate::prevailing_wage::WageLevel;
+use crate::public_access::DocumentCategory;
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct CreateCompleteCustomerRequest {
     pub email: String,
     pub login_email: String,
@@ -14,9 +19,20 @@ pub struct CreateCompleteCustomerRequest {
     pub dob: NaiveDate,
     pub sex: String,
     pub marital_status: String,
+    /// Any common format; numbers without a country code are read as numbers
+    /// of `phone_region`. Saved in E.164.
     pub phone: String,
+    /// Two-letter country code for a `phone` without a country code; defaults
+    /// to `phone.default_region` (US).
+    pub phone_region: Option<String>,
     pub emergency_contact_name: String,
     pub emergency_contact_phone: String,
+    pub emergency_contact_phone_region: Option<String>,
+    /// Derived from `phone` and `emergency_contact_phone`.
+    #[serde(skip_deserializing)]
+    pub phone_display: Option<String>,
+    #[serde(skip_deserializing)]
+    pub emergency_contact_phone_display: Option<String>,
     pub employment_start_date: NaiveDate,
     pub street_name: String,
     pub city: String,
@@ -27,16 +43,39 @@ pub struct CreateCompleteCustomerRequest {
     pub client_city: String,
     pub client_state: String,
     pub client_zip: String,
+    /// Derived from `zip` and `client_zip` when the address is normalized.
+    #[serde(skip_deserializing)]
+    pub county: Option<String>,
+    #[serde(skip_deserializing)]
+    pub msa: Option<String>,
+    #[serde(skip_deserializing)]
+    pub client_county: Option<String>,
+    #[serde(skip_deserializing)]
+    pub client_msa: Option<String>,
     pub lca_title: String,
     pub lca_salary: Decimal,
     pub lca_code: String,
-    pub receipt_number: String,
+    /// OFLC wage level declared on the LCA: `I`, `II`, `III` or `IV`. The
+    /// prevailing wage check assumes `I` when it isn't set.
+    pub lca_wage_level: Option<String>,
+    /// USCIS receipt number. New customers are `Draft` and get theirs with the
+    /// transition to `Filed`, so only seed file customers past that may set it.
+    pub receipt_number: Option<String>,
     pub h1b_start_date: NaiveDate,
     pub h1b_end_date: NaiveDate,
+    /// New customers start as `Draft`, so this may only be left out or be
+    /// `Draft`; later statuses are reached through `/customers/{id}/transitions`.
+    /// The memory backend's seed file may set any status.
     pub h1b_status: Option<String>,
+    /// When the PERM labor certification (or the I-140, if filed first) was
+    /// filed. Filed 365 days before the six-year limit, it allows AC21
+    /// one-year extensions.
+    pub labor_certification_filed_on: Option<NaiveDate>,
+    /// When the I-140 was approved; allows AC21 three-year extensions.
+    pub i140_approved_on: Option<NaiveDate>,
 }
 
-#[derive(Debug, FromRow, Serialize, ToSchema)]
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
 pub struct CreateCustomer {
     pub customer_id: Uuid,
     pub email: String,
@@ -45,9 +84,14 @@ pub struct CreateCustomer {
     pub dob: NaiveDate,
     pub sex: String,
     pub marital_status: String,
+    /// E.164, e.g. `+12125550100`.
     pub phone: String,
     pub emergency_contact_name: String,
     pub emergency_contact_phone: String,
+    /// `phone` formatted for display: national for US numbers, international
+    /// otherwise. `None` for numbers saved before normalization.
+    pub phone_display: Option<String>,
+    pub emergency_contact_phone_display: Option<String>,
     pub employment_start_date: NaiveDate,
     pub street_name: String,
     pub city: String,
@@ -58,25 +102,55 @@ pub struct CreateCustomer {
     pub client_city: String,
     pub client_state: String,
     pub client_zip: String,
+    /// County of `zip`, `None` if the ZIP isn't in the ZIP dataset.
+    pub county: Option<String>,
+    /// CBSA code of the metropolitan area `zip` is in.
+    pub msa: Option<String>,
+    pub client_county: Option<String>,
+    pub client_msa: Option<String>,
     pub lca_title: String,
     pub lca_salary: Decimal,
     pub lca_code: String,
-    pub receipt_number: String,
+    pub lca_wage_level: Option<String>,
+    /// `None` until the petition is filed.
+    pub receipt_number: Option<String>,
     pub h1b_start_date: NaiveDate,
     pub h1b_end_date: NaiveDate,
+    pub login_email: String,
     pub h1b_status: String,
+    pub labor_certification_filed_on: Option<NaiveDate>,
+    pub i140_approved_on: Option<NaiveDate>,
+    /// `None` for customers created before this was recorded.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Set when the record is soft-deleted; deleted records are hidden from reads
+    /// unless an admin passes `include_deleted=true`.
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<String>,
+    pub delete_reason: Option<String>,
+    /// The customer this one was merged into; set together with `deleted_at`.
+    pub merged_into: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+/// Partial update: only the fields present are changed. Status changes go through
+/// `/customers/{id}/transitions`, deletion through soft delete and restore.
+#[derive(Debug, Default, Deserialize, ToSchema)]
 pub struct UpdateVisaDetailsRequest {
+    pub email: Option<String>,
+    pub login_email: Option<String>,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub dob: Option<NaiveDate>,
     pub sex: Option<String>,
     pub marital_status: Option<String>,
     pub phone: Option<String>,
+    pub phone_region: Option<String>,
     pub emergency_contact_name: Option<String>,
     pub emergency_contact_phone: Option<String>,
+    pub emergency_contact_phone_region: Option<String>,
+    #[serde(skip_deserializing)]
+    pub phone_display: Option<String>,
+    #[serde(skip_deserializing)]
+    pub emergency_contact_phone_display: Option<String>,
     pub employment_start_date: Option<NaiveDate>,
     pub street_name: Option<String>,
     pub city: Option<String>,
@@ -87,13 +161,708 @@ pub struct UpdateVisaDetailsRequest {
     pub client_city: Option<String>,
     pub client_state: Option<String>,
     pub client_zip: Option<String>,
+    /// Derived from `zip` and `client_zip`; only written when those change.
+    #[serde(skip_deserializing)]
+    pub county: Option<String>,
+    #[serde(skip_deserializing)]
+    pub msa: Option<String>,
+    #[serde(skip_deserializing)]
+    pub client_county: Option<String>,
+    #[serde(skip_deserializing)]
+    pub client_msa: Option<String>,
     pub lca_title: Option<String>,
     pub lca_salary: Option<Decimal>,
     pub lca_code: Option<String>,
+    pub lca_wage_level: Option<String>,
+    /// Corrects the receipt number of a customer who has filed; a `Draft`
+    /// customer gets one with the transition to `Filed`.
     pub receipt_number: Option<String>,
     pub h1b_start_date: Option<NaiveDate>,
     pub h1b_end_date: Option<NaiveDate>,
-    pub h1b_status: Option<String>,
+    pub labor_certification_filed_on: Option<NaiveDate>,
+    pub i140_approved_on: Option<NaiveDate>,
+    /// With a worksite change outside the current MSA: the move is a
+    /// short-term placement (20 CFR 655.735) rather than a permanent one.
+    pub short_term_placement: Option<bool>,
+    /// With a worksite change: when work starts at the new worksite, which
+    /// the compliance task's deadline is counted from. Defaults to today.
+    pub worksite_effective_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateCustomerParams {
+    /// When true and a customer with the same `email` exists, replace its
+    /// details instead of creating a duplicate. Meant for re-imports.
+    pub upsert: Option<bool>,
+}
+
+/// Optional body for soft delete.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct SoftDeleteOptions {
+    /// Why the record is being deleted, kept in `delete_reason`.
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReadParams {
+    /// Include soft-deleted customers. Admin only.
+    pub include_deleted: Option<bool>,
+}
+
+/// Moves a customer to another `h1b_status`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TransitionRequest {
+    pub to_status: H1bStatus,
+    /// Why the status changed, e.g. "I-797 approval notice received". Required.
+    pub reason: String,
+    /// When the change took effect, which may differ from when it was recorded.
+    pub effective_date: NaiveDate,
+    /// Only accepted when moving to `Filed`, and required then if the customer
+    /// has no receipt number yet. Saved on the customer.
+    pub receipt_number: Option<String>,
+}
+
+/// One recorded `h1b_status` change.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct StatusTransition {
+    pub transition_id: Uuid,
+    pub customer_id: Uuid,
+    pub from_status: String,
+    pub to_status: String,
+    pub reason: String,
+    pub effective_date: NaiveDate,
+    pub transitioned_by: String,
+    pub created_at: DateTime<Utc>,
+    /// The merged duplicate this transition was recorded on, if it came over
+    /// in a merge; `from_status` and `to_status` are that customer's.
+    pub merged_from: Option<Uuid>,
+}
+
+/// Current status, the states it may move to next, and the history, oldest first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CustomerTransitions {
+    pub customer_id: Uuid,
+    pub h1b_status: String,
+    /// Empty for terminal states and deleted customers.
+    pub allowed_next: Vec<H1bStatus>,
+    pub history: Vec<StatusTransition>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransitionResponse {
+    pub customer: CreateCustomer,
+    pub transition: StatusTransition,
+    pub allowed_next: Vec<H1bStatus>,
+}
+
+/// A USCIS case status as first observed for a receipt. A new entry is only
+/// written when the status text changes; `last_checked_at` moves on every poll.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct CaseStatusEntry {
+    pub entry_id: Uuid,
+    pub receipt_number: String,
+    /// `None` if the customer was removed after the status was recorded.
+    pub customer_id: Option<Uuid>,
+    /// Status title as USCIS words it, e.g. "Case Was Approved".
+    pub status: String,
+    pub description: Option<String>,
+    /// The status this one replaced; `None` for the first observation.
+    pub previous_status: Option<String>,
+    pub provider: String,
+    pub observed_at: DateTime<Utc>,
+    pub last_checked_at: DateTime<Utc>,
+}
+
+/// Status history for one receipt, oldest first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CaseStatusTimeline {
+    pub receipt_number: String,
+    pub current: Option<CaseStatusEntry>,
+    pub history: Vec<CaseStatusEntry>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CaseStatusChangesParams {
+    /// Only changes observed after this time. Defaults to the last 7 days.
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Outcome of one polling run over the pending receipts.
+#[derive(Debug, Default, Clone, Serialize, ToSchema)]
+pub struct CaseStatusRunSummary {
+    pub checked: usize,
+    pub changed: usize,
+    /// Receipts the provider doesn't know about.
+    pub not_found: usize,
+    pub failed: usize,
+}
+
+/// A load of OFLC wage files; the latest one is what checks run against.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct PrevailingWageImport {
+    pub import_id: Uuid,
+    /// Directory the files were read from.
+    pub source: String,
+    pub wages: i32,
+    pub areas: i32,
+    pub zips: i32,
+    pub imported_by: String,
+    pub imported_at: DateTime<Utc>,
+}
+
+/// Annual prevailing wage per level (hourly OFLC wage × 2080). `None` where
+/// OFLC publishes no wage for the level.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PrevailingWageLevels {
+    pub level_1: Option<Decimal>,
+    pub level_2: Option<Decimal>,
+    pub level_3: Option<Decimal>,
+    pub level_4: Option<Decimal>,
+}
+
+impl PrevailingWageLevels {
+    pub fn get(&self, level: WageLevel) -> Option<Decimal> {
+        match level {
+            WageLevel::I => self.level_1,
+            WageLevel::II => self.level_2,
+            WageLevel::III => self.level_3,
+            WageLevel::IV => self.level_4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LcaWageStatus {
+    /// `lca_salary` meets the wage for the required level.
+    Compliant,
+    /// `lca_salary` is below the wage for the required level. Needs attention.
+    Underpaid,
+    /// The wage data doesn't cover this worksite, occupation or level; see `detail`.
+    Unknown,
+}
+
+/// Prevailing wage check for one customer.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LcaWageCheck {
+    pub customer_id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub lca_code: String,
+    pub lca_title: String,
+    /// Annual salary on the LCA.
+    pub lca_salary: Decimal,
+    /// `client_zip`, where the work is performed.
+    pub worksite_zip: String,
+    /// OFLC area code (the MSA code for metropolitan areas).
+    pub area: Option<String>,
+    pub area_name: Option<String>,
+    pub levels: Option<PrevailingWageLevels>,
+    /// `lca_wage_level`, or `I` when the customer has none.
+    pub required_level: WageLevel,
+    pub required_wage: Option<Decimal>,
+    /// Highest level whose wage `lca_salary` reaches, if any.
+    pub highest_level_met: Option<WageLevel>,
+    /// How far `lca_salary` is below `required_wage`.
+    pub shortfall: Option<Decimal>,
+    pub status: LcaWageStatus,
+    /// Why the status is `unknown`.
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LcaWageReport {
+    /// `None` until wage data has been imported; every check is then `unknown`.
+    pub wage_data: Option<PrevailingWageImport>,
+    pub compliant: usize,
+    pub underpaid: usize,
+    pub unknown: usize,
+    pub customers: Vec<LcaWageCheck>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LcaWageReportParams {
+    /// Only list customers with this status, e.g. `underpaid`. The counts always cover everyone.
+    pub status: Option<LcaWageStatus>,
+}
+
+/// An H-1B petition other than the one on the customer record.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct Petition {
+    pub petition_id: Uuid,
+    pub customer_id: Uuid,
+    pub receipt_number: Option<String>,
+    pub employer: Option<String>,
+    pub valid_from: NaiveDate,
+    pub valid_to: NaiveDate,
+    pub recorded_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Records an earlier petition, e.g. one with a previous employer.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct PetitionRequest {
+    /// Normalized like the customer's `receipt_number`, and unique the same way.
+    pub receipt_number: Option<String>,
+    pub employer: Option<String>,
+    pub valid_from: NaiveDate,
+    pub valid_to: NaiveDate,
+}
+
+/// Time outside the US.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct Trip {
+    pub trip_id: Uuid,
+    pub customer_id: Uuid,
+    pub departed_on: NaiveDate,
+    /// `None` while the customer is still abroad.
+    pub returned_on: Option<NaiveDate>,
+    /// Where the customer was admitted on return, e.g. `SFO`.
+    pub port_of_entry: Option<String>,
+    /// Class of admission on return, e.g. `H1B`.
+    pub admission_class: Option<String>,
+    /// The I-94 number issued on return.
+    pub i94_number: Option<String>,
+    pub recorded_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Records a trip outside the US, or replaces one. The port of entry,
+/// admission class and I-94 number describe the return, so they need `returned_on`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TripRequest {
+    pub departed_on: NaiveDate,
+    pub returned_on: Option<NaiveDate>,
+    pub port_of_entry: Option<String>,
+    /// Uppercased without dashes or spaces: `H-1B` becomes `H1B`.
+    pub admission_class: Option<String>,
+    /// Eleven letters and digits; spaces and dashes are dropped.
+    pub i94_number: Option<String>,
+}
+
+/// Outcome of an I-94 travel history import.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TripImport {
+    pub imported: Vec<Trip>,
+    pub skipped: Vec<SkippedTrip>,
+}
+
+/// A row or trip from the travel history that wasn't recorded, and why.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SkippedTrip {
+    /// CSV line of the departure, or of the arrival if there is no departure.
+    pub line: u64,
+    pub departed_on: Option<NaiveDate>,
+    pub returned_on: Option<NaiveDate>,
+    pub reason: String,
+}
+
+/// Days used of the six-year H-1B limit and what is left.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MaxStay {
+    pub customer_id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub as_of: NaiveDate,
+    /// Six years, counted as 2,190 days.
+    pub limit_days: i64,
+    /// Days inside an H-1B validity period up to `as_of`.
+    pub validity_days: i64,
+    /// Full days spent abroad during those periods, which don't count towards the limit.
+    pub recapturable_days: i64,
+    /// `validity_days` minus `recapturable_days`.
+    pub days_used: i64,
+    /// Zero once the limit is reached.
+    pub days_remaining: i64,
+    /// When the limit is reached if the customer stays in the US from `as_of`
+    /// on, or the day it was reached if that has already happened.
+    pub projected_max_out_date: NaiveDate,
+    pub ac21: Ac21Eligibility,
+    /// The periods counted, merged where they overlap, oldest first.
+    pub periods: Vec<ValidityPeriod>,
+    /// Trips with days abroad inside a period, oldest first.
+    pub trips: Vec<RecapturedTrip>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ValidityPeriod {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Receipt numbers of the petitions making up the period.
+    pub receipt_numbers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RecapturedTrip {
+    pub trip_id: Uuid,
+    pub departed_on: NaiveDate,
+    pub returned_on: Option<NaiveDate>,
+    pub days: i64,
+}
+
+/// Whether AC21 allows H-1B time beyond six years.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Ac21Eligibility {
+    /// §106(a): the labor certification or I-140 was filed at least 365 days
+    /// before the projected max-out date, so one-year extensions are available.
+    pub one_year_extensions: bool,
+    /// The date §106(a) one-year extensions become available, if a filing is recorded.
+    pub one_year_extensions_from: Option<NaiveDate>,
+    /// §104(c): the I-140 is approved, so three-year extensions are available.
+    pub three_year_extensions: bool,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MaxStayReportParams {
+    /// Only list customers who max out within this many days, AC21 aside.
+    pub within_days: Option<i64>,
+}
+
+/// Maximum stay for every live customer, soonest max-out first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MaxStayReport {
+    pub as_of: NaiveDate,
+    pub customers: Vec<MaxStay>,
+}
+
+/// Aggregate figures over live customers for the admin dashboard.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Dashboard {
+    /// Expiry windows count from this date.
+    pub as_of: NaiveDate,
+    pub total_customers: i64,
+    /// Every status, including those with no customers.
+    pub by_status: Vec<StatusCount>,
+    /// Approved or Active customers whose `h1b_end_date` falls within 30, 60,
+    /// 90 and 180 days; each window includes the shorter ones.
+    pub expiring: Vec<ExpiringCount>,
+    /// The last 12 months, oldest first, including the current one.
+    pub new_per_month: Vec<MonthCount>,
+    /// Customers created before creation dates were recorded.
+    pub created_unknown: i64,
+    /// Clients with the most customers, ignoring case and surrounding spaces in `client_name`.
+    pub top_clients: Vec<ClientCount>,
+    /// Most common occupations first.
+    pub salary_by_lca_code: Vec<SalaryDistribution>,
+    /// By worksite state (`client_state`), most customers first.
+    pub by_work_state: Vec<StateCount>,
+    /// Compliance tasks not yet completed, and those of them past `due_on`.
+    pub open_compliance_tasks: i64,
+    pub overdue_compliance_tasks: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StatusCount {
+    pub h1b_status: H1bStatus,
+    pub customers: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExpiringCount {
+    pub within_days: i32,
+    pub customers: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MonthCount {
+    /// `YYYY-MM`, in UTC.
+    pub month: String,
+    pub customers: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClientCount {
+    pub client_name: String,
+    pub customers: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StateCount {
+    pub state: String,
+    pub customers: i64,
+}
+
+/// LCA salaries of the customers with one `lca_code`. Quartiles interpolate
+/// between salaries, as `percentile_cont` does, and are rounded to cents.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SalaryDistribution {
+    pub lca_code: String,
+    /// From the SOC catalog, if the code is in it.
+    pub title: Option<String>,
+    pub customers: i64,
+    pub min: Decimal,
+    pub p25: Decimal,
+    pub median: Decimal,
+    pub p75: Decimal,
+    pub max: Decimal,
+    pub average: Decimal,
+}
+
+/// Something doubtful about otherwise accepted input, e.g. an `lca_title` that
+/// doesn't match its SOC code. The change is saved anyway.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ValidationWarning {
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationWarning {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), message: message.into() }
+    }
+}
+
+/// The saved customer, plus any warnings about the input.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateCustomerResponse {
+    #[serde(flatten)]
+    pub customer: CreateCustomer,
+    /// Omitted when empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ValidationWarning>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SocSearchParams {
+    /// Part of a title (`software dev`) or code (`15-12`).
+    pub q: String,
+    /// At most this many results; default 10, maximum 50.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DuplicateParams {
+    /// Only pairs scoring at least this, from 0 to 1; default 0.5.
+    pub min_score: Option<f64>,
+}
+
+/// How a worksite change made through an update affects the LCA. Outside
+/// metropolitan areas the county stands in for the MSA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WorksiteChangeKind {
+    /// Within the same area of intended employment: no new LCA or amendment,
+    /// but the LCA notice has to be posted at the new worksite.
+    SameMsa,
+    /// A material change: a new LCA and an amended petition before work
+    /// starts there (Matter of Simeio Solutions).
+    NewMsa,
+    /// Outside the area for a limited time, which 20 CFR 655.735 allows
+    /// without a new LCA.
+    ShortTermPlacement,
+    /// One of the worksites couldn't be placed in an MSA or county, so the
+    /// change needs a manual review.
+    Unknown,
+}
+
+/// A classified worksite change, returned with the update that made it.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WorksiteChange {
+    pub classification: WorksiteChangeKind,
+    /// The area of the old and new worksite: an MSA name or a county.
+    pub from_area: Option<String>,
+    pub to_area: Option<String>,
+    pub effective_date: NaiveDate,
+    /// Also the compliance task's reason and deadline.
+    pub reason: String,
+    pub due_on: NaiveDate,
+}
+
+/// A compliance task to record.
+#[derive(Debug, Clone)]
+pub struct NewComplianceTask {
+    pub kind: String,
+    pub reason: String,
+    pub due_on: NaiveDate,
+    pub created_by: String,
+}
+
+/// A follow-up the API raised for a customer, open until marked done.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct ComplianceTask {
+    pub task_id: Uuid,
+    pub customer_id: Uuid,
+    /// `lca_posting`, `lca_amendment`, `short_term_placement` or `worksite_review`.
+    pub kind: String,
+    pub reason: String,
+    pub due_on: NaiveDate,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// `None` while the task is open.
+    pub completed_at: Option<DateTime<Utc>>,
+    pub completed_by: Option<String>,
+}
+
+/// A document uploaded for the customer's LCA public access file.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct LcaDocument {
+    pub document_id: Uuid,
+    pub customer_id: Uuid,
+    /// A `DocumentCategory`, e.g. `notice_posting`.
+    pub category: String,
+    pub filename: String,
+    /// `application/pdf` or `image/jpeg`.
+    pub content_type: String,
+    pub size_bytes: i32,
+    pub page_count: i32,
+    pub uploaded_by: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// A checked upload, ready to store.
+#[derive(Debug, Clone)]
+pub struct NewLcaDocument {
+    pub category: DocumentCategory,
+    pub filename: String,
+    pub content_type: String,
+    pub page_count: i32,
+    pub content: Vec<u8>,
+    pub uploaded_by: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LcaDocumentParams {
+    pub category: DocumentCategory,
+    /// Shown in the public access file's table of contents; defaults to the category.
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PublicAccessFileParams {
+    /// ETA case number of the certified LCA, e.g. `I-200-26123-123456`.
+    /// Printed on the cover memo and kept with the record.
+    pub lca_case_number: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SummaryParams {
+    /// Include soft-deleted customers. Admin only.
+    pub include_deleted: Option<bool>,
+    /// Text printed diagonally across every page, e.g. `DRAFT` or
+    /// `PRIVILEGED & CONFIDENTIAL`. At most 40 characters.
+    pub watermark: Option<String>,
+}
+
+/// A record of a public access file having been generated, with the LCA
+/// details it was generated for. The PDF itself isn't kept.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct PublicAccessFile {
+    pub file_id: Uuid,
+    pub customer_id: Uuid,
+    pub lca_case_number: Option<String>,
+    pub lca_code: String,
+    pub lca_title: String,
+    pub lca_salary: Decimal,
+    pub lca_wage_level: Option<String>,
+    /// The worksite address on the LCA.
+    pub worksite: String,
+    /// Uploaded documents included, in the order they appear.
+    pub document_ids: Vec<Uuid>,
+    /// Required sections that had nothing on file, as `DocumentCategory` values.
+    pub missing: Vec<String>,
+    pub page_count: i32,
+    /// SHA-256 of the PDF, hex encoded.
+    pub sha256: String,
+    pub generated_by: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct I129Params {
+    /// Include soft-deleted customers. Admin only.
+    pub include_deleted: Option<bool>,
+    /// Part 2, Item 2. `continuation` (an extension) for `Approved` and
+    /// `Active` customers, `new_employment` for the rest, if not given.
+    pub basis: Option<I129Basis>,
+    /// ETA case number of the certified LCA, e.g. `I-200-26123-123456`. If not
+    /// given, the one the public access file was last generated with.
+    pub lca_case_number: Option<String>,
+}
+
+/// A field of Form I-129 or its supplements.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct I129Field {
+    /// Stable name of the field, e.g. `part3.family_name`.
+    pub key: String,
+    /// AcroForm field name in the fillable PDF; see `field_names`.
+    pub pdf_field: String,
+    /// Part or supplement of the form, e.g. "Part 3. Beneficiary Information".
+    pub section: String,
+    pub label: String,
+    /// As the form takes it: dates `MM/DD/YYYY`, checked boxes `Yes`.
+    /// `None` leaves the field blank.
+    pub value: Option<String>,
+    /// Stored field(s) the value comes from, if any.
+    pub source: Option<String>,
+}
+
+/// A required field, or choice between fields, that couldn't be filled.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct I129MissingField {
+    pub key: String,
+    pub section: String,
+    pub label: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct I129Export {
+    pub customer_id: Uuid,
+    pub basis: I129Basis,
+    /// Where `pdf_field` names come from: the field names file, or `export
+    /// keys` when none is configured.
+    pub field_names: String,
+    /// In form order.
+    pub fields: Vec<I129Field>,
+    pub missing: Vec<I129MissingField>,
+}
+
+/// Two live customers that look like the same person.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DuplicatePair {
+    /// From 0 to 1; the evidence in `reasons` adds up, capped at 1.
+    pub score: f64,
+    /// e.g. "same name and date of birth", "similar email".
+    pub reasons: Vec<String>,
+    pub customers: Vec<CreateCustomer>,
+}
+
+/// Folds `duplicate_id` into the customer in the path.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct MergeRequest {
+    /// The customer to merge away. It may already be soft-deleted.
+    pub duplicate_id: Uuid,
+    /// Kept in the duplicate's `delete_reason`.
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MergeResponse {
+    /// The customer that stays; its own details are kept as they were.
+    pub customer: CreateCustomer,
+    pub merged_customer_id: Uuid,
+    /// Status transitions moved over from the duplicate, marked `merged_from`.
+    pub transitions_moved: u64,
+    pub petitions_moved: u64,
+    pub trips_moved: u64,
+    pub compliance_tasks_moved: u64,
+    /// Case status history entries moved over from the duplicate.
+    pub case_status_entries_moved: u64,
+    /// Uploaded public access file documents moved over.
+    pub lca_documents_moved: u64,
+    /// Generated public access file records moved over.
+    pub public_access_files_moved: u64,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
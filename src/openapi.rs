@@ -0,0 +1,98 @@
+use utoipa::OpenApi;
+
+use crate::address::ZipCode;
+use crate::handlers;
+use crate::i129::I129Basis;
+use crate::lifecycle::H1bStatus;
+use crate::models::*;
+use crate::prevailing_wage::WageLevel;
+use crate::public_access::DocumentCategory;
+use crate::soc::SocOccupation;
+
+/// OpenAPI document for every route mounted in `main.rs`.
+/// Served at `/api-docs/openapi.json` and rendered by Swagger UI at `/docs`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Visa API",
+        description = "H1B customer management API"
+    ),
+    paths(
+        handlers::health_check,
+        handlers::test_connection,
+        handlers::livez,
+        handlers::readyz,
+        handlers::create_visa_details,
+        handlers::get_all_customers_with_status,
+        handlers::get_customer,
+        handlers::get_customer_by_receipt,
+        handlers::get_customer_by_id,
+        handlers::get_customer_by_email,
+        handlers::soft_delete_customer_by_id,
+        handlers::update_customer_by_id,
+        handlers::get_customer_by_login_email,
+        handlers::get_all_customers_no_filter,
+        handlers::activate_customer_by_id,
+        handlers::restore_customer,
+        handlers::get_customer_transitions,
+        handlers::transition_customer,
+        handlers::get_duplicate_customers,
+        handlers::get_customer_duplicates,
+        handlers::merge_customers,
+        handlers::get_customer_petitions,
+        handlers::add_customer_petition,
+        handlers::get_customer_trips,
+        handlers::add_customer_trip,
+        handlers::update_customer_trip,
+        handlers::import_customer_trips,
+        handlers::get_customer_compliance_tasks,
+        handlers::get_open_compliance_tasks,
+        handlers::complete_compliance_task,
+        handlers::get_customer_lca_documents,
+        handlers::upload_lca_document,
+        handlers::download_lca_document,
+        handlers::delete_lca_document,
+        handlers::generate_public_access_file,
+        handlers::get_customer_public_access_files,
+        handlers::get_customer_max_stay,
+        handlers::get_max_stay_report,
+        handlers::get_customer_summary_pdf,
+        handlers::get_customer_i129,
+        handlers::get_customer_i129_xfdf,
+        handlers::get_case_status,
+        handlers::get_case_status_changes,
+        handlers::case_status_events,
+        handlers::run_case_status_check,
+        handlers::get_dashboard,
+        handlers::get_customer_prevailing_wage,
+        handlers::get_prevailing_wage_report,
+        handlers::import_prevailing_wages,
+        handlers::search_soc,
+        handlers::get_soc_occupation,
+        handlers::get_zip_code,
+    ),
+    components(schemas(CreateCompleteCustomerRequest, CreateCustomer, UpdateVisaDetailsRequest, SoftDeleteOptions,
+        H1bStatus, TransitionRequest, StatusTransition, CustomerTransitions, TransitionResponse,
+        DuplicatePair, MergeRequest, MergeResponse, WorksiteChange, WorksiteChangeKind, ComplianceTask,
+        DocumentCategory, LcaDocument, PublicAccessFile,
+        I129Basis, I129Field, I129MissingField, I129Export,
+        Petition, PetitionRequest, Trip, TripRequest, TripImport, SkippedTrip, MaxStay, ValidityPeriod, RecapturedTrip, Ac21Eligibility, MaxStayReport,
+        Dashboard, StatusCount, ExpiringCount, MonthCount, ClientCount, SalaryDistribution, StateCount,
+        CaseStatusEntry, CaseStatusTimeline, CaseStatusRunSummary,
+        WageLevel, PrevailingWageImport, PrevailingWageLevels, LcaWageStatus, LcaWageCheck, LcaWageReport,
+        ValidationWarning, CreateCustomerResponse, SocOccupation, ZipCode)),
+    tags(
+        (name = "health", description = "Liveness, readiness and connectivity checks"),
+        (name = "customers", description = "H1B customer records"),
+        (name = "max_stay", description = "Six-year H-1B limit: petitions, travel history, recaptured time abroad and AC21"),
+        (name = "compliance_tasks", description = "Follow-ups raised for customers, e.g. a new LCA after a worksite change"),
+        (name = "public_access", description = "LCA public access files: supporting documents and the generated PDF"),
+        (name = "i129", description = "Form I-129 and H-1B supplement fields filled from the customer record"),
+        (name = "dashboard", description = "Aggregate figures for the admin dashboard"),
+        (name = "case_status", description = "USCIS case status tracking for pending receipts"),
+        (name = "prevailing_wage", description = "LCA salaries checked against OFLC prevailing wages"),
+        (name = "soc", description = "SOC occupation catalog for `lca_code`"),
+        (name = "addresses", description = "ZIP code dataset used to normalize addresses")
+    )
+)]
+pub struct ApiDoc;
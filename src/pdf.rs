@@ -0,0 +1,555 @@
+//! PDF output on top of lopdf: pages of text laid out in the standard
+//! Helvetica fonts, JPEG scans placed one to a page, and the pages of
+//! uploaded PDFs, put together in order into one document.
+//!
+//! The standard fonts need no embedding but only cover Windows-1252, so
+//! characters outside it are printed as `?`.
+
+use chrono::{DateTime, Utc};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Bookmark, Document, Object, ObjectId, Stream, StringFormat};
+use rust_decimal::Decimal;
+
+/// US Letter, in points.
+pub const PAGE_WIDTH: f32 = 612.0;
+pub const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 72.0;
+const TEXT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+/// Width of the label column of a `Block::Field`.
+const LABEL_WIDTH: f32 = 160.0;
+
+/// Page attributes a page may inherit from its ancestors in the page tree.
+const INHERITED: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Advance widths of Helvetica for ` ` to `~`, in thousandths of the font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // ` ` to `/`
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // `0` to `?`
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // `@` to `O`
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // `P` to `_`
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // `` ` `` to `o`
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // `p` to `~`
+];
+
+/// A piece of text content; `layout` turns a list of them into pages.
+#[derive(Debug, Clone)]
+pub enum Block {
+    Title(String),
+    Heading(String),
+    Paragraph(String),
+    /// Small print, e.g. a note under a table.
+    Note(String),
+    /// A label and its value side by side, the value wrapped in its column.
+    Field(String, String),
+    /// A line of a table of contents: text, dot leaders and a right-aligned value.
+    Entry(String, String),
+    Space,
+    PageBreak,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+/// A line of text placed on a page; `y` is its baseline.
+#[derive(Debug, Clone)]
+struct Line {
+    font: Font,
+    size: f32,
+    x: f32,
+    y: f32,
+    text: String,
+}
+
+/// The lines of one page, as laid out by `layout`.
+#[derive(Debug, Clone, Default)]
+pub struct TextPage {
+    lines: Vec<Line>,
+}
+
+/// Lays `blocks` out on as many pages as they need. Always returns at least one page.
+pub fn layout(blocks: &[Block]) -> Vec<TextPage> {
+    let mut cursor = Cursor { pages: vec![TextPage::default()], y: PAGE_HEIGHT - MARGIN };
+    for block in blocks {
+        match block {
+            Block::Title(text) => {
+                for line in wrap(text, Font::Bold, 18.0, TEXT_WIDTH) {
+                    cursor.line(Font::Bold, 18.0, 24.0, MARGIN, line);
+                }
+                cursor.space(8.0);
+            }
+            Block::Heading(text) => {
+                // Keep a heading on the page of the text under it.
+                if cursor.y - 4.0 * 15.0 < MARGIN {
+                    cursor.break_page();
+                }
+                cursor.space(6.0);
+                for line in wrap(text, Font::Bold, 13.0, TEXT_WIDTH) {
+                    cursor.line(Font::Bold, 13.0, 18.0, MARGIN, line);
+                }
+                cursor.space(2.0);
+            }
+            Block::Paragraph(text) => {
+                for line in wrap(text, Font::Regular, 11.0, TEXT_WIDTH) {
+                    cursor.line(Font::Regular, 11.0, 15.0, MARGIN, line);
+                }
+                cursor.space(6.0);
+            }
+            Block::Note(text) => {
+                for line in wrap(text, Font::Regular, 9.0, TEXT_WIDTH) {
+                    cursor.line(Font::Regular, 9.0, 12.0, MARGIN, line);
+                }
+                cursor.space(4.0);
+            }
+            Block::Field(label, value) => {
+                let labels = wrap(label, Font::Bold, 11.0, LABEL_WIDTH - 10.0);
+                let values = wrap(value, Font::Regular, 11.0, TEXT_WIDTH - LABEL_WIDTH);
+                for i in 0..labels.len().max(values.len()) {
+                    if cursor.y - 15.0 < MARGIN {
+                        cursor.break_page();
+                    }
+                    cursor.y -= 15.0;
+                    if let Some(label) = labels.get(i) {
+                        cursor.place(Font::Bold, 11.0, MARGIN, label.clone());
+                    }
+                    if let Some(value) = values.get(i) {
+                        cursor.place(Font::Regular, 11.0, MARGIN + LABEL_WIDTH, value.clone());
+                    }
+                }
+                cursor.space(3.0);
+            }
+            Block::Entry(text, value) => {
+                let value_width = text_width(value, Font::Regular, 11.0);
+                let lines = wrap(text, Font::Regular, 11.0, TEXT_WIDTH - value_width - 30.0);
+                let last = lines.len() - 1;
+                for (i, line) in lines.into_iter().enumerate() {
+                    if i < last {
+                        cursor.line(Font::Regular, 11.0, 16.0, MARGIN, line);
+                        continue;
+                    }
+                    let dot = text_width(".", Font::Regular, 11.0);
+                    let gap = TEXT_WIDTH - text_width(&line, Font::Regular, 11.0) - value_width - 12.0;
+                    let leaders = ".".repeat((gap / dot).max(0.0) as usize);
+                    let leaders_x = PAGE_WIDTH - MARGIN - value_width - 6.0 - text_width(&leaders, Font::Regular, 11.0);
+                    cursor.line(Font::Regular, 11.0, 16.0, MARGIN, line);
+                    cursor.place(Font::Regular, 11.0, leaders_x, leaders);
+                    cursor.place(Font::Regular, 11.0, PAGE_WIDTH - MARGIN - value_width, value.clone());
+                }
+            }
+            Block::Space => cursor.space(12.0),
+            Block::PageBreak => cursor.break_page(),
+        }
+    }
+    cursor.pages
+}
+
+struct Cursor {
+    pages: Vec<TextPage>,
+    y: f32,
+}
+
+impl Cursor {
+    /// Moves down by `leading` and places `text` there, on a new page if it doesn't fit.
+    fn line(&mut self, font: Font, size: f32, leading: f32, x: f32, text: String) {
+        if self.y - leading < MARGIN {
+            self.break_page();
+        }
+        self.y -= leading;
+        self.place(font, size, x, text);
+    }
+
+    /// Places `text` on the current baseline.
+    fn place(&mut self, font: Font, size: f32, x: f32, text: String) {
+        let y = self.y;
+        self.pages.last_mut().expect("layout starts with a page").lines.push(Line { font, size, x, y, text });
+    }
+
+    fn space(&mut self, points: f32) {
+        self.y -= points;
+    }
+
+    /// Starts a new page, unless the current one is still empty.
+    fn break_page(&mut self) {
+        if self.pages.last().is_some_and(|page| !page.lines.is_empty()) {
+            self.pages.push(TextPage::default());
+        }
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+}
+
+/// Splits `text` into lines no wider than `width`, at spaces where possible.
+/// Newlines in `text` always start a new line.
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if text_width(&candidate, font, size) <= width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // A word wider than the line, e.g. a long file name, is split anywhere.
+            for c in word.chars() {
+                if !line.is_empty() && text_width(&format!("{}{}", line, c), font, size) > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Width of `text` in points. Helvetica-Bold is taken as 6% wider than
+/// Helvetica, which is close enough for wrapping.
+fn text_width(text: &str, font: Font, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => HELVETICA_WIDTHS[c as usize - ' ' as usize] as u32,
+            _ => 556,
+        })
+        .sum();
+    let scale = if font == Font::Bold { 1.06 } else { 1.0 };
+    units as f32 * size / 1000.0 * scale
+}
+
+/// An amount as printed, e.g. `$125,000.00`.
+pub fn usd(amount: Decimal) -> String {
+    let amount = amount.round_dp(2);
+    let text = format!("{:.2}", amount.abs());
+    let (whole, cents) = text.split_once('.').unwrap_or((&text, "00"));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("{}${}.{}", if amount.is_sign_negative() { "-" } else { "" }, grouped, cents)
+}
+
+/// `text` in the fonts' WinAnsiEncoding.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Width, height and colour components of a baseline or progressive JPEG,
+/// read from its start-of-frame marker.
+pub fn jpeg_dimensions(jpeg: &[u8]) -> Option<(u16, u16, u8)> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= jpeg.len() {
+        if jpeg[i] != 0xFF {
+            return None;
+        }
+        let marker = jpeg[i + 1];
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([jpeg[i + 2], jpeg[i + 3]]) as usize;
+        let is_frame = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_frame {
+            let frame = jpeg.get(i + 4..i + 10)?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]);
+            let width = u16::from_be_bytes([frame[3], frame[4]]);
+            return (width > 0 && height > 0).then_some((width, height, frame[5]));
+        }
+        i += 2 + length;
+    }
+    None
+}
+
+/// Pages of `pdf`, or why it can't be added to a document.
+pub fn pdf_page_count(pdf: &[u8]) -> Result<usize, String> {
+    let document = Document::load_mem(pdf).map_err(|e| format!("not a readable PDF: {}", e))?;
+    if document.is_encrypted() {
+        return Err("the PDF is encrypted".to_string());
+    }
+    match document.get_pages().len() {
+        0 => Err("the PDF has no pages".to_string()),
+        pages => Ok(pages),
+    }
+}
+
+/// Printed on every page of a set of text pages.
+#[derive(Debug, Clone, Default)]
+pub struct Decoration {
+    /// Small print at the bottom left, next to the page number.
+    pub footer: Option<String>,
+    /// Large light grey text across the page, under the content, e.g. `DRAFT`.
+    pub watermark: Option<String>,
+}
+
+/// `text` across the middle of the page at 45 degrees, as large as fits.
+fn watermark_operations(text: &str) -> Vec<Operation> {
+    let diagonal = (PAGE_WIDTH * PAGE_WIDTH + PAGE_HEIGHT * PAGE_HEIGHT).sqrt();
+    let size = (diagonal * 0.6 / text_width(text, Font::Bold, 1.0).max(1.0)).min(96.0);
+    let half = text_width(text, Font::Bold, size) / 2.0;
+    let (sin, cos) = std::f32::consts::FRAC_PI_4.sin_cos();
+    // Start so that the middle of the baseline, raised by a third of the
+    // size, lands on the centre of the page.
+    let (rise_x, rise_y) = (-sin * size / 3.0, cos * size / 3.0);
+    let x = PAGE_WIDTH / 2.0 - half * cos - rise_x;
+    let y = PAGE_HEIGHT / 2.0 - half * sin - rise_y;
+    vec![
+        Operation::new("q", vec![]),
+        Operation::new("g", vec![0.85.into()]),
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec![Font::Bold.resource().into(), size.into()]),
+        Operation::new("Tm", vec![cos.into(), sin.into(), (-sin).into(), cos.into(), x.into(), y.into()]),
+        Operation::new("Tj", vec![Object::String(win_ansi(text), StringFormat::Literal)]),
+        Operation::new("ET", vec![]),
+        Operation::new("Q", vec![]),
+    ]
+}
+
+/// A document being put together page by page.
+pub struct PdfBuilder {
+    doc: Document,
+    pages_id: ObjectId,
+    fonts_id: ObjectId,
+    pages: Vec<ObjectId>,
+}
+
+impl Default for PdfBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfBuilder {
+    pub fn new() -> Self {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let font = |name: &str| {
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => name.to_string(),
+                "Encoding" => "WinAnsiEncoding",
+            }
+        };
+        let fonts_id = doc.add_object(dictionary! {
+            Font::Regular.resource() => font("Helvetica"),
+            Font::Bold.resource() => font("Helvetica-Bold"),
+        });
+        Self { doc, pages_id, fonts_id, pages: Vec::new() }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn add_text_pages(&mut self, pages: &[TextPage]) {
+        self.add_decorated_text_pages(pages, &Decoration::default());
+    }
+
+    /// Adds `pages` with `decoration` on each; the footer's page numbers
+    /// count these pages only.
+    pub fn add_decorated_text_pages(&mut self, pages: &[TextPage], decoration: &Decoration) {
+        for (i, page) in pages.iter().enumerate() {
+            let mut operations = Vec::new();
+            // First, so the text is printed over it.
+            if let Some(watermark) = &decoration.watermark {
+                operations.extend(watermark_operations(watermark));
+            }
+            let mut lines = page.lines.clone();
+            if let Some(footer) = &decoration.footer {
+                let number = format!("Page {} of {}", i + 1, pages.len());
+                let y = MARGIN / 2.0;
+                let x = PAGE_WIDTH - MARGIN - text_width(&number, Font::Regular, 8.0);
+                lines.push(Line { font: Font::Regular, size: 8.0, x: MARGIN, y, text: footer.clone() });
+                lines.push(Line { font: Font::Regular, size: 8.0, x, y, text: number });
+            }
+            for line in &lines {
+                operations.extend([
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec![line.font.resource().into(), line.size.into()]),
+                    Operation::new("Td", vec![line.x.into(), line.y.into()]),
+                    Operation::new("Tj", vec![Object::String(win_ansi(&line.text), StringFormat::Literal)]),
+                    Operation::new("ET", vec![]),
+                ]);
+            }
+            let resources = dictionary! { "Font" => self.fonts_id };
+            self.push_page(resources.into(), Content { operations });
+        }
+    }
+
+    /// Adds a page with the JPEG scaled to fit inside the margins.
+    pub fn add_jpeg(&mut self, jpeg: &[u8]) -> Result<(), String> {
+        let (width, height, components) = jpeg_dimensions(jpeg).ok_or("not a readable JPEG")?;
+        let color_space = match components {
+            1 => "DeviceGray",
+            3 => "DeviceRGB",
+            4 => "DeviceCMYK",
+            n => return Err(format!("JPEG with {} colour components is not supported", n)),
+        };
+        let mut image = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => width as i64,
+            "Height" => height as i64,
+            "ColorSpace" => color_space,
+            "BitsPerComponent" => 8,
+            "Filter" => "DCTDecode",
+        };
+        if components == 4 {
+            // CMYK JPEGs are nearly always written inverted, Adobe style.
+            image.set("Decode", [1, 0, 1, 0, 1, 0, 1, 0].map(Object::from).to_vec());
+        }
+        let image_id = self.doc.add_object(Stream::new(image, jpeg.to_vec()).with_compression(false));
+
+        let scale = (TEXT_WIDTH / width as f32).min((PAGE_HEIGHT - 2.0 * MARGIN) / height as f32);
+        let (w, h) = (width as f32 * scale, height as f32 * scale);
+        let (x, y) = ((PAGE_WIDTH - w) / 2.0, PAGE_HEIGHT - MARGIN - h);
+        let operations = vec![
+            Operation::new("q", vec![]),
+            Operation::new("cm", vec![w.into(), 0.into(), 0.into(), h.into(), x.into(), y.into()]),
+            Operation::new("Do", vec!["Im1".into()]),
+            Operation::new("Q", vec![]),
+        ];
+        let resources = dictionary! { "XObject" => dictionary! { "Im1" => image_id } };
+        self.push_page(resources.into(), Content { operations });
+        Ok(())
+    }
+
+    /// Adds every page of `pdf`, as they are. Returns how many were added.
+    pub fn add_pdf(&mut self, pdf: &[u8]) -> Result<usize, String> {
+        let mut source = Document::load_mem(pdf).map_err(|e| format!("not a readable PDF: {}", e))?;
+        if source.is_encrypted() {
+            return Err("the PDF is encrypted".to_string());
+        }
+        source.renumber_objects_with(self.doc.max_id + 1);
+        let page_ids: Vec<ObjectId> = source.get_pages().into_values().collect();
+        if page_ids.is_empty() {
+            return Err("the PDF has no pages".to_string());
+        }
+
+        // The pages move under this document's page tree, so what they
+        // inherited from theirs is copied onto them.
+        let mut pages = Vec::with_capacity(page_ids.len());
+        for id in &page_ids {
+            let mut page = source.get_dictionary(*id).map_err(|e| format!("unreadable page: {}", e))?.clone();
+            for key in INHERITED {
+                if !page.has(key) {
+                    if let Some(value) = inherited(&source, *id, key) {
+                        page.set(key, value);
+                    }
+                }
+            }
+            if !page.has(b"MediaBox") {
+                page.set("MediaBox", vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()]);
+            }
+            page.set("Parent", self.pages_id);
+            pages.push((*id, page));
+        }
+
+        // The source's catalog and page tree come along too; nothing refers
+        // to them any more, so `finish` drops them.
+        self.doc.max_id = self.doc.max_id.max(source.max_id);
+        self.doc.objects.extend(source.objects);
+        for (id, page) in pages {
+            self.doc.objects.insert(id, page.into());
+            self.pages.push(id);
+        }
+        Ok(page_ids.len())
+    }
+
+    /// Adds an outline entry for the page at `page_index`, counting from 0.
+    pub fn bookmark(&mut self, title: &str, page_index: usize) {
+        if let Some(page) = self.pages.get(page_index) {
+            self.doc.add_bookmark(Bookmark::new(title.to_string(), [0.0, 0.0, 0.0], 0, *page), None);
+        }
+    }
+
+    pub fn finish(mut self, title: &str, created_at: DateTime<Utc>) -> Result<Vec<u8>, String> {
+        let kids: Vec<Object> = self.pages.iter().map(|id| (*id).into()).collect();
+        let pages = dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => self.pages.len() as i64,
+        };
+        self.doc.objects.insert(self.pages_id, pages.into());
+
+        let mut catalog = dictionary! { "Type" => "Catalog", "Pages" => self.pages_id };
+        if let Some(outline_id) = self.doc.build_outline() {
+            catalog.set("Outlines", outline_id);
+            catalog.set("PageMode", "UseOutlines");
+        }
+        let catalog_id = self.doc.add_object(catalog);
+        let info_id = self.doc.add_object(dictionary! {
+            "Title" => Object::String(win_ansi(title), StringFormat::Literal),
+            "Producer" => Object::string_literal("visa-api"),
+            "CreationDate" => Object::string_literal(created_at.format("D:%Y%m%d%H%M%SZ").to_string()),
+        });
+        self.doc.trailer.set("Root", catalog_id);
+        self.doc.trailer.set("Info", info_id);
+
+        self.doc.prune_objects();
+        self.doc.compress();
+        let mut out = Vec::new();
+        self.doc.save_to(&mut out).map_err(|e| format!("writing the PDF failed: {}", e))?;
+        Ok(out)
+    }
+
+    fn push_page(&mut self, resources: Object, content: Content) {
+        let content = content.encode().expect("content operations encode");
+        let content_id = self.doc.add_object(Stream::new(dictionary! {}, content));
+        let page_id = self.doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => self.pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+            "Resources" => resources,
+            "Contents" => content_id,
+        });
+        self.pages.push(page_id);
+    }
+}
+
+/// `key` from the nearest ancestor of `page` in the page tree that has it.
+fn inherited(doc: &Document, page: ObjectId, key: &[u8]) -> Option<Object> {
+    let mut node = doc.get_dictionary(page).ok()?;
+    // Bounded, in case a broken file has a cycle in its page tree.
+    for _ in 0..64 {
+        let parent = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = doc.get_dictionary(parent).ok()?;
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+    }
+    None
+}
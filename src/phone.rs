@@ -0,0 +1,86 @@
+//! Phone numbers, parsed and validated with libphonenumber's metadata and
+//! stored in E.164 (`+12125550100`) with a display form next to them.
+
+use std::str::FromStr;
+
+pub use phonenumber::country::Id as Region;
+use phonenumber::Mode;
+
+/// A valid phone number in both stored forms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phone {
+    pub e164: String,
+    /// National format for numbers in the home region (`(212) 555-0100`),
+    /// international otherwise (`+91 98765 43210`).
+    pub display: String,
+}
+
+/// Region for an ISO 3166 code such as `US` or `in`.
+pub fn parse_region(code: &str) -> Result<Region, String> {
+    Region::from_str(&code.trim().to_ascii_uppercase())
+        .map_err(|_| format!("unknown region \"{}\": expected a two-letter country code such as US", code.trim()))
+}
+
+/// Parses `raw` and checks it is a valid number. A number without a country
+/// code (no `+`, `00` or `011` prefix) is read as a number of `region`;
+/// `home` picks the display format.
+pub fn normalize(raw: &str, region: Region, home: Region) -> Result<Phone, String> {
+    let invalid = |reason: &str| format!("invalid phone number \"{}\": {}", raw.trim(), reason);
+    let number = phonenumber::parse(Some(region), raw.trim()).map_err(|e| invalid(&e.to_string()))?;
+    if !phonenumber::is_valid(&number) {
+        let region = number.country().id().unwrap_or(region);
+        return Err(invalid(&format!("not a valid number in {}", region.as_ref())));
+    }
+
+    let mode = if number.country().id() == Some(home) { Mode::National } else { Mode::International };
+    Ok(Phone {
+        e164: number.format().mode(Mode::E164).to_string(),
+        display: number.format().mode(mode).to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const US: Region = Region::US;
+
+    #[test]
+    fn us_numbers_in_common_formats() {
+        for raw in ["(212) 555 0100", "212-555-0100", "+1 212 555 0100", "011 1 212 555 0100"] {
+            let phone = normalize(raw, US, US).unwrap();
+            assert_eq!(phone.e164, "+12125550100", "{}", raw);
+            assert_eq!(phone.display, "(212) 555-0100", "{}", raw);
+        }
+    }
+
+    #[test]
+    fn foreign_numbers_display_internationally() {
+        let phone = normalize("+91 98765 43210", US, US).unwrap();
+        assert_eq!(phone.e164, "+919876543210");
+        assert_eq!(phone.display, "+91 98765 43210");
+
+        let phone = normalize("98765 43210", Region::IN, US).unwrap();
+        assert_eq!(phone.e164, "+919876543210");
+    }
+
+    #[test]
+    fn home_region_picks_the_national_format() {
+        let phone = normalize("98765 43210", Region::IN, Region::IN).unwrap();
+        assert_eq!(phone.display, "098765 43210");
+    }
+
+    #[test]
+    fn rejects_short_and_invalid_numbers() {
+        assert!(normalize("555-1234", US, US).is_err());
+        assert!(normalize("not a number", US, US).is_err());
+        let err = normalize("+1 212 000 0000", US, US).unwrap_err();
+        assert!(err.contains("not a valid number in US"), "{}", err);
+    }
+
+    #[test]
+    fn parses_region_codes() {
+        assert_eq!(parse_region(" in ").unwrap(), Region::IN);
+        assert!(parse_region("XX").is_err());
+    }
+}
@@ -0,0 +1,298 @@
+//! Reads OFLC wage files from a directory, as unpacked from the yearly
+//! "All Industries" download at flag.dol.gov, plus a ZIP crosswalk:
+//!
+//! - `ALC_Export.csv`: `Area`, `SocCode`, `GeoLvl`, `Level1`..`Level4`, `Average` (hourly)
+//! - `Geography.csv`: `Area`, `AreaName`, `StateAb`, one row per county of an area
+//! - `zip_area.csv`: `zip` and `area` (or HUD's `ZIP` and `CBSA`). With a
+//!   `TOT_RATIO` or `RES_RATIO` column, a ZIP spanning several areas goes to
+//!   the one holding the largest share.
+//!
+//! Headers are matched case-insensitively.
+
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{soc_key, zip5};
+use crate::store::{PrevailingWage, PrevailingWageData, WageArea};
+
+pub const WAGE_FILE: &str = "ALC_Export.csv";
+pub const GEOGRAPHY_FILE: &str = "Geography.csv";
+pub const ZIP_FILE: &str = "zip_area.csv";
+
+#[derive(Debug)]
+pub struct ImportError {
+    pub file: String,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.file, self.message)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Loads all three files from `dir`. Nothing is partially loaded: any bad row fails the import.
+pub fn load_dir(dir: &str) -> Result<PrevailingWageData, ImportError> {
+    let dir = Path::new(dir);
+    Ok(PrevailingWageData {
+        source: dir.display().to_string(),
+        wages: read_wages(&dir.join(WAGE_FILE))?,
+        areas: read_areas(&dir.join(GEOGRAPHY_FILE))?,
+        zips: read_zips(&dir.join(ZIP_FILE))?,
+    })
+}
+
+/// A CSV file with its header row, for looking columns up by name.
+struct CsvFile {
+    name: String,
+    reader: csv::Reader<std::fs::File>,
+    headers: Vec<String>,
+}
+
+impl CsvFile {
+    fn open(path: &Path) -> Result<Self, ImportError> {
+        let name = path.display().to_string();
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(path)
+            .map_err(|e| ImportError { file: name.clone(), message: e.to_string() })?;
+        let headers = reader
+            .headers()
+            .map_err(|e| ImportError { file: name.clone(), message: e.to_string() })?
+            .iter()
+            // Excel exports start with a byte order mark.
+            .map(|h| h.trim_start_matches('\u{feff}').trim().to_ascii_lowercase())
+            .collect();
+        Ok(Self { name, reader, headers })
+    }
+
+    fn error(&self, message: impl Into<String>) -> ImportError {
+        ImportError { file: self.name.clone(), message: message.into() }
+    }
+
+    /// Index of the first of `names` present in the header.
+    fn column(&self, names: &[&str]) -> Option<usize> {
+        names.iter().find_map(|name| self.headers.iter().position(|h| h == name))
+    }
+
+    fn required_column(&self, names: &[&str]) -> Result<usize, ImportError> {
+        self.column(names)
+            .ok_or_else(|| self.error(format!("missing column {}", names.join(" or "))))
+    }
+
+    /// Data rows with their line numbers, for error messages.
+    fn rows(&mut self) -> impl Iterator<Item = Result<(u64, csv::StringRecord), ImportError>> + '_ {
+        let name = self.name.clone();
+        self.reader.records().map(move |record| {
+            let record = record.map_err(|e| ImportError { file: name.clone(), message: e.to_string() })?;
+            let line = record.position().map_or(0, |p| p.line());
+            Ok((line, record))
+        })
+    }
+}
+
+fn field(record: &csv::StringRecord, index: usize) -> &str {
+    record.get(index).unwrap_or_default().trim()
+}
+
+/// OFLC leaves unpublished levels blank (or `0`); those are `None`.
+fn wage(file: &CsvFile, line: u64, value: &str) -> Result<Option<Decimal>, ImportError> {
+    if value.is_empty() || value.eq_ignore_ascii_case("n/a") {
+        return Ok(None);
+    }
+    let wage = Decimal::from_str(value.trim_start_matches('$').replace(',', "").as_str())
+        .map_err(|_| file.error(format!("line {}: `{}` is not a wage", line, value)))?;
+    Ok((!wage.is_zero()).then_some(wage))
+}
+
+fn read_wages(path: &Path) -> Result<Vec<PrevailingWage>, ImportError> {
+    let mut file = CsvFile::open(path)?;
+    let area = file.required_column(&["area"])?;
+    let soc_code = file.required_column(&["soccode", "soc_code"])?;
+    let geo_level = file.column(&["geolvl", "geo_level"]);
+    let levels = [
+        file.required_column(&["level1"])?,
+        file.required_column(&["level2"])?,
+        file.required_column(&["level3"])?,
+        file.required_column(&["level4"])?,
+    ];
+    let average = file.column(&["average"]);
+
+    let records: Vec<_> = file.rows().collect::<Result<_, _>>()?;
+    let mut wages = Vec::with_capacity(records.len());
+    for (line, record) in records {
+        let [level_1, level_2, level_3, level_4] = [
+            wage(&file, line, field(&record, levels[0]))?,
+            wage(&file, line, field(&record, levels[1]))?,
+            wage(&file, line, field(&record, levels[2]))?,
+            wage(&file, line, field(&record, levels[3]))?,
+        ];
+        wages.push(PrevailingWage {
+            area: field(&record, area).to_string(),
+            soc_code: soc_key(field(&record, soc_code)),
+            geo_level: geo_level.map(|i| field(&record, i).to_string()).filter(|v| !v.is_empty()),
+            level_1,
+            level_2,
+            level_3,
+            level_4,
+            average: average.map(|i| wage(&file, line, field(&record, i))).transpose()?.flatten(),
+        });
+    }
+    if wages.is_empty() {
+        return Err(file.error("no wage rows"));
+    }
+    Ok(wages)
+}
+
+fn read_areas(path: &Path) -> Result<Vec<WageArea>, ImportError> {
+    let mut file = CsvFile::open(path)?;
+    let area = file.required_column(&["area"])?;
+    let area_name = file.required_column(&["areaname", "area_name"])?;
+    let state = file.column(&["stateab", "state"]);
+
+    let mut areas = Vec::new();
+    let mut seen = HashSet::new();
+    for row in file.rows() {
+        let (_, record) = row?;
+        let code = field(&record, area).to_string();
+        if !seen.insert(code.clone()) {
+            continue;
+        }
+        areas.push(WageArea {
+            area: code,
+            area_name: field(&record, area_name).to_string(),
+            state: state.map(|i| field(&record, i).to_string()).filter(|v| !v.is_empty()),
+        });
+    }
+    Ok(areas)
+}
+
+fn read_zips(path: &Path) -> Result<Vec<(String, String)>, ImportError> {
+    let mut file = CsvFile::open(path)?;
+    let zip = file.required_column(&["zip", "zipcode", "zip_code"])?;
+    let area = file.required_column(&["area", "cbsa", "msa"])?;
+    let ratio = file.column(&["tot_ratio", "res_ratio"]);
+
+    let records: Vec<_> = file.rows().collect::<Result<_, _>>()?;
+    let mut best: HashMap<String, (String, Decimal)> = HashMap::new();
+    for (line, record) in records {
+        // Spreadsheets drop leading zeros, so `7102` is New Jersey's `07102`.
+        let raw = field(&record, zip);
+        let padded = format!("{:0>5}", raw);
+        let zip = zip5(&padded).ok_or_else(|| file.error(format!("line {}: `{}` is not a ZIP code", line, raw)))?;
+        let share = match ratio {
+            Some(i) => Decimal::from_str(field(&record, i)).unwrap_or_default(),
+            None => Decimal::ZERO,
+        };
+        let area = field(&record, area).to_string();
+        match best.get(&zip) {
+            Some((_, current)) if *current >= share => {}
+            _ => {
+                best.insert(zip, (area, share));
+            }
+        }
+    }
+    Ok(best.into_iter().map(|(zip, (area, _))| (zip, area)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{MemoryPrevailingWageStore, PrevailingWageStore};
+    use std::path::PathBuf;
+
+    const GEOGRAPHY: &str = "Area,AreaName,StateAb\n35620,New York-Newark-Jersey City,NY\n35620,New York-Newark-Jersey City,NJ\n";
+    const ZIPS: &str = "ZIP,CBSA\n10001,35620\n7102,35620\n";
+
+    /// Writes the three files to a fresh directory, as an OFLC download unpacks.
+    fn wage_dir(name: &str, wages: &str, zips: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("prevailing-wage-test-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(WAGE_FILE), wages).unwrap();
+        std::fs::write(dir.join(GEOGRAPHY_FILE), GEOGRAPHY).unwrap();
+        std::fs::write(dir.join(ZIP_FILE), zips).unwrap();
+        dir
+    }
+
+    fn load(name: &str, wages: &str, zips: &str) -> Result<PrevailingWageData, ImportError> {
+        let dir = wage_dir(name, wages, zips);
+        let result = load_dir(dir.to_str().unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+        result
+    }
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn reads_level_columns() {
+        let wages = "\u{feff}Area,SocCode,GeoLvl,Level1,Level2,Level3,Level4,Average\n\
+            35620,15-1252,1,$45.50,\"1,052.25\",0,,60.00\n";
+        let data = load("levels", wages, ZIPS).unwrap();
+
+        let wage = &data.wages[0];
+        assert_eq!((wage.area.as_str(), wage.soc_code.as_str(), wage.geo_level.as_deref()), ("35620", "15-1252", Some("1")));
+        assert_eq!(wage.level_1, Some(dec("45.50")));
+        assert_eq!(wage.level_2, Some(dec("1052.25")));
+        // OFLC leaves unpublished levels blank or 0.
+        assert_eq!((wage.level_3, wage.level_4), (None, None));
+        assert_eq!(wage.average, Some(dec("60.00")));
+        assert_eq!(data.areas.len(), 1);
+    }
+
+    #[test]
+    fn pads_zips_and_keeps_the_largest_share() {
+        let zips = "zip,area,tot_ratio\n7102,35620,0.2\n07102,35084,0.8\n10001,35620,1\n";
+        let wages = "area,soc_code,level1,level2,level3,level4\n35620,15-1252,1,2,3,4\n";
+        let mut found = load("zips", wages, zips).unwrap().zips;
+        found.sort();
+        assert_eq!(found, [("07102".to_string(), "35084".to_string()), ("10001".to_string(), "35620".to_string())]);
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        let header = "Area,SocCode,Level1,Level2,Level3,Level4\n";
+        let err = load("bad-wage", &format!("{}35620,15-1252,45.50,abc,60,70\n", header), ZIPS).unwrap_err();
+        assert!(err.file.ends_with(WAGE_FILE), "{}", err);
+        assert!(err.message.contains("line 2: `abc` is not a wage"), "{}", err);
+
+        let err = load("no-level", "Area,SocCode,Level1,Level2,Level3\n35620,15-1252,1,2,3\n", ZIPS).unwrap_err();
+        assert!(err.message.contains("missing column level4"), "{}", err);
+
+        let err = load("empty", header, ZIPS).unwrap_err();
+        assert!(err.message.contains("no wage rows"), "{}", err);
+
+        let row = format!("{}35620,15-1252,1,2,3,4\n", header);
+        let err = load("bad-zip", &row, "zip,area\n1000A,35620\n").unwrap_err();
+        assert!(err.file.ends_with(ZIP_FILE), "{}", err);
+        assert!(err.message.contains("`1000A` is not a ZIP code"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn replace_swaps_in_the_new_import() {
+        let store = MemoryPrevailingWageStore::new();
+        let header = "Area,SocCode,Level1,Level2,Level3,Level4\n";
+        let first = load("first", &format!("{}35620,15-1252,40,50,60,70\n", header), ZIPS).unwrap();
+        store.replace(&first, "admin").await.unwrap();
+
+        let lookup = store.lookup("10001", "15-1252").await.unwrap();
+        assert_eq!(lookup.area_name.as_deref(), Some("New York-Newark-Jersey City"));
+        assert_eq!(lookup.wage.and_then(|w| w.level_2), Some(dec("50")));
+        // A ZIP outside the crosswalk has no area, so no wage either.
+        let unknown = store.lookup("99501", "15-1252").await.unwrap();
+        assert!(unknown.area.is_none() && unknown.wage.is_none());
+
+        let second = load("second", &format!("{}35620,15-1253,41,51,61,71\n", header), "ZIP,CBSA\n10001,35620\n").unwrap();
+        let import = store.replace(&second, "admin").await.unwrap();
+        assert_eq!((import.wages, import.zips), (1, 1));
+        assert!(store.lookup("10001", "15-1252").await.unwrap().wage.is_none());
+        assert!(store.lookup("07102", "15-1253").await.unwrap().area.is_none());
+        assert_eq!(store.lookup("10001", "15-1253").await.unwrap().wage.and_then(|w| w.level_1), Some(dec("41")));
+    }
+}
@@ -0,0 +1,137 @@
+//! LCA prevailing wage check against OFLC wage data.
+//!
+//! OFLC publishes hourly prevailing wages per area and SOC code at four levels
+//! (`import` reads their CSV files). A customer's worksite ZIP (`client_zip`)
+//! picks the area, `lca_code` the occupation and `lca_wage_level` the level the
+//! annual `lca_salary` has to reach.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+
+pub use crate::address::zip5;
+use crate::models::{CreateCustomer, LcaWageCheck, LcaWageStatus, PrevailingWageLevels};
+use crate::store::WageLookup;
+
+pub mod import;
+
+/// Hours in the work year OFLC uses to turn hourly wages into annual ones.
+const HOURS_PER_YEAR: i64 = 2080;
+
+/// OFLC wage level, from entry level (`I`) to fully competent (`IV`). Stored as `wage_level_enum`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+pub enum WageLevel {
+    I,
+    II,
+    III,
+    IV,
+}
+
+impl WageLevel {
+    pub const ALL: [WageLevel; 4] = [WageLevel::I, WageLevel::II, WageLevel::III, WageLevel::IV];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WageLevel::I => "I",
+            WageLevel::II => "II",
+            WageLevel::III => "III",
+            WageLevel::IV => "IV",
+        }
+    }
+}
+
+impl fmt::Display for WageLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WageLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WageLevel::ALL
+            .into_iter()
+            .find(|level| level.as_str() == s)
+            .ok_or_else(|| format!("unknown wage level `{}`", s))
+    }
+}
+
+/// SOC code as OFLC writes it (`15-1252`); see `soc::normalize`. Codes that
+/// don't parse are returned trimmed and won't match anything.
+pub fn soc_key(lca_code: &str) -> String {
+    crate::soc::normalize(lca_code).unwrap_or_else(|_| lca_code.trim().to_string())
+}
+
+pub fn annual(hourly: Decimal) -> Decimal {
+    (hourly * Decimal::from(HOURS_PER_YEAR)).round_dp(2)
+}
+
+/// Compares `customer.lca_salary` with the annual wage for the required level.
+/// `lookup` is `None` when the worksite ZIP couldn't be read.
+pub fn check(customer: &CreateCustomer, lookup: Option<&WageLookup>) -> LcaWageCheck {
+    let required_level = customer
+        .lca_wage_level
+        .as_deref()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(WageLevel::I);
+    let soc_code = soc_key(&customer.lca_code);
+
+    let mut result = LcaWageCheck {
+        customer_id: customer.customer_id,
+        first_name: customer.first_name.clone(),
+        last_name: customer.last_name.clone(),
+        lca_code: customer.lca_code.clone(),
+        lca_title: customer.lca_title.clone(),
+        lca_salary: customer.lca_salary,
+        worksite_zip: customer.client_zip.clone(),
+        area: None,
+        area_name: None,
+        levels: None,
+        required_level,
+        required_wage: None,
+        highest_level_met: None,
+        shortfall: None,
+        status: LcaWageStatus::Unknown,
+        detail: None,
+    };
+
+    let Some(lookup) = lookup else {
+        result.detail = Some(format!("worksite ZIP `{}` is not a ZIP code", customer.client_zip));
+        return result;
+    };
+    let Some(area) = &lookup.area else {
+        result.detail = Some(format!("no OFLC area for worksite ZIP {}", customer.client_zip));
+        return result;
+    };
+    result.area = Some(area.clone());
+    result.area_name = lookup.area_name.clone();
+    let Some(wage) = &lookup.wage else {
+        result.detail = Some(format!("no OFLC wage for SOC {} in area {}", soc_code, area));
+        return result;
+    };
+
+    let levels = PrevailingWageLevels {
+        level_1: wage.level_1.map(annual),
+        level_2: wage.level_2.map(annual),
+        level_3: wage.level_3.map(annual),
+        level_4: wage.level_4.map(annual),
+    };
+    result.highest_level_met = WageLevel::ALL
+        .into_iter()
+        .rev()
+        .find(|level| levels.get(*level).is_some_and(|wage| customer.lca_salary >= wage));
+    result.required_wage = levels.get(required_level);
+    result.levels = Some(levels);
+
+    match result.required_wage {
+        None => result.detail = Some(format!("OFLC publishes no Level {} wage for SOC {} in area {}", required_level, soc_code, area)),
+        Some(required) if customer.lca_salary >= required => result.status = LcaWageStatus::Compliant,
+        Some(required) => {
+            result.status = LcaWageStatus::Underpaid;
+            result.shortfall = Some(required - customer.lca_salary);
+        }
+    }
+    result
+}
@@ -0,0 +1,403 @@
+//! The public access file of an LCA (20 CFR 655.760(a)): the documents the
+//! employer has to make available for public examination from one working
+//! day after the LCA is filed, put together into one PDF behind a generated
+//! cover memo and table of contents.
+//!
+//! The wage rate and prevailing wage source sections get a page generated
+//! from the customer's LCA fields and the OFLC wage data; the others are
+//! uploaded. A required section with nothing on file is listed as missing
+//! in the memo rather than failing the whole file.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::{CreateCustomer, LcaDocument, LcaWageCheck, LcaWageStatus, NewLcaDocument, PrevailingWageImport};
+use crate::pdf::{layout, usd, Block, PdfBuilder, TextPage};
+
+/// Largest document that can be uploaded, 20 MB.
+pub const MAX_UPLOAD_BYTES: usize = 20 * 1024 * 1024;
+
+/// Longest `filename` kept for an upload.
+const MAX_FILENAME_LENGTH: usize = 200;
+
+/// A section of the public access file. Uploads are filed under one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentCategory {
+    /// The LCA as certified by the Department of Labor.
+    CertifiedLca,
+    /// Documentation of the wage rate paid; a page is generated from `lca_salary`.
+    WageRate,
+    /// Memo explaining how the actual wage was set for similarly employed workers.
+    ActualWageMemo,
+    /// Documentation of the prevailing wage and its source; a page is
+    /// generated from the OFLC wage data when it covers the worksite.
+    PrevailingWageSource,
+    /// Evidence the LCA notice was posted or sent electronically.
+    NoticePosting,
+    /// Summary of the benefits offered to US workers and H-1B workers alike.
+    BenefitsSummary,
+    /// Anything else worth keeping in the file.
+    Other,
+}
+
+impl DocumentCategory {
+    /// In the order the sections appear in the file.
+    pub const ALL: [DocumentCategory; 7] = [
+        DocumentCategory::CertifiedLca,
+        DocumentCategory::WageRate,
+        DocumentCategory::ActualWageMemo,
+        DocumentCategory::PrevailingWageSource,
+        DocumentCategory::NoticePosting,
+        DocumentCategory::BenefitsSummary,
+        DocumentCategory::Other,
+    ];
+
+    /// The label in the database and the API.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DocumentCategory::CertifiedLca => "certified_lca",
+            DocumentCategory::WageRate => "wage_rate",
+            DocumentCategory::ActualWageMemo => "actual_wage_memo",
+            DocumentCategory::PrevailingWageSource => "prevailing_wage_source",
+            DocumentCategory::NoticePosting => "notice_posting",
+            DocumentCategory::BenefitsSummary => "benefits_summary",
+            DocumentCategory::Other => "other",
+        }
+    }
+
+    /// Section heading in the file.
+    pub fn title(self) -> &'static str {
+        match self {
+            DocumentCategory::CertifiedLca => "Certified LCA",
+            DocumentCategory::WageRate => "Wage rate",
+            DocumentCategory::ActualWageMemo => "Actual wage memo",
+            DocumentCategory::PrevailingWageSource => "Prevailing wage and its source",
+            DocumentCategory::NoticePosting => "Notice posting evidence",
+            DocumentCategory::BenefitsSummary => "Benefits summary",
+            DocumentCategory::Other => "Other documents",
+        }
+    }
+
+    /// Whether the file is incomplete without this section.
+    pub fn is_required(self) -> bool {
+        self != DocumentCategory::Other
+    }
+}
+
+impl fmt::Display for DocumentCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DocumentCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DocumentCategory::ALL
+            .into_iter()
+            .find(|category| category.as_str() == s)
+            .ok_or_else(|| format!("unknown document category `{}`", s))
+    }
+}
+
+/// Checks an upload is a PDF the file can include or a JPEG, going by its
+/// content rather than any declared type.
+pub fn check_upload(content: Vec<u8>, category: DocumentCategory, filename: Option<&str>, uploaded_by: &str) -> Result<NewLcaDocument, String> {
+    let (content_type, extension, page_count) = if content.starts_with(b"%PDF-") {
+        ("application/pdf", "pdf", crate::pdf::pdf_page_count(&content)?)
+    } else if content.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        crate::pdf::jpeg_dimensions(&content).ok_or("not a readable JPEG")?;
+        ("image/jpeg", "jpg", 1)
+    } else {
+        return Err("only PDF and JPEG files can be uploaded".to_string());
+    };
+    let filename = match filename.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => name.chars().filter(|c| !c.is_control()).take(MAX_FILENAME_LENGTH).collect(),
+        None => format!("{}.{}", category.as_str(), extension),
+    };
+    Ok(NewLcaDocument {
+        category,
+        filename,
+        content_type: content_type.to_string(),
+        page_count: page_count as i32,
+        content,
+        uploaded_by: uploaded_by.to_string(),
+    })
+}
+
+/// Uppercased, in the ETA form `I-200-26123-123456`.
+pub fn normalize_case_number(raw: &str) -> Result<String, String> {
+    let number = raw.trim().to_ascii_uppercase();
+    let parts: Vec<&str> = number.split('-').collect();
+    let digits = |part: &str, length: usize| part.len() == length && part.chars().all(|c| c.is_ascii_digit());
+    match parts.as_slice() {
+        ["I", a, b, c] if digits(a, 3) && digits(b, 5) && digits(c, 6) => Ok(number),
+        _ => Err(format!("invalid LCA case number \"{}\": expected the form I-200-26123-123456", raw.trim())),
+    }
+}
+
+/// What a public access file is put together from.
+pub struct Contents {
+    pub customer: CreateCustomer,
+    pub wage_check: LcaWageCheck,
+    /// The OFLC wage data `wage_check` ran against, if any is loaded.
+    pub wage_data: Option<PrevailingWageImport>,
+    pub lca_case_number: Option<String>,
+    /// Uploaded documents with their content, oldest first.
+    pub documents: Vec<(LcaDocument, Vec<u8>)>,
+    pub generated_by: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// A generated public access file.
+pub struct PublicAccessPdf {
+    pub pdf: Vec<u8>,
+    pub page_count: usize,
+    /// Uploaded documents included, in order.
+    pub document_ids: Vec<Uuid>,
+    pub missing: Vec<DocumentCategory>,
+}
+
+struct Section<'a> {
+    number: usize,
+    category: DocumentCategory,
+    /// Shown ahead of the uploads; empty if the section has no generated page.
+    generated: Vec<TextPage>,
+    documents: Vec<&'a (LcaDocument, Vec<u8>)>,
+}
+
+impl Section<'_> {
+    fn heading(&self) -> String {
+        format!("{}. {}", self.number, self.category.title())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.generated.is_empty() && self.documents.is_empty()
+    }
+}
+
+/// Where a section starts, `None` if it has nothing on file, and where each
+/// of its documents starts.
+struct Placement {
+    section: Option<usize>,
+    documents: Vec<usize>,
+}
+
+/// Puts the file together: cover memo, table of contents, then a section
+/// per category in `DocumentCategory::ALL` order. Empty sections other
+/// than required ones are left out.
+pub fn assemble(contents: &Contents) -> Result<PublicAccessPdf, String> {
+    let mut sections = Vec::new();
+    for category in DocumentCategory::ALL {
+        let documents: Vec<_> = contents.documents.iter().filter(|(d, _)| d.category == category.as_str()).collect();
+        let mut section = Section { number: sections.len() + 1, category, generated: Vec::new(), documents };
+        let generated = match category {
+            DocumentCategory::WageRate => wage_rate_page(&section, contents),
+            DocumentCategory::PrevailingWageSource => prevailing_wage_page(&section, contents),
+            _ => Vec::new(),
+        };
+        if !generated.is_empty() {
+            section.generated = layout(&generated);
+        }
+        if category.is_required() || !section.is_empty() {
+            sections.push(section);
+        }
+    }
+    let missing: Vec<DocumentCategory> = sections.iter().filter(|s| s.is_empty()).map(|s| s.category).collect();
+
+    let memo = layout(&cover_memo(contents, &sections, &missing));
+    // Page numbers don't change how many lines the contents take.
+    let placeholders: Vec<Placement> = sections
+        .iter()
+        .map(|s| Placement { section: Some(9999), documents: vec![9999; s.documents.len()] })
+        .collect();
+    let toc_pages = layout(&table_of_contents(&sections, &placeholders)).len();
+    let mut next = memo.len() + toc_pages + 1;
+    let mut placements = Vec::with_capacity(sections.len());
+    for section in &sections {
+        let start = (!section.is_empty()).then_some(next);
+        next += section.generated.len();
+        let mut documents = Vec::with_capacity(section.documents.len());
+        for (document, _) in &section.documents {
+            documents.push(next);
+            next += document.page_count.max(0) as usize;
+        }
+        placements.push(Placement { section: start, documents });
+    }
+    let toc = layout(&table_of_contents(&sections, &placements));
+
+    let mut builder = PdfBuilder::new();
+    builder.add_text_pages(&memo);
+    builder.bookmark("Cover memo", 0);
+    builder.add_text_pages(&toc);
+    builder.bookmark("Table of contents", memo.len());
+    let mut document_ids = Vec::new();
+    for section in &sections {
+        if section.is_empty() {
+            continue;
+        }
+        builder.bookmark(&section.heading(), builder.page_count());
+        builder.add_text_pages(&section.generated);
+        for (document, content) in &section.documents {
+            let added = match document.content_type.as_str() {
+                "image/jpeg" => builder.add_jpeg(content),
+                _ => builder.add_pdf(content).map(|_| ()),
+            };
+            added.map_err(|e| format!("{} ({}): {}", document.filename, document.document_id, e))?;
+            document_ids.push(document.document_id);
+        }
+    }
+
+    let page_count = builder.page_count();
+    let title = format!("LCA public access file: {} {}", contents.customer.first_name, contents.customer.last_name);
+    let pdf = builder.finish(&title, contents.generated_at)?;
+    Ok(PublicAccessPdf { pdf, page_count, document_ids, missing })
+}
+
+fn cover_memo(contents: &Contents, sections: &[Section], missing: &[DocumentCategory]) -> Vec<Block> {
+    let customer = &contents.customer;
+    let mut blocks = vec![
+        Block::Title("LCA Public Access File".to_string()),
+        Block::Field("H-1B worker".to_string(), format!("{} {}", customer.first_name, customer.last_name)),
+        Block::Field("LCA case number".to_string(), contents.lca_case_number.clone().unwrap_or_else(|| "not recorded".to_string())),
+        Block::Field("Job title".to_string(), customer.lca_title.clone()),
+        Block::Field("SOC code".to_string(), customer.lca_code.clone()),
+        Block::Field("Wage level".to_string(), customer.lca_wage_level.clone().unwrap_or_else(|| "not recorded".to_string())),
+        Block::Field("Wage rate".to_string(), format!("{} per year", usd(customer.lca_salary))),
+        Block::Field("Worksite".to_string(), worksite(customer)),
+        Block::Field("Period of employment".to_string(), format!("{} to {}", customer.h1b_start_date, customer.h1b_end_date)),
+        Block::Field("Generated".to_string(), format!("{} by {}", contents.generated_at.format("%Y-%m-%d %H:%M UTC"), contents.generated_by)),
+        Block::Space,
+        Block::Heading("Memo".to_string()),
+        Block::Paragraph(
+            "This file holds the documentation 20 CFR 655.760(a) requires the employer to make available for public \
+             examination, at its principal place of business in the US or at the place of employment, within one \
+             working day after the LCA is filed. It must be kept for one year beyond the last date on which an H-1B \
+             worker is employed under the LCA, or one year after the LCA ends or is withdrawn if no one is."
+                .to_string(),
+        ),
+        Block::Paragraph(format!(
+            "The wage rate and prevailing wage pages were generated from the LCA details on record on {}; the other \
+             sections are copies of the documents on file.",
+            contents.generated_at.format("%Y-%m-%d")
+        )),
+        Block::Heading("Sections".to_string()),
+    ];
+    for section in sections {
+        let documents = match section.documents.len() {
+            0 => None,
+            1 => Some("1 document".to_string()),
+            n => Some(format!("{} documents", n)),
+        };
+        let status = match (section.generated.is_empty(), documents) {
+            (true, None) => "NOT ON FILE".to_string(),
+            (true, Some(documents)) => documents,
+            (false, None) => "generated page".to_string(),
+            (false, Some(documents)) => format!("generated page and {}", documents),
+        };
+        blocks.push(Block::Field(section.heading(), status));
+    }
+    if !missing.is_empty() {
+        let titles: Vec<&str> = missing.iter().map(|category| category.title()).collect();
+        blocks.push(Block::Heading("Missing documents".to_string()));
+        blocks.push(Block::Paragraph(format!(
+            "The file is incomplete: nothing is on file for {}. Add them before the file is made available.",
+            titles.join(", ")
+        )));
+    }
+    blocks
+}
+
+fn table_of_contents(sections: &[Section], placements: &[Placement]) -> Vec<Block> {
+    let mut blocks = vec![Block::Title("Table of Contents".to_string())];
+    for (section, placement) in sections.iter().zip(placements) {
+        let page = placement.section.map_or_else(|| "not on file".to_string(), |page| page.to_string());
+        blocks.push(Block::Entry(section.heading(), page));
+        for (n, ((document, _), page)) in section.documents.iter().zip(&placement.documents).enumerate() {
+            blocks.push(Block::Entry(format!("{}.{} {}", section.number, n + 1, document.filename), page.to_string()));
+        }
+    }
+    blocks
+}
+
+/// 20 CFR 655.760(a)(2): documentation of the wage rate to be paid.
+fn wage_rate_page(section: &Section, contents: &Contents) -> Vec<Block> {
+    let customer = &contents.customer;
+    let mut blocks = vec![
+        Block::Title(section.heading()),
+        Block::Paragraph(format!(
+            "The wage rate to be paid to {} {} as {} under this LCA is {} per year.",
+            customer.first_name,
+            customer.last_name,
+            customer.lca_title,
+            usd(customer.lca_salary)
+        )),
+        Block::Field("Annual wage".to_string(), usd(customer.lca_salary)),
+        Block::Field("Hourly equivalent".to_string(), usd((customer.lca_salary / Decimal::from(2080)).round_dp(2))),
+        Block::Field("Wage level".to_string(), customer.lca_wage_level.clone().unwrap_or_else(|| "not recorded".to_string())),
+        Block::Field("Employment from".to_string(), customer.employment_start_date.to_string()),
+    ];
+    if !section.documents.is_empty() {
+        blocks.push(Block::Space);
+        blocks.push(Block::Note("Supporting documentation on file follows this page.".to_string()));
+    }
+    blocks
+}
+
+/// 20 CFR 655.760(a)(3): the prevailing wage and its source. Nothing is
+/// generated when the OFLC data doesn't cover the worksite and occupation.
+fn prevailing_wage_page(section: &Section, contents: &Contents) -> Vec<Block> {
+    let check = &contents.wage_check;
+    let (Some(wage), Some(data)) = (check.required_wage, &contents.wage_data) else {
+        return Vec::new();
+    };
+    let area = match (&check.area_name, &check.area) {
+        (Some(name), Some(code)) => format!("{} ({})", name, code),
+        (None, Some(code)) => code.clone(),
+        _ => format!("ZIP {}", check.worksite_zip),
+    };
+    let mut blocks = vec![
+        Block::Title(section.heading()),
+        Block::Paragraph(format!(
+            "The prevailing wage for SOC {} at Level {} in the area of intended employment is {} per year. The LCA \
+             wage of {} {} it.",
+            check.lca_code,
+            check.required_level,
+            usd(wage),
+            usd(check.lca_salary),
+            if check.status == LcaWageStatus::Compliant { "meets" } else { "does not meet" }
+        )),
+        Block::Field("Source".to_string(), "OFLC Online Wage Library (Foreign Labor Certification Data Center)".to_string()),
+        Block::Field("Area".to_string(), area),
+        Block::Field("Worksite ZIP".to_string(), check.worksite_zip.clone()),
+        Block::Field("SOC code".to_string(), check.lca_code.clone()),
+        Block::Field("Wage level".to_string(), check.required_level.to_string()),
+        Block::Field("Prevailing wage".to_string(), format!("{} per year", usd(wage))),
+        Block::Field("Wage data loaded".to_string(), data.imported_at.format("%Y-%m-%d").to_string()),
+    ];
+    if let Some(levels) = &check.levels {
+        let level = |wage: Option<Decimal>| wage.map_or_else(|| "not published".to_string(), usd);
+        blocks.push(Block::Space);
+        blocks.push(Block::Heading("All levels".to_string()));
+        blocks.push(Block::Field("Level I".to_string(), level(levels.level_1)));
+        blocks.push(Block::Field("Level II".to_string(), level(levels.level_2)));
+        blocks.push(Block::Field("Level III".to_string(), level(levels.level_3)));
+        blocks.push(Block::Field("Level IV".to_string(), level(levels.level_4)));
+        blocks.push(Block::Note("Annual wages: the OFLC hourly wage times 2080 hours.".to_string()));
+    }
+    blocks
+}
+
+/// The worksite on the LCA, as printed on the memo and kept with a generation record.
+pub fn worksite(customer: &CreateCustomer) -> String {
+    format!(
+        "{}, {}, {}, {} {}",
+        customer.client_name, customer.client_street_name, customer.client_city, customer.client_state, customer.client_zip
+    )
+}
@@ -0,0 +1,73 @@
+//! USCIS receipt numbers: three-letter service center prefix plus ten digits,
+//! e.g. `EAC2412345678`.
+
+/// Service center and office prefixes USCIS issues receipts under.
+pub const SERVICE_CENTER_PREFIXES: &[&str] = &[
+    "EAC", "WAC", "LIN", "SRC", "NBC", "MSC", "IOE", "YSC", "NSC", "TSC", "VSC", "CSC",
+];
+
+const DIGITS: usize = 10;
+
+/// Uppercases and drops the spaces and dashes that come along when a receipt
+/// number is pasted from a PDF, then checks the format.
+pub fn normalize(raw: &str) -> Result<String, String> {
+    let receipt: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && !is_dash(*c))
+        .flat_map(char::to_uppercase)
+        .collect();
+
+    let valid = receipt.len() == 3 + DIGITS
+        && receipt.is_char_boundary(3)
+        && SERVICE_CENTER_PREFIXES.contains(&&receipt[..3])
+        && receipt[3..].bytes().all(|b| b.is_ascii_digit());
+    if valid {
+        Ok(receipt)
+    } else {
+        Err(format!(
+            "invalid receipt number \"{}\": expected a service center prefix ({}) followed by {} digits",
+            raw.trim(),
+            SERVICE_CENTER_PREFIXES.join(", "),
+            DIGITS
+        ))
+    }
+}
+
+/// ASCII hyphen plus the dash variants PDF text extraction tends to produce.
+fn is_dash(c: char) -> bool {
+    matches!(c, '-' | '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2212}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_spaces_dashes_and_case() {
+        assert_eq!(normalize("EAC2412345678").unwrap(), "EAC2412345678");
+        assert_eq!(normalize(" eac-24-123-45678 ").unwrap(), "EAC2412345678");
+        assert_eq!(normalize("WAC 24 123 45678").unwrap(), "WAC2412345678");
+        assert_eq!(normalize("ioe\u{2013}0912345678").unwrap(), "IOE0912345678");
+    }
+
+    #[test]
+    fn rejects_unknown_prefixes_and_wrong_lengths() {
+        assert!(normalize("ABC2412345678").is_err());
+        assert!(normalize("EAC241234567").is_err());
+        assert!(normalize("EAC24123456789").is_err());
+        assert!(normalize("EAC241234567X").is_err());
+        assert!(normalize("").is_err());
+    }
+
+    #[test]
+    fn non_ascii_input_is_rejected_without_panicking() {
+        assert!(normalize("ÉAC241234567").is_err());
+        assert!(normalize("EA\u{00C7}2412345678").is_err());
+    }
+
+    #[test]
+    fn error_quotes_the_trimmed_input() {
+        let err = normalize("  EAC123  ").unwrap_err();
+        assert!(err.starts_with("invalid receipt number \"EAC123\""), "{}", err);
+    }
+}
@@ -0,0 +1,275 @@
+//! Standard Occupational Classification (SOC 2018) catalog for `lca_code`.
+//!
+//! The embedded catalog (`soc_2018.csv`) covers the occupations H-1B petitions
+//! are usually filed under. The full list can be loaded instead from the BLS
+//! `soc_2018_definitions` file saved as CSV (`soc.catalog_file`).
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use utoipa::ToSchema;
+
+const EMBEDDED_CATALOG: &str = include_str!("soc_2018.csv");
+
+/// SOC 2018 major groups, the first two digits of every code.
+const MAJOR_GROUPS: &[&str] = &[
+    "11", "13", "15", "17", "19", "21", "23", "25", "27", "29", "31", "33",
+    "35", "37", "39", "41", "43", "45", "47", "49", "51", "53", "55",
+];
+
+/// Below this similarity an `lca_title` is reported as not matching its SOC title.
+const TITLE_MATCH_THRESHOLD: f64 = 0.5;
+
+/// Words that say nothing about the occupation itself.
+const TITLE_NOISE: &[&str] = &[
+    "and", "or", "of", "the", "for", "in", "except", "all", "other", "including",
+    "sr", "senior", "jr", "junior", "lead", "principal", "staff", "associate", "assistant", "i", "ii", "iii", "iv",
+];
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SocOccupation {
+    /// Detailed SOC code, e.g. `15-1252`.
+    pub code: String,
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Debug)]
+pub struct CatalogError(String);
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid SOC catalog: {}", self.0)
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// Detailed occupations by code.
+#[derive(Debug, Clone)]
+pub struct SocCatalog {
+    occupations: BTreeMap<String, SocOccupation>,
+    /// Where the catalog came from, for logs.
+    pub source: String,
+}
+
+impl SocCatalog {
+    pub fn embedded() -> Self {
+        Self::from_csv(EMBEDDED_CATALOG.as_bytes(), "embedded")
+            .expect("the embedded SOC catalog is valid")
+    }
+
+    /// Reads a CSV with `SOC Code`, `SOC Title` and `SOC Definition` columns. If
+    /// there is a `SOC Group` column, only `Detailed` rows are kept.
+    pub fn from_file(path: &str) -> Result<Self, CatalogError> {
+        let file = std::fs::File::open(path).map_err(|e| CatalogError(format!("{}: {}", path, e)))?;
+        Self::from_csv(file, path)
+    }
+
+    fn from_csv(reader: impl std::io::Read, source: &str) -> Result<Self, CatalogError> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| CatalogError(e.to_string()))?
+            .iter()
+            .map(|h| h.trim_start_matches('\u{feff}').trim().to_ascii_lowercase())
+            .collect();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let code = column("soc code").ok_or_else(|| CatalogError("missing column `SOC Code`".to_string()))?;
+        let title = column("soc title").ok_or_else(|| CatalogError("missing column `SOC Title`".to_string()))?;
+        let description = column("soc definition");
+        let group = column("soc group");
+
+        let mut occupations = BTreeMap::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| CatalogError(e.to_string()))?;
+            let field = |i: usize| record.get(i).unwrap_or_default().trim().to_string();
+            if group.is_some_and(|i| !field(i).eq_ignore_ascii_case("detailed")) {
+                continue;
+            }
+            let Ok(code) = normalize(&field(code)) else {
+                continue;
+            };
+            occupations.insert(code.clone(), SocOccupation {
+                code,
+                title: field(title),
+                description: description.map(field).unwrap_or_default(),
+            });
+        }
+        if occupations.is_empty() {
+            return Err(CatalogError("no detailed occupations".to_string()));
+        }
+        Ok(Self { occupations, source: source.to_string() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.occupations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.occupations.is_empty()
+    }
+
+    /// Looks up a code in any form `normalize` accepts.
+    pub fn get(&self, code: &str) -> Option<&SocOccupation> {
+        normalize(code).ok().and_then(|code| self.occupations.get(&code))
+    }
+
+    /// Occupations matching `query` for autocomplete, best first. A query that
+    /// looks like a code matches by prefix (`15-12`); otherwise every word has to
+    /// start a word of the title or, ranked lower, appear in the description.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SocOccupation> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let code_prefix: String = query.chars().filter(|c| c.is_ascii_digit()).collect();
+        if !code_prefix.is_empty() && query.chars().all(|c| c.is_ascii_digit() || c == '-' || c == '.') {
+            return self
+                .occupations
+                .values()
+                .filter(|o| o.code.replace('-', "").starts_with(&code_prefix))
+                .take(limit)
+                .cloned()
+                .collect();
+        }
+
+        let terms: Vec<&str> = query.split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty()).collect();
+        let mut matches: Vec<(u8, &SocOccupation)> = self
+            .occupations
+            .values()
+            .filter_map(|o| {
+                let title = o.title.to_lowercase();
+                let title_words: Vec<&str> = title.split(|c: char| !c.is_alphanumeric()).collect();
+                let in_title = |t: &&str| title_words.iter().any(|w| w.starts_with(*t));
+                if terms.iter().all(in_title) {
+                    // Titles starting with the query first.
+                    return Some((if title.starts_with(&query) { 0 } else { 1 }, o));
+                }
+                let description = o.description.to_lowercase();
+                terms
+                    .iter()
+                    .all(|t| in_title(t) || description.contains(*t))
+                    .then_some((2, o))
+            })
+            .collect();
+        matches.sort_by(|(a_rank, a), (b_rank, b)| a_rank.cmp(b_rank).then_with(|| a.title.cmp(&b.title)));
+        matches.into_iter().take(limit).map(|(_, o)| o.clone()).collect()
+    }
+}
+
+/// Canonical `DD-DDDD` form of a detailed SOC code. Accepts the O*NET form
+/// (`15-1252.00`) and missing dashes, and checks the major group exists.
+pub fn normalize(raw: &str) -> Result<String, String> {
+    let trimmed = raw.trim();
+    let base = trimmed.strip_suffix(".00").unwrap_or(trimmed);
+    let digits: String = base.chars().filter(|c| *c != '-').collect();
+    if digits.len() != 6 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("invalid SOC code \"{}\": expected six digits like 15-1252", trimmed));
+    }
+    if !MAJOR_GROUPS.contains(&&digits[..2]) {
+        return Err(format!("invalid SOC code \"{}\": {} is not a SOC major group", trimmed, &digits[..2]));
+    }
+    Ok(format!("{}-{}", &digits[..2], &digits[2..]))
+}
+
+/// How much a job title looks like a SOC title, from 0 to 1: the share of the
+/// job title's words (ignoring seniority and filler, and plural endings) found
+/// in the SOC title, or their overall character similarity if that is higher.
+pub fn title_similarity(job_title: &str, soc_title: &str) -> f64 {
+    let job = title_words(job_title);
+    let soc = title_words(soc_title);
+    if job.is_empty() || soc.is_empty() {
+        return 0.0;
+    }
+    let shared = job
+        .iter()
+        .filter(|word| soc.iter().any(|s| strsim::jaro_winkler(word, s) >= 0.9))
+        .count();
+    let overlap = shared as f64 / job.len() as f64;
+    overlap.max(strsim::sorensen_dice(&job.join(" "), &soc.join(" ")))
+}
+
+/// Whether `job_title` is too far from `soc_title` to be the same occupation.
+pub fn title_mismatch(job_title: &str, soc_title: &str) -> bool {
+    title_similarity(job_title, soc_title) < TITLE_MATCH_THRESHOLD
+}
+
+fn title_words(title: &str) -> Vec<String> {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !TITLE_NOISE.contains(w))
+        .map(|w| match w.strip_suffix('s') {
+            Some(stem) if stem.len() > 3 && !stem.ends_with('s') => stem.to_string(),
+            _ => w.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(occupations: &[SocOccupation]) -> Vec<&str> {
+        occupations.iter().map(|o| o.code.as_str()).collect()
+    }
+
+    #[test]
+    fn normalizes_codes() {
+        for raw in ["15-1252", " 151252 ", "15-1252.00"] {
+            assert_eq!(normalize(raw).unwrap(), "15-1252", "{}", raw);
+        }
+        assert!(normalize("15-125").is_err());
+        assert!(normalize("15-12a2").is_err());
+        assert!(normalize("99-1252").unwrap_err().contains("not a SOC major group"));
+    }
+
+    #[test]
+    fn looks_up_an_exact_code() {
+        let catalog = SocCatalog::embedded();
+        assert_eq!(catalog.get("15-1252.00").map(|o| o.title.as_str()), Some("Software Developers"));
+        assert!(catalog.get("15-1259").is_none());
+        assert_eq!(codes(&catalog.search("15-1252", 10)), ["15-1252"]);
+    }
+
+    #[test]
+    fn searches_codes_by_prefix() {
+        let catalog = SocCatalog::embedded();
+        assert_eq!(codes(&catalog.search("15-125", 10)), ["15-1251", "15-1252", "15-1253", "15-1254", "15-1255"]);
+        assert_eq!(catalog.search("15-125", 2).len(), 2);
+        assert!(catalog.search("  ", 10).is_empty());
+    }
+
+    #[test]
+    fn searches_titles_before_descriptions() {
+        let catalog = SocCatalog::embedded();
+        let found = catalog.search("software dev", 10);
+        assert_eq!(found[0].code, "15-1252");
+
+        // Every word has to match, not just one of them.
+        assert!(catalog.search("software xylophone", 10).is_empty());
+
+        // "nursing" is only in the description of Registered Nurses.
+        assert!(codes(&catalog.search("nursing", 10)).contains(&"29-1141"));
+        let web = catalog.search("developers", 10);
+        assert!(codes(&web).starts_with(&["15-1252", "15-1254"]), "{:?}", codes(&web));
+    }
+
+    #[test]
+    fn close_titles_match() {
+        for job_title in ["Software Developers", "Senior Software Developer", "Software Developer II", "Sr. Software Dev"] {
+            assert!(!title_mismatch(job_title, "Software Developers"), "{}", job_title);
+        }
+        assert!(!title_mismatch("Staff Accountant", "Accountants and Auditors"));
+    }
+
+    #[test]
+    fn different_occupations_mismatch() {
+        assert!(title_mismatch("Registered Nurse", "Software Developers"));
+        assert!(title_mismatch("Marketing Manager", "Accountants and Auditors"));
+        assert!(title_mismatch("", "Software Developers"));
+        assert!(title_similarity("Registered Nurse", "Registered Nurses") > title_similarity("Nurse Manager", "Registered Nurses"));
+    }
+}
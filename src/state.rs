@@ -0,0 +1,106 @@
+use sqlx::PgPool;
+use std::sync::Arc;
+use crate::address::ZipDirectory;
+use crate::config::settings::Config;
+use crate::case_status::CaseStatusTracker;
+use crate::i129::FieldNames;
+use crate::soc::SocCatalog;
+use crate::store::{
+    CaseStatusStore, CircuitBreaker, CustomerStore, IdempotencyStore, LcaDocumentStore, PrevailingWageStore, Resilience,
+    ResilientStore,
+};
+use crate::validation::Validator;
+
+/// Shared application state handed to every handler through `Router::with_state`.
+///
+/// Cloned per request, so everything in here is either cheap to clone (`PgPool`
+/// is a handle) or behind an `Arc`. Tests build their own instance pointing at
+/// an isolated database instead of relying on process-wide globals.
+#[derive(Clone)]
+pub struct AppState {
+    /// `None` when running on the in-memory store.
+    pub pool: Option<PgPool>,
+    pub config: Arc<Config>,
+    pub customers: Arc<dyn CustomerStore>,
+    /// Shared with the store's retry layer; the HTTP layer reads it to fail fast.
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    pub idempotency: Arc<dyn IdempotencyStore>,
+    pub case_status: Arc<dyn CaseStatusStore>,
+    /// `None` when no case status provider is configured.
+    pub case_status_tracker: Option<Arc<CaseStatusTracker>>,
+    pub prevailing_wages: Arc<dyn PrevailingWageStore>,
+    pub lca_documents: Arc<dyn LcaDocumentStore>,
+    pub soc: Arc<SocCatalog>,
+    pub zips: Arc<ZipDirectory>,
+    pub i129_field_names: Arc<FieldNames>,
+}
+
+/// Reference data loaded at startup.
+pub struct ReferenceData {
+    pub soc: SocCatalog,
+    pub zips: ZipDirectory,
+    pub i129_field_names: FieldNames,
+}
+
+/// One store per kind of data, all on the same backend.
+pub struct Stores {
+    pub customers: Arc<dyn CustomerStore>,
+    pub idempotency: Arc<dyn IdempotencyStore>,
+    pub case_status: Arc<dyn CaseStatusStore>,
+    pub prevailing_wages: Arc<dyn PrevailingWageStore>,
+    pub lca_documents: Arc<dyn LcaDocumentStore>,
+}
+
+impl Stores {
+    /// Wraps every store with the same retry policy and circuit breaker.
+    pub fn resilient(self, resilience: Arc<Resilience>) -> Self {
+        Self {
+            customers: Arc::new(ResilientStore::new(self.customers, resilience.clone())),
+            idempotency: Arc::new(ResilientStore::new(self.idempotency, resilience.clone())),
+            case_status: Arc::new(ResilientStore::new(self.case_status, resilience.clone())),
+            prevailing_wages: Arc::new(ResilientStore::new(self.prevailing_wages, resilience.clone())),
+            lca_documents: Arc::new(ResilientStore::new(self.lca_documents, resilience)),
+        }
+    }
+}
+
+impl AppState {
+    pub fn new(
+        pool: Option<PgPool>,
+        config: Config,
+        stores: Stores,
+        circuit_breaker: Arc<CircuitBreaker>,
+        case_status_tracker: Option<Arc<CaseStatusTracker>>,
+        reference: ReferenceData,
+    ) -> Self {
+        Self {
+            pool,
+            config: Arc::new(config),
+            customers: stores.customers,
+            circuit_breaker,
+            idempotency: stores.idempotency,
+            case_status: stores.case_status,
+            case_status_tracker,
+            prevailing_wages: stores.prevailing_wages,
+            lca_documents: stores.lca_documents,
+            soc: Arc::new(reference.soc),
+            zips: Arc::new(reference.zips),
+            i129_field_names: Arc::new(reference.i129_field_names),
+        }
+    }
+
+    /// Input checks against the loaded reference data.
+    pub fn validator(&self) -> Validator<'_> {
+        Validator {
+            soc: &self.soc,
+            zips: &self.zips,
+            reject_unknown_soc_codes: self.config.soc.reject_unknown_codes,
+            default_phone_region: self.config.phone.default_region,
+        }
+    }
+
+    /// Schema holding the application tables, validated as a plain identifier at startup.
+    pub fn schema(&self) -> &str {
+        &self.config.database.schema
+    }
+}
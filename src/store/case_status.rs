@@ -0,0 +1,235 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::RwLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::{postgres, StoreError};
+use crate::models::CaseStatusEntry;
+
+/// A status as reported by a `CaseStatusProvider`, before it is recorded.
+#[derive(Debug, Clone)]
+pub struct CaseStatusObservation {
+    pub receipt_number: String,
+    pub customer_id: Uuid,
+    pub status: String,
+    pub description: Option<String>,
+    pub provider: String,
+}
+
+/// Per-receipt timeline of USCIS case statuses.
+#[async_trait]
+pub trait CaseStatusStore: Send + Sync {
+    /// Records `observation`. Returns the new entry if the status differs from
+    /// the latest one for the receipt (or there was none); otherwise only bumps
+    /// the latest entry's `last_checked_at` and returns `None`.
+    async fn record(&self, observation: &CaseStatusObservation) -> Result<Option<CaseStatusEntry>, StoreError>;
+
+    /// Every entry for the receipt, oldest first.
+    async fn history(&self, receipt_number: &str) -> Result<Vec<CaseStatusEntry>, StoreError>;
+
+    /// Entries that replaced an earlier status, observed after `since`, newest first.
+    async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<CaseStatusEntry>, StoreError>;
+}
+
+const ENTRY_COLUMNS: &str = "entry_id, receipt_number, customer_id, status, description, previous_status,
+        provider, observed_at, last_checked_at";
+
+pub struct PgCaseStatusStore {
+    pool: PgPool,
+    schema: String,
+    statement_timeout: Option<Duration>,
+}
+
+impl PgCaseStatusStore {
+    pub fn new(pool: PgPool, schema: impl Into<String>) -> Self {
+        Self { pool, schema: schema.into(), statement_timeout: None }
+    }
+
+    /// Cancels any statement running longer than `timeout` (`None` disables the limit).
+    pub fn with_statement_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.statement_timeout = timeout;
+        self
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, StoreError> {
+        postgres::begin(&self.pool, self.statement_timeout).await
+    }
+}
+
+#[async_trait]
+impl CaseStatusStore for PgCaseStatusStore {
+    async fn record(&self, o: &CaseStatusObservation) -> Result<Option<CaseStatusEntry>, StoreError> {
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+
+        // Several instances may poll the same receipt; only one may decide it changed.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("{schema}.case_status_history:{}", o.receipt_number))
+            .execute(&mut *tx)
+            .await?;
+        let sql = format!("SELECT entry_id, status FROM {schema}.case_status_history
+            WHERE receipt_number = $1 ORDER BY observed_at DESC LIMIT 1");
+        let latest: Option<(Uuid, String)> = sqlx::query_as(&sql).bind(&o.receipt_number).fetch_optional(&mut *tx).await?;
+
+        let entry = match latest {
+            Some((entry_id, status)) if status == o.status => {
+                let sql = format!("UPDATE {schema}.case_status_history SET last_checked_at = now() WHERE entry_id = $1");
+                sqlx::query(&sql).bind(entry_id).execute(&mut *tx).await?;
+                None
+            }
+            latest => {
+                let sql = format!("INSERT INTO {schema}.case_status_history (
+                        receipt_number, customer_id, status, description, previous_status, provider
+                    ) VALUES ($1, $2, $3, $4, $5, $6)
+                    RETURNING {ENTRY_COLUMNS}");
+                let entry: CaseStatusEntry = sqlx::query_as(&sql)
+                    .bind(&o.receipt_number)
+                    .bind(o.customer_id)
+                    .bind(&o.status)
+                    .bind(&o.description)
+                    .bind(latest.map(|(_, status)| status))
+                    .bind(&o.provider)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                Some(entry)
+            }
+        };
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn history(&self, receipt_number: &str) -> Result<Vec<CaseStatusEntry>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {ENTRY_COLUMNS} FROM {schema}.case_status_history
+            WHERE receipt_number = $1 ORDER BY observed_at");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(receipt_number).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<CaseStatusEntry>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {ENTRY_COLUMNS} FROM {schema}.case_status_history
+            WHERE previous_status IS NOT NULL AND observed_at > $1 ORDER BY observed_at DESC");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(since).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+}
+
+/// In-memory `CaseStatusStore` for the memory storage backend.
+#[derive(Default)]
+pub struct MemoryCaseStatusStore {
+    entries: RwLock<Vec<CaseStatusEntry>>,
+}
+
+impl MemoryCaseStatusStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves every entry of customer `from` to customer `to`, for
+    /// `MemoryCustomerStore::merge`. Returns how many entries moved.
+    pub fn reassign(&self, from: Uuid, to: Uuid) -> u64 {
+        let mut entries = self.entries.write().unwrap();
+        let mut moved = 0;
+        for e in entries.iter_mut().filter(|e| e.customer_id == Some(from)) {
+            e.customer_id = Some(to);
+            moved += 1;
+        }
+        moved
+    }
+}
+
+#[async_trait]
+impl CaseStatusStore for MemoryCaseStatusStore {
+    async fn record(&self, o: &CaseStatusObservation) -> Result<Option<CaseStatusEntry>, StoreError> {
+        let mut entries = self.entries.write().unwrap();
+        let now = Utc::now();
+        let latest = entries.iter().rposition(|e| e.receipt_number == o.receipt_number);
+        if let Some(i) = latest.filter(|i| entries[*i].status == o.status) {
+            entries[i].last_checked_at = now;
+            return Ok(None);
+        }
+
+        let entry = CaseStatusEntry {
+            entry_id: Uuid::new_v4(),
+            receipt_number: o.receipt_number.clone(),
+            customer_id: Some(o.customer_id),
+            status: o.status.clone(),
+            description: o.description.clone(),
+            previous_status: latest.map(|i| entries[i].status.clone()),
+            provider: o.provider.clone(),
+            observed_at: now,
+            last_checked_at: now,
+        };
+        entries.push(entry.clone());
+        Ok(Some(entry))
+    }
+
+    async fn history(&self, receipt_number: &str) -> Result<Vec<CaseStatusEntry>, StoreError> {
+        let entries = self.entries.read().unwrap();
+        Ok(entries.iter().filter(|e| e.receipt_number == receipt_number).cloned().collect())
+    }
+
+    async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<CaseStatusEntry>, StoreError> {
+        let entries = self.entries.read().unwrap();
+        Ok(entries
+            .iter()
+            .rev()
+            .filter(|e| e.previous_status.is_some() && e.observed_at > since)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observed(receipt_number: &str, status: &str) -> CaseStatusObservation {
+        CaseStatusObservation {
+            receipt_number: receipt_number.to_string(),
+            customer_id: Uuid::nil(),
+            status: status.to_string(),
+            description: None,
+            provider: "file".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_only_status_changes() {
+        let store = MemoryCaseStatusStore::new();
+        let first = store.record(&observed("EAC2412345678", "Case Was Received")).await.unwrap().unwrap();
+        assert_eq!(first.previous_status, None);
+
+        assert!(store.record(&observed("EAC2412345678", "Case Was Received")).await.unwrap().is_none());
+        let history = store.history("EAC2412345678").await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].last_checked_at >= first.last_checked_at);
+
+        let changed = store.record(&observed("EAC2412345678", "Case Was Approved")).await.unwrap().unwrap();
+        assert_eq!(changed.previous_status.as_deref(), Some("Case Was Received"));
+        assert_eq!(store.history("EAC2412345678").await.unwrap().len(), 2);
+
+        // Going back to an earlier status is a change too.
+        assert!(store.record(&observed("EAC2412345678", "Case Was Received")).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn receipts_have_separate_timelines() {
+        let store = MemoryCaseStatusStore::new();
+        let first = store.record(&observed("EAC2412345678", "Case Was Received")).await.unwrap().unwrap();
+        let other = store.record(&observed("WAC2412345678", "Case Was Received")).await.unwrap().unwrap();
+        assert_eq!(other.previous_status, None);
+
+        store.record(&observed("WAC2412345678", "Case Was Approved")).await.unwrap();
+        let changes = store.changes_since(first.observed_at).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].receipt_number, "WAC2412345678");
+    }
+}
@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{postgres, StoreError};
+
+/// A claim with no stored response older than this is treated as abandoned
+/// (e.g. the instance handling it crashed) and can be taken over.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The parts of a response needed to replay it.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status_code: u16,
+    pub content_type: Option<String>,
+    pub location: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum IdempotencyClaim {
+    /// First use of the key (or the previous use expired): process the request.
+    Claimed,
+    /// Same key and payload as a completed request: send its response again.
+    Replay(StoredResponse),
+    /// Same key, but the first request hasn't finished yet.
+    InProgress,
+    /// The key was already used with a different payload.
+    PayloadMismatch,
+}
+
+/// Remembers responses to mutating requests per `(principal, key)` for a TTL.
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    async fn claim(&self, principal: &str, key: &str, request_hash: &str) -> Result<IdempotencyClaim, StoreError>;
+
+    async fn complete(&self, principal: &str, key: &str, response: &StoredResponse) -> Result<(), StoreError>;
+
+    /// Drops an unfinished claim so the client can retry, e.g. after a server error.
+    async fn release(&self, principal: &str, key: &str) -> Result<(), StoreError>;
+
+    /// Deletes expired entries; returns how many were removed.
+    async fn purge_expired(&self) -> Result<u64, StoreError>;
+}
+
+pub struct PgIdempotencyStore {
+    pool: PgPool,
+    schema: String,
+    ttl: Duration,
+    statement_timeout: Option<Duration>,
+}
+
+impl PgIdempotencyStore {
+    pub fn new(pool: PgPool, schema: impl Into<String>, ttl: Duration) -> Self {
+        Self { pool, schema: schema.into(), ttl, statement_timeout: None }
+    }
+
+    /// Cancels any statement running longer than `timeout` (`None` disables the limit).
+    pub fn with_statement_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.statement_timeout = timeout;
+        self
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, StoreError> {
+        postgres::begin(&self.pool, self.statement_timeout).await
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for PgIdempotencyStore {
+    async fn claim(&self, principal: &str, key: &str, request_hash: &str) -> Result<IdempotencyClaim, StoreError> {
+        let schema = &self.schema;
+        // Takes the key if it is new, expired, or held by an abandoned request.
+        let sql = format!("INSERT INTO {schema}.idempotency_keys (principal, idempotency_key, request_hash, expires_at)
+            VALUES ($1, $2, $3, now() + make_interval(secs => $4))
+            ON CONFLICT (principal, idempotency_key) DO UPDATE SET
+                request_hash = EXCLUDED.request_hash, status_code = NULL, content_type = NULL,
+                location = NULL, response_body = NULL, created_at = now(), expires_at = EXCLUDED.expires_at
+            WHERE idempotency_keys.expires_at < now()
+               OR (idempotency_keys.status_code IS NULL AND idempotency_keys.created_at < now() - make_interval(secs => $5))
+            RETURNING true");
+        let mut tx = self.begin().await?;
+        let claimed: Option<bool> = sqlx::query_scalar(&sql)
+            .bind(principal).bind(key).bind(request_hash)
+            .bind(self.ttl.as_secs_f64()).bind(CLAIM_TIMEOUT.as_secs_f64())
+            .fetch_optional(&mut *tx)
+            .await?;
+        if claimed.is_some() {
+            tx.commit().await?;
+            return Ok(IdempotencyClaim::Claimed);
+        }
+
+        let sql = format!("SELECT request_hash, status_code, content_type, location, response_body
+            FROM {schema}.idempotency_keys WHERE principal = $1 AND idempotency_key = $2");
+        let row: Option<(String, Option<i16>, Option<String>, Option<String>, Option<Vec<u8>>)> = sqlx::query_as(&sql)
+            .bind(principal).bind(key)
+            .fetch_optional(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(match row {
+            // Purged between the two statements: the next retry will claim it.
+            None => IdempotencyClaim::InProgress,
+            Some((hash, _, _, _, _)) if hash != request_hash => IdempotencyClaim::PayloadMismatch,
+            Some((_, None, _, _, _)) => IdempotencyClaim::InProgress,
+            Some((_, Some(status_code), content_type, location, body)) => IdempotencyClaim::Replay(StoredResponse {
+                status_code: status_code as u16,
+                content_type,
+                location,
+                body: body.unwrap_or_default(),
+            }),
+        })
+    }
+
+    async fn complete(&self, principal: &str, key: &str, response: &StoredResponse) -> Result<(), StoreError> {
+        let schema = &self.schema;
+        let sql = format!("UPDATE {schema}.idempotency_keys
+            SET status_code = $3, content_type = $4, location = $5, response_body = $6
+            WHERE principal = $1 AND idempotency_key = $2");
+        let mut tx = self.begin().await?;
+        sqlx::query(&sql)
+            .bind(principal).bind(key)
+            .bind(response.status_code as i16).bind(&response.content_type).bind(&response.location).bind(&response.body)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn release(&self, principal: &str, key: &str) -> Result<(), StoreError> {
+        let schema = &self.schema;
+        let sql = format!("DELETE FROM {schema}.idempotency_keys
+            WHERE principal = $1 AND idempotency_key = $2 AND status_code IS NULL");
+        let mut tx = self.begin().await?;
+        sqlx::query(&sql).bind(principal).bind(key).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<u64, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("DELETE FROM {schema}.idempotency_keys WHERE expires_at < now()");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query(&sql).execute(&mut *tx).await?.rows_affected();
+        tx.commit().await?;
+        Ok(result)
+    }
+}
+
+/// In-memory `IdempotencyStore` for the memory storage backend.
+pub struct MemoryIdempotencyStore {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), MemoryEntry>>,
+}
+
+struct MemoryEntry {
+    request_hash: String,
+    response: Option<StoredResponse>,
+    created_at: Instant,
+    expires_at: Instant,
+}
+
+impl MemoryIdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for MemoryIdempotencyStore {
+    async fn claim(&self, principal: &str, key: &str, request_hash: &str) -> Result<IdempotencyClaim, StoreError> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let id = (principal.to_string(), key.to_string());
+
+        if let Some(entry) = entries.get(&id) {
+            let abandoned = entry.response.is_none() && now.duration_since(entry.created_at) > CLAIM_TIMEOUT;
+            if entry.expires_at > now && !abandoned {
+                return Ok(if entry.request_hash != request_hash {
+                    IdempotencyClaim::PayloadMismatch
+                } else {
+                    match &entry.response {
+                        Some(response) => IdempotencyClaim::Replay(response.clone()),
+                        None => IdempotencyClaim::InProgress,
+                    }
+                });
+            }
+        }
+
+        entries.insert(id, MemoryEntry {
+            request_hash: request_hash.to_string(),
+            response: None,
+            created_at: now,
+            expires_at: now + self.ttl,
+        });
+        Ok(IdempotencyClaim::Claimed)
+    }
+
+    async fn complete(&self, principal: &str, key: &str, response: &StoredResponse) -> Result<(), StoreError> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(&(principal.to_string(), key.to_string())) {
+            entry.response = Some(response.clone());
+        }
+        Ok(())
+    }
+
+    async fn release(&self, principal: &str, key: &str) -> Result<(), StoreError> {
+        let mut entries = self.entries.lock().unwrap();
+        let id = (principal.to_string(), key.to_string());
+        if entries.get(&id).is_some_and(|entry| entry.response.is_none()) {
+            entries.remove(&id);
+        }
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<u64, StoreError> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let before = entries.len();
+        entries.retain(|_, entry| entry.expires_at > now);
+        Ok((before - entries.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status_code: u16) -> StoredResponse {
+        StoredResponse {
+            status_code,
+            content_type: Some("application/json".to_string()),
+            location: Some("/customers/1".to_string()),
+            body: b"{}".to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn completed_request_is_replayed() {
+        let store = MemoryIdempotencyStore::new(Duration::from_secs(60));
+        assert!(matches!(store.claim("client:portal", "k1", "hash").await.unwrap(), IdempotencyClaim::Claimed));
+        store.complete("client:portal", "k1", &response(201)).await.unwrap();
+
+        let IdempotencyClaim::Replay(stored) = store.claim("client:portal", "k1", "hash").await.unwrap() else {
+            panic!("expected a replay");
+        };
+        assert_eq!(stored.status_code, 201);
+        assert_eq!(stored.location.as_deref(), Some("/customers/1"));
+    }
+
+    #[tokio::test]
+    async fn different_payload_is_a_mismatch() {
+        let store = MemoryIdempotencyStore::new(Duration::from_secs(60));
+        store.claim("client:portal", "k1", "hash").await.unwrap();
+        assert!(matches!(store.claim("client:portal", "k1", "other").await.unwrap(), IdempotencyClaim::PayloadMismatch));
+        store.complete("client:portal", "k1", &response(201)).await.unwrap();
+        assert!(matches!(store.claim("client:portal", "k1", "other").await.unwrap(), IdempotencyClaim::PayloadMismatch));
+    }
+
+    #[tokio::test]
+    async fn unfinished_request_is_in_progress_until_released() {
+        let store = MemoryIdempotencyStore::new(Duration::from_secs(60));
+        store.claim("client:portal", "k1", "hash").await.unwrap();
+        assert!(matches!(store.claim("client:portal", "k1", "hash").await.unwrap(), IdempotencyClaim::InProgress));
+
+        store.release("client:portal", "k1").await.unwrap();
+        assert!(matches!(store.claim("client:portal", "k1", "hash").await.unwrap(), IdempotencyClaim::Claimed));
+    }
+
+    #[tokio::test]
+    async fn release_keeps_a_completed_response() {
+        let store = MemoryIdempotencyStore::new(Duration::from_secs(60));
+        store.claim("client:portal", "k1", "hash").await.unwrap();
+        store.complete("client:portal", "k1", &response(200)).await.unwrap();
+        store.release("client:portal", "k1").await.unwrap();
+        assert!(matches!(store.claim("client:portal", "k1", "hash").await.unwrap(), IdempotencyClaim::Replay(_)));
+    }
+
+    #[tokio::test]
+    async fn keys_are_scoped_by_principal() {
+        let store = MemoryIdempotencyStore::new(Duration::from_secs(60));
+        store.claim("client:portal", "k1", "hash").await.unwrap();
+        store.complete("client:portal", "k1", &response(201)).await.unwrap();
+        assert!(matches!(store.claim("client:other", "k1", "other").await.unwrap(), IdempotencyClaim::Claimed));
+    }
+
+    #[tokio::test]
+    async fn expired_keys_can_be_reused_and_are_purged() {
+        let store = MemoryIdempotencyStore::new(Duration::from_millis(20));
+        store.claim("client:portal", "k1", "hash").await.unwrap();
+        store.complete("client:portal", "k1", &response(201)).await.unwrap();
+        store.claim("client:portal", "k2", "hash").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        assert!(matches!(store.claim("client:portal", "k1", "other").await.unwrap(), IdempotencyClaim::Claimed));
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+        assert!(matches!(store.claim("client:portal", "k1", "other").await.unwrap(), IdempotencyClaim::InProgress));
+    }
+}
@@ -0,0 +1,248 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use std::sync::RwLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::{postgres, StoreError};
+use crate::models::{LcaDocument, NewLcaDocument, PublicAccessFile};
+
+/// Documents uploaded for customers' LCA public access files, and a record
+/// of each file generated from them.
+#[async_trait]
+pub trait LcaDocumentStore: Send + Sync {
+    async fn add(&self, customer_id: Uuid, document: &NewLcaDocument) -> Result<LcaDocument, StoreError>;
+
+    /// The customer's documents without their content, oldest first.
+    async fn list(&self, customer_id: Uuid) -> Result<Vec<LcaDocument>, StoreError>;
+
+    /// A document with its content; `None` if the customer has no such document.
+    async fn get(&self, customer_id: Uuid, document_id: Uuid) -> Result<Option<(LcaDocument, Vec<u8>)>, StoreError>;
+
+    /// Every document of the customer with its content, oldest first.
+    async fn contents(&self, customer_id: Uuid) -> Result<Vec<(LcaDocument, Vec<u8>)>, StoreError>;
+
+    /// Returns whether the customer had the document.
+    async fn delete(&self, customer_id: Uuid, document_id: Uuid) -> Result<bool, StoreError>;
+
+    async fn record_generation(&self, file: &PublicAccessFile) -> Result<(), StoreError>;
+
+    /// Generation records of the customer, newest first.
+    async fn generations(&self, customer_id: Uuid) -> Result<Vec<PublicAccessFile>, StoreError>;
+}
+
+const DOCUMENT_COLUMNS: &str = "document_id, customer_id, category, filename, content_type, size_bytes, page_count,
+        uploaded_by, uploaded_at";
+
+const FILE_COLUMNS: &str = "file_id, customer_id, lca_case_number, lca_code, lca_title, lca_salary, lca_wage_level,
+        worksite, document_ids, missing, page_count, sha256, generated_by, generated_at";
+
+#[derive(FromRow)]
+struct DocumentRow {
+    #[sqlx(flatten)]
+    document: LcaDocument,
+    content: Vec<u8>,
+}
+
+pub struct PgLcaDocumentStore {
+    pool: PgPool,
+    schema: String,
+    statement_timeout: Option<Duration>,
+}
+
+impl PgLcaDocumentStore {
+    pub fn new(pool: PgPool, schema: impl Into<String>) -> Self {
+        Self { pool, schema: schema.into(), statement_timeout: None }
+    }
+
+    /// Cancels any statement running longer than `timeout` (`None` disables the limit).
+    pub fn with_statement_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.statement_timeout = timeout;
+        self
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, StoreError> {
+        postgres::begin(&self.pool, self.statement_timeout).await
+    }
+}
+
+#[async_trait]
+impl LcaDocumentStore for PgLcaDocumentStore {
+    async fn add(&self, customer_id: Uuid, d: &NewLcaDocument) -> Result<LcaDocument, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("INSERT INTO {schema}.lca_documents (
+                customer_id, category, filename, content_type, size_bytes, page_count, content, uploaded_by
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {DOCUMENT_COLUMNS}");
+        let mut tx = self.begin().await?;
+        let document = sqlx::query_as(&sql)
+            .bind(customer_id)
+            .bind(d.category.as_str())
+            .bind(&d.filename)
+            .bind(&d.content_type)
+            .bind(d.content.len() as i32)
+            .bind(d.page_count)
+            .bind(&d.content)
+            .bind(&d.uploaded_by)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(document)
+    }
+
+    async fn list(&self, customer_id: Uuid) -> Result<Vec<LcaDocument>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {DOCUMENT_COLUMNS} FROM {schema}.lca_documents
+            WHERE customer_id = $1 ORDER BY uploaded_at, document_id");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn get(&self, customer_id: Uuid, document_id: Uuid) -> Result<Option<(LcaDocument, Vec<u8>)>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {DOCUMENT_COLUMNS}, content FROM {schema}.lca_documents
+            WHERE customer_id = $1 AND document_id = $2");
+        let mut tx = self.begin().await?;
+        let row: Option<DocumentRow> = sqlx::query_as(&sql).bind(customer_id).bind(document_id).fetch_optional(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(row.map(|row| (row.document, row.content)))
+    }
+
+    async fn contents(&self, customer_id: Uuid) -> Result<Vec<(LcaDocument, Vec<u8>)>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {DOCUMENT_COLUMNS}, content FROM {schema}.lca_documents
+            WHERE customer_id = $1 ORDER BY uploaded_at, document_id");
+        let mut tx = self.begin().await?;
+        let rows: Vec<DocumentRow> = sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(rows.into_iter().map(|row| (row.document, row.content)).collect())
+    }
+
+    async fn delete(&self, customer_id: Uuid, document_id: Uuid) -> Result<bool, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("DELETE FROM {schema}.lca_documents WHERE customer_id = $1 AND document_id = $2");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query(&sql).bind(customer_id).bind(document_id).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn record_generation(&self, f: &PublicAccessFile) -> Result<(), StoreError> {
+        let schema = &self.schema;
+        let sql = format!("INSERT INTO {schema}.public_access_files ({FILE_COLUMNS})
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)");
+        let mut tx = self.begin().await?;
+        sqlx::query(&sql)
+            .bind(f.file_id)
+            .bind(f.customer_id)
+            .bind(&f.lca_case_number)
+            .bind(&f.lca_code)
+            .bind(&f.lca_title)
+            .bind(f.lca_salary)
+            .bind(&f.lca_wage_level)
+            .bind(&f.worksite)
+            .bind(&f.document_ids)
+            .bind(&f.missing)
+            .bind(f.page_count)
+            .bind(&f.sha256)
+            .bind(&f.generated_by)
+            .bind(f.generated_at)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn generations(&self, customer_id: Uuid) -> Result<Vec<PublicAccessFile>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {FILE_COLUMNS} FROM {schema}.public_access_files
+            WHERE customer_id = $1 ORDER BY generated_at DESC");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+}
+
+/// In-memory `LcaDocumentStore` for the memory storage backend.
+#[derive(Default)]
+pub struct MemoryLcaDocumentStore {
+    documents: RwLock<Vec<(LcaDocument, Vec<u8>)>>,
+    files: RwLock<Vec<PublicAccessFile>>,
+}
+
+impl MemoryLcaDocumentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the documents and generation records of customer `from` to
+    /// customer `to`, for `MemoryCustomerStore::merge`. Returns how many
+    /// documents and how many generation records moved.
+    pub fn reassign(&self, from: Uuid, to: Uuid) -> (u64, u64) {
+        let mut moved = (0, 0);
+        for (d, _) in self.documents.write().unwrap().iter_mut().filter(|(d, _)| d.customer_id == from) {
+            d.customer_id = to;
+            moved.0 += 1;
+        }
+        for f in self.files.write().unwrap().iter_mut().filter(|f| f.customer_id == from) {
+            f.customer_id = to;
+            moved.1 += 1;
+        }
+        moved
+    }
+}
+
+#[async_trait]
+impl LcaDocumentStore for MemoryLcaDocumentStore {
+    async fn add(&self, customer_id: Uuid, d: &NewLcaDocument) -> Result<LcaDocument, StoreError> {
+        let document = LcaDocument {
+            document_id: Uuid::new_v4(),
+            customer_id,
+            category: d.category.as_str().to_string(),
+            filename: d.filename.clone(),
+            content_type: d.content_type.clone(),
+            size_bytes: d.content.len() as i32,
+            page_count: d.page_count,
+            uploaded_by: d.uploaded_by.clone(),
+            uploaded_at: Utc::now(),
+        };
+        self.documents.write().unwrap().push((document.clone(), d.content.clone()));
+        Ok(document)
+    }
+
+    async fn list(&self, customer_id: Uuid) -> Result<Vec<LcaDocument>, StoreError> {
+        let documents = self.documents.read().unwrap();
+        Ok(documents.iter().filter(|(d, _)| d.customer_id == customer_id).map(|(d, _)| d.clone()).collect())
+    }
+
+    async fn get(&self, customer_id: Uuid, document_id: Uuid) -> Result<Option<(LcaDocument, Vec<u8>)>, StoreError> {
+        let documents = self.documents.read().unwrap();
+        Ok(documents.iter().find(|(d, _)| d.customer_id == customer_id && d.document_id == document_id).cloned())
+    }
+
+    async fn contents(&self, customer_id: Uuid) -> Result<Vec<(LcaDocument, Vec<u8>)>, StoreError> {
+        let documents = self.documents.read().unwrap();
+        Ok(documents.iter().filter(|(d, _)| d.customer_id == customer_id).cloned().collect())
+    }
+
+    async fn delete(&self, customer_id: Uuid, document_id: Uuid) -> Result<bool, StoreError> {
+        let mut documents = self.documents.write().unwrap();
+        let before = documents.len();
+        documents.retain(|(d, _)| !(d.customer_id == customer_id && d.document_id == document_id));
+        Ok(documents.len() < before)
+    }
+
+    async fn record_generation(&self, file: &PublicAccessFile) -> Result<(), StoreError> {
+        self.files.write().unwrap().push(file.clone());
+        Ok(())
+    }
+
+    async fn generations(&self, customer_id: Uuid) -> Result<Vec<PublicAccessFile>, StoreError> {
+        let files = self.files.read().unwrap();
+        Ok(files.iter().rev().filter(|f| f.customer_id == customer_id).cloned().collect())
+    }
+}
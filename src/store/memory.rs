@@ -0,0 +1,786 @@
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+use super::*;
+use crate::dashboard;
+
+/// In-memory `CustomerStore` with the same semantics as the Postgres one.
+///
+/// Customers are kept in insertion order. Enum columns are validated against
+/// the same value sets the database enforces, so bad input fails the same way.
+#[derive(Default)]
+pub struct MemoryCustomerStore {
+    customers: RwLock<Vec<CreateCustomer>>,
+    transitions: RwLock<Vec<StatusTransition>>,
+    petitions: RwLock<Vec<Petition>>,
+    trips: RwLock<Vec<Trip>>,
+    compliance_tasks: RwLock<Vec<ComplianceTask>>,
+    /// History kept by other memory stores that `merge` moves too, as the
+    /// Postgres merge does within its transaction.
+    case_status: Option<Arc<MemoryCaseStatusStore>>,
+    lca_documents: Option<Arc<MemoryLcaDocumentStore>>,
+}
+
+impl MemoryCustomerStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_history_stores(mut self, case_status: Arc<MemoryCaseStatusStore>, lca_documents: Arc<MemoryLcaDocumentStore>) -> Self {
+        self.case_status = Some(case_status);
+        self.lca_documents = Some(lca_documents);
+        self
+    }
+
+    /// Builds a store pre-populated with `seed`, e.g. fake data for frontend work.
+    pub fn with_customers(seed: &[CreateCompleteCustomerRequest]) -> Result<Self, StoreError> {
+        let store = Self::new();
+        for customer in seed {
+            let record = to_record(Uuid::new_v4(), customer)?;
+            let mut customers = store.customers.write().unwrap();
+            check_receipt_unique(&customers, &store.petitions.read().unwrap(), record.receipt_number.as_deref(), record.customer_id)?;
+            check_emails_unique(&customers, Some(&record.email), Some(&record.login_email), record.customer_id)?;
+            customers.push(record);
+        }
+        Ok(store)
+    }
+}
+
+fn check_enum(column: &str, value: &str, allowed: &[&str]) -> Result<(), StoreError> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        Err(StoreError::Invalid(format!("invalid input value for {}: \"{}\"", column, value)))
+    }
+}
+
+fn to_record(customer_id: Uuid, c: &CreateCompleteCustomerRequest) -> Result<CreateCustomer, StoreError> {
+    let h1b_status = c.h1b_status.clone().unwrap_or_else(|| "Draft".to_string());
+    check_enum("sex_enum", &c.sex, SEX_VALUES)?;
+    check_enum("marital_status_enum", &c.marital_status, MARITAL_STATUS_VALUES)?;
+    parse_status(&h1b_status)?;
+    if let Some(level) = &c.lca_wage_level {
+        check_enum("wage_level_enum", level, WAGE_LEVEL_VALUES)?;
+    }
+    let receipt_number = c.receipt_number.as_deref().map(normalize_receipt).transpose()?;
+    if receipt_number.is_some() {
+        check_receipt_allowed(&h1b_status)?;
+    }
+
+    Ok(CreateCustomer {
+        customer_id,
+        email: c.email.clone(),
+        first_name: c.first_name.clone(),
+        last_name: c.last_name.clone(),
+        dob: c.dob,
+        sex: c.sex.clone(),
+        marital_status: c.marital_status.clone(),
+        phone: c.phone.clone(),
+        emergency_contact_name: c.emergency_contact_name.clone(),
+        emergency_contact_phone: c.emergency_contact_phone.clone(),
+        phone_display: c.phone_display.clone(),
+        emergency_contact_phone_display: c.emergency_contact_phone_display.clone(),
+        employment_start_date: c.employment_start_date,
+        street_name: c.street_name.clone(),
+        city: c.city.clone(),
+        state: c.state.clone(),
+        zip: c.zip.clone(),
+        client_name: c.client_name.clone(),
+        client_street_name: c.client_street_name.clone(),
+        client_city: c.client_city.clone(),
+        client_state: c.client_state.clone(),
+        client_zip: c.client_zip.clone(),
+        county: c.county.clone(),
+        msa: c.msa.clone(),
+        client_county: c.client_county.clone(),
+        client_msa: c.client_msa.clone(),
+        lca_title: c.lca_title.clone(),
+        lca_salary: c.lca_salary,
+        lca_code: c.lca_code.clone(),
+        lca_wage_level: c.lca_wage_level.clone(),
+        receipt_number,
+        h1b_start_date: c.h1b_start_date,
+        h1b_end_date: c.h1b_end_date,
+        login_email: c.login_email.clone(),
+        h1b_status,
+        labor_certification_filed_on: c.labor_certification_filed_on,
+        i140_approved_on: c.i140_approved_on,
+        created_at: Some(Utc::now()),
+        deleted_at: None,
+        deleted_by: None,
+        delete_reason: None,
+        merged_into: None,
+    })
+}
+
+fn is_deleted(customer: &CreateCustomer) -> bool {
+    customer.deleted_at.is_some()
+}
+
+fn is_visible(customer: &CreateCustomer, deleted: Deleted) -> bool {
+    deleted == Deleted::Include || !is_deleted(customer)
+}
+
+/// Mirrors the partial unique index on `receipt_number` and the petition
+/// check: live customers other than `customer_id` must not already hold it,
+/// on their record or on a petition.
+fn check_receipt_unique(customers: &[CreateCustomer], petitions: &[Petition], receipt_number: Option<&str>, customer_id: Uuid) -> Result<(), StoreError> {
+    let Some(receipt_number) = receipt_number else {
+        return Ok(());
+    };
+    let live = |id: Uuid| id != customer_id && customers.iter().any(|c| c.customer_id == id && !is_deleted(c));
+    let taken = customers.iter().any(|c| live(c.customer_id) && c.receipt_number.as_deref() == Some(receipt_number))
+        || petitions.iter().any(|p| live(p.customer_id) && p.receipt_number.as_deref() == Some(receipt_number));
+    if taken {
+        Err(receipt_in_use())
+    } else {
+        Ok(())
+    }
+}
+
+/// Mirrors the partial unique indexes on `lower(email)` and `lower(login_email)`.
+fn check_emails_unique(customers: &[CreateCustomer], email: Option<&str>, login_email: Option<&str>, customer_id: Uuid) -> Result<(), StoreError> {
+    let taken = |column: fn(&CreateCustomer) -> &str, value: &str| {
+        let value = value.to_lowercase();
+        customers
+            .iter()
+            .any(|c| c.customer_id != customer_id && !is_deleted(c) && column(c).to_lowercase() == value)
+    };
+    if email.is_some_and(|email| taken(|c| &c.email, email)) {
+        return Err(StoreError::Conflict("email is already used by another customer".to_string()));
+    }
+    if login_email.is_some_and(|login_email| taken(|c| &c.login_email, login_email)) {
+        return Err(StoreError::Conflict("login_email is already used by another customer".to_string()));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl CustomerStore for MemoryCustomerStore {
+    async fn create(&self, customer: &CreateCompleteCustomerRequest) -> Result<CreateCustomer, StoreError> {
+        let record = to_record(Uuid::new_v4(), customer)?;
+        let mut customers = self.customers.write().unwrap();
+        check_receipt_unique(&customers, &self.petitions.read().unwrap(), record.receipt_number.as_deref(), record.customer_id)?;
+        check_emails_unique(&customers, Some(&record.email), Some(&record.login_email), record.customer_id)?;
+        customers.push(record.clone());
+        Ok(record)
+    }
+
+    async fn upsert_by_email(&self, customer: &CreateCompleteCustomerRequest) -> Result<UpsertOutcome, StoreError> {
+        let mut customers = self.customers.write().unwrap();
+        let email = customer.email.to_lowercase();
+        let matched = customers.iter().position(|c| c.email.to_lowercase() == email && !is_deleted(c));
+
+        match matched {
+            None => {
+                let record = to_record(Uuid::new_v4(), customer)?;
+                check_receipt_unique(&customers, &self.petitions.read().unwrap(), record.receipt_number.as_deref(), record.customer_id)?;
+                check_emails_unique(&customers, Some(&record.email), Some(&record.login_email), record.customer_id)?;
+                customers.push(record.clone());
+                Ok(UpsertOutcome::Created(Box::new(record)))
+            }
+            Some(i) => {
+                let existing = &customers[i];
+                if let Some(requested) = customer.h1b_status.as_deref().filter(|requested| *requested != existing.h1b_status) {
+                    return Err(status_change_in_upsert(&existing.h1b_status, requested));
+                }
+                if customer.receipt_number.is_some() {
+                    check_receipt_allowed(&existing.h1b_status)?;
+                }
+                let mut record = to_record(existing.customer_id, &CreateCompleteCustomerRequest {
+                    h1b_status: Some(existing.h1b_status.clone()),
+                    ..customer.clone()
+                })?;
+                record.receipt_number = record.receipt_number.or_else(|| existing.receipt_number.clone());
+                record.created_at = existing.created_at;
+                check_receipt_unique(&customers, &self.petitions.read().unwrap(), record.receipt_number.as_deref(), record.customer_id)?;
+                check_emails_unique(&customers, Some(&record.email), Some(&record.login_email), record.customer_id)?;
+                customers[i] = record.clone();
+                Ok(UpsertOutcome::Updated(Box::new(record)))
+            }
+        }
+    }
+
+    async fn get_by_id(&self, customer_id: Uuid, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError> {
+        let customers = self.customers.read().unwrap();
+        Ok(customers.iter().find(|c| c.customer_id == customer_id && is_visible(c, deleted)).cloned())
+    }
+
+    async fn get_by_receipt(&self, receipt_number: &str, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError> {
+        let receipt_number = normalize_receipt(receipt_number)?;
+        let customers = self.customers.read().unwrap();
+        let mut matches: Vec<&CreateCustomer> = customers
+            .iter()
+            .filter(|c| c.receipt_number.as_deref() == Some(receipt_number.as_str()) && is_visible(c, deleted))
+            .collect();
+        matches.sort_by_key(|c| (is_deleted(c), std::cmp::Reverse(c.deleted_at)));
+        Ok(matches.first().map(|c| (*c).clone()))
+    }
+
+    async fn find_by_email(&self, email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        let email = email.to_lowercase();
+        let customers = self.customers.read().unwrap();
+        Ok(customers
+            .iter()
+            .filter(|c| (c.email.to_lowercase() == email || c.login_email.to_lowercase() == email) && is_visible(c, deleted))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_by_login_email(&self, login_email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        let login_email = login_email.to_lowercase();
+        let customers = self.customers.read().unwrap();
+        Ok(customers
+            .iter()
+            .filter(|c| c.login_email.to_lowercase() == login_email && is_visible(c, deleted))
+            .cloned()
+            .collect())
+    }
+
+    async fn list(&self, filter: CustomerFilter, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        let customers = self.customers.read().unwrap();
+        Ok(customers
+            .iter()
+            .filter(|c| match filter {
+                CustomerFilter::ActiveOnly => c.h1b_status == "Active",
+                CustomerFilter::All => true,
+                CustomerFilter::PhonesNotNormalized => c.phone_display.is_none() || c.emergency_contact_phone_display.is_none(),
+            })
+            .filter(|c| is_visible(c, deleted))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(&self, customer_id: Uuid, u: &UpdateVisaDetailsRequest, task: Option<&NewComplianceTask>) -> Result<bool, StoreError> {
+        if let Some(sex) = &u.sex {
+            check_enum("sex_enum", sex, SEX_VALUES)?;
+        }
+        if let Some(marital_status) = &u.marital_status {
+            check_enum("marital_status_enum", marital_status, MARITAL_STATUS_VALUES)?;
+        }
+        if let Some(level) = &u.lca_wage_level {
+            check_enum("wage_level_enum", level, WAGE_LEVEL_VALUES)?;
+        }
+        let receipt_number = u.receipt_number.as_deref().map(normalize_receipt).transpose()?;
+
+        let mut customers = self.customers.write().unwrap();
+        if receipt_number.is_some() {
+            match customers.iter().find(|c| c.customer_id == customer_id && !is_deleted(c)) {
+                None => return Ok(false),
+                Some(c) => check_receipt_allowed(&c.h1b_status)?,
+            }
+        }
+        check_receipt_unique(&customers, &self.petitions.read().unwrap(), receipt_number.as_deref(), customer_id)?;
+        check_emails_unique(&customers, u.email.as_deref(), u.login_email.as_deref(), customer_id)?;
+        let Some(c) = customers.iter_mut().find(|c| c.customer_id == customer_id && !is_deleted(c)) else {
+            return Ok(false);
+        };
+
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        set(&mut c.email, &u.email);
+        set(&mut c.first_name, &u.first_name);
+        set(&mut c.last_name, &u.last_name);
+        set(&mut c.dob, &u.dob);
+        set(&mut c.sex, &u.sex);
+        set(&mut c.marital_status, &u.marital_status);
+        set(&mut c.phone, &u.phone);
+        set(&mut c.emergency_contact_name, &u.emergency_contact_name);
+        set(&mut c.emergency_contact_phone, &u.emergency_contact_phone);
+        if u.phone.is_some() {
+            c.phone_display = u.phone_display.clone();
+        }
+        if u.emergency_contact_phone.is_some() {
+            c.emergency_contact_phone_display = u.emergency_contact_phone_display.clone();
+        }
+        set(&mut c.employment_start_date, &u.employment_start_date);
+        set(&mut c.street_name, &u.street_name);
+        set(&mut c.city, &u.city);
+        set(&mut c.state, &u.state);
+        set(&mut c.zip, &u.zip);
+        set(&mut c.client_name, &u.client_name);
+        set(&mut c.client_street_name, &u.client_street_name);
+        set(&mut c.client_city, &u.client_city);
+        set(&mut c.client_state, &u.client_state);
+        set(&mut c.client_zip, &u.client_zip);
+        // Derived from the ZIP, so they follow it even when the new ZIP is unknown.
+        if u.zip.is_some() {
+            c.county = u.county.clone();
+            c.msa = u.msa.clone();
+        }
+        if u.client_zip.is_some() {
+            c.client_county = u.client_county.clone();
+            c.client_msa = u.client_msa.clone();
+        }
+        set(&mut c.lca_title, &u.lca_title);
+        set(&mut c.lca_salary, &u.lca_salary);
+        set(&mut c.lca_code, &u.lca_code);
+        if u.lca_wage_level.is_some() {
+            c.lca_wage_level = u.lca_wage_level.clone();
+        }
+        if receipt_number.is_some() {
+            c.receipt_number = receipt_number;
+        }
+        set(&mut c.h1b_start_date, &u.h1b_start_date);
+        set(&mut c.h1b_end_date, &u.h1b_end_date);
+        set(&mut c.login_email, &u.login_email);
+        if u.labor_certification_filed_on.is_some() {
+            c.labor_certification_filed_on = u.labor_certification_filed_on;
+        }
+        if u.i140_approved_on.is_some() {
+            c.i140_approved_on = u.i140_approved_on;
+        }
+
+        if let Some(task) = task {
+            self.compliance_tasks.write().unwrap().push(ComplianceTask {
+                task_id: Uuid::new_v4(),
+                customer_id,
+                kind: task.kind.clone(),
+                reason: task.reason.clone(),
+                due_on: task.due_on,
+                created_by: task.created_by.clone(),
+                created_at: Utc::now(),
+                completed_at: None,
+                completed_by: None,
+            });
+        }
+        Ok(true)
+    }
+
+    async fn soft_delete(&self, customer_id: Uuid, deleted_by: &str, reason: Option<&str>) -> Result<SoftDeleteOutcome, StoreError> {
+        let mut customers = self.customers.write().unwrap();
+        match customers.iter_mut().find(|c| c.customer_id == customer_id && !is_deleted(c)) {
+            Some(c) => {
+                c.deleted_at = Some(Utc::now());
+                c.deleted_by = Some(deleted_by.to_string());
+                c.delete_reason = reason.map(str::to_string);
+                Ok(SoftDeleteOutcome::Deleted)
+            }
+            None => Ok(SoftDeleteOutcome::NotFound),
+        }
+    }
+
+    async fn restore(&self, customer_id: Uuid) -> Result<RestoreOutcome, StoreError> {
+        let mut customers = self.customers.write().unwrap();
+        let Some(i) = customers.iter().position(|c| c.customer_id == customer_id) else {
+            return Ok(RestoreOutcome::NotFound);
+        };
+        if !is_deleted(&customers[i]) {
+            return Ok(RestoreOutcome::NotDeleted);
+        }
+        if let Some(into) = customers[i].merged_into {
+            return Err(already_merged(customer_id, into));
+        }
+        // While deleted, its receipt numbers may have been taken.
+        let petitions = self.petitions.read().unwrap();
+        let own_petitions = petitions.iter().filter(|p| p.customer_id == customer_id).filter_map(|p| p.receipt_number.as_deref());
+        for receipt_number in customers[i].receipt_number.as_deref().into_iter().chain(own_petitions) {
+            check_receipt_unique(&customers, &petitions, Some(receipt_number), customer_id)?;
+        }
+        drop(petitions);
+        check_emails_unique(&customers, Some(&customers[i].email), Some(&customers[i].login_email), customer_id)?;
+
+        let c = &mut customers[i];
+        c.deleted_at = None;
+        c.deleted_by = None;
+        c.delete_reason = None;
+        Ok(RestoreOutcome::Restored(Box::new(c.clone())))
+    }
+
+    async fn transition(&self, customer_id: Uuid, request: &TransitionRequest, transitioned_by: &str) -> Result<TransitionOutcome, StoreError> {
+        let receipt_number = transition_receipt(request)?;
+        let mut customers = self.customers.write().unwrap();
+        let Some(i) = customers.iter().position(|c| c.customer_id == customer_id && !is_deleted(c)) else {
+            return Ok(TransitionOutcome::NotFound);
+        };
+        let from = parse_status(&customers[i].h1b_status)?;
+        if !from.can_transition_to(request.to_status) {
+            return Ok(TransitionOutcome::NotAllowed { from });
+        }
+        if request.to_status.requires_receipt_number() {
+            if receipt_number.is_none() && customers[i].receipt_number.is_none() {
+                return Err(receipt_required(request.to_status));
+            }
+            check_receipt_unique(&customers, &self.petitions.read().unwrap(), receipt_number.as_deref(), customer_id)?;
+        }
+
+        let c = &mut customers[i];
+        if receipt_number.is_some() {
+            c.receipt_number = receipt_number;
+        }
+        c.h1b_status = request.to_status.as_str().to_string();
+        let transition = StatusTransition {
+            transition_id: Uuid::new_v4(),
+            customer_id,
+            from_status: from.as_str().to_string(),
+            to_status: request.to_status.as_str().to_string(),
+            reason: request.reason.clone(),
+            effective_date: request.effective_date,
+            transitioned_by: transitioned_by.to_string(),
+            created_at: Utc::now(),
+            merged_from: None,
+        };
+        self.transitions.write().unwrap().push(transition.clone());
+        Ok(TransitionOutcome::Applied(Box::new(c.clone()), Box::new(transition)))
+    }
+
+    async fn transitions(&self, customer_id: Uuid) -> Result<Vec<StatusTransition>, StoreError> {
+        let transitions = self.transitions.read().unwrap();
+        Ok(transitions.iter().filter(|t| t.customer_id == customer_id).cloned().collect())
+    }
+
+    async fn petitions(&self, customer_id: Uuid) -> Result<Vec<Petition>, StoreError> {
+        let mut petitions: Vec<Petition> =
+            self.petitions.read().unwrap().iter().filter(|p| p.customer_id == customer_id).cloned().collect();
+        petitions.sort_by_key(|p| (p.valid_from, p.created_at));
+        Ok(petitions)
+    }
+
+    async fn petitions_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Petition>, StoreError> {
+        let mut petitions: Vec<Petition> =
+            self.petitions.read().unwrap().iter().filter(|p| customer_ids.contains(&p.customer_id)).cloned().collect();
+        petitions.sort_by_key(|p| (p.customer_id, p.valid_from, p.created_at));
+        Ok(petitions)
+    }
+
+    async fn add_petition(&self, customer_id: Uuid, p: &PetitionRequest, recorded_by: &str) -> Result<Option<Petition>, StoreError> {
+        if p.valid_to < p.valid_from {
+            return Err(StoreError::Invalid("valid_to is before valid_from".to_string()));
+        }
+        let receipt_number = p.receipt_number.as_deref().map(normalize_receipt).transpose()?;
+        let customers = self.customers.read().unwrap();
+        if !customers.iter().any(|c| c.customer_id == customer_id && !is_deleted(c)) {
+            return Ok(None);
+        }
+        let mut petitions = self.petitions.write().unwrap();
+        if let Some(receipt_number) = &receipt_number {
+            check_receipt_unique(&customers, &petitions, Some(receipt_number), customer_id)?;
+            let own = petitions.iter().any(|p| p.customer_id == customer_id && p.receipt_number.as_ref() == Some(receipt_number));
+            if own {
+                return Err(receipt_in_use());
+            }
+        }
+
+        let petition = Petition {
+            petition_id: Uuid::new_v4(),
+            customer_id,
+            receipt_number,
+            employer: p.employer.clone(),
+            valid_from: p.valid_from,
+            valid_to: p.valid_to,
+            recorded_by: recorded_by.to_string(),
+            created_at: Utc::now(),
+        };
+        petitions.push(petition.clone());
+        Ok(Some(petition))
+    }
+
+    async fn trips(&self, customer_id: Uuid) -> Result<Vec<Trip>, StoreError> {
+        let mut trips: Vec<Trip> = self.trips.read().unwrap().iter().filter(|t| t.customer_id == customer_id).cloned().collect();
+        trips.sort_by_key(|t| (t.departed_on, t.created_at));
+        Ok(trips)
+    }
+
+    async fn trips_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Trip>, StoreError> {
+        let mut trips: Vec<Trip> = self.trips.read().unwrap().iter().filter(|t| customer_ids.contains(&t.customer_id)).cloned().collect();
+        trips.sort_by_key(|t| (t.customer_id, t.departed_on, t.created_at));
+        Ok(trips)
+    }
+
+    async fn add_trip(&self, customer_id: Uuid, trip: &TripRequest, recorded_by: &str) -> Result<Option<Trip>, StoreError> {
+        let trip = normalize_trip(trip)?;
+        let customers = self.customers.read().unwrap();
+        if !customers.iter().any(|c| c.customer_id == customer_id && !is_deleted(c)) {
+            return Ok(None);
+        }
+        let mut trips = self.trips.write().unwrap();
+        let own: Vec<Trip> = trips.iter().filter(|t| t.customer_id == customer_id).cloned().collect();
+        check_trip(&own, &trip, None)?;
+
+        let trip = Trip {
+            trip_id: Uuid::new_v4(),
+            customer_id,
+            departed_on: trip.departed_on,
+            returned_on: trip.returned_on,
+            port_of_entry: trip.port_of_entry,
+            admission_class: trip.admission_class,
+            i94_number: trip.i94_number,
+            recorded_by: recorded_by.to_string(),
+            created_at: Utc::now(),
+        };
+        trips.push(trip.clone());
+        Ok(Some(trip))
+    }
+
+    async fn update_trip(&self, customer_id: Uuid, trip_id: Uuid, trip: &TripRequest) -> Result<Option<Trip>, StoreError> {
+        let trip = normalize_trip(trip)?;
+        let customers = self.customers.read().unwrap();
+        if !customers.iter().any(|c| c.customer_id == customer_id && !is_deleted(c)) {
+            return Ok(None);
+        }
+        let mut trips = self.trips.write().unwrap();
+        let own: Vec<Trip> = trips.iter().filter(|t| t.customer_id == customer_id).cloned().collect();
+        if !own.iter().any(|t| t.trip_id == trip_id) {
+            return Ok(None);
+        }
+        check_trip(&own, &trip, Some(trip_id))?;
+
+        let Some(existing) = trips.iter_mut().find(|t| t.trip_id == trip_id) else {
+            return Ok(None);
+        };
+        existing.departed_on = trip.departed_on;
+        existing.returned_on = trip.returned_on;
+        existing.port_of_entry = trip.port_of_entry;
+        existing.admission_class = trip.admission_class;
+        existing.i94_number = trip.i94_number;
+        Ok(Some(existing.clone()))
+    }
+
+    async fn compliance_tasks(&self, customer_id: Uuid) -> Result<Vec<ComplianceTask>, StoreError> {
+        let mut tasks: Vec<ComplianceTask> =
+            self.compliance_tasks.read().unwrap().iter().filter(|t| t.customer_id == customer_id).cloned().collect();
+        tasks.sort_by_key(|t| (t.completed_at.is_some(), t.due_on, t.created_at));
+        Ok(tasks)
+    }
+
+    async fn open_compliance_tasks(&self) -> Result<Vec<ComplianceTask>, StoreError> {
+        let customers = self.customers.read().unwrap();
+        let live = |customer_id: Uuid| customers.iter().any(|c| c.customer_id == customer_id && !is_deleted(c));
+        let mut tasks: Vec<ComplianceTask> = self
+            .compliance_tasks
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|t| t.completed_at.is_none() && live(t.customer_id))
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|t| (t.due_on, t.created_at));
+        Ok(tasks)
+    }
+
+    async fn complete_compliance_task(&self, task_id: Uuid, completed_by: &str) -> Result<Option<ComplianceTask>, StoreError> {
+        let mut tasks = self.compliance_tasks.write().unwrap();
+        let Some(task) = tasks.iter_mut().find(|t| t.task_id == task_id) else {
+            return Ok(None);
+        };
+        if task.completed_at.is_some() {
+            return Err(task_already_completed(task));
+        }
+        task.completed_at = Some(Utc::now());
+        task.completed_by = Some(completed_by.to_string());
+        Ok(Some(task.clone()))
+    }
+
+    async fn merge(&self, customer_id: Uuid, duplicate_id: Uuid, merged_by: &str, reason: Option<&str>) -> Result<MergeOutcome, StoreError> {
+        if customer_id == duplicate_id {
+            return Err(merge_into_self(customer_id));
+        }
+        let mut customers = self.customers.write().unwrap();
+        let survivor = customers.iter().position(|c| c.customer_id == customer_id && !is_deleted(c));
+        let duplicate = customers.iter().position(|c| c.customer_id == duplicate_id);
+        let (Some(survivor), Some(duplicate)) = (survivor, duplicate) else {
+            return Ok(MergeOutcome::NotFound);
+        };
+
+        let d = &mut customers[duplicate];
+        if let Some(into) = d.merged_into {
+            return Err(already_merged(duplicate_id, into));
+        }
+        d.deleted_at = d.deleted_at.or_else(|| Some(Utc::now()));
+        d.deleted_by = Some(merged_by.to_string());
+        d.delete_reason = Some(merge_reason(customer_id, reason));
+        d.merged_into = Some(customer_id);
+
+        let mut moved = MergeMoved::default();
+        for t in self.transitions.write().unwrap().iter_mut().filter(|t| t.customer_id == duplicate_id) {
+            t.customer_id = customer_id;
+            t.merged_from = t.merged_from.or(Some(duplicate_id));
+            moved.transitions += 1;
+        }
+        for p in self.petitions.write().unwrap().iter_mut().filter(|p| p.customer_id == duplicate_id) {
+            p.customer_id = customer_id;
+            moved.petitions += 1;
+        }
+        for t in self.trips.write().unwrap().iter_mut().filter(|t| t.customer_id == duplicate_id) {
+            t.customer_id = customer_id;
+            moved.trips += 1;
+        }
+        for t in self.compliance_tasks.write().unwrap().iter_mut().filter(|t| t.customer_id == duplicate_id) {
+            t.customer_id = customer_id;
+            moved.compliance_tasks += 1;
+        }
+        if let Some(case_status) = &self.case_status {
+            moved.case_status_entries = case_status.reassign(duplicate_id, customer_id);
+        }
+        if let Some(lca_documents) = &self.lca_documents {
+            (moved.lca_documents, moved.public_access_files) = lca_documents.reassign(duplicate_id, customer_id);
+        }
+        Ok(MergeOutcome::Merged { customer: Box::new(customers[survivor].clone()), moved })
+    }
+
+    async fn dashboard(&self, today: NaiveDate) -> Result<Dashboard, StoreError> {
+        let customers = self.customers.read().unwrap();
+        let live: Vec<&CreateCustomer> = customers.iter().filter(|c| !is_deleted(c)).collect();
+
+        let mut by_status = HashMap::new();
+        let mut new_per_month = HashMap::new();
+        let mut clients: HashMap<String, (String, i64)> = HashMap::new();
+        let mut salaries: HashMap<&str, Vec<Decimal>> = HashMap::new();
+        let mut states: HashMap<&str, i64> = HashMap::new();
+        let since = dashboard::month_starts(today)[0];
+        for c in &live {
+            *by_status.entry(c.h1b_status.clone()).or_insert(0) += 1;
+            if let Some(created) = c.created_at.map(|at| at.date_naive()).filter(|created| *created >= since) {
+                *new_per_month.entry(dashboard::month_label(created)).or_insert(0) += 1;
+            }
+            let name = c.client_name.trim();
+            let client = clients.entry(name.to_lowercase()).or_insert_with(|| (name.to_string(), 0));
+            if name < client.0.as_str() {
+                client.0 = name.to_string();
+            }
+            client.1 += 1;
+            salaries.entry(&c.lca_code).or_default().push(c.lca_salary);
+            *states.entry(&c.client_state).or_insert(0) += 1;
+        }
+
+        let approved = dashboard::approved_statuses();
+        let expiring = dashboard::EXPIRY_WINDOWS
+            .into_iter()
+            .map(|within_days| {
+                let until = today + chrono::Days::new(within_days as u64);
+                let customers = live
+                    .iter()
+                    .filter(|c| approved.contains(&c.h1b_status) && (today..=until).contains(&c.h1b_end_date))
+                    .count() as i64;
+                ExpiringCount { within_days, customers }
+            })
+            .collect();
+
+        let mut top_clients: Vec<ClientCount> =
+            clients.into_values().map(|(client_name, customers)| ClientCount { client_name, customers }).collect();
+        top_clients.sort_by(|a, b| b.customers.cmp(&a.customers).then_with(|| a.client_name.cmp(&b.client_name)));
+        top_clients.truncate(dashboard::TOP_CLIENTS as usize);
+
+        let mut salary_by_lca_code: Vec<SalaryDistribution> = salaries
+            .into_iter()
+            .map(|(lca_code, mut values)| {
+                values.sort();
+                let quartile = |fraction| dashboard::round_cents(dashboard::percentile(&values, Decimal::new(fraction, 2)));
+                let total: Decimal = values.iter().sum();
+                SalaryDistribution {
+                    lca_code: lca_code.to_string(),
+                    title: None,
+                    customers: values.len() as i64,
+                    min: values[0],
+                    p25: quartile(25),
+                    median: quartile(50),
+                    p75: quartile(75),
+                    max: values[values.len() - 1],
+                    average: dashboard::round_cents(total / Decimal::from(values.len())),
+                }
+            })
+            .collect();
+        salary_by_lca_code.sort_by(|a, b| b.customers.cmp(&a.customers).then_with(|| a.lca_code.cmp(&b.lca_code)));
+
+        let mut by_work_state: Vec<StateCount> =
+            states.into_iter().map(|(state, customers)| StateCount { state: state.to_string(), customers }).collect();
+        by_work_state.sort_by(|a, b| b.customers.cmp(&a.customers).then_with(|| a.state.cmp(&b.state)));
+
+        let tasks = self.compliance_tasks.read().unwrap();
+        let open: Vec<&ComplianceTask> = tasks
+            .iter()
+            .filter(|t| t.completed_at.is_none() && live.iter().any(|c| c.customer_id == t.customer_id))
+            .collect();
+
+        Ok(Dashboard {
+            as_of: today,
+            total_customers: live.len() as i64,
+            by_status: dashboard::fill_statuses(&by_status),
+            expiring,
+            new_per_month: dashboard::fill_months(today, &new_per_month),
+            created_unknown: live.iter().filter(|c| c.created_at.is_none()).count() as i64,
+            top_clients,
+            salary_by_lca_code,
+            by_work_state,
+            open_compliance_tasks: open.len() as i64,
+            overdue_compliance_tasks: open.iter().filter(|t| t.due_on < today).count() as i64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{customer, date};
+
+    fn customer_ending(status: &str, h1b_end_date: &str) -> CreateCustomer {
+        CreateCustomer { h1b_status: status.to_string(), h1b_end_date: date(h1b_end_date), ..customer() }
+    }
+
+    fn task(due_on: &str) -> NewComplianceTask {
+        NewComplianceTask {
+            kind: "lca_posting".to_string(),
+            reason: "New worksite".to_string(),
+            due_on: date(due_on),
+            created_by: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn dashboard_counts_live_customers_as_of_today() {
+        let store = MemoryCustomerStore::new();
+        let deleted = CreateCustomer { deleted_at: Some(Utc::now()), ..customer_ending("Active", "2026-02-01") };
+        let customers = [
+            customer_ending("Active", "2026-02-01"),
+            customer_ending("Approved", "2026-03-20"),
+            customer_ending("Active", "2026-06-30"),
+            customer_ending("Filed", "2026-02-01"),
+            customer_ending("Active", "2026-01-10"),
+            deleted.clone(),
+        ];
+        let ids: Vec<Uuid> = customers.iter().map(|c| c.customer_id).collect();
+        store.customers.write().unwrap().extend(customers);
+
+        let none = UpdateVisaDetailsRequest::default();
+        assert!(store.update(ids[0], &none, Some(&task("2026-01-10"))).await.unwrap());
+        assert!(store.update(ids[1], &none, Some(&task("2026-02-01"))).await.unwrap());
+        assert!(store.update(ids[2], &none, Some(&task("2026-01-01"))).await.unwrap());
+        let done = store.compliance_tasks(ids[2]).await.unwrap()[0].task_id;
+        store.complete_compliance_task(done, "test").await.unwrap();
+        // A deleted customer's tasks aren't open work.
+        store.compliance_tasks.write().unwrap().push(ComplianceTask {
+            task_id: Uuid::new_v4(),
+            customer_id: deleted.customer_id,
+            kind: "lca_posting".to_string(),
+            reason: "New worksite".to_string(),
+            due_on: date("2026-01-01"),
+            created_by: "test".to_string(),
+            created_at: Utc::now(),
+            completed_at: None,
+            completed_by: None,
+        });
+
+        let dashboard = store.dashboard(date("2026-01-15")).await.unwrap();
+        assert_eq!(dashboard.as_of, date("2026-01-15"));
+        assert_eq!(dashboard.total_customers, 5);
+
+        let by_status: Vec<(&str, i64)> = dashboard.by_status.iter().map(|s| (s.h1b_status.as_str(), s.customers)).collect();
+        assert_eq!(by_status.len(), H1bStatus::ALL.len());
+        for (status, customers) in [("Draft", 0), ("Filed", 1), ("Approved", 1), ("Active", 3)] {
+            assert!(by_status.contains(&(status, customers)), "{} in {:?}", status, by_status);
+        }
+
+        // Only Approved and Active customers expiring from today on; each window includes the shorter ones.
+        let expiring: Vec<(i32, i64)> = dashboard.expiring.iter().map(|e| (e.within_days, e.customers)).collect();
+        assert_eq!(expiring, [(30, 1), (60, 1), (90, 2), (180, 3)]);
+
+        assert_eq!((dashboard.open_compliance_tasks, dashboard.overdue_compliance_tasks), (2, 1));
+    }
+}
@@ -0,0 +1,368 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use std::fmt;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::lifecycle::H1bStatus;
+use crate::models::*;
+
+pub mod case_status;
+pub mod idempotency;
+pub mod lca_documents;
+pub mod memory;
+pub mod postgres;
+pub mod prevailing_wage;
+pub mod resilient;
+
+pub use case_status::{CaseStatusObservation, CaseStatusStore, MemoryCaseStatusStore, PgCaseStatusStore};
+pub use idempotency::{IdempotencyStore, MemoryIdempotencyStore, PgIdempotencyStore};
+pub use lca_documents::{LcaDocumentStore, MemoryLcaDocumentStore, PgLcaDocumentStore};
+pub use memory::MemoryCustomerStore;
+pub use postgres::PgCustomerStore;
+pub use prevailing_wage::{
+    MemoryPrevailingWageStore, PgPrevailingWageStore, PrevailingWage, PrevailingWageData, PrevailingWageStore, WageArea,
+    WageLookup,
+};
+pub use resilient::{Admission, CircuitBreaker, Resilience, ResilientStore};
+
+/// Persistence operations for H1B customers.
+///
+/// `PgCustomerStore` is the production implementation; `MemoryCustomerStore`
+/// follows the same semantics without a database, for local development with
+/// seeded data and for fast handler tests.
+///
+/// Receipt numbers are normalized on the way in and unique among non-deleted
+/// customers and their earlier petitions, as are `email` and `login_email`
+/// ignoring case; a clash is a `StoreError::Conflict`.
+///
+/// Soft-deleted customers (`deleted_at` set) are invisible to every read unless
+/// the caller passes `Deleted::Include`, and can't be updated until restored.
+/// `h1b_status` is the visa's status and has nothing to do with deletion; after
+/// creation it only changes through `transition`.
+#[async_trait]
+pub trait CustomerStore: Send + Sync {
+    async fn create(&self, customer: &CreateCompleteCustomerRequest) -> Result<CreateCustomer, StoreError>;
+
+    /// Replaces the details of the non-deleted customer with this `email` (ignoring case), or
+    /// creates one if there is none. When updating, a `h1b_status` that differs
+    /// from the current one is rejected; status changes need a transition.
+    async fn upsert_by_email(&self, customer: &CreateCompleteCustomerRequest) -> Result<UpsertOutcome, StoreError>;
+
+    async fn get_by_id(&self, customer_id: Uuid, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError>;
+
+    /// The customer holding this receipt number, normalized first. With `Deleted::Include`
+    /// a live customer wins over deleted ones that used the same number.
+    async fn get_by_receipt(&self, receipt_number: &str, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError>;
+
+    /// Customers whose `email` or `login_email` matches, regardless of h1b status.
+    async fn find_by_email(&self, email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError>;
+
+    async fn find_by_login_email(&self, login_email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError>;
+
+    async fn list(&self, filter: CustomerFilter, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError>;
+
+    /// Applies the fields present in `update` and records `task`, e.g. one
+    /// raised for a worksite change, in the same transaction; returns `false`
+    /// if no such customer exists.
+    async fn update(&self, customer_id: Uuid, update: &UpdateVisaDetailsRequest, task: Option<&NewComplianceTask>) -> Result<bool, StoreError>;
+
+    /// Marks the customer deleted, recording who did it and why.
+    async fn soft_delete(&self, customer_id: Uuid, deleted_by: &str, reason: Option<&str>) -> Result<SoftDeleteOutcome, StoreError>;
+
+    /// Undoes a soft delete, clearing the deletion columns. A customer merged
+    /// into another can't be restored.
+    async fn restore(&self, customer_id: Uuid) -> Result<RestoreOutcome, StoreError>;
+
+    /// Moves a non-deleted customer to `request.to_status` if the lifecycle allows
+    /// it, recording the change in the same transaction.
+    async fn transition(&self, customer_id: Uuid, request: &TransitionRequest, transitioned_by: &str) -> Result<TransitionOutcome, StoreError>;
+
+    /// Recorded status changes for a customer, oldest first.
+    async fn transitions(&self, customer_id: Uuid) -> Result<Vec<StatusTransition>, StoreError>;
+
+    /// Earlier petitions recorded for a customer, oldest first.
+    async fn petitions(&self, customer_id: Uuid) -> Result<Vec<Petition>, StoreError>;
+
+    /// Earlier petitions of all these customers in one read, each customer's
+    /// oldest first; for reports that would otherwise ask once per customer.
+    async fn petitions_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Petition>, StoreError>;
+
+    /// Records an earlier petition; `None` if the customer doesn't exist or is deleted.
+    async fn add_petition(&self, customer_id: Uuid, petition: &PetitionRequest, recorded_by: &str) -> Result<Option<Petition>, StoreError>;
+
+    /// Trips outside the US, oldest first.
+    async fn trips(&self, customer_id: Uuid) -> Result<Vec<Trip>, StoreError>;
+
+    /// Trips of all these customers in one read, each customer's oldest first.
+    async fn trips_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Trip>, StoreError>;
+
+    /// Records a trip; `None` if the customer doesn't exist or is deleted. A
+    /// trip overlapping another of the customer's, or repeating its I-94
+    /// number, is a `StoreError::Conflict`.
+    async fn add_trip(&self, customer_id: Uuid, trip: &TripRequest, recorded_by: &str) -> Result<Option<Trip>, StoreError>;
+
+    /// Replaces a trip's details, checked like `add_trip`; `None` if the
+    /// customer doesn't exist or is deleted, or has no such trip.
+    async fn update_trip(&self, customer_id: Uuid, trip_id: Uuid, trip: &TripRequest) -> Result<Option<Trip>, StoreError>;
+
+    /// Compliance tasks raised for a customer, open ones first, each by due date.
+    async fn compliance_tasks(&self, customer_id: Uuid) -> Result<Vec<ComplianceTask>, StoreError>;
+
+    /// Open compliance tasks of non-deleted customers, soonest due first.
+    async fn open_compliance_tasks(&self) -> Result<Vec<ComplianceTask>, StoreError>;
+
+    /// Marks a task done; `None` if there is no such task. A task already done
+    /// is a `StoreError::Conflict`.
+    async fn complete_compliance_task(&self, task_id: Uuid, completed_by: &str) -> Result<Option<ComplianceTask>, StoreError>;
+
+    /// Folds `duplicate_id` into the non-deleted customer `customer_id`: the
+    /// duplicate's status transitions, petitions, trips, compliance tasks, case
+    /// status history and LCA documents move over and it is soft-deleted with
+    /// `merged_into` set, all or nothing. The duplicate may already be deleted; one that was
+    /// already merged is a `StoreError::Conflict`.
+    async fn merge(&self, customer_id: Uuid, duplicate_id: Uuid, merged_by: &str, reason: Option<&str>) -> Result<MergeOutcome, StoreError>;
+
+    /// Aggregate figures over non-deleted customers, with expiry windows
+    /// counted from `today`. `SalaryDistribution::title` is left for the caller.
+    async fn dashboard(&self, today: NaiveDate) -> Result<Dashboard, StoreError>;
+}
+
+/// Which customers a list returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomerFilter {
+    ActiveOnly,
+    All,
+    /// Saved before phone normalization: a phone without its display form.
+    PhonesNotNormalized,
+}
+
+/// Whether a read sees soft-deleted customers. Only admins may ask for `Include`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deleted {
+    Exclude,
+    Include,
+}
+
+#[derive(Debug)]
+pub enum UpsertOutcome {
+    Created(Box<CreateCustomer>),
+    Updated(Box<CreateCustomer>),
+}
+
+#[derive(Debug)]
+pub enum SoftDeleteOutcome {
+    Deleted,
+    /// No such customer, or it was already deleted.
+    NotFound,
+}
+
+#[derive(Debug)]
+pub enum RestoreOutcome {
+    Restored(Box<CreateCustomer>),
+    NotDeleted,
+    NotFound,
+}
+
+#[derive(Debug)]
+pub enum MergeOutcome {
+    Merged { customer: Box<CreateCustomer>, moved: MergeMoved },
+    /// Either customer doesn't exist, or the one that stays is deleted.
+    NotFound,
+}
+
+/// History rows a merge moved from the duplicate.
+#[derive(Debug, Default)]
+pub struct MergeMoved {
+    pub transitions: u64,
+    pub petitions: u64,
+    pub trips: u64,
+    pub compliance_tasks: u64,
+    pub case_status_entries: u64,
+    pub lca_documents: u64,
+    pub public_access_files: u64,
+}
+
+#[derive(Debug)]
+pub enum TransitionOutcome {
+    Applied(Box<CreateCustomer>, Box<StatusTransition>),
+    /// The lifecycle doesn't allow moving from the current status to the requested one.
+    NotAllowed { from: H1bStatus },
+    /// No such customer, or it is deleted.
+    NotFound,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    /// The input was rejected by the store, e.g. an unknown enum value.
+    Invalid(String),
+    /// The request conflicts with existing data, e.g. an ambiguous upsert match.
+    Conflict(String),
+    Database(sqlx::Error),
+    /// The database is failing or the circuit breaker is open; try again later.
+    Unavailable { retry_after: Duration },
+}
+
+/// How a failed store call should be handled by the retry policy and circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Caused by the request itself (bad input, constraint violation). The
+    /// database is healthy; retrying won't help.
+    Permanent,
+    /// The operation certainly didn't take effect (rolled back, or never got a
+    /// connection), so any operation may be retried.
+    RetryAny,
+    /// The connection broke mid-flight; the statement may or may not have run,
+    /// so only idempotent operations are retried.
+    RetryIdempotent,
+    /// The database is unhealthy (e.g. statement timeout); retrying would only add load.
+    NoRetry,
+}
+
+impl StoreError {
+    pub fn kind(&self) -> FailureKind {
+        let e = match self {
+            StoreError::Invalid(_) | StoreError::Conflict(_) => return FailureKind::Permanent,
+            StoreError::Unavailable { .. } => return FailureKind::NoRetry,
+            StoreError::Database(e) => e,
+        };
+        match e {
+            sqlx::Error::PoolTimedOut => FailureKind::RetryAny,
+            sqlx::Error::Io(_) | sqlx::Error::Tls(_) => FailureKind::RetryIdempotent,
+            sqlx::Error::Database(db) => match db.code().as_deref() {
+                // serialization_failure, deadlock_detected: the transaction was rolled back
+                Some("40001") | Some("40P01") => FailureKind::RetryAny,
+                // cannot_connect_now: server starting up or shutting down
+                Some("57P03") => FailureKind::RetryAny,
+                // admin_shutdown, crash_shutdown: the pooler or server dropped the session
+                Some("57P01") | Some("57P02") => FailureKind::RetryIdempotent,
+                Some(code) if code.starts_with("08") => FailureKind::RetryIdempotent,
+                // query_canceled: hit the statement timeout
+                Some("57014") => FailureKind::NoRetry,
+                _ => FailureKind::Permanent,
+            },
+            _ => FailureKind::Permanent,
+        }
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Invalid(message) => write!(f, "invalid input: {}", message),
+            StoreError::Conflict(message) => write!(f, "conflict: {}", message),
+            StoreError::Database(e) => write!(f, "database error: {}", e),
+            StoreError::Unavailable { retry_after } => {
+                write!(f, "database unavailable, retry after {}s", retry_after.as_secs())
+            }
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            // invalid_text_representation: bad enum/date/number literal in the input
+            sqlx::Error::Database(db) if db.code().as_deref() == Some("22P02") => {
+                StoreError::Invalid(db.message().to_string())
+            }
+            // unique_violation
+            sqlx::Error::Database(db) if db.code().as_deref() == Some("23505") => match db.constraint() {
+                Some(RECEIPT_NUMBER_INDEX) => receipt_in_use(),
+                Some(EMAIL_INDEX) => StoreError::Conflict("email is already used by another customer".to_string()),
+                Some(LOGIN_EMAIL_INDEX) => StoreError::Conflict("login_email is already used by another customer".to_string()),
+                _ => StoreError::Conflict(db.message().to_string()),
+            },
+            _ => StoreError::Database(e),
+        }
+    }
+}
+
+/// Partial unique index on `h1bcustomer.receipt_number`, from migration 0005.
+const RECEIPT_NUMBER_INDEX: &str = "h1bcustomer_receipt_number_key";
+/// Partial unique indexes on `lower(email)` and `lower(login_email)`, from migration 0010.
+const EMAIL_INDEX: &str = "h1bcustomer_email_key";
+const LOGIN_EMAIL_INDEX: &str = "h1bcustomer_login_email_key";
+
+fn receipt_in_use() -> StoreError {
+    StoreError::Conflict("receipt number is already used by another customer".to_string())
+}
+
+fn normalize_receipt(raw: &str) -> Result<String, StoreError> {
+    crate::receipt::normalize(raw).map_err(StoreError::Invalid)
+}
+
+/// The normalized receipt number a transition saves on the customer; only
+/// moves to a status that needs one may set it.
+fn transition_receipt(request: &TransitionRequest) -> Result<Option<String>, StoreError> {
+    if request.receipt_number.is_some() && !request.to_status.requires_receipt_number() {
+        return Err(StoreError::Invalid(format!("receipt_number can't be set when moving to {}", request.to_status)));
+    }
+    request.receipt_number.as_deref().map(normalize_receipt).transpose()
+}
+
+/// A receipt number comes with the move to `Filed`; after that it may be
+/// corrected, but a customer who hasn't filed can't hold one.
+fn check_receipt_allowed(h1b_status: &str) -> Result<(), StoreError> {
+    if parse_status(h1b_status)?.has_filed() {
+        return Ok(());
+    }
+    Err(StoreError::Invalid(format!(
+        "receipt_number can't be set on a {} customer; send it with the transition to Filed",
+        h1b_status
+    )))
+}
+
+fn receipt_required(to_status: H1bStatus) -> StoreError {
+    StoreError::Invalid(format!("a receipt number is required to move to {}; send receipt_number", to_status))
+}
+
+fn normalize_trip(trip: &TripRequest) -> Result<TripRequest, StoreError> {
+    crate::travel::normalize(trip).map_err(StoreError::Invalid)
+}
+
+fn check_trip(trips: &[Trip], trip: &TripRequest, replacing: Option<Uuid>) -> Result<(), StoreError> {
+    match crate::travel::conflict(trips, trip, replacing) {
+        Some(message) => Err(StoreError::Conflict(message)),
+        None => Ok(()),
+    }
+}
+
+fn task_already_completed(task: &ComplianceTask) -> StoreError {
+    let by = task.completed_by.as_deref().unwrap_or("unknown");
+    StoreError::Conflict(format!("compliance task {} was already completed by {}", task.task_id, by))
+}
+
+fn merge_into_self(customer_id: Uuid) -> StoreError {
+    StoreError::Invalid(format!("can't merge customer {} into itself", customer_id))
+}
+
+/// The duplicate's `delete_reason`; it says where the customer went either way.
+fn merge_reason(customer_id: Uuid, reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) => format!("merged into {}: {}", customer_id, reason),
+        None => format!("merged into {}", customer_id),
+    }
+}
+
+fn already_merged(duplicate_id: Uuid, into: Uuid) -> StoreError {
+    StoreError::Conflict(format!("customer {} was already merged into {}", duplicate_id, into))
+}
+
+pub const SEX_VALUES: &[&str] = &["MALE", "FEMALE", "OTHER"];
+pub const MARITAL_STATUS_VALUES: &[&str] = &["SINGLE", "MARRIED", "DIVORCED", "WIDOWED"];
+pub const WAGE_LEVEL_VALUES: &[&str] = &["I", "II", "III", "IV"];
+/// Upserts replace details but must not skip the lifecycle.
+fn status_change_in_upsert(current: &str, requested: &str) -> StoreError {
+    StoreError::Conflict(format!(
+        "upsert would change h1b_status from {} to {}; use /customers/{{id}}/transitions",
+        current, requested
+    ))
+}
+
+/// The stored label always parses; the database enum and `H1bStatus` list the same values.
+fn parse_status(value: &str) -> Result<H1bStatus, StoreError> {
+    value.parse().map_err(StoreError::Invalid)
+}
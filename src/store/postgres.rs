@@ -0,0 +1,795 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, Transaction};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::*;
+use crate::dashboard;
+
+const CUSTOMER_COLUMNS: &str = "customer_id, email, first_name, last_name, dob, sex::text, marital_status::text, phone,
+        emergency_contact_name, emergency_contact_phone, phone_display, emergency_contact_phone_display, employment_start_date,
+        street_name, city, state, zip,
+        client_name, client_street_name, client_city, client_state, client_zip,
+        county, msa, client_county, client_msa,
+        lca_title, lca_salary, lca_code, lca_wage_level::text, receipt_number, h1b_start_date, h1b_end_date, login_email,
+        h1b_status::text, labor_certification_filed_on, i140_approved_on, created_at, deleted_at, deleted_by, delete_reason, merged_into";
+
+const PETITION_COLUMNS: &str = "petition_id, customer_id, receipt_number, employer, valid_from, valid_to, recorded_by, created_at";
+
+const TASK_COLUMNS: &str = "task_id, customer_id, kind, reason, due_on, created_by, created_at, completed_at, completed_by";
+const TRIP_COLUMNS: &str = "trip_id, customer_id, departed_on, returned_on, port_of_entry, admission_class, i94_number, recorded_by, created_at";
+
+const TRANSITION_COLUMNS: &str = "transition_id, customer_id, from_status::text, to_status::text, reason,
+        effective_date, transitioned_by, created_at, merged_from";
+
+/// `lca_code`, count, then min, quartiles, max and average salary.
+type SalaryRow = (String, i64, Decimal, Decimal, Decimal, Decimal, Decimal, Decimal);
+
+/// Serializes writes that claim `receipt_number`, on customers or petitions.
+async fn lock_receipt(tx: &mut Transaction<'static, Postgres>, schema: &str, receipt_number: &str) -> Result<(), StoreError> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(format!("{schema}.receipt_number:{}", receipt_number))
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Every store operation runs in its own transaction so the statement timeout
+/// can be set with `SET LOCAL` semantics; session-level settings don't survive
+/// a transaction pooler.
+pub(crate) async fn begin(pool: &PgPool, statement_timeout: Option<Duration>) -> Result<Transaction<'static, Postgres>, StoreError> {
+    let mut tx = pool.begin().await?;
+    if let Some(timeout) = statement_timeout {
+        sqlx::query("SELECT set_config('statement_timeout', $1, true)")
+            .bind(format!("{}ms", timeout.as_millis()))
+            .execute(&mut *tx)
+            .await?;
+    }
+    Ok(tx)
+}
+
+fn deleted_condition(deleted: Deleted) -> &'static str {
+    match deleted {
+        Deleted::Exclude => "deleted_at IS NULL",
+        Deleted::Include => "TRUE",
+    }
+}
+
+pub struct PgCustomerStore {
+    pool: PgPool,
+    schema: String,
+    statement_timeout: Option<Duration>,
+}
+
+impl PgCustomerStore {
+    pub fn new(pool: PgPool, schema: impl Into<String>) -> Self {
+        Self { pool, schema: schema.into(), statement_timeout: None }
+    }
+
+    /// Cancels any statement running longer than `timeout` (`None` disables the limit).
+    pub fn with_statement_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.statement_timeout = timeout;
+        self
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, StoreError> {
+        begin(&self.pool, self.statement_timeout).await
+    }
+
+    /// Locks the row and returns its `h1b_status` and whether it is soft-deleted.
+    async fn lock_customer(&self, tx: &mut Transaction<'static, Postgres>, customer_id: Uuid) -> Result<Option<(String, bool)>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT h1b_status::text, deleted_at IS NOT NULL
+            FROM {schema}.h1bcustomer WHERE customer_id = $1 FOR UPDATE");
+        Ok(sqlx::query_as(&sql).bind(customer_id).fetch_optional(&mut **tx).await?)
+    }
+
+    async fn customer_trips(&self, tx: &mut Transaction<'static, Postgres>, customer_id: Uuid) -> Result<Vec<Trip>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {TRIP_COLUMNS} FROM {schema}.h1b_trips WHERE customer_id = $1");
+        Ok(sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut **tx).await?)
+    }
+
+    /// The customers' unique index can't see petitions, so a customer's receipt
+    /// number is checked against other live customers' petitions here, under
+    /// the same per-receipt lock `add_petition` takes.
+    async fn check_receipt_not_in_petitions(&self, tx: &mut Transaction<'static, Postgres>, receipt_number: &str, customer_id: Uuid) -> Result<(), StoreError> {
+        let schema = &self.schema;
+        lock_receipt(tx, schema, receipt_number).await?;
+        let sql = format!("SELECT EXISTS (SELECT 1 FROM {schema}.h1b_petitions p JOIN {schema}.h1bcustomer c USING (customer_id)
+            WHERE p.receipt_number = $1 AND p.customer_id <> $2 AND c.deleted_at IS NULL)");
+        let taken: bool = sqlx::query_scalar(&sql).bind(receipt_number).bind(customer_id).fetch_one(&mut **tx).await?;
+        if taken {
+            return Err(receipt_in_use());
+        }
+        Ok(())
+    }
+
+    async fn insert(&self, tx: &mut Transaction<'static, Postgres>, c: &CreateCompleteCustomerRequest) -> Result<CreateCustomer, StoreError> {
+        let schema = &self.schema;
+        let h1b_status = c.h1b_status.as_deref().unwrap_or("Draft");
+        let receipt_number = c.receipt_number.as_deref().map(normalize_receipt).transpose()?;
+        if let Some(receipt_number) = &receipt_number {
+            check_receipt_allowed(h1b_status)?;
+            self.check_receipt_not_in_petitions(tx, receipt_number, Uuid::nil()).await?;
+        }
+
+        let sql = format!("INSERT INTO {schema}.h1bcustomer (
+                email, first_name, last_name, dob, sex, marital_status, phone,
+                emergency_contact_name, emergency_contact_phone, employment_start_date,
+                street_name, city, state, zip,
+                client_name, client_street_name, client_city, client_state, client_zip,
+                lca_title, lca_salary, lca_code, receipt_number, h1b_start_date, h1b_end_date, login_email, h1b_status,
+                lca_wage_level, county, msa, client_county, client_msa, phone_display, emergency_contact_phone_display,
+                labor_certification_filed_on, i140_approved_on
+            ) VALUES (
+                $1, $2, $3, $4, $5::text::{schema}.sex_enum, $6::text::{schema}.marital_status_enum, $7,
+                $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26,
+                $27::text::{schema}.h1b_status_enum, $28::text::{schema}.wage_level_enum, $29, $30, $31, $32, $33, $34, $35, $36
+            ) RETURNING {CUSTOMER_COLUMNS}");
+
+        let customer = sqlx::query_as::<_, CreateCustomer>(&sql)
+            .bind(&c.email).bind(&c.first_name).bind(&c.last_name).bind(c.dob)
+            .bind(&c.sex).bind(&c.marital_status).bind(&c.phone)
+            .bind(&c.emergency_contact_name).bind(&c.emergency_contact_phone).bind(c.employment_start_date)
+            .bind(&c.street_name).bind(&c.city).bind(&c.state).bind(&c.zip)
+            .bind(&c.client_name).bind(&c.client_street_name).bind(&c.client_city)
+            .bind(&c.client_state).bind(&c.client_zip)
+            .bind(&c.lca_title).bind(c.lca_salary).bind(&c.lca_code)
+            .bind(&receipt_number).bind(c.h1b_start_date).bind(c.h1b_end_date).bind(&c.login_email).bind(h1b_status)
+            .bind(&c.lca_wage_level)
+            .bind(&c.county).bind(&c.msa).bind(&c.client_county).bind(&c.client_msa)
+            .bind(&c.phone_display).bind(&c.emergency_contact_phone_display)
+            .bind(c.labor_certification_filed_on).bind(c.i140_approved_on)
+            .fetch_one(&mut **tx)
+            .await?;
+        Ok(customer)
+    }
+
+    /// Overwrites every column from `c` except `h1b_status`, which callers have
+    /// already checked is unchanged, and a `receipt_number` that `c` leaves out.
+    async fn replace(&self, tx: &mut Transaction<'static, Postgres>, customer_id: Uuid, c: &CreateCompleteCustomerRequest) -> Result<CreateCustomer, StoreError> {
+        let schema = &self.schema;
+        let receipt_number = c.receipt_number.as_deref().map(normalize_receipt).transpose()?;
+        if let Some(receipt_number) = &receipt_number {
+            self.check_receipt_not_in_petitions(tx, receipt_number, customer_id).await?;
+        }
+        let sql = format!("UPDATE {schema}.h1bcustomer SET
+                email = $2, first_name = $3, last_name = $4, dob = $5,
+                sex = $6::text::{schema}.sex_enum, marital_status = $7::text::{schema}.marital_status_enum, phone = $8,
+                emergency_contact_name = $9, emergency_contact_phone = $10, employment_start_date = $11,
+                street_name = $12, city = $13, state = $14, zip = $15,
+                client_name = $16, client_street_name = $17, client_city = $18, client_state = $19, client_zip = $20,
+                lca_title = $21, lca_salary = $22, lca_code = $23, receipt_number = COALESCE($24, receipt_number),
+                h1b_start_date = $25, h1b_end_date = $26, login_email = $27,
+                lca_wage_level = $28::text::{schema}.wage_level_enum,
+                county = $29, msa = $30, client_county = $31, client_msa = $32,
+                phone_display = $33, emergency_contact_phone_display = $34,
+                labor_certification_filed_on = $35, i140_approved_on = $36
+            WHERE customer_id = $1
+            RETURNING {CUSTOMER_COLUMNS}");
+
+        let customer = sqlx::query_as::<_, CreateCustomer>(&sql)
+            .bind(customer_id)
+            .bind(&c.email).bind(&c.first_name).bind(&c.last_name).bind(c.dob)
+            .bind(&c.sex).bind(&c.marital_status).bind(&c.phone)
+            .bind(&c.emergency_contact_name).bind(&c.emergency_contact_phone).bind(c.employment_start_date)
+            .bind(&c.street_name).bind(&c.city).bind(&c.state).bind(&c.zip)
+            .bind(&c.client_name).bind(&c.client_street_name).bind(&c.client_city)
+            .bind(&c.client_state).bind(&c.client_zip)
+            .bind(&c.lca_title).bind(c.lca_salary).bind(&c.lca_code)
+            .bind(&receipt_number).bind(c.h1b_start_date).bind(c.h1b_end_date).bind(&c.login_email)
+            .bind(&c.lca_wage_level)
+            .bind(&c.county).bind(&c.msa).bind(&c.client_county).bind(&c.client_msa)
+            .bind(&c.phone_display).bind(&c.emergency_contact_phone_display)
+            .bind(c.labor_certification_filed_on).bind(c.i140_approved_on)
+            .fetch_one(&mut **tx)
+            .await?;
+        Ok(customer)
+    }
+}
+
+#[async_trait]
+impl CustomerStore for PgCustomerStore {
+    async fn create(&self, c: &CreateCompleteCustomerRequest) -> Result<CreateCustomer, StoreError> {
+        let mut tx = self.begin().await?;
+        let customer = self.insert(&mut tx, c).await?;
+        tx.commit().await?;
+        Ok(customer)
+    }
+
+    async fn upsert_by_email(&self, c: &CreateCompleteCustomerRequest) -> Result<UpsertOutcome, StoreError> {
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+
+        // The unique index on lower(email) makes the second of two concurrent
+        // inserts fail; serialize upserts per email so it updates instead.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("{schema}.h1bcustomer.email:{}", c.email.to_lowercase()))
+            .execute(&mut *tx)
+            .await?;
+        let sql = format!("SELECT customer_id, h1b_status::text FROM {schema}.h1bcustomer
+            WHERE lower(email) = lower($1) AND deleted_at IS NULL FOR UPDATE");
+        let matched: Option<(Uuid, String)> = sqlx::query_as(&sql).bind(&c.email).fetch_optional(&mut *tx).await?;
+
+        let outcome = match matched {
+            None => UpsertOutcome::Created(Box::new(self.insert(&mut tx, c).await?)),
+            Some((customer_id, status)) => {
+                if let Some(requested) = c.h1b_status.as_deref().filter(|requested| *requested != status) {
+                    return Err(status_change_in_upsert(&status, requested));
+                }
+                if c.receipt_number.is_some() {
+                    check_receipt_allowed(&status)?;
+                }
+                UpsertOutcome::Updated(Box::new(self.replace(&mut tx, customer_id, c).await?))
+            }
+        };
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    async fn get_by_id(&self, customer_id: Uuid, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError> {
+        let schema = &self.schema;
+        let visible = deleted_condition(deleted);
+        let sql = format!("SELECT {CUSTOMER_COLUMNS}
+            FROM {schema}.h1bcustomer WHERE customer_id = $1 AND {visible}");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_id).fetch_optional(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn get_by_receipt(&self, receipt_number: &str, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError> {
+        let receipt_number = normalize_receipt(receipt_number)?;
+        let schema = &self.schema;
+        let visible = deleted_condition(deleted);
+        let sql = format!("SELECT {CUSTOMER_COLUMNS}
+            FROM {schema}.h1bcustomer WHERE receipt_number = $1 AND {visible}
+            ORDER BY deleted_at IS NOT NULL, deleted_at DESC LIMIT 1");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(receipt_number).fetch_optional(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn find_by_email(&self, email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        let schema = &self.schema;
+        let visible = deleted_condition(deleted);
+        let sql = format!("SELECT {CUSTOMER_COLUMNS}
+            FROM {schema}.h1bcustomer WHERE (lower(email) = lower($1) OR lower(login_email) = lower($1)) AND {visible}");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(email).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn find_by_login_email(&self, login_email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        let schema = &self.schema;
+        let visible = deleted_condition(deleted);
+        let sql = format!("SELECT {CUSTOMER_COLUMNS}
+            FROM {schema}.h1bcustomer WHERE lower(login_email) = lower($1) AND {visible}");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(login_email).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn list(&self, filter: CustomerFilter, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        let schema = &self.schema;
+        let status = match filter {
+            CustomerFilter::ActiveOnly => "h1b_status = 'Active'",
+            CustomerFilter::All => "TRUE",
+            CustomerFilter::PhonesNotNormalized => "(phone_display IS NULL OR emergency_contact_phone_display IS NULL)",
+        };
+        let visible = deleted_condition(deleted);
+        let sql = format!("SELECT {CUSTOMER_COLUMNS} FROM {schema}.h1bcustomer WHERE {status} AND {visible}");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn update(&self, customer_id: Uuid, u: &UpdateVisaDetailsRequest, task: Option<&NewComplianceTask>) -> Result<bool, StoreError> {
+        let receipt_number = u.receipt_number.as_deref().map(normalize_receipt).transpose()?;
+        let schema = &self.schema;
+        let sql = format!("UPDATE {schema}.h1bcustomer SET
+            email = COALESCE($2, email), first_name = COALESCE($3, first_name), last_name = COALESCE($4, last_name),
+            dob = COALESCE($5, dob),
+            sex = COALESCE($6::text::{schema}.sex_enum, sex),
+            marital_status = COALESCE($7::text::{schema}.marital_status_enum, marital_status),
+            phone = COALESCE($8, phone), emergency_contact_name = COALESCE($9, emergency_contact_name),
+            emergency_contact_phone = COALESCE($10, emergency_contact_phone),
+            employment_start_date = COALESCE($11, employment_start_date),
+            street_name = COALESCE($12, street_name), city = COALESCE($13, city), state = COALESCE($14, state), zip = COALESCE($15, zip),
+            client_name = COALESCE($16, client_name), client_street_name = COALESCE($17, client_street_name),
+            client_city = COALESCE($18, client_city), client_state = COALESCE($19, client_state), client_zip = COALESCE($20, client_zip),
+            lca_title = COALESCE($21, lca_title), lca_salary = COALESCE($22, lca_salary), lca_code = COALESCE($23, lca_code),
+            receipt_number = COALESCE($24, receipt_number),
+            h1b_start_date = COALESCE($25, h1b_start_date), h1b_end_date = COALESCE($26, h1b_end_date),
+            login_email = COALESCE($27, login_email),
+            lca_wage_level = COALESCE($28::text::{schema}.wage_level_enum, lca_wage_level),
+            county = CASE WHEN $15::text IS NULL THEN county ELSE $29 END,
+            msa = CASE WHEN $15::text IS NULL THEN msa ELSE $30 END,
+            client_county = CASE WHEN $20::text IS NULL THEN client_county ELSE $31 END,
+            client_msa = CASE WHEN $20::text IS NULL THEN client_msa ELSE $32 END,
+            phone_display = CASE WHEN $8::text IS NULL THEN phone_display ELSE $33 END,
+            emergency_contact_phone_display = CASE WHEN $10::text IS NULL THEN emergency_contact_phone_display ELSE $34 END,
+            labor_certification_filed_on = COALESCE($35, labor_certification_filed_on),
+            i140_approved_on = COALESCE($36, i140_approved_on)
+            WHERE customer_id = $1 AND deleted_at IS NULL");
+
+        let mut tx = self.begin().await?;
+        if let Some(receipt_number) = &receipt_number {
+            match self.lock_customer(&mut tx, customer_id).await? {
+                None | Some((_, true)) => return Ok(false),
+                Some((status, false)) => check_receipt_allowed(&status)?,
+            }
+            self.check_receipt_not_in_petitions(&mut tx, receipt_number, customer_id).await?;
+        }
+        let result = sqlx::query(&sql)
+            .bind(customer_id)
+            .bind(&u.email).bind(&u.first_name).bind(&u.last_name)
+            .bind(u.dob).bind(&u.sex).bind(&u.marital_status)
+            .bind(&u.phone).bind(&u.emergency_contact_name).bind(&u.emergency_contact_phone)
+            .bind(u.employment_start_date)
+            .bind(&u.street_name).bind(&u.city).bind(&u.state).bind(&u.zip)
+            .bind(&u.client_name).bind(&u.client_street_name)
+            .bind(&u.client_city).bind(&u.client_state).bind(&u.client_zip)
+            .bind(&u.lca_title).bind(u.lca_salary).bind(&u.lca_code)
+            .bind(&receipt_number)
+            .bind(u.h1b_start_date).bind(u.h1b_end_date)
+            .bind(&u.login_email)
+            .bind(&u.lca_wage_level)
+            .bind(&u.county).bind(&u.msa).bind(&u.client_county).bind(&u.client_msa)
+            .bind(&u.phone_display).bind(&u.emergency_contact_phone_display)
+            .bind(u.labor_certification_filed_on).bind(u.i140_approved_on)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        if let Some(task) = task {
+            let sql = format!("INSERT INTO {schema}.compliance_tasks (customer_id, kind, reason, due_on, created_by)
+                VALUES ($1, $2, $3, $4, $5)");
+            sqlx::query(&sql)
+                .bind(customer_id)
+                .bind(&task.kind)
+                .bind(&task.reason)
+                .bind(task.due_on)
+                .bind(&task.created_by)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn soft_delete(&self, customer_id: Uuid, deleted_by: &str, reason: Option<&str>) -> Result<SoftDeleteOutcome, StoreError> {
+        let mut tx = self.begin().await?;
+        match self.lock_customer(&mut tx, customer_id).await? {
+            None | Some((_, true)) => return Ok(SoftDeleteOutcome::NotFound),
+            Some((_, false)) => {}
+        }
+
+        let schema = &self.schema;
+        let sql = format!("UPDATE {schema}.h1bcustomer SET deleted_at = now(), deleted_by = $2, delete_reason = $3
+            WHERE customer_id = $1");
+        sqlx::query(&sql).bind(customer_id).bind(deleted_by).bind(reason).execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(SoftDeleteOutcome::Deleted)
+    }
+
+    async fn restore(&self, customer_id: Uuid) -> Result<RestoreOutcome, StoreError> {
+        let mut tx = self.begin().await?;
+        match self.lock_customer(&mut tx, customer_id).await? {
+            None => return Ok(RestoreOutcome::NotFound),
+            Some((_, false)) => return Ok(RestoreOutcome::NotDeleted),
+            Some((_, true)) => {}
+        }
+
+        let schema = &self.schema;
+        let sql = format!("SELECT merged_into FROM {schema}.h1bcustomer WHERE customer_id = $1");
+        let merged_into: Option<Uuid> = sqlx::query_scalar(&sql).bind(customer_id).fetch_one(&mut *tx).await?;
+        if let Some(into) = merged_into {
+            return Err(already_merged(customer_id, into));
+        }
+        // While deleted, its receipt numbers may have been taken by petitions.
+        let sql = format!("WITH mine AS (
+                SELECT receipt_number FROM {schema}.h1b_petitions WHERE customer_id = $1 AND receipt_number IS NOT NULL
+                UNION SELECT receipt_number FROM {schema}.h1bcustomer WHERE customer_id = $1 AND receipt_number IS NOT NULL
+            )
+            SELECT EXISTS (SELECT 1 FROM {schema}.h1bcustomer
+                    WHERE customer_id <> $1 AND deleted_at IS NULL AND receipt_number IN (SELECT receipt_number FROM mine))
+                OR EXISTS (SELECT 1 FROM {schema}.h1b_petitions p JOIN {schema}.h1bcustomer c USING (customer_id)
+                    WHERE p.customer_id <> $1 AND c.deleted_at IS NULL AND p.receipt_number IN (SELECT receipt_number FROM mine))");
+        let taken: bool = sqlx::query_scalar(&sql).bind(customer_id).fetch_one(&mut *tx).await?;
+        if taken {
+            return Err(receipt_in_use());
+        }
+
+        let sql = format!("UPDATE {schema}.h1bcustomer SET deleted_at = NULL, deleted_by = NULL, delete_reason = NULL
+            WHERE customer_id = $1
+            RETURNING {CUSTOMER_COLUMNS}");
+        let customer: CreateCustomer = sqlx::query_as(&sql).bind(customer_id).fetch_one(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(RestoreOutcome::Restored(Box::new(customer)))
+    }
+
+    async fn transition(&self, customer_id: Uuid, request: &TransitionRequest, transitioned_by: &str) -> Result<TransitionOutcome, StoreError> {
+        let receipt_number = transition_receipt(request)?;
+        let mut tx = self.begin().await?;
+        let from = match self.lock_customer(&mut tx, customer_id).await? {
+            None | Some((_, true)) => return Ok(TransitionOutcome::NotFound),
+            Some((status, false)) => parse_status(&status)?,
+        };
+        if !from.can_transition_to(request.to_status) {
+            return Ok(TransitionOutcome::NotAllowed { from });
+        }
+
+        let schema = &self.schema;
+        if request.to_status.requires_receipt_number() {
+            match &receipt_number {
+                Some(receipt_number) => self.check_receipt_not_in_petitions(&mut tx, receipt_number, customer_id).await?,
+                None => {
+                    let sql = format!("SELECT receipt_number IS NOT NULL FROM {schema}.h1bcustomer WHERE customer_id = $1");
+                    let has_receipt: bool = sqlx::query_scalar(&sql).bind(customer_id).fetch_one(&mut *tx).await?;
+                    if !has_receipt {
+                        return Err(receipt_required(request.to_status));
+                    }
+                }
+            }
+        }
+
+        let sql = format!("UPDATE {schema}.h1bcustomer SET h1b_status = $2::text::{schema}.h1b_status_enum,
+                receipt_number = COALESCE($3, receipt_number)
+            WHERE customer_id = $1
+            RETURNING {CUSTOMER_COLUMNS}");
+        let customer: CreateCustomer = sqlx::query_as(&sql)
+            .bind(customer_id)
+            .bind(request.to_status.as_str())
+            .bind(&receipt_number)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let sql = format!("INSERT INTO {schema}.h1b_status_transitions (
+                customer_id, from_status, to_status, reason, effective_date, transitioned_by
+            ) VALUES (
+                $1, $2::text::{schema}.h1b_status_enum, $3::text::{schema}.h1b_status_enum, $4, $5, $6
+            ) RETURNING {TRANSITION_COLUMNS}");
+        let transition: StatusTransition = sqlx::query_as(&sql)
+            .bind(customer_id)
+            .bind(from.as_str())
+            .bind(request.to_status.as_str())
+            .bind(&request.reason)
+            .bind(request.effective_date)
+            .bind(transitioned_by)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(TransitionOutcome::Applied(Box::new(customer), Box::new(transition)))
+    }
+
+    async fn transitions(&self, customer_id: Uuid) -> Result<Vec<StatusTransition>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {TRANSITION_COLUMNS} FROM {schema}.h1b_status_transitions
+            WHERE customer_id = $1 ORDER BY created_at, transition_id");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn petitions(&self, customer_id: Uuid) -> Result<Vec<Petition>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {PETITION_COLUMNS} FROM {schema}.h1b_petitions
+            WHERE customer_id = $1 ORDER BY valid_from, created_at");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn petitions_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Petition>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {PETITION_COLUMNS} FROM {schema}.h1b_petitions
+            WHERE customer_id = ANY($1) ORDER BY customer_id, valid_from, created_at");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_ids).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn add_petition(&self, customer_id: Uuid, p: &PetitionRequest, recorded_by: &str) -> Result<Option<Petition>, StoreError> {
+        if p.valid_to < p.valid_from {
+            return Err(StoreError::Invalid("valid_to is before valid_from".to_string()));
+        }
+        let receipt_number = p.receipt_number.as_deref().map(normalize_receipt).transpose()?;
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+        match self.lock_customer(&mut tx, customer_id).await? {
+            None | Some((_, true)) => return Ok(None),
+            Some((_, false)) => {}
+        }
+
+        if let Some(receipt_number) = &receipt_number {
+            lock_receipt(&mut tx, schema, receipt_number).await?;
+            let sql = format!("SELECT EXISTS (SELECT 1 FROM {schema}.h1bcustomer
+                    WHERE receipt_number = $1 AND customer_id <> $2 AND deleted_at IS NULL)
+                OR EXISTS (SELECT 1 FROM {schema}.h1b_petitions p JOIN {schema}.h1bcustomer c USING (customer_id)
+                    WHERE p.receipt_number = $1 AND c.deleted_at IS NULL)");
+            let taken: bool = sqlx::query_scalar(&sql).bind(receipt_number).bind(customer_id).fetch_one(&mut *tx).await?;
+            if taken {
+                return Err(receipt_in_use());
+            }
+        }
+
+        let sql = format!("INSERT INTO {schema}.h1b_petitions (customer_id, receipt_number, employer, valid_from, valid_to, recorded_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING {PETITION_COLUMNS}");
+        let petition: Petition = sqlx::query_as(&sql)
+            .bind(customer_id)
+            .bind(&receipt_number)
+            .bind(&p.employer)
+            .bind(p.valid_from)
+            .bind(p.valid_to)
+            .bind(recorded_by)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some(petition))
+    }
+
+    async fn trips(&self, customer_id: Uuid) -> Result<Vec<Trip>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {TRIP_COLUMNS} FROM {schema}.h1b_trips
+            WHERE customer_id = $1 ORDER BY departed_on, created_at");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn trips_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Trip>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {TRIP_COLUMNS} FROM {schema}.h1b_trips
+            WHERE customer_id = ANY($1) ORDER BY customer_id, departed_on, created_at");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_ids).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn add_trip(&self, customer_id: Uuid, trip: &TripRequest, recorded_by: &str) -> Result<Option<Trip>, StoreError> {
+        let trip = normalize_trip(trip)?;
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+        // The customer row lock keeps two trips added at once from overlapping.
+        match self.lock_customer(&mut tx, customer_id).await? {
+            None | Some((_, true)) => return Ok(None),
+            Some((_, false)) => {}
+        }
+        check_trip(&self.customer_trips(&mut tx, customer_id).await?, &trip, None)?;
+
+        let sql = format!("INSERT INTO {schema}.h1b_trips
+                (customer_id, departed_on, returned_on, port_of_entry, admission_class, i94_number, recorded_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING {TRIP_COLUMNS}");
+        let trip: Trip = sqlx::query_as(&sql)
+            .bind(customer_id)
+            .bind(trip.departed_on)
+            .bind(trip.returned_on)
+            .bind(&trip.port_of_entry)
+            .bind(&trip.admission_class)
+            .bind(&trip.i94_number)
+            .bind(recorded_by)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some(trip))
+    }
+
+    async fn update_trip(&self, customer_id: Uuid, trip_id: Uuid, trip: &TripRequest) -> Result<Option<Trip>, StoreError> {
+        let trip = normalize_trip(trip)?;
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+        match self.lock_customer(&mut tx, customer_id).await? {
+            None | Some((_, true)) => return Ok(None),
+            Some((_, false)) => {}
+        }
+        let trips = self.customer_trips(&mut tx, customer_id).await?;
+        if !trips.iter().any(|t| t.trip_id == trip_id) {
+            return Ok(None);
+        }
+        check_trip(&trips, &trip, Some(trip_id))?;
+
+        let sql = format!("UPDATE {schema}.h1b_trips
+            SET departed_on = $3, returned_on = $4, port_of_entry = $5, admission_class = $6, i94_number = $7
+            WHERE trip_id = $1 AND customer_id = $2
+            RETURNING {TRIP_COLUMNS}");
+        let trip: Trip = sqlx::query_as(&sql)
+            .bind(trip_id)
+            .bind(customer_id)
+            .bind(trip.departed_on)
+            .bind(trip.returned_on)
+            .bind(&trip.port_of_entry)
+            .bind(&trip.admission_class)
+            .bind(&trip.i94_number)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some(trip))
+    }
+
+    async fn compliance_tasks(&self, customer_id: Uuid) -> Result<Vec<ComplianceTask>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {TASK_COLUMNS} FROM {schema}.compliance_tasks
+            WHERE customer_id = $1 ORDER BY completed_at IS NOT NULL, due_on, created_at");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).bind(customer_id).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn open_compliance_tasks(&self) -> Result<Vec<ComplianceTask>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {TASK_COLUMNS} FROM {schema}.compliance_tasks
+            WHERE completed_at IS NULL
+                AND customer_id IN (SELECT customer_id FROM {schema}.h1bcustomer WHERE deleted_at IS NULL)
+            ORDER BY due_on, created_at");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn complete_compliance_task(&self, task_id: Uuid, completed_by: &str) -> Result<Option<ComplianceTask>, StoreError> {
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+        let sql = format!("SELECT {TASK_COLUMNS} FROM {schema}.compliance_tasks WHERE task_id = $1 FOR UPDATE");
+        let task: Option<ComplianceTask> = sqlx::query_as(&sql).bind(task_id).fetch_optional(&mut *tx).await?;
+        match &task {
+            None => return Ok(None),
+            Some(task) if task.completed_at.is_some() => return Err(task_already_completed(task)),
+            Some(_) => {}
+        }
+
+        let sql = format!("UPDATE {schema}.compliance_tasks SET completed_at = now(), completed_by = $2
+            WHERE task_id = $1
+            RETURNING {TASK_COLUMNS}");
+        let task = sqlx::query_as(&sql).bind(task_id).bind(completed_by).fetch_one(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(Some(task))
+    }
+
+    async fn merge(&self, customer_id: Uuid, duplicate_id: Uuid, merged_by: &str, reason: Option<&str>) -> Result<MergeOutcome, StoreError> {
+        if customer_id == duplicate_id {
+            return Err(merge_into_self(customer_id));
+        }
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+
+        // Lock both rows in a fixed order so two opposite merges can't deadlock.
+        let sql = format!("SELECT customer_id, deleted_at IS NOT NULL, merged_into FROM {schema}.h1bcustomer
+            WHERE customer_id IN ($1, $2) ORDER BY customer_id FOR UPDATE");
+        let rows: Vec<(Uuid, bool, Option<Uuid>)> =
+            sqlx::query_as(&sql).bind(customer_id).bind(duplicate_id).fetch_all(&mut *tx).await?;
+        let survivor = rows.iter().find(|(id, _, _)| *id == customer_id);
+        let duplicate = rows.iter().find(|(id, _, _)| *id == duplicate_id);
+        let merged_into = match (survivor, duplicate) {
+            (Some((_, false, _)), Some((_, _, merged_into))) => *merged_into,
+            _ => return Ok(MergeOutcome::NotFound),
+        };
+        if let Some(into) = merged_into {
+            return Err(already_merged(duplicate_id, into));
+        }
+
+        let mut moved = MergeMoved::default();
+        let sql = format!("UPDATE {schema}.h1b_status_transitions SET customer_id = $1, merged_from = COALESCE(merged_from, $2)
+            WHERE customer_id = $2");
+        moved.transitions = sqlx::query(&sql).bind(customer_id).bind(duplicate_id).execute(&mut *tx).await?.rows_affected();
+        for (table, count) in [
+            ("h1b_petitions", &mut moved.petitions),
+            ("h1b_trips", &mut moved.trips),
+            ("compliance_tasks", &mut moved.compliance_tasks),
+            ("case_status_history", &mut moved.case_status_entries),
+            ("lca_documents", &mut moved.lca_documents),
+            ("public_access_files", &mut moved.public_access_files),
+        ] {
+            let sql = format!("UPDATE {schema}.{table} SET customer_id = $1 WHERE customer_id = $2");
+            *count = sqlx::query(&sql).bind(customer_id).bind(duplicate_id).execute(&mut *tx).await?.rows_affected();
+        }
+
+        let sql = format!("UPDATE {schema}.h1bcustomer SET deleted_at = COALESCE(deleted_at, now()),
+                deleted_by = $3, delete_reason = $4, merged_into = $1
+            WHERE customer_id = $2");
+        sqlx::query(&sql)
+            .bind(customer_id)
+            .bind(duplicate_id)
+            .bind(merged_by)
+            .bind(merge_reason(customer_id, reason))
+            .execute(&mut *tx)
+            .await?;
+
+        let sql = format!("SELECT {CUSTOMER_COLUMNS} FROM {schema}.h1bcustomer WHERE customer_id = $1");
+        let customer: CreateCustomer = sqlx::query_as(&sql).bind(customer_id).fetch_one(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(MergeOutcome::Merged { customer: Box::new(customer), moved })
+    }
+
+    async fn dashboard(&self, today: NaiveDate) -> Result<Dashboard, StoreError> {
+        let schema = &self.schema;
+        let mut tx = self.begin().await?;
+
+        let sql = format!("SELECT count(*), count(*) FILTER (WHERE created_at IS NULL)
+            FROM {schema}.h1bcustomer WHERE deleted_at IS NULL");
+        let (total_customers, created_unknown): (i64, i64) = sqlx::query_as(&sql).fetch_one(&mut *tx).await?;
+
+        let sql = format!("SELECT h1b_status::text, count(*) FROM {schema}.h1bcustomer
+            WHERE deleted_at IS NULL GROUP BY 1");
+        let by_status: HashMap<String, i64> = sqlx::query_as(&sql).fetch_all(&mut *tx).await?.into_iter().collect();
+
+        let sql = format!("SELECT w, count(c.customer_id) FROM unnest($2::int[]) AS w
+            LEFT JOIN {schema}.h1bcustomer c ON c.deleted_at IS NULL AND c.h1b_status::text = ANY($3)
+                AND c.h1b_end_date BETWEEN $1 AND $1 + w
+            GROUP BY w ORDER BY w");
+        let expiring: Vec<(i32, i64)> = sqlx::query_as(&sql)
+            .bind(today)
+            .bind(dashboard::EXPIRY_WINDOWS.to_vec())
+            .bind(dashboard::approved_statuses())
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let since = dashboard::month_starts(today)[0].and_time(chrono::NaiveTime::MIN).and_utc();
+        let sql = format!("SELECT to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM'), count(*) FROM {schema}.h1bcustomer
+            WHERE deleted_at IS NULL AND created_at >= $1 GROUP BY 1");
+        let new_per_month: HashMap<String, i64> = sqlx::query_as(&sql).bind(since).fetch_all(&mut *tx).await?.into_iter().collect();
+
+        let sql = format!("SELECT min(btrim(client_name)), count(*) FROM {schema}.h1bcustomer
+            WHERE deleted_at IS NULL GROUP BY lower(btrim(client_name)) ORDER BY 2 DESC, 1 LIMIT $1");
+        let top_clients: Vec<(String, i64)> = sqlx::query_as(&sql).bind(dashboard::TOP_CLIENTS).fetch_all(&mut *tx).await?;
+
+        let sql = format!("SELECT lca_code, count(*), min(lca_salary),
+                round(percentile_cont(0.25) WITHIN GROUP (ORDER BY lca_salary)::numeric, 2),
+                round(percentile_cont(0.5) WITHIN GROUP (ORDER BY lca_salary)::numeric, 2),
+                round(percentile_cont(0.75) WITHIN GROUP (ORDER BY lca_salary)::numeric, 2),
+                max(lca_salary), round(avg(lca_salary), 2)
+            FROM {schema}.h1bcustomer WHERE deleted_at IS NULL GROUP BY lca_code ORDER BY 2 DESC, 1");
+        let salaries: Vec<SalaryRow> = sqlx::query_as(&sql).fetch_all(&mut *tx).await?;
+
+        let sql = format!("SELECT client_state, count(*) FROM {schema}.h1bcustomer
+            WHERE deleted_at IS NULL GROUP BY 1 ORDER BY 2 DESC, 1");
+        let by_work_state: Vec<(String, i64)> = sqlx::query_as(&sql).fetch_all(&mut *tx).await?;
+
+        let sql = format!("SELECT count(*), count(*) FILTER (WHERE due_on < $1) FROM {schema}.compliance_tasks
+            WHERE completed_at IS NULL
+                AND customer_id IN (SELECT customer_id FROM {schema}.h1bcustomer WHERE deleted_at IS NULL)");
+        let (open_compliance_tasks, overdue_compliance_tasks): (i64, i64) =
+            sqlx::query_as(&sql).bind(today).fetch_one(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(Dashboard {
+            as_of: today,
+            total_customers,
+            by_status: dashboard::fill_statuses(&by_status),
+            expiring: expiring.into_iter().map(|(within_days, customers)| ExpiringCount { within_days, customers }).collect(),
+            new_per_month: dashboard::fill_months(today, &new_per_month),
+            created_unknown,
+            top_clients: top_clients.into_iter().map(|(client_name, customers)| ClientCount { client_name, customers }).collect(),
+            salary_by_lca_code: salaries
+                .into_iter()
+                .map(|(lca_code, customers, min, p25, median, p75, max, average)| SalaryDistribution {
+                    lca_code, title: None, customers, min, p25, median, p75, max, average,
+                })
+                .collect(),
+            by_work_state: by_work_state.into_iter().map(|(state, customers)| StateCount { state, customers }).collect(),
+            open_compliance_tasks,
+            overdue_compliance_tasks,
+        })
+    }
+}
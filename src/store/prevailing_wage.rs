@@ -0,0 +1,327 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::{postgres, StoreError};
+use crate::models::PrevailingWageImport;
+
+/// One `ALC_Export.csv` row. Wages are hourly.
+#[derive(Debug, Clone, FromRow)]
+pub struct PrevailingWage {
+    pub area: String,
+    pub soc_code: String,
+    pub geo_level: Option<String>,
+    pub level_1: Option<Decimal>,
+    pub level_2: Option<Decimal>,
+    pub level_3: Option<Decimal>,
+    pub level_4: Option<Decimal>,
+    pub average: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WageArea {
+    pub area: String,
+    pub area_name: String,
+    pub state: Option<String>,
+}
+
+/// A complete set of wage files, as read by `prevailing_wage::import`.
+#[derive(Debug, Clone)]
+pub struct PrevailingWageData {
+    /// Where the files came from, kept with the import.
+    pub source: String,
+    pub wages: Vec<PrevailingWage>,
+    pub areas: Vec<WageArea>,
+    /// `(zip, area)` pairs.
+    pub zips: Vec<(String, String)>,
+}
+
+/// What the wage data says about a worksite ZIP and SOC code.
+#[derive(Debug, Clone, Default)]
+pub struct WageLookup {
+    /// `None` if the ZIP isn't in the crosswalk.
+    pub area: Option<String>,
+    pub area_name: Option<String>,
+    /// `None` if OFLC has no wage for the SOC code in the area.
+    pub wage: Option<PrevailingWage>,
+}
+
+/// OFLC prevailing wage tables. Imports replace the previous data entirely.
+#[async_trait]
+pub trait PrevailingWageStore: Send + Sync {
+    async fn replace(&self, data: &PrevailingWageData, imported_by: &str) -> Result<PrevailingWageImport, StoreError>;
+
+    /// The import currently in use, `None` before the first one.
+    async fn latest_import(&self) -> Result<Option<PrevailingWageImport>, StoreError>;
+
+    /// Looks up a five-digit ZIP and a normalized SOC code (see `prevailing_wage::soc_key`).
+    async fn lookup(&self, zip: &str, soc_code: &str) -> Result<WageLookup, StoreError>;
+
+    /// `lookup` for many `(zip, soc_code)` pairs in one read. Every pair asked
+    /// for is in the result, with an empty `WageLookup` for unknown ZIPs.
+    async fn lookup_many(&self, keys: &[(String, String)]) -> Result<HashMap<(String, String), WageLookup>, StoreError>;
+}
+
+/// Rows per `INSERT ... SELECT FROM unnest(...)`; ALC_Export has a few hundred thousand.
+const INSERT_BATCH: usize = 5000;
+
+const IMPORT_COLUMNS: &str = "import_id, source, wages, areas, zips, imported_by, imported_at";
+
+pub struct PgPrevailingWageStore {
+    pool: PgPool,
+    schema: String,
+    statement_timeout: Option<Duration>,
+}
+
+impl PgPrevailingWageStore {
+    pub fn new(pool: PgPool, schema: impl Into<String>) -> Self {
+        Self { pool, schema: schema.into(), statement_timeout: None }
+    }
+
+    /// Cancels any statement running longer than `timeout` (`None` disables the limit).
+    pub fn with_statement_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.statement_timeout = timeout;
+        self
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, StoreError> {
+        postgres::begin(&self.pool, self.statement_timeout).await
+    }
+}
+
+#[derive(FromRow)]
+struct LookupRow {
+    area: String,
+    area_name: Option<String>,
+    #[sqlx(flatten)]
+    wage: LookupWage,
+}
+
+/// A `LookupRow` with the pair it answers, for `lookup_many`.
+#[derive(FromRow)]
+struct KeyedLookupRow {
+    key_zip: String,
+    key_soc_code: String,
+    #[sqlx(flatten)]
+    lookup: LookupRow,
+}
+
+impl From<LookupRow> for WageLookup {
+    fn from(row: LookupRow) -> Self {
+        let w = row.wage;
+        let wage = w.soc_code.map(|soc_code| PrevailingWage {
+            area: row.area.clone(),
+            soc_code,
+            geo_level: w.geo_level,
+            level_1: w.level_1,
+            level_2: w.level_2,
+            level_3: w.level_3,
+            level_4: w.level_4,
+            average: w.average,
+        });
+        WageLookup { area: Some(row.area), area_name: row.area_name, wage }
+    }
+}
+
+/// The `prevailing_wages` half of the lookup join; all NULL when there's no match.
+#[derive(FromRow)]
+struct LookupWage {
+    soc_code: Option<String>,
+    geo_level: Option<String>,
+    level_1: Option<Decimal>,
+    level_2: Option<Decimal>,
+    level_3: Option<Decimal>,
+    level_4: Option<Decimal>,
+    average: Option<Decimal>,
+}
+
+#[async_trait]
+impl PrevailingWageStore for PgPrevailingWageStore {
+    async fn replace(&self, data: &PrevailingWageData, imported_by: &str) -> Result<PrevailingWageImport, StoreError> {
+        let schema = &self.schema;
+        // No statement timeout here: a full import takes longer than any request should.
+        let mut tx = self.pool.begin().await?;
+
+        // Readers keep seeing the old data until this commits.
+        let sql = format!("TRUNCATE {schema}.prevailing_wages, {schema}.wage_areas, {schema}.wage_area_zips");
+        sqlx::query(&sql).execute(&mut *tx).await?;
+
+        let sql = format!("INSERT INTO {schema}.prevailing_wages (
+                area, soc_code, geo_level, level_1, level_2, level_3, level_4, average
+            ) SELECT * FROM unnest($1::text[], $2::text[], $3::text[], $4::numeric[], $5::numeric[], $6::numeric[], $7::numeric[], $8::numeric[])
+            ON CONFLICT (area, soc_code) DO NOTHING");
+        for batch in data.wages.chunks(INSERT_BATCH) {
+            sqlx::query(&sql)
+                .bind(batch.iter().map(|w| w.area.clone()).collect::<Vec<_>>())
+                .bind(batch.iter().map(|w| w.soc_code.clone()).collect::<Vec<_>>())
+                .bind(batch.iter().map(|w| w.geo_level.clone()).collect::<Vec<_>>())
+                .bind(batch.iter().map(|w| w.level_1).collect::<Vec<_>>())
+                .bind(batch.iter().map(|w| w.level_2).collect::<Vec<_>>())
+                .bind(batch.iter().map(|w| w.level_3).collect::<Vec<_>>())
+                .bind(batch.iter().map(|w| w.level_4).collect::<Vec<_>>())
+                .bind(batch.iter().map(|w| w.average).collect::<Vec<_>>())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let sql = format!("INSERT INTO {schema}.wage_areas (area, area_name, state)
+            SELECT * FROM unnest($1::text[], $2::text[], $3::text[])
+            ON CONFLICT (area) DO NOTHING");
+        for batch in data.areas.chunks(INSERT_BATCH) {
+            sqlx::query(&sql)
+                .bind(batch.iter().map(|a| a.area.clone()).collect::<Vec<_>>())
+                .bind(batch.iter().map(|a| a.area_name.clone()).collect::<Vec<_>>())
+                .bind(batch.iter().map(|a| a.state.clone()).collect::<Vec<_>>())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let sql = format!("INSERT INTO {schema}.wage_area_zips (zip, area)
+            SELECT * FROM unnest($1::text[], $2::text[])
+            ON CONFLICT (zip) DO NOTHING");
+        for batch in data.zips.chunks(INSERT_BATCH) {
+            sqlx::query(&sql)
+                .bind(batch.iter().map(|(zip, _)| zip.clone()).collect::<Vec<_>>())
+                .bind(batch.iter().map(|(_, area)| area.clone()).collect::<Vec<_>>())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let sql = format!("INSERT INTO {schema}.prevailing_wage_imports (source, wages, areas, zips, imported_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING {IMPORT_COLUMNS}");
+        let import: PrevailingWageImport = sqlx::query_as(&sql)
+            .bind(&data.source)
+            .bind(data.wages.len() as i32)
+            .bind(data.areas.len() as i32)
+            .bind(data.zips.len() as i32)
+            .bind(imported_by)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(import)
+    }
+
+    async fn latest_import(&self) -> Result<Option<PrevailingWageImport>, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT {IMPORT_COLUMNS} FROM {schema}.prevailing_wage_imports
+            ORDER BY imported_at DESC LIMIT 1");
+        let mut tx = self.begin().await?;
+        let result = sqlx::query_as(&sql).fetch_optional(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn lookup(&self, zip: &str, soc_code: &str) -> Result<WageLookup, StoreError> {
+        let schema = &self.schema;
+        let sql = format!("SELECT z.area, a.area_name,
+                w.soc_code, w.geo_level, w.level_1, w.level_2, w.level_3, w.level_4, w.average
+            FROM {schema}.wage_area_zips z
+            LEFT JOIN {schema}.wage_areas a ON a.area = z.area
+            LEFT JOIN {schema}.prevailing_wages w ON w.area = z.area AND w.soc_code = $2
+            WHERE z.zip = $1");
+        let mut tx = self.begin().await?;
+        let row: Option<LookupRow> = sqlx::query_as(&sql).bind(zip).bind(soc_code).fetch_optional(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(row.map(WageLookup::from).unwrap_or_default())
+    }
+
+    async fn lookup_many(&self, keys: &[(String, String)]) -> Result<HashMap<(String, String), WageLookup>, StoreError> {
+        let schema = &self.schema;
+        let (zips, soc_codes): (Vec<&str>, Vec<&str>) = keys.iter().map(|(zip, soc)| (zip.as_str(), soc.as_str())).unzip();
+        let sql = format!("SELECT k.zip AS key_zip, k.soc_code AS key_soc_code, z.area, a.area_name,
+                w.soc_code, w.geo_level, w.level_1, w.level_2, w.level_3, w.level_4, w.average
+            FROM unnest($1::text[], $2::text[]) AS k (zip, soc_code)
+            JOIN {schema}.wage_area_zips z ON z.zip = k.zip
+            LEFT JOIN {schema}.wage_areas a ON a.area = z.area
+            LEFT JOIN {schema}.prevailing_wages w ON w.area = z.area AND w.soc_code = k.soc_code");
+        let mut tx = self.begin().await?;
+        let rows: Vec<KeyedLookupRow> = sqlx::query_as(&sql).bind(&zips).bind(&soc_codes).fetch_all(&mut *tx).await?;
+        tx.commit().await?;
+
+        let mut result: HashMap<(String, String), WageLookup> = keys.iter().map(|key| (key.clone(), WageLookup::default())).collect();
+        for row in rows {
+            result.insert((row.key_zip, row.key_soc_code), row.lookup.into());
+        }
+        Ok(result)
+    }
+}
+
+/// In-memory `PrevailingWageStore` for the memory storage backend.
+#[derive(Default)]
+pub struct MemoryPrevailingWageStore {
+    data: RwLock<MemoryWageData>,
+}
+
+#[derive(Default)]
+struct MemoryWageData {
+    wages: HashMap<(String, String), PrevailingWage>,
+    area_names: HashMap<String, String>,
+    zips: HashMap<String, String>,
+    latest_import: Option<PrevailingWageImport>,
+}
+
+impl MemoryPrevailingWageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MemoryWageData {
+    fn lookup(&self, zip: &str, soc_code: &str) -> WageLookup {
+        let Some(area) = self.zips.get(zip) else {
+            return WageLookup::default();
+        };
+        WageLookup {
+            area: Some(area.clone()),
+            area_name: self.area_names.get(area).cloned(),
+            wage: self.wages.get(&(area.clone(), soc_code.to_string())).cloned(),
+        }
+    }
+}
+
+#[async_trait]
+impl PrevailingWageStore for MemoryPrevailingWageStore {
+    async fn replace(&self, data: &PrevailingWageData, imported_by: &str) -> Result<PrevailingWageImport, StoreError> {
+        let import = PrevailingWageImport {
+            import_id: Uuid::new_v4(),
+            source: data.source.clone(),
+            wages: data.wages.len() as i32,
+            areas: data.areas.len() as i32,
+            zips: data.zips.len() as i32,
+            imported_by: imported_by.to_string(),
+            imported_at: Utc::now(),
+        };
+        // First row wins on duplicates, like ON CONFLICT DO NOTHING.
+        let mut replacement = MemoryWageData { latest_import: Some(import.clone()), ..Default::default() };
+        for wage in &data.wages {
+            replacement.wages.entry((wage.area.clone(), wage.soc_code.clone())).or_insert_with(|| wage.clone());
+        }
+        for area in &data.areas {
+            replacement.area_names.entry(area.area.clone()).or_insert_with(|| area.area_name.clone());
+        }
+        for (zip, area) in &data.zips {
+            replacement.zips.entry(zip.clone()).or_insert_with(|| area.clone());
+        }
+        *self.data.write().unwrap() = replacement;
+        Ok(import)
+    }
+
+    async fn latest_import(&self) -> Result<Option<PrevailingWageImport>, StoreError> {
+        Ok(self.data.read().unwrap().latest_import.clone())
+    }
+
+    async fn lookup(&self, zip: &str, soc_code: &str) -> Result<WageLookup, StoreError> {
+        Ok(self.data.read().unwrap().lookup(zip, soc_code))
+    }
+
+    async fn lookup_many(&self, keys: &[(String, String)]) -> Result<HashMap<(String, String), WageLookup>, StoreError> {
+        let data = self.data.read().unwrap();
+        Ok(keys.iter().map(|(zip, soc_code)| ((zip.clone(), soc_code.clone()), data.lookup(zip, soc_code))).collect())
+    }
+}
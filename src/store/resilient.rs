@@ -0,0 +1,465 @@
+use async_trait::async_trait;
+use rand::Rng;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::*;
+use super::idempotency::{IdempotencyClaim, StoredResponse};
+use crate::config::settings::{CircuitBreakerConfig, RetryConfig};
+
+/// How long callers are told to wait when a failure isn't covered by an open breaker.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Retries for transient failures and a circuit breaker, shared by every store.
+///
+/// Idempotent operations retry on any transient failure; the others only when
+/// the failed attempt certainly had no effect; see `FailureKind`.
+/// Once retries are exhausted the caller gets `StoreError::Unavailable`.
+pub struct Resilience {
+    retry: RetryConfig,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl Resilience {
+    pub fn new(retry: RetryConfig, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { retry, breaker }
+    }
+
+    pub async fn call<T, F, Fut>(&self, operation: &str, idempotent: bool, f: F) -> Result<T, StoreError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, StoreError>>,
+    {
+        let admission = self.breaker.check().map_err(|retry_after| StoreError::Unavailable { retry_after })?;
+
+        let mut attempt = 1;
+        loop {
+            let e = match f().await {
+                Ok(value) => {
+                    admission.succeeded();
+                    return Ok(value);
+                }
+                Err(e) => e,
+            };
+
+            let kind = e.kind();
+            if kind == FailureKind::Permanent {
+                // The database answered; it's the request that was wrong.
+                admission.succeeded();
+                return Err(e);
+            }
+
+            let retryable = kind == FailureKind::RetryAny || (idempotent && kind == FailureKind::RetryIdempotent);
+            if retryable && attempt < self.retry.max_attempts {
+                let delay = self.backoff(attempt);
+                eprintln!("⚠️  {} attempt {} failed, retrying in {:?}: {}", operation, attempt, delay, e);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            eprintln!("❌ {} failed after {} attempt(s): {}", operation, attempt, e);
+            admission.failed();
+            let retry_after = self.breaker.retry_after().unwrap_or(DEFAULT_RETRY_AFTER);
+            return Err(StoreError::Unavailable { retry_after });
+        }
+    }
+
+    /// Full jitter: a random delay up to `base * 2^(attempt - 1)`, capped at `max_delay_ms`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.retry.base_delay_ms.saturating_mul(1 << (attempt - 1).min(16));
+        let ceiling = exponential.min(self.retry.max_delay_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling))
+    }
+}
+
+/// Wraps a store with a shared `Resilience`. Implements each store trait,
+/// deciding per operation whether it is safe to replay.
+pub struct ResilientStore<S: ?Sized = dyn CustomerStore> {
+    inner: Arc<S>,
+    resilience: Arc<Resilience>,
+}
+
+impl<S: ?Sized> ResilientStore<S> {
+    pub fn new(inner: Arc<S>, resilience: Arc<Resilience>) -> Self {
+        Self { inner, resilience }
+    }
+
+    async fn call<T, F, Fut>(&self, operation: &str, idempotent: bool, f: F) -> Result<T, StoreError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, StoreError>>,
+    {
+        self.resilience.call(operation, idempotent, f).await
+    }
+}
+
+// Reads, `update` and `upsert_by_email` (which set absolute values) are
+// idempotent; `create`, `soft_delete`, `restore` and `transition` are not.
+#[async_trait]
+impl CustomerStore for ResilientStore<dyn CustomerStore> {
+    async fn create(&self, customer: &CreateCompleteCustomerRequest) -> Result<CreateCustomer, StoreError> {
+        self.call("create", false, || self.inner.create(customer)).await
+    }
+
+    async fn upsert_by_email(&self, customer: &CreateCompleteCustomerRequest) -> Result<UpsertOutcome, StoreError> {
+        // Replaying the same upsert leaves the same row behind, so it is safe to retry.
+        self.call("upsert_by_email", true, || self.inner.upsert_by_email(customer)).await
+    }
+
+    async fn get_by_id(&self, customer_id: Uuid, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError> {
+        self.call("get_by_id", true, || self.inner.get_by_id(customer_id, deleted)).await
+    }
+
+    async fn get_by_receipt(&self, receipt_number: &str, deleted: Deleted) -> Result<Option<CreateCustomer>, StoreError> {
+        self.call("get_by_receipt", true, || self.inner.get_by_receipt(receipt_number, deleted)).await
+    }
+
+    async fn find_by_email(&self, email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        self.call("find_by_email", true, || self.inner.find_by_email(email, deleted)).await
+    }
+
+    async fn find_by_login_email(&self, login_email: &str, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        self.call("find_by_login_email", true, || self.inner.find_by_login_email(login_email, deleted)).await
+    }
+
+    async fn list(&self, filter: CustomerFilter, deleted: Deleted) -> Result<Vec<CreateCustomer>, StoreError> {
+        self.call("list", true, || self.inner.list(filter, deleted)).await
+    }
+
+    async fn update(&self, customer_id: Uuid, update: &UpdateVisaDetailsRequest, task: Option<&NewComplianceTask>) -> Result<bool, StoreError> {
+        // A replay could record the compliance task twice.
+        self.call("update", task.is_none(), || self.inner.update(customer_id, update, task)).await
+    }
+
+    async fn soft_delete(&self, customer_id: Uuid, deleted_by: &str, reason: Option<&str>) -> Result<SoftDeleteOutcome, StoreError> {
+        self.call("soft_delete", false, || self.inner.soft_delete(customer_id, deleted_by, reason)).await
+    }
+
+    async fn restore(&self, customer_id: Uuid) -> Result<RestoreOutcome, StoreError> {
+        self.call("restore", false, || self.inner.restore(customer_id)).await
+    }
+
+    async fn transition(&self, customer_id: Uuid, request: &TransitionRequest, transitioned_by: &str) -> Result<TransitionOutcome, StoreError> {
+        self.call("transition", false, || self.inner.transition(customer_id, request, transitioned_by)).await
+    }
+
+    async fn transitions(&self, customer_id: Uuid) -> Result<Vec<StatusTransition>, StoreError> {
+        self.call("transitions", true, || self.inner.transitions(customer_id)).await
+    }
+
+    async fn petitions(&self, customer_id: Uuid) -> Result<Vec<Petition>, StoreError> {
+        self.call("petitions", true, || self.inner.petitions(customer_id)).await
+    }
+
+    async fn petitions_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Petition>, StoreError> {
+        self.call("petitions_for", true, || self.inner.petitions_for(customer_ids)).await
+    }
+
+    async fn add_petition(&self, customer_id: Uuid, petition: &PetitionRequest, recorded_by: &str) -> Result<Option<Petition>, StoreError> {
+        self.call("add_petition", false, || self.inner.add_petition(customer_id, petition, recorded_by)).await
+    }
+
+    async fn trips(&self, customer_id: Uuid) -> Result<Vec<Trip>, StoreError> {
+        self.call("trips", true, || self.inner.trips(customer_id)).await
+    }
+
+    async fn trips_for(&self, customer_ids: &[Uuid]) -> Result<Vec<Trip>, StoreError> {
+        self.call("trips_for", true, || self.inner.trips_for(customer_ids)).await
+    }
+
+    async fn add_trip(&self, customer_id: Uuid, trip: &TripRequest, recorded_by: &str) -> Result<Option<Trip>, StoreError> {
+        self.call("add_trip", false, || self.inner.add_trip(customer_id, trip, recorded_by)).await
+    }
+
+    async fn update_trip(&self, customer_id: Uuid, trip_id: Uuid, trip: &TripRequest) -> Result<Option<Trip>, StoreError> {
+        self.call("update_trip", true, || self.inner.update_trip(customer_id, trip_id, trip)).await
+    }
+
+    async fn compliance_tasks(&self, customer_id: Uuid) -> Result<Vec<ComplianceTask>, StoreError> {
+        self.call("compliance_tasks", true, || self.inner.compliance_tasks(customer_id)).await
+    }
+
+    async fn open_compliance_tasks(&self) -> Result<Vec<ComplianceTask>, StoreError> {
+        self.call("open_compliance_tasks", true, || self.inner.open_compliance_tasks()).await
+    }
+
+    async fn complete_compliance_task(&self, task_id: Uuid, completed_by: &str) -> Result<Option<ComplianceTask>, StoreError> {
+        // A replay after success would fail with "already completed".
+        self.call("complete_compliance_task", false, || self.inner.complete_compliance_task(task_id, completed_by)).await
+    }
+
+    async fn merge(&self, customer_id: Uuid, duplicate_id: Uuid, merged_by: &str, reason: Option<&str>) -> Result<MergeOutcome, StoreError> {
+        // A replay after a successful merge would fail with "already merged".
+        self.call("merge", false, || self.inner.merge(customer_id, duplicate_id, merged_by, reason)).await
+    }
+
+    async fn dashboard(&self, today: NaiveDate) -> Result<Dashboard, StoreError> {
+        self.call("dashboard", true, || self.inner.dashboard(today)).await
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for ResilientStore<dyn IdempotencyStore> {
+    async fn claim(&self, principal: &str, key: &str, request_hash: &str) -> Result<IdempotencyClaim, StoreError> {
+        // A replay after a successful claim would find the key in progress.
+        self.call("claim", false, || self.inner.claim(principal, key, request_hash)).await
+    }
+
+    async fn complete(&self, principal: &str, key: &str, response: &StoredResponse) -> Result<(), StoreError> {
+        self.call("complete", true, || self.inner.complete(principal, key, response)).await
+    }
+
+    async fn release(&self, principal: &str, key: &str) -> Result<(), StoreError> {
+        self.call("release", true, || self.inner.release(principal, key)).await
+    }
+
+    async fn purge_expired(&self) -> Result<u64, StoreError> {
+        self.call("purge_expired", true, || self.inner.purge_expired()).await
+    }
+}
+
+#[async_trait]
+impl CaseStatusStore for ResilientStore<dyn CaseStatusStore> {
+    async fn record(&self, observation: &CaseStatusObservation) -> Result<Option<CaseStatusEntry>, StoreError> {
+        // A replay after success would report the new status as unchanged.
+        self.call("record", false, || self.inner.record(observation)).await
+    }
+
+    async fn history(&self, receipt_number: &str) -> Result<Vec<CaseStatusEntry>, StoreError> {
+        self.call("history", true, || self.inner.history(receipt_number)).await
+    }
+
+    async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<CaseStatusEntry>, StoreError> {
+        self.call("changes_since", true, || self.inner.changes_since(since)).await
+    }
+}
+
+#[async_trait]
+impl PrevailingWageStore for ResilientStore<dyn PrevailingWageStore> {
+    async fn replace(&self, data: &PrevailingWageData, imported_by: &str) -> Result<PrevailingWageImport, StoreError> {
+        self.call("replace", false, || self.inner.replace(data, imported_by)).await
+    }
+
+    async fn latest_import(&self) -> Result<Option<PrevailingWageImport>, StoreError> {
+        self.call("latest_import", true, || self.inner.latest_import()).await
+    }
+
+    async fn lookup(&self, zip: &str, soc_code: &str) -> Result<WageLookup, StoreError> {
+        self.call("lookup", true, || self.inner.lookup(zip, soc_code)).await
+    }
+
+    async fn lookup_many(&self, keys: &[(String, String)]) -> Result<HashMap<(String, String), WageLookup>, StoreError> {
+        self.call("lookup_many", true, || self.inner.lookup_many(keys)).await
+    }
+}
+
+#[async_trait]
+impl LcaDocumentStore for ResilientStore<dyn LcaDocumentStore> {
+    async fn add(&self, customer_id: Uuid, document: &NewLcaDocument) -> Result<LcaDocument, StoreError> {
+        self.call("add", false, || self.inner.add(customer_id, document)).await
+    }
+
+    async fn list(&self, customer_id: Uuid) -> Result<Vec<LcaDocument>, StoreError> {
+        self.call("list", true, || self.inner.list(customer_id)).await
+    }
+
+    async fn get(&self, customer_id: Uuid, document_id: Uuid) -> Result<Option<(LcaDocument, Vec<u8>)>, StoreError> {
+        self.call("get", true, || self.inner.get(customer_id, document_id)).await
+    }
+
+    async fn contents(&self, customer_id: Uuid) -> Result<Vec<(LcaDocument, Vec<u8>)>, StoreError> {
+        self.call("contents", true, || self.inner.contents(customer_id)).await
+    }
+
+    async fn delete(&self, customer_id: Uuid, document_id: Uuid) -> Result<bool, StoreError> {
+        // A replay after success would report the document as missing.
+        self.call("delete", false, || self.inner.delete(customer_id, document_id)).await
+    }
+
+    async fn record_generation(&self, file: &PublicAccessFile) -> Result<(), StoreError> {
+        self.call("record_generation", false, || self.inner.record_generation(file)).await
+    }
+
+    async fn generations(&self, customer_id: Uuid) -> Result<Vec<PublicAccessFile>, StoreError> {
+        self.call("generations", true, || self.inner.generations(customer_id)).await
+    }
+}
+
+/// Consecutive-failure circuit breaker shared by the store and the HTTP layer.
+///
+/// Closed: everything goes through. After `failure_threshold` failed operations
+/// in a row it opens and rejects calls for `open_secs`. Then it lets a single
+/// trial call through (half-open): success closes it, failure reopens it.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_for: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            failure_threshold: config.failure_threshold,
+            open_for: Duration::from_secs(config.open_secs),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Admits a call, or returns how long the caller should wait.
+    pub fn check(&self) -> Result<Admission<'_>, Duration> {
+        let mut state = self.state.lock().unwrap();
+        let Some(open_until) = state.open_until else {
+            return Ok(Admission { breaker: self, trial: false, reported: false });
+        };
+        let now = Instant::now();
+        if now < open_until {
+            return Err(open_until - now);
+        }
+        if state.trial_in_flight {
+            return Err(DEFAULT_RETRY_AFTER);
+        }
+        state.trial_in_flight = true;
+        Ok(Admission { breaker: self, trial: true, reported: false })
+    }
+
+    /// Time left before the breaker admits another call, if it is open.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        let open_until = state.open_until?;
+        let now = Instant::now();
+        if now < open_until {
+            Some(open_until - now)
+        } else if state.trial_in_flight {
+            Some(DEFAULT_RETRY_AFTER)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().open_until.is_some()
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.open_until.is_some() {
+            println!("✅ Database recovered, circuit breaker closed");
+        }
+        *state = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.trial_in_flight || state.consecutive_failures >= self.failure_threshold {
+            eprintln!("❌ Circuit breaker open for {:?} after {} consecutive failures", self.open_for, state.consecutive_failures);
+            state.open_until = Some(Instant::now() + self.open_for);
+            state.trial_in_flight = false;
+        }
+    }
+}
+
+/// A call let through by `CircuitBreaker::check`, to be reported with
+/// `succeeded` or `failed`.
+///
+/// Dropping the half-open trial unreported, e.g. because the client
+/// disconnected and its request future was cancelled, counts as a failure:
+/// otherwise the breaker would wait forever for a trial that never reports.
+#[must_use]
+pub struct Admission<'a> {
+    breaker: &'a CircuitBreaker,
+    trial: bool,
+    reported: bool,
+}
+
+impl Admission<'_> {
+    pub fn succeeded(mut self) {
+        self.reported = true;
+        self.breaker.record_success();
+    }
+
+    pub fn failed(mut self) {
+        self.reported = true;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if self.trial && !self.reported {
+            eprintln!("⚠️  Circuit breaker trial call was cancelled before it finished");
+            self.breaker.record_failure();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryCustomerStore;
+
+    fn store(failure_threshold: u32, open_secs: u64) -> ResilientStore {
+        let retry = RetryConfig { max_attempts: 1, base_delay_ms: 0, max_delay_ms: 0 };
+        let breaker = Arc::new(CircuitBreaker::new(&CircuitBreakerConfig { failure_threshold, open_secs }));
+        let inner: Arc<dyn CustomerStore> = Arc::new(MemoryCustomerStore::new());
+        ResilientStore::new(inner, Arc::new(Resilience::new(retry, breaker)))
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures_and_closes_on_success() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerConfig { failure_threshold: 2, open_secs: 60 });
+        breaker.check().unwrap().failed();
+        assert!(breaker.check().is_ok());
+        breaker.check().unwrap().failed();
+        assert!(breaker.is_open());
+        assert!(breaker.check().is_err());
+        assert!(breaker.retry_after().unwrap() > Duration::from_secs(50));
+    }
+
+    #[test]
+    fn half_open_admits_one_trial_at_a_time() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerConfig { failure_threshold: 1, open_secs: 0 });
+        breaker.check().unwrap().failed();
+        let trial = breaker.check().unwrap();
+        assert_eq!(breaker.check().err(), Some(DEFAULT_RETRY_AFTER));
+        trial.succeeded();
+        assert!(!breaker.is_open());
+        assert!(breaker.retry_after().is_none());
+    }
+
+    #[tokio::test]
+    async fn cancelled_trial_does_not_wedge_the_breaker() {
+        let store = store(1, 0);
+        store.resilience.breaker.check().unwrap().failed();
+        assert!(store.resilience.breaker.is_open());
+
+        // The trial never finishes; the timeout drops its future.
+        let trial = store.call("trial", true, std::future::pending::<Result<(), StoreError>>);
+        assert!(tokio::time::timeout(Duration::from_millis(10), trial).await.is_err());
+
+        // Reopened for `open_secs` (zero here) rather than stuck half-open.
+        assert!(store.resilience.breaker.retry_after().is_none());
+        store.call("next", true, || async { Ok(()) }).await.unwrap();
+        assert!(!store.resilience.breaker.is_open());
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_do_not_count_as_failures() {
+        let store = store(1, 60);
+        let result = store.call("bad input", true, || async { Err::<(), _>(StoreError::Invalid("nope".to_string())) }).await;
+        assert!(matches!(result, Err(StoreError::Invalid(_))));
+        assert!(!store.resilience.breaker.is_open());
+    }
+}
@@ -0,0 +1,457 @@
+//! The printable case summary of a customer: a PDF of the customer record,
+//! LCA, petition history, upcoming deadlines and documents on file, laid
+//! out from the typed models with `pdf::layout`.
+
+use chrono::{DateTime, Months, NaiveDate, Utc};
+
+use crate::lifecycle::H1bStatus;
+use crate::models::{
+    CaseStatusEntry, ComplianceTask, CreateCustomer, LcaDocument, LcaWageCheck, LcaWageStatus, MaxStay, Petition,
+    PublicAccessFile, StatusTransition,
+};
+use crate::pdf::{layout, usd, Block, Decoration, PdfBuilder};
+use crate::public_access::DocumentCategory;
+
+/// Longest watermark accepted, in characters.
+pub const MAX_WATERMARK_LENGTH: usize = 40;
+
+/// Everything the summary shows, loaded by the caller.
+pub struct CaseSummary {
+    pub customer: CreateCustomer,
+    /// Title of `lca_code` in the SOC catalog, if it is there.
+    pub soc_title: Option<String>,
+    pub wage_check: LcaWageCheck,
+    /// Earlier petitions, oldest first.
+    pub petitions: Vec<Petition>,
+    pub transitions: Vec<StatusTransition>,
+    /// Latest USCIS status of `receipt_number`, if it is tracked.
+    pub case_status: Option<CaseStatusEntry>,
+    pub max_stay: MaxStay,
+    pub compliance_tasks: Vec<ComplianceTask>,
+    pub documents: Vec<LcaDocument>,
+    /// Newest first.
+    pub public_access_files: Vec<PublicAccessFile>,
+    pub generated_by: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// A dated item in the deadlines section.
+struct Deadline {
+    date: NaiveDate,
+    what: String,
+}
+
+pub fn render(summary: &CaseSummary, watermark: Option<&str>) -> Result<Vec<u8>, String> {
+    let customer = &summary.customer;
+    let generated = format!("{} by {}", summary.generated_at.format("%Y-%m-%d %H:%M UTC"), summary.generated_by);
+    let decoration = Decoration {
+        footer: Some(format!("Case summary of {} {}, generated {}", customer.first_name, customer.last_name, generated)),
+        watermark: watermark.map(str::trim).filter(|w| !w.is_empty()).map(str::to_string),
+    };
+
+    let mut builder = PdfBuilder::new();
+    builder.add_decorated_text_pages(&layout(&blocks(summary, &generated)), &decoration);
+    let title = format!("Case summary: {} {}", customer.first_name, customer.last_name);
+    builder.finish(&title, summary.generated_at)
+}
+
+fn blocks(summary: &CaseSummary, generated: &str) -> Vec<Block> {
+    let c = &summary.customer;
+    let field = |label: &str, value: String| Block::Field(label.to_string(), value);
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "not recorded".to_string());
+    let date = |value: Option<NaiveDate>| value.map_or_else(|| "not recorded".to_string(), |d| d.to_string());
+
+    let mut blocks = vec![
+        Block::Title(format!("Case Summary: {} {}", c.first_name, c.last_name)),
+        Block::Note(format!("Customer {}. Generated {}.", c.customer_id, generated)),
+    ];
+    if let Some(deleted_at) = c.deleted_at {
+        let mut deleted = format!("This customer was deleted on {}", deleted_at.format("%Y-%m-%d"));
+        if let Some(by) = &c.deleted_by {
+            deleted.push_str(&format!(" by {}", by));
+        }
+        if let Some(into) = c.merged_into {
+            deleted.push_str(&format!(", merged into {}", into));
+        }
+        if let Some(reason) = &c.delete_reason {
+            deleted.push_str(&format!(": {}", reason));
+        }
+        blocks.push(Block::Paragraph(deleted));
+    }
+
+    blocks.extend([
+        Block::Heading("Personal details".to_string()),
+        field("Name", format!("{} {}", c.first_name, c.last_name)),
+        field("Date of birth", c.dob.to_string()),
+        field("Sex", c.sex.clone()),
+        field("Marital status", c.marital_status.clone()),
+        field("Email", c.email.clone()),
+        field("Login email", c.login_email.clone()),
+        field("Phone", c.phone_display.clone().unwrap_or_else(|| c.phone.clone())),
+        field(
+            "Emergency contact",
+            format!(
+                "{}, {}",
+                c.emergency_contact_name,
+                c.emergency_contact_phone_display.clone().unwrap_or_else(|| c.emergency_contact_phone.clone())
+            ),
+        ),
+        Block::Heading("Home address".to_string()),
+        field("Address", format!("{}, {}, {} {}", c.street_name, c.city, c.state, c.zip)),
+        field("County", optional(&c.county)),
+        field("MSA", optional(&c.msa)),
+        Block::Heading("Client worksite".to_string()),
+        field("Client", c.client_name.clone()),
+        field("Address", format!("{}, {}, {} {}", c.client_street_name, c.client_city, c.client_state, c.client_zip)),
+        field("County", optional(&c.client_county)),
+        field("MSA", optional(&c.client_msa)),
+        Block::Heading("LCA".to_string()),
+        field("Job title", c.lca_title.clone()),
+        field(
+            "SOC code",
+            match &summary.soc_title {
+                Some(title) => format!("{} ({})", c.lca_code, title),
+                None => c.lca_code.clone(),
+            },
+        ),
+        field("Wage level", optional(&c.lca_wage_level)),
+        field("Salary", format!("{} per year", usd(c.lca_salary))),
+        field("Prevailing wage", prevailing_wage(&summary.wage_check)),
+        Block::Heading("Current petition".to_string()),
+        field("Receipt number", optional(&c.receipt_number)),
+        field("H-1B status", c.h1b_status.clone()),
+        field("Validity", format!("{} to {}", c.h1b_start_date, c.h1b_end_date)),
+        field("Employment start", c.employment_start_date.to_string()),
+    ]);
+    if let Some(entry) = &summary.case_status {
+        blocks.push(field("USCIS case status", format!("{} (as of {})", entry.status, entry.last_checked_at.format("%Y-%m-%d"))));
+    }
+    blocks.extend([
+        field("Labor certification filed", date(c.labor_certification_filed_on)),
+        field("I-140 approved", date(c.i140_approved_on)),
+    ]);
+
+    blocks.push(Block::Heading("Petition history".to_string()));
+    if summary.petitions.is_empty() {
+        blocks.push(Block::Paragraph("No earlier petitions recorded.".to_string()));
+    }
+    for p in &summary.petitions {
+        let receipt = p.receipt_number.clone().unwrap_or_else(|| "no receipt number".to_string());
+        let employer = p.employer.clone().unwrap_or_else(|| "employer not recorded".to_string());
+        blocks.push(field(&format!("{} to {}", p.valid_from, p.valid_to), format!("{}, {}", receipt, employer)));
+    }
+    let stay = &summary.max_stay;
+    blocks.push(field(
+        "Six-year limit",
+        format!("{} of {} days used, {} remaining ({} recaptured from trips abroad)", stay.days_used, stay.limit_days, stay.days_remaining, stay.recapturable_days),
+    ));
+
+    blocks.push(Block::Heading("Status history".to_string()));
+    if summary.transitions.is_empty() {
+        blocks.push(Block::Paragraph("No status changes recorded.".to_string()));
+    }
+    for t in &summary.transitions {
+        let mut change = format!("{} to {}", t.from_status, t.to_status);
+        if let Some(merged_from) = t.merged_from {
+            change.push_str(&format!(" on merged customer {}", merged_from));
+        }
+        blocks.push(field(&t.effective_date.to_string(), format!("{}: {} ({})", change, t.reason, t.transitioned_by)));
+    }
+
+    blocks.push(Block::Heading("Upcoming deadlines".to_string()));
+    let deadlines = deadlines(summary);
+    if deadlines.is_empty() {
+        blocks.push(Block::Paragraph("None.".to_string()));
+    }
+    let today = summary.generated_at.date_naive();
+    for d in deadlines {
+        let label = if d.date < today { format!("{} (overdue)", d.date) } else { d.date.to_string() };
+        blocks.push(field(&label, d.what));
+    }
+
+    blocks.push(Block::Heading("Documents on file".to_string()));
+    if summary.documents.is_empty() {
+        blocks.push(Block::Paragraph("No LCA public access file documents uploaded.".to_string()));
+    }
+    for d in &summary.documents {
+        let category = d.category.parse::<DocumentCategory>().map_or(d.category.as_str(), |c| c.title());
+        let pages = if d.page_count == 1 { "1 page".to_string() } else { format!("{} pages", d.page_count) };
+        blocks.push(field(category, format!("{}, {}, uploaded {} by {}", d.filename, pages, d.uploaded_at.format("%Y-%m-%d"), d.uploaded_by)));
+    }
+    match summary.public_access_files.first() {
+        Some(file) => {
+            let mut generated = format!("last generated {} by {}", file.generated_at.format("%Y-%m-%d"), file.generated_by);
+            if !file.missing.is_empty() {
+                generated.push_str(&format!(", missing {}", file.missing.join(", ")));
+            }
+            blocks.push(field("Public access file", generated));
+        }
+        None => blocks.push(field("Public access file", "never generated".to_string())),
+    }
+    blocks
+}
+
+fn prevailing_wage(check: &LcaWageCheck) -> String {
+    match (check.status, check.required_wage) {
+        (LcaWageStatus::Compliant, Some(wage)) => format!("{} at Level {}: met", usd(wage), check.required_level),
+        (LcaWageStatus::Underpaid, Some(wage)) => format!(
+            "{} at Level {}: NOT met, {} short",
+            usd(wage),
+            check.required_level,
+            check.shortfall.map_or_else(String::new, usd)
+        ),
+        _ => format!("unknown ({})", check.detail.as_deref().unwrap_or("no wage data")),
+    }
+}
+
+/// Deadlines from the generation date on, soonest first, plus open
+/// compliance tasks that are already overdue.
+fn deadlines(summary: &CaseSummary) -> Vec<Deadline> {
+    let c = &summary.customer;
+    let today = summary.generated_at.date_naive();
+    let mut deadlines = Vec::new();
+
+    let status = c.h1b_status.parse::<H1bStatus>().ok();
+    let in_status = matches!(status, Some(H1bStatus::Approved | H1bStatus::Active));
+    if in_status {
+        if let Some(from) = c.h1b_end_date.checked_sub_months(Months::new(6)) {
+            deadlines.push(Deadline { date: from, what: "Extension can be filed from (six months before validity ends)".to_string() });
+        }
+    }
+    deadlines.push(Deadline { date: c.h1b_end_date, what: "H-1B validity ends".to_string() });
+
+    let ac21 = &summary.max_stay.ac21;
+    let mut max_out = "Six-year limit reached".to_string();
+    if ac21.three_year_extensions {
+        max_out.push_str("; AC21 three-year extensions available (I-140 approved)");
+    } else if ac21.one_year_extensions {
+        max_out.push_str("; AC21 one-year extensions available");
+    }
+    deadlines.push(Deadline { date: summary.max_stay.projected_max_out_date, what: max_out });
+    if let Some(from) = ac21.one_year_extensions_from.filter(|_| !ac21.one_year_extensions) {
+        deadlines.push(Deadline { date: from, what: "AC21 one-year extensions become available".to_string() });
+    }
+
+    deadlines.retain(|d| d.date >= today);
+    for task in summary.compliance_tasks.iter().filter(|t| t.completed_at.is_none()) {
+        deadlines.push(Deadline { date: task.due_on, what: format!("{}: {}", task.kind, task.reason) });
+    }
+    deadlines.sort_by_key(|d| d.date);
+    deadlines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Trip;
+    use crate::store::{PrevailingWage, WageLookup};
+    use crate::testing::{customer, date, petition, trip};
+    use rust_decimal::Decimal;
+    use uuid::Uuid;
+
+    const GENERATED: &str = "2025-01-01 12:00 UTC by ops";
+
+    const WITH_HISTORY: &str = "\
+# Case Summary: Ann Lee
+(Customer 00000000-0000-0000-0000-000000000001. Generated 2025-01-01 12:00 UTC by ops.)
+## Personal details
+Name: Ann Lee
+Date of birth: 1990-04-12
+Sex: FEMALE
+Marital status: SINGLE
+Email: ann.lee@example.com
+Login email: ann.lee@example.com
+Phone: (212) 555-0100
+Emergency contact: Bo Lee, (212) 555-0101
+## Home address
+Address: 1 Penn Plz, New York, NY 10001
+County: New York County
+MSA: 35620
+## Client worksite
+Client: Acme
+Address: 350 5th Ave, New York, NY 10001
+County: New York County
+MSA: 35620
+## LCA
+Job title: Software Developer
+SOC code: 15-1252 (Software Developers)
+Wage level: II
+Salary: $150,000.00 per year
+Prevailing wage: $124,800.00 at Level II: met
+## Current petition
+Receipt number: EAC2312345678
+H-1B status: Active
+Validity: 2023-10-01 to 2026-09-30
+Employment start: 2023-10-01
+Labor certification filed: not recorded
+I-140 approved: not recorded
+## Petition history
+2020-10-01 to 2023-09-30: WAC2012345678, Old Co
+Six-year limit: 1535 of 2190 days used, 655 remaining (19 recaptured from trips abroad)
+## Status history
+2023-10-01: Approved to Active: Started work (ops)
+## Upcoming deadlines
+2024-12-20 (overdue): lca_posting: Post the LCA notice at the new worksite
+2026-03-30: Extension can be filed from (six months before validity ends)
+2026-09-30: H-1B validity ends
+2026-10-18: Six-year limit reached
+## Documents on file
+No LCA public access file documents uploaded.
+Public access file: never generated";
+
+    const EMPTY: &str = "\
+# Case Summary: Ann Lee
+(Customer 00000000-0000-0000-0000-000000000001. Generated 2025-01-01 12:00 UTC by ops.)
+## Personal details
+Name: Ann Lee
+Date of birth: 1990-04-12
+Sex: FEMALE
+Marital status: SINGLE
+Email: ann.lee@example.com
+Login email: ann.lee@example.com
+Phone: +12125550100
+Emergency contact: Bo Lee, +12125550101
+## Home address
+Address: 1 Penn Plz, New York, NY 10001
+County: not recorded
+MSA: not recorded
+## Client worksite
+Client: Acme
+Address: 350 5th Ave, New York, NY 10001
+County: not recorded
+MSA: not recorded
+## LCA
+Job title: Software Developer
+SOC code: 15-1252
+Wage level: not recorded
+Salary: $150,000.00 per year
+Prevailing wage: unknown (no OFLC wage for SOC 15-1252 in area 35620)
+## Current petition
+Receipt number: not recorded
+H-1B status: Draft
+Validity: 2023-10-01 to 2026-09-30
+Employment start: 2023-10-01
+Labor certification filed: not recorded
+I-140 approved: not recorded
+## Petition history
+No earlier petitions recorded.
+Six-year limit: 0 of 2190 days used, 2190 remaining (0 recaptured from trips abroad)
+## Status history
+No status changes recorded.
+## Upcoming deadlines
+2026-09-30: H-1B validity ends
+2030-12-31: Six-year limit reached
+## Documents on file
+No LCA public access file documents uploaded.
+Public access file: never generated";
+
+    fn generated_at() -> DateTime<Utc> {
+        "2025-01-01T12:00:00Z".parse().unwrap()
+    }
+
+    /// The blocks as plain text, one line each, for comparing whole summaries.
+    fn text(blocks: &[Block]) -> String {
+        let lines: Vec<String> = blocks
+            .iter()
+            .map(|block| match block {
+                Block::Title(text) => format!("# {}", text),
+                Block::Heading(text) => format!("## {}", text),
+                Block::Paragraph(text) => text.clone(),
+                Block::Note(text) => format!("({})", text),
+                Block::Field(label, value) => format!("{}: {}", label, value),
+                Block::Entry(text, value) => format!("{} ... {}", text, value),
+                Block::Space => String::new(),
+                Block::PageBreak => "---".to_string(),
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    fn summary(customer: CreateCustomer, petitions: Vec<Petition>, trips: &[Trip], wage: Option<PrevailingWage>) -> CaseSummary {
+        let today = generated_at().date_naive();
+        let lookup = WageLookup { area: Some("35620".to_string()), area_name: Some("New York-Newark-Jersey City".to_string()), wage };
+        CaseSummary {
+            wage_check: crate::prevailing_wage::check(&customer, Some(&lookup)),
+            max_stay: crate::max_stay::calculate(&customer, &petitions, trips, today),
+            soc_title: None,
+            petitions,
+            transitions: Vec::new(),
+            case_status: None,
+            compliance_tasks: Vec::new(),
+            documents: Vec::new(),
+            public_access_files: Vec::new(),
+            generated_by: "ops".to_string(),
+            generated_at: generated_at(),
+            customer,
+        }
+    }
+
+    #[test]
+    fn customer_with_history() {
+        let c = CreateCustomer { customer_id: Uuid::from_u128(1), ..customer() };
+        let id = c.customer_id;
+        let earlier = Petition {
+            receipt_number: Some("WAC2012345678".to_string()),
+            employer: Some("Old Co".to_string()),
+            ..petition(id, "2020-10-01", "2023-09-30")
+        };
+        let wage = PrevailingWage {
+            area: "35620".to_string(),
+            soc_code: "15-1252".to_string(),
+            geo_level: None,
+            level_1: Some(Decimal::new(5000, 2)),
+            level_2: Some(Decimal::new(6000, 2)),
+            level_3: Some(Decimal::new(7000, 2)),
+            level_4: Some(Decimal::new(8000, 2)),
+            average: None,
+        };
+        let mut s = summary(c, vec![earlier], &[trip(id, "2024-03-01", Some("2024-03-21"))], Some(wage));
+        s.soc_title = Some("Software Developers".to_string());
+        s.transitions.push(StatusTransition {
+            transition_id: Uuid::from_u128(2),
+            customer_id: id,
+            from_status: "Approved".to_string(),
+            to_status: "Active".to_string(),
+            reason: "Started work".to_string(),
+            effective_date: date("2023-10-01"),
+            transitioned_by: "ops".to_string(),
+            created_at: generated_at(),
+            merged_from: None,
+        });
+        s.compliance_tasks.push(ComplianceTask {
+            task_id: Uuid::from_u128(3),
+            customer_id: id,
+            kind: "lca_posting".to_string(),
+            reason: "Post the LCA notice at the new worksite".to_string(),
+            due_on: date("2024-12-20"),
+            created_by: "ops".to_string(),
+            created_at: generated_at(),
+            completed_at: None,
+            completed_by: None,
+        });
+
+        assert_eq!(text(&blocks(&s, GENERATED)), WITH_HISTORY);
+        let pdf = render(&s, Some("DRAFT")).unwrap();
+        assert!(crate::pdf::pdf_page_count(&pdf).unwrap() >= 1);
+    }
+
+    #[test]
+    fn empty_customer() {
+        let c = CreateCustomer {
+            customer_id: Uuid::from_u128(1),
+            h1b_status: "Draft".to_string(),
+            receipt_number: None,
+            county: None,
+            msa: None,
+            client_county: None,
+            client_msa: None,
+            lca_wage_level: None,
+            phone_display: None,
+            emergency_contact_phone_display: None,
+            ..customer()
+        };
+        let s = summary(c, Vec::new(), &[], None);
+        assert_eq!(text(&blocks(&s, GENERATED)), EMPTY);
+        render(&s, None).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+//! Fixtures shared by the unit tests.
+
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{CreateCustomer, Petition, Trip};
+
+pub fn date(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+}
+
+/// An active H-1B customer in New York, valid 2023-10-01 to 2026-09-30.
+pub fn customer() -> CreateCustomer {
+    CreateCustomer {
+        customer_id: Uuid::new_v4(),
+        email: "ann.lee@example.com".to_string(),
+        first_name: "Ann".to_string(),
+        last_name: "Lee".to_string(),
+        dob: date("1990-04-12"),
+        sex: "FEMALE".to_string(),
+        marital_status: "SINGLE".to_string(),
+        phone: "+12125550100".to_string(),
+        emergency_contact_name: "Bo Lee".to_string(),
+        emergency_contact_phone: "+12125550101".to_string(),
+        phone_display: Some("(212) 555-0100".to_string()),
+        emergency_contact_phone_display: Some("(212) 555-0101".to_string()),
+        employment_start_date: date("2023-10-01"),
+        street_name: "1 Penn Plz".to_string(),
+        city: "New York".to_string(),
+        state: "NY".to_string(),
+        zip: "10001".to_string(),
+        client_name: "Acme".to_string(),
+        client_street_name: "350 5th Ave".to_string(),
+        client_city: "New York".to_string(),
+        client_state: "NY".to_string(),
+        client_zip: "10001".to_string(),
+        county: Some("New York County".to_string()),
+        msa: Some("35620".to_string()),
+        client_county: Some("New York County".to_string()),
+        client_msa: Some("35620".to_string()),
+        lca_title: "Software Developer".to_string(),
+        lca_salary: Decimal::new(15_000_000, 2),
+        lca_code: "15-1252".to_string(),
+        lca_wage_level: Some("II".to_string()),
+        receipt_number: Some("EAC2312345678".to_string()),
+        h1b_start_date: date("2023-10-01"),
+        h1b_end_date: date("2026-09-30"),
+        login_email: "ann.lee@example.com".to_string(),
+        h1b_status: "Active".to_string(),
+        labor_certification_filed_on: None,
+        i140_approved_on: None,
+        created_at: None,
+        deleted_at: None,
+        deleted_by: None,
+        delete_reason: None,
+        merged_into: None,
+    }
+}
+
+pub fn petition(customer_id: Uuid, valid_from: &str, valid_to: &str) -> Petition {
+    Petition {
+        petition_id: Uuid::new_v4(),
+        customer_id,
+        receipt_number: None,
+        employer: None,
+        valid_from: date(valid_from),
+        valid_to: date(valid_to),
+        recorded_by: "test".to_string(),
+        created_at: Utc::now(),
+    }
+}
+
+pub fn trip(customer_id: Uuid, departed_on: &str, returned_on: Option<&str>) -> Trip {
+    Trip {
+        trip_id: Uuid::new_v4(),
+        customer_id,
+        departed_on: date(departed_on),
+        returned_on: returned_on.map(date),
+        port_of_entry: None,
+        admission_class: None,
+        i94_number: None,
+        recorded_by: "test".to_string(),
+        created_at: Utc::now(),
+    }
+}
@@ -0,0 +1,359 @@
+//! Travel history: trips outside the US, checked against each other and the
+//! customer's H-1B validity, and read from the I-94 travel history CBP lets a
+//! traveller download.
+//!
+//! The history lists one arrival or departure per row with `Date`, `Type` and
+//! `Location` columns; `Class of Admission` and `I-94 Number` are read too when
+//! present. Rows are paired in date order: a departure and the next arrival
+//! make a trip, and a final departure is a trip the customer hasn't come back
+//! from yet.
+
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::models::{SkippedTrip, Trip, TripRequest, ValidityPeriod};
+
+const I94_NUMBER_LENGTH: usize = 11;
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%m/%d/%Y"];
+
+/// Trims the free text fields, dropping empty ones, and normalizes the
+/// admission class and I-94 number.
+pub fn normalize(trip: &TripRequest) -> Result<TripRequest, String> {
+    if trip.returned_on.is_some_and(|returned| returned < trip.departed_on) {
+        return Err("returned_on is before departed_on".to_string());
+    }
+    let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    let compact = |value: &str| -> String {
+        value.chars().filter(|c| !c.is_whitespace() && *c != '-').flat_map(char::to_uppercase).collect()
+    };
+
+    let port_of_entry = text(&trip.port_of_entry);
+    let admission_class = text(&trip.admission_class).map(|class| compact(&class));
+    let i94_number = match text(&trip.i94_number) {
+        Some(raw) => {
+            let number = compact(&raw);
+            if number.len() != I94_NUMBER_LENGTH || !number.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(format!("invalid I-94 number \"{}\": expected {} letters and digits", raw, I94_NUMBER_LENGTH));
+            }
+            Some(number)
+        }
+        None => None,
+    };
+    if trip.returned_on.is_none() && (port_of_entry.is_some() || admission_class.is_some() || i94_number.is_some()) {
+        return Err("port_of_entry, admission_class and i94_number describe the return and need returned_on".to_string());
+    }
+
+    Ok(TripRequest { departed_on: trip.departed_on, returned_on: trip.returned_on, port_of_entry, admission_class, i94_number })
+}
+
+/// Why `trip` can't be recorded next to the customer's other `trips`, if it
+/// can't: it overlaps one, or repeats its I-94 number. `replacing` is left out
+/// of the comparison. Returning and leaving again on the same day is fine.
+pub fn conflict(trips: &[Trip], trip: &TripRequest, replacing: Option<Uuid>) -> Option<String> {
+    let others = trips.iter().filter(|other| Some(other.trip_id) != replacing);
+    for other in others {
+        let overlaps = trip.departed_on < other.returned_on.unwrap_or(NaiveDate::MAX)
+            && other.departed_on < trip.returned_on.unwrap_or(NaiveDate::MAX);
+        if overlaps {
+            return Some(format!("trip overlaps trip {} ({})", other.trip_id, dates(other.departed_on, other.returned_on)));
+        }
+        if trip.i94_number.is_some() && trip.i94_number == other.i94_number {
+            return Some(format!("I-94 number is already recorded on trip {}", other.trip_id));
+        }
+    }
+    None
+}
+
+/// A trip counts for recapture only while the customer holds H-1B status, so
+/// one that doesn't touch any validity period from departure to return is
+/// most likely a typo or belongs to a different visa.
+pub fn outside_validity(periods: &[ValidityPeriod], trip: &TripRequest) -> bool {
+    let last = trip.returned_on.unwrap_or(trip.departed_on);
+    !periods.iter().any(|p| p.from <= last && trip.departed_on <= p.to)
+}
+
+pub fn outside_validity_reason(trip: &TripRequest) -> String {
+    format!("trip ({}) is outside the customer's H-1B validity", dates(trip.departed_on, trip.returned_on))
+}
+
+fn dates(departed_on: NaiveDate, returned_on: Option<NaiveDate>) -> String {
+    match returned_on {
+        Some(returned_on) => format!("{} to {}", departed_on, returned_on),
+        None => format!("from {}, not yet returned", departed_on),
+    }
+}
+
+/// Trips read from an I-94 travel history, each with the CSV line of its
+/// departure, and the rows that didn't make one.
+#[derive(Debug, Default)]
+pub struct I94History {
+    pub trips: Vec<(u64, TripRequest)>,
+    pub skipped: Vec<SkippedTrip>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Arrival,
+    Departure,
+}
+
+#[derive(Clone)]
+struct Event {
+    line: u64,
+    date: NaiveDate,
+    direction: Direction,
+    location: String,
+    admission_class: String,
+    i94_number: String,
+}
+
+/// Reads a travel history CSV. A missing column or unreadable CSV is an
+/// error; rows with a bad date or type are skipped with the reason.
+pub fn parse_i94_history(csv: &str) -> Result<I94History, String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(csv.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| h.trim_start_matches('\u{feff}').trim().to_ascii_lowercase())
+        .collect();
+    let column = |names: &[&str]| names.iter().find_map(|name| headers.iter().position(|h| h == name));
+    let date = column(&["date"]).ok_or("missing column Date")?;
+    let direction = column(&["type", "event"]).ok_or("missing column Type")?;
+    let location = column(&["location", "port of entry"]);
+    let admission_class = column(&["class of admission", "admission class", "class"]);
+    let i94_number = column(&["i-94 number", "i94 number", "admission (i-94) record number", "admission number"]);
+
+    let mut history = I94History::default();
+    let mut events = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default().trim().to_string();
+        let skip = |reason: String| SkippedTrip { line, departed_on: None, returned_on: None, reason };
+
+        let raw_date = field(Some(date));
+        let Some(date) = DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(&raw_date, format).ok()) else {
+            history.skipped.push(skip(format!("`{}` is not a date", raw_date)));
+            continue;
+        };
+        let raw_direction = field(Some(direction));
+        let lowercase = raw_direction.to_ascii_lowercase();
+        let direction = if lowercase.starts_with("arr") {
+            Direction::Arrival
+        } else if lowercase.starts_with("dep") {
+            Direction::Departure
+        } else {
+            history.skipped.push(skip(format!("`{}` is neither an arrival nor a departure", raw_direction)));
+            continue;
+        };
+        events.push(Event {
+            line,
+            date,
+            direction,
+            location: field(location),
+            admission_class: field(admission_class),
+            i94_number: field(i94_number),
+        });
+    }
+
+    // CBP lists the newest first. On a day with both, an arrival closes a
+    // trip still open and a departure starts a day trip otherwise.
+    events.sort_by_key(|event| event.date);
+    let mut departure: Option<Event> = None;
+    let mut i = 0;
+    while i < events.len() {
+        let day = events[i].date;
+        let end = events[i..].iter().position(|event| event.date != day).map_or(events.len(), |n| i + n);
+        let (mut arrivals, mut departures): (Vec<usize>, Vec<usize>) =
+            (i..end).partition(|&n| events[n].direction == Direction::Arrival);
+        let order = if departure.is_some() {
+            arrivals.append(&mut departures);
+            arrivals
+        } else {
+            departures.append(&mut arrivals);
+            departures
+        };
+        for n in order {
+            pair(&mut history, &mut departure, &events[n]);
+        }
+        i = end;
+    }
+    if let Some(open) = departure {
+        history.trips.push((open.line, trip(&open, None)));
+    }
+    Ok(history)
+}
+
+fn pair(history: &mut I94History, departure: &mut Option<Event>, event: &Event) {
+    match event.direction {
+        Direction::Departure => {
+            if let Some(earlier) = departure.replace(event.clone()) {
+                history.skipped.push(SkippedTrip {
+                    line: earlier.line,
+                    departed_on: Some(earlier.date),
+                    returned_on: None,
+                    reason: "no arrival recorded before the next departure".to_string(),
+                });
+            }
+        }
+        Direction::Arrival => match departure.take() {
+            Some(departed) => history.trips.push((departed.line, trip(&departed, Some(event)))),
+            None => history.skipped.push(SkippedTrip {
+                line: event.line,
+                departed_on: None,
+                returned_on: Some(event.date),
+                reason: "no departure recorded before this arrival".to_string(),
+            }),
+        },
+    }
+}
+
+fn trip(departed: &Event, arrival: Option<&Event>) -> TripRequest {
+    let value = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    TripRequest {
+        departed_on: departed.date,
+        returned_on: arrival.map(|a| a.date),
+        port_of_entry: arrival.and_then(|a| value(&a.location)),
+        admission_class: arrival.and_then(|a| value(&a.admission_class)),
+        i94_number: arrival.and_then(|a| value(&a.i94_number)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{date, trip as recorded};
+
+    fn request(departed_on: &str, returned_on: Option<&str>) -> TripRequest {
+        TripRequest {
+            departed_on: date(departed_on),
+            returned_on: returned_on.map(date),
+            port_of_entry: None,
+            admission_class: None,
+            i94_number: None,
+        }
+    }
+
+    #[test]
+    fn normalize_cleans_the_return_details() {
+        let mut trip = request("2024-03-01", Some("2024-03-11"));
+        trip.port_of_entry = Some(" SFO ".to_string());
+        trip.admission_class = Some("h-1b".to_string());
+        trip.i94_number = Some("1234 5678 90a".to_string());
+        let trip = normalize(&trip).unwrap();
+        assert_eq!(trip.port_of_entry.as_deref(), Some("SFO"));
+        assert_eq!(trip.admission_class.as_deref(), Some("H1B"));
+        assert_eq!(trip.i94_number.as_deref(), Some("1234567890A"));
+
+        let mut blank = request("2024-03-01", None);
+        blank.port_of_entry = Some("  ".to_string());
+        assert!(normalize(&blank).unwrap().port_of_entry.is_none());
+    }
+
+    #[test]
+    fn normalize_rejects_impossible_trips() {
+        assert!(normalize(&request("2024-03-11", Some("2024-03-01"))).is_err());
+
+        let mut short = request("2024-03-01", Some("2024-03-11"));
+        short.i94_number = Some("12345".to_string());
+        assert!(normalize(&short).unwrap_err().contains("invalid I-94 number"));
+
+        let mut open = request("2024-03-01", None);
+        open.port_of_entry = Some("SFO".to_string());
+        assert!(normalize(&open).unwrap_err().contains("need returned_on"));
+    }
+
+    #[test]
+    fn overlapping_trips_conflict() {
+        let customer_id = uuid::Uuid::new_v4();
+        let trips = [recorded(customer_id, "2024-03-01", Some("2024-03-11"))];
+        assert!(conflict(&trips, &request("2024-03-10", Some("2024-03-20")), None).is_some());
+        assert!(conflict(&trips, &request("2024-02-01", None), None).is_some());
+        // Back on the 11th and off again the same day.
+        assert!(conflict(&trips, &request("2024-03-11", Some("2024-03-15")), None).is_none());
+        assert!(conflict(&trips, &request("2024-02-20", Some("2024-03-01")), None).is_none());
+        // Editing a trip doesn't conflict with its old dates.
+        assert!(conflict(&trips, &request("2024-03-02", Some("2024-03-12")), Some(trips[0].trip_id)).is_none());
+    }
+
+    #[test]
+    fn open_trip_blocks_everything_after_it() {
+        let customer_id = uuid::Uuid::new_v4();
+        let trips = [recorded(customer_id, "2024-03-01", None)];
+        assert!(conflict(&trips, &request("2025-01-01", Some("2025-01-05")), None).is_some());
+        assert!(conflict(&trips, &request("2024-01-01", Some("2024-02-01")), None).is_none());
+    }
+
+    #[test]
+    fn repeated_i94_number_conflicts() {
+        let customer_id = uuid::Uuid::new_v4();
+        let mut earlier = recorded(customer_id, "2023-03-01", Some("2023-03-11"));
+        earlier.i94_number = Some("1234567890A".to_string());
+        let mut trip = request("2024-03-01", Some("2024-03-11"));
+        trip.i94_number = Some("1234567890A".to_string());
+        assert!(conflict(&[earlier], &trip, None).unwrap().contains("I-94 number"));
+    }
+
+    #[test]
+    fn trips_outside_every_validity_period() {
+        let periods = [ValidityPeriod { from: date("2023-10-01"), to: date("2026-09-30"), receipt_numbers: Vec::new() }];
+        assert!(!outside_validity(&periods, &request("2024-03-01", Some("2024-03-11"))));
+        assert!(!outside_validity(&periods, &request("2023-09-20", Some("2023-10-01"))));
+        assert!(outside_validity(&periods, &request("2023-01-01", Some("2023-02-01"))));
+        assert!(outside_validity(&[], &request("2024-03-01", None)));
+    }
+
+    #[test]
+    fn pairs_cbp_history_newest_first() {
+        let csv = "\u{feff}Date,Type,Location,Class of Admission,I-94 Number\n\
+            2024-09-01,Departure,JFK,,\n\
+            2024-03-11,Arrival,SFO,H1B,1234567890A\n\
+            03/01/2024,Departure,SFO,,\n";
+        let history = parse_i94_history(csv).unwrap();
+        assert!(history.skipped.is_empty());
+        assert_eq!(history.trips.len(), 2);
+
+        let (line, returned) = &history.trips[0];
+        assert_eq!(*line, 4);
+        assert_eq!((returned.departed_on, returned.returned_on), (date("2024-03-01"), Some(date("2024-03-11"))));
+        assert_eq!(returned.port_of_entry.as_deref(), Some("SFO"));
+        assert_eq!(returned.admission_class.as_deref(), Some("H1B"));
+        assert_eq!(returned.i94_number.as_deref(), Some("1234567890A"));
+
+        let (_, open) = &history.trips[1];
+        assert_eq!((open.departed_on, open.returned_on), (date("2024-09-01"), None));
+    }
+
+    #[test]
+    fn same_day_arrival_closes_the_open_trip_first() {
+        let csv = "Date,Type\n2024-03-01,Departure\n2024-03-11,Departure\n2024-03-11,Arrival\n2024-03-15,Arrival\n";
+        let history = parse_i94_history(csv).unwrap();
+        let dates: Vec<_> = history.trips.iter().map(|(_, t)| (t.departed_on, t.returned_on)).collect();
+        assert_eq!(
+            dates,
+            vec![(date("2024-03-01"), Some(date("2024-03-11"))), (date("2024-03-11"), Some(date("2024-03-15")))]
+        );
+    }
+
+    #[test]
+    fn unpaired_and_unreadable_rows_are_skipped() {
+        let csv = "Date,Type\n2024-01-05,Arrival\nyesterday,Departure\n2024-02-01,Transit\n\
+            2024-03-01,Departure\n2024-04-01,Departure\n2024-04-10,Arrival\n";
+        let history = parse_i94_history(csv).unwrap();
+        assert_eq!(history.trips.len(), 1);
+        let reasons: Vec<&str> = history.skipped.iter().map(|s| s.reason.as_str()).collect();
+        assert_eq!(reasons.len(), 4);
+        assert!(reasons.contains(&"no departure recorded before this arrival"));
+        assert!(reasons.contains(&"no arrival recorded before the next departure"));
+        assert!(reasons.iter().any(|r| r.contains("is not a date")));
+        assert!(reasons.iter().any(|r| r.contains("neither an arrival nor a departure")));
+    }
+
+    #[test]
+    fn missing_columns_are_an_error() {
+        assert_eq!(parse_i94_history("Type,Location\nArrival,SFO\n").unwrap_err(), "missing column Date");
+        assert_eq!(parse_i94_history("Date,Location\n2024-01-01,SFO\n").unwrap_err(), "missing column Type");
+    }
+}
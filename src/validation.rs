@@ -0,0 +1,165 @@
+//! Checks on customer input that need more than the store knows: reference
+//! data such as the SOC catalog and the ZIP dataset, or phone number metadata. Fields are normalized in place; problems that
+//! make the input unusable are errors (400), doubtful ones come back as
+//! warnings alongside the saved customer.
+
+use crate::address::{Address, AddressError, NormalizedAddress, ZipDirectory};
+use crate::models::{CreateCompleteCustomerRequest, CreateCustomer, UpdateVisaDetailsRequest, ValidationWarning};
+use crate::phone::{self, Phone, Region};
+use crate::soc::{self, SocCatalog};
+
+/// County and MSA derived from a ZIP code.
+type CountyAndMsa = (Option<String>, Option<String>);
+
+/// Reference data and policy for the checks.
+pub struct Validator<'a> {
+    pub soc: &'a SocCatalog,
+    pub zips: &'a ZipDirectory,
+    /// Reject SOC codes missing from the catalog instead of warning about them.
+    pub reject_unknown_soc_codes: bool,
+    /// Country of phone numbers entered without a country code.
+    pub default_phone_region: Region,
+}
+
+impl Validator<'_> {
+    pub fn check_new(&self, c: &mut CreateCompleteCustomerRequest) -> Result<Vec<ValidationWarning>, String> {
+        let mut warnings = Vec::new();
+        c.lca_code = self.check_lca_code(&c.lca_code, &c.lca_title, &mut warnings)?;
+
+        let phone = self.check_phone("phone", &c.phone, c.phone_region.as_deref())?;
+        (c.phone, c.phone_display) = (phone.e164, Some(phone.display));
+        let phone = self.check_phone("emergency_contact_phone", &c.emergency_contact_phone, c.emergency_contact_phone_region.as_deref())?;
+        (c.emergency_contact_phone, c.emergency_contact_phone_display) = (phone.e164, Some(phone.display));
+
+        let mut home = Address { street_name: c.street_name.clone(), city: c.city.clone(), state: c.state.clone(), zip: c.zip.clone() };
+        (c.county, c.msa) = self.check_address("", &mut home, &mut warnings)?;
+        (c.street_name, c.city, c.state, c.zip) = (home.street_name, home.city, home.state, home.zip);
+
+        let mut worksite = Address {
+            street_name: c.client_street_name.clone(),
+            city: c.client_city.clone(),
+            state: c.client_state.clone(),
+            zip: c.client_zip.clone(),
+        };
+        (c.client_county, c.client_msa) = self.check_address("client_", &mut worksite, &mut warnings)?;
+        (c.client_street_name, c.client_city, c.client_state, c.client_zip) =
+            (worksite.street_name, worksite.city, worksite.state, worksite.zip);
+        Ok(warnings)
+    }
+
+    /// `current` is the stored customer, if any, for fields the update leaves alone.
+    pub fn check_update(&self, u: &mut UpdateVisaDetailsRequest, current: Option<&CreateCustomer>) -> Result<Vec<ValidationWarning>, String> {
+        let mut warnings = Vec::new();
+        if u.lca_code.is_some() || u.lca_title.is_some() {
+            let code = u.lca_code.as_deref().or(current.map(|c| c.lca_code.as_str()));
+            let title = u.lca_title.as_deref().or(current.map(|c| c.lca_title.as_str()));
+            if let (Some(code), Some(title)) = (code, title) {
+                match self.check_lca_code(code, title, &mut warnings) {
+                    Ok(code) if u.lca_code.is_some() => u.lca_code = Some(code),
+                    Ok(_) => {}
+                    // A stored code from before validation shouldn't block editing the title.
+                    Err(e) if u.lca_code.is_none() => warnings.push(ValidationWarning::new("lca_code", e)),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        if let Some(raw) = &u.phone {
+            let phone = self.check_phone("phone", raw, u.phone_region.as_deref())?;
+            (u.phone, u.phone_display) = (Some(phone.e164), Some(phone.display));
+        }
+        if let Some(raw) = &u.emergency_contact_phone {
+            let phone = self.check_phone("emergency_contact_phone", raw, u.emergency_contact_phone_region.as_deref())?;
+            (u.emergency_contact_phone, u.emergency_contact_phone_display) = (Some(phone.e164), Some(phone.display));
+        }
+
+        // Without the stored customer there's nothing to merge with, and the update will 404.
+        if let Some(current) = current {
+            let home = [&mut u.street_name, &mut u.city, &mut u.state, &mut u.zip];
+            let stored = [&current.street_name, &current.city, &current.state, &current.zip];
+            if let Some((county, msa)) = self.check_address_update("", home, stored, &mut warnings)? {
+                (u.county, u.msa) = (county, msa);
+            }
+            let worksite = [&mut u.client_street_name, &mut u.client_city, &mut u.client_state, &mut u.client_zip];
+            let stored = [&current.client_street_name, &current.client_city, &current.client_state, &current.client_zip];
+            if let Some((county, msa)) = self.check_address_update("client_", worksite, stored, &mut warnings)? {
+                (u.client_county, u.client_msa) = (county, msa);
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Normalizes the address fields an update sets, checked together with the
+    /// stored ones. Returns the derived county and MSA if any field was set.
+    fn check_address_update(
+        &self,
+        prefix: &str,
+        fields: [&mut Option<String>; 4],
+        stored: [&String; 4],
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> Result<Option<CountyAndMsa>, String> {
+        if fields.iter().all(|f| f.is_none()) {
+            return Ok(None);
+        }
+        let merged = |i: usize| fields[i].clone().unwrap_or_else(|| stored[i].clone());
+        let mut address = Address { street_name: merged(0), city: merged(1), state: merged(2), zip: merged(3) };
+
+        // A stored state or ZIP from before normalization shouldn't block editing the others.
+        let derived = match self.zips.normalize(&mut address) {
+            Ok(normalized) => address_warnings(prefix, normalized, warnings),
+            Err(AddressError { field: "state", .. }) if fields[2].is_none() => return Ok(None),
+            Err(AddressError { field: "zip", .. }) if fields[3].is_none() => return Ok(None),
+            Err(e) => return Err(format!("{}{}: {}", prefix, e.field, e.message)),
+        };
+
+        let [street_name, city, state, zip] = fields;
+        for (field, value) in [(street_name, address.street_name), (city, address.city), (state, address.state), (zip, address.zip)] {
+            if field.is_some() {
+                *field = Some(value);
+            }
+        }
+        Ok(Some(derived))
+    }
+
+    /// Normalizes one of the customer's addresses. Field names in messages get
+    /// `prefix` (`client_` for the worksite). Returns the county and MSA.
+    fn check_address(&self, prefix: &str, address: &mut Address, warnings: &mut Vec<ValidationWarning>) -> Result<CountyAndMsa, String> {
+        let normalized = self.zips.normalize(address).map_err(|e| format!("{}{}: {}", prefix, e.field, e.message))?;
+        Ok(address_warnings(prefix, normalized, warnings))
+    }
+
+    /// `region` is the request's `<field>_region`, for a number without a country code.
+    fn check_phone(&self, field: &str, raw: &str, region: Option<&str>) -> Result<Phone, String> {
+        let region = match region {
+            Some(code) => phone::parse_region(code).map_err(|e| format!("{}_region: {}", field, e))?,
+            None => self.default_phone_region,
+        };
+        phone::normalize(raw, region, self.default_phone_region).map_err(|e| format!("{}: {}", field, e))
+    }
+
+    /// Returns the normalized code.
+    fn check_lca_code(&self, code: &str, title: &str, warnings: &mut Vec<ValidationWarning>) -> Result<String, String> {
+        let code = soc::normalize(code)?;
+        match self.soc.get(&code) {
+            None if self.reject_unknown_soc_codes => return Err(format!("SOC code {} is not in the SOC catalog", code)),
+            None => warnings.push(ValidationWarning::new(
+                "lca_code",
+                format!("SOC code {} is not in the SOC catalog ({}); check it against the SOC 2018 list", code, self.soc.source),
+            )),
+            Some(occupation) if soc::title_mismatch(title, &occupation.title) => warnings.push(ValidationWarning::new(
+                "lca_title",
+                format!("\"{}\" doesn't look like SOC {} \"{}\"", title.trim(), code, occupation.title),
+            )),
+            Some(_) => {}
+        }
+        Ok(code)
+    }
+}
+
+/// Turns the city and state mismatches into warnings and returns the county and MSA.
+fn address_warnings(prefix: &str, normalized: NormalizedAddress, warnings: &mut Vec<ValidationWarning>) -> CountyAndMsa {
+    for mismatch in normalized.mismatches {
+        warnings.push(ValidationWarning::new(&format!("{}{}", prefix, mismatch.field), mismatch.message));
+    }
+    (normalized.county, normalized.msa)
+}
@@ -0,0 +1,287 @@
+//! Classifies a change of the worksite (`client_*` address) made through a
+//! customer update, and the compliance task it calls for.
+//!
+//! A move within the area of intended employment, taken as the MSA (or the
+//! county outside metropolitan areas), only needs the LCA notice posted at the
+//! new worksite. A move outside it is a material change needing a new LCA and
+//! an amended petition before work starts there, unless it is a short-term
+//! placement, which is allowed for a limited number of workdays.
+//!
+//! The MSA comes from the ZIP dataset, so with only the embedded sample
+//! loaded most moves outside the big metro areas come out `Unknown`.
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use crate::address::{normalize_city, normalize_street, zip5, ZipDirectory};
+use crate::models::{CreateCustomer, NewComplianceTask, UpdateVisaDetailsRequest, WorksiteChange, WorksiteChangeKind};
+
+/// Workdays a year a short-term placement may last (20 CFR 655.735(c)); 60
+/// if the worker keeps a home near the permanent worksite.
+pub const SHORT_TERM_WORKDAYS: u32 = 30;
+
+/// The area of intended employment of a worksite.
+#[derive(Debug, PartialEq, Eq)]
+enum Area {
+    Msa(String),
+    /// County and state, for a ZIP outside any MSA.
+    County(String, String),
+}
+
+/// `None` unless the update changes the worksite's street, city, state or ZIP.
+/// `update` must already be validated, so its address is normalized.
+pub fn classify(current: &CreateCustomer, update: &UpdateVisaDetailsRequest, zips: &ZipDirectory, today: NaiveDate) -> Option<WorksiteChange> {
+    let street = update.client_street_name.as_deref().unwrap_or(&current.client_street_name);
+    let city = update.client_city.as_deref().unwrap_or(&current.client_city);
+    let state = update.client_state.as_deref().unwrap_or(&current.client_state);
+    let zip = update.client_zip.as_deref().unwrap_or(&current.client_zip);
+    let same = |a: &str, b: &str| a.trim().eq_ignore_ascii_case(b.trim());
+    let unchanged = same(&normalize_street(street), &normalize_street(&current.client_street_name))
+        && same(&normalize_city(city), &normalize_city(&current.client_city))
+        && same(state, &current.client_state)
+        && zip5(zip) == zip5(&current.client_zip);
+    if unchanged {
+        return None;
+    }
+
+    let from = area(&current.client_zip, &current.client_state, current.client_msa.as_deref(), current.client_county.as_deref(), zips);
+    let to = area(zip, state, update.client_msa.as_deref(), update.client_county.as_deref(), zips);
+    let same_area = match (&from, &to) {
+        _ if zip5(zip).is_some() && zip5(zip) == zip5(&current.client_zip) => Some(true),
+        (Some(from), Some(to)) if from == to => Some(true),
+        (Some(Area::Msa(_)), Some(_)) | (Some(_), Some(Area::Msa(_))) => Some(false),
+        // Two counties outside MSAs may still be within commuting distance.
+        _ => None,
+    };
+    let classification = match same_area {
+        Some(true) => WorksiteChangeKind::SameMsa,
+        _ if update.short_term_placement == Some(true) => WorksiteChangeKind::ShortTermPlacement,
+        Some(false) => WorksiteChangeKind::NewMsa,
+        None => WorksiteChangeKind::Unknown,
+    };
+
+    let from_area = from.map(|a| label(&a, &current.client_zip, zips));
+    let to_area = to.map(|a| label(&a, zip, zips));
+    let effective_date = update.worksite_effective_date.unwrap_or(today);
+    let (from_label, to_label) = (
+        from_area.clone().unwrap_or_else(|| format!("ZIP {}", current.client_zip)),
+        to_area.clone().unwrap_or_else(|| format!("ZIP {}", zip)),
+    );
+    let (reason, due_on) = match classification {
+        WorksiteChangeKind::SameMsa => (
+            format!(
+                "Worksite moved within {}: no new LCA or amendment is needed, but the LCA notice must be posted at the new worksite by {} and stay up for 10 business days",
+                to_label, effective_date
+            ),
+            effective_date,
+        ),
+        WorksiteChangeKind::NewMsa => (
+            format!(
+                "Worksite moved from {} to {}, outside the area of intended employment: a new LCA must be certified and an amended H-1B petition filed before work starts there on {} (Matter of Simeio Solutions)",
+                from_label, to_label, effective_date
+            ),
+            effective_date,
+        ),
+        WorksiteChangeKind::ShortTermPlacement => {
+            let due_on = add_workdays(effective_date, SHORT_TERM_WORKDAYS);
+            (
+                format!(
+                    "Short-term placement in {} from {}: allowed without a new LCA for {} workdays a year (60 if the worker keeps a home near the permanent worksite); a new LCA and amended petition are needed to stay past {}",
+                    to_label, effective_date, SHORT_TERM_WORKDAYS, due_on
+                ),
+                due_on,
+            )
+        }
+        WorksiteChangeKind::Unknown => (
+            format!(
+                "Worksite moved from {} to {}, which couldn't be compared by MSA: check whether the new worksite is within normal commuting distance; if not, a new LCA and amended petition are needed before work starts there on {}",
+                from_label, to_label, effective_date
+            ),
+            effective_date,
+        ),
+    };
+
+    Some(WorksiteChange { classification, from_area, to_area, effective_date, reason, due_on })
+}
+
+/// The compliance task recording `change`.
+pub fn task(change: &WorksiteChange, created_by: &str) -> NewComplianceTask {
+    let kind = match change.classification {
+        WorksiteChangeKind::SameMsa => "lca_posting",
+        WorksiteChangeKind::NewMsa => "lca_amendment",
+        WorksiteChangeKind::ShortTermPlacement => "short_term_placement",
+        WorksiteChangeKind::Unknown => "worksite_review",
+    };
+    NewComplianceTask {
+        kind: kind.to_string(),
+        reason: change.reason.clone(),
+        due_on: change.due_on,
+        created_by: created_by.to_string(),
+    }
+}
+
+/// From the ZIP dataset, or the MSA and county stored with the address if
+/// the dataset loaded now doesn't have the ZIP.
+fn area(zip: &str, state: &str, msa: Option<&str>, county: Option<&str>, zips: &ZipDirectory) -> Option<Area> {
+    if let Some(z) = zips.get(zip) {
+        return Some(match &z.msa {
+            Some(msa) => Area::Msa(msa.clone()),
+            None => Area::County(z.county.clone(), z.state.clone()),
+        });
+    }
+    match (msa, county) {
+        (Some(msa), _) => Some(Area::Msa(msa.to_string())),
+        (None, Some(county)) => Some(Area::County(county.to_string(), state.to_string())),
+        (None, None) => None,
+    }
+}
+
+/// The MSA's name from the ZIP dataset if it has one, else its CBSA code.
+fn label(area: &Area, zip: &str, zips: &ZipDirectory) -> String {
+    match area {
+        Area::Msa(code) => match zips.get(zip).filter(|z| z.msa.as_ref() == Some(code)).and_then(|z| z.msa_name.clone()) {
+            Some(name) => format!("{} (CBSA {})", name, code),
+            None => format!("CBSA {}", code),
+        },
+        Area::County(county, state) => format!("{}, {}", county, state),
+    }
+}
+
+/// The `workdays`th weekday counting from `start`, which counts if it is one.
+/// Holidays aren't known, so they count as workdays.
+fn add_workdays(start: NaiveDate, workdays: u32) -> NaiveDate {
+    let mut counted = 0;
+    let mut day = start;
+    loop {
+        if !matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+            counted += 1;
+            if counted == workdays {
+                return day;
+            }
+        }
+        let Some(next) = day.succ_opt() else {
+            return day;
+        };
+        day = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{customer, date};
+
+    const ZIPS: &str = "zip,city,state,county,msa,msa_name
+10001,New York,NY,New York County,35620,\"New York-Newark-Jersey City, NY-NJ-PA\"
+07102,Newark,NJ,Essex County,35620,\"New York-Newark-Jersey City, NY-NJ-PA\"
+94105,San Francisco,CA,San Francisco County,41860,\"San Francisco-Oakland-Fremont, CA\"
+59001,Absarokee,MT,Stillwater County,,
+59011,Big Timber,MT,Sweet Grass County,,
+";
+
+    fn zips() -> ZipDirectory {
+        ZipDirectory::from_csv(ZIPS.as_bytes(), "test").unwrap()
+    }
+
+    fn move_to(street: &str, city: &str, state: &str, zip: &str) -> UpdateVisaDetailsRequest {
+        UpdateVisaDetailsRequest {
+            client_street_name: Some(street.to_string()),
+            client_city: Some(city.to_string()),
+            client_state: Some(state.to_string()),
+            client_zip: Some(zip.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn classify_move(update: &UpdateVisaDetailsRequest) -> WorksiteChange {
+        classify(&customer(), update, &zips(), date("2025-03-03")).expect("the worksite changed")
+    }
+
+    #[test]
+    fn unchanged_address_is_not_a_move() {
+        let c = customer();
+        assert!(classify(&c, &UpdateVisaDetailsRequest::default(), &zips(), date("2025-03-03")).is_none());
+        let retyped = move_to("350 5th avenue", "new york", "NY", "10001-1234");
+        assert!(classify(&c, &retyped, &zips(), date("2025-03-03")).is_none());
+    }
+
+    #[test]
+    fn move_within_the_msa_needs_a_posting() {
+        let change = classify_move(&move_to("1 Gateway Ctr", "Newark", "NJ", "07102"));
+        assert_eq!(change.classification, WorksiteChangeKind::SameMsa);
+        assert_eq!(change.to_area.as_deref(), Some("New York-Newark-Jersey City, NY-NJ-PA (CBSA 35620)"));
+        assert_eq!((change.effective_date, change.due_on), (date("2025-03-03"), date("2025-03-03")));
+        assert_eq!(task(&change, "admin:ops").kind, "lca_posting");
+    }
+
+    #[test]
+    fn new_street_in_the_same_zip_is_the_same_area() {
+        let mut c = customer();
+        c.client_zip = "10099".to_string();
+        let update = move_to("500 7th Ave", "New York", "NY", "10099");
+        let change = classify(&c, &update, &zips(), date("2025-03-03")).unwrap();
+        assert_eq!(change.classification, WorksiteChangeKind::SameMsa);
+    }
+
+    #[test]
+    fn move_to_another_msa_needs_an_amendment_by_the_effective_date() {
+        let mut update = move_to("1 Market St", "San Francisco", "CA", "94105");
+        update.worksite_effective_date = Some(date("2025-06-01"));
+        let change = classify_move(&update);
+        assert_eq!(change.classification, WorksiteChangeKind::NewMsa);
+        assert_eq!(change.due_on, date("2025-06-01"));
+        assert!(change.reason.contains("amended H-1B petition"));
+        assert_eq!(task(&change, "admin:ops").kind, "lca_amendment");
+    }
+
+    #[test]
+    fn short_term_placement_is_due_after_30_workdays() {
+        let mut update = move_to("1 Market St", "San Francisco", "CA", "94105");
+        update.short_term_placement = Some(true);
+        update.worksite_effective_date = Some(date("2025-03-03"));
+        let change = classify_move(&update);
+        assert_eq!(change.classification, WorksiteChangeKind::ShortTermPlacement);
+        // Six weeks of five workdays, from a Monday to a Friday.
+        assert_eq!(change.due_on, date("2025-04-11"));
+        assert_eq!(task(&change, "admin:ops").kind, "short_term_placement");
+
+        // A weekend start doesn't count until Monday.
+        update.worksite_effective_date = Some(date("2025-03-01"));
+        assert_eq!(classify_move(&update).due_on, date("2025-04-11"));
+    }
+
+    #[test]
+    fn short_term_flag_is_ignored_within_the_msa() {
+        let mut update = move_to("1 Gateway Ctr", "Newark", "NJ", "07102");
+        update.short_term_placement = Some(true);
+        assert_eq!(classify_move(&update).classification, WorksiteChangeKind::SameMsa);
+    }
+
+    #[test]
+    fn unknown_zip_or_rural_counties_need_a_review() {
+        let change = classify_move(&move_to("1 Main St", "Cheyenne", "WY", "82001"));
+        assert_eq!(change.classification, WorksiteChangeKind::Unknown);
+        assert_eq!(change.to_area, None);
+        assert!(change.reason.contains("ZIP 82001"));
+        assert_eq!(task(&change, "admin:ops").kind, "worksite_review");
+
+        let mut c = customer();
+        (c.client_zip, c.client_state, c.client_msa, c.client_county) = ("59001".into(), "MT".into(), None, None);
+        let update = move_to("1 Mcleod St", "Big Timber", "MT", "59011");
+        let change = classify(&c, &update, &zips(), date("2025-03-03")).unwrap();
+        assert_eq!(change.classification, WorksiteChangeKind::Unknown);
+        assert_eq!(change.from_area.as_deref(), Some("Stillwater County, MT"));
+
+        // From outside any MSA into one is a move to a new area.
+        let change = classify(&c, &move_to("1 Market St", "San Francisco", "CA", "94105"), &zips(), date("2025-03-03")).unwrap();
+        assert_eq!(change.classification, WorksiteChangeKind::NewMsa);
+    }
+
+    #[test]
+    fn stored_msa_stands_in_for_a_zip_missing_from_the_dataset() {
+        let mut c = customer();
+        c.client_zip = "10199".to_string();
+        let change = classify(&c, &move_to("1 Gateway Ctr", "Newark", "NJ", "07102"), &zips(), date("2025-03-03")).unwrap();
+        assert_eq!(change.classification, WorksiteChangeKind::SameMsa);
+        assert_eq!(change.from_area.as_deref(), Some("CBSA 35620"));
+    }
+}
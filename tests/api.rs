@@ -0,0 +1,491 @@
+//! Drives the full router on the in-memory stores: routing, extractors,
+//! validation, status codes and the middleware around the handlers.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::Router;
+use lopdf::{dictionary, Document, Object, Stream};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tower::Service;
+use visa_api::address::ZipDirectory;
+use visa_api::app::build_router;
+use visa_api::config::settings::Config;
+use visa_api::i129::FieldNames;
+use visa_api::soc::SocCatalog;
+use visa_api::state::{AppState, ReferenceData, Stores};
+use visa_api::store::{
+    CircuitBreaker, MemoryCaseStatusStore, MemoryCustomerStore, MemoryIdempotencyStore, MemoryLcaDocumentStore,
+    MemoryPrevailingWageStore,
+};
+
+const TOKEN: &str = "router-test-admin-token";
+const CLIENT_KEY: &str = "router-test-client-key";
+
+fn app() -> Router {
+    app_with_breaker().0
+}
+
+/// The router and the circuit breaker it shares with the stores.
+fn app_with_breaker() -> (Router, Arc<CircuitBreaker>) {
+    let mut config = Config::default();
+    config.auth.admin_tokens.insert("ops".to_string(), TOKEN.to_string());
+    config.auth.client_tokens.insert("portal".to_string(), CLIENT_KEY.to_string());
+    let case_status = Arc::new(MemoryCaseStatusStore::new());
+    let lca_documents = Arc::new(MemoryLcaDocumentStore::new());
+    let stores = Stores {
+        customers: Arc::new(MemoryCustomerStore::new().with_history_stores(case_status.clone(), lca_documents.clone())),
+        idempotency: Arc::new(MemoryIdempotencyStore::new(Duration::from_secs(3600))),
+        case_status,
+        prevailing_wages: Arc::new(MemoryPrevailingWageStore::new()),
+        lca_documents,
+    };
+    let breaker = Arc::new(CircuitBreaker::new(&config.database.circuit_breaker));
+    let reference = ReferenceData { soc: SocCatalog::embedded(), zips: ZipDirectory::embedded(), i129_field_names: FieldNames::keys() };
+    (build_router(AppState::new(None, config, stores, breaker.clone(), None, reference)), breaker)
+}
+
+struct Call {
+    method: Method,
+    uri: String,
+    body: Option<Value>,
+    token: Option<&'static str>,
+    idempotency_key: Option<&'static str>,
+}
+
+fn get(uri: impl Into<String>) -> Call {
+    Call { method: Method::GET, uri: uri.into(), body: None, token: Some(TOKEN), idempotency_key: None }
+}
+
+fn post(uri: impl Into<String>, body: Value) -> Call {
+    Call { method: Method::POST, uri: uri.into(), body: Some(body), token: Some(TOKEN), idempotency_key: None }
+}
+
+fn put(uri: impl Into<String>, body: Value) -> Call {
+    Call { method: Method::PUT, uri: uri.into(), body: Some(body), token: Some(TOKEN), idempotency_key: None }
+}
+
+async fn send(app: &Router, call: Call) -> (StatusCode, Value) {
+    let (status, _, body) = send_with_headers(app, call).await;
+    (status, body)
+}
+
+async fn send_with_headers(app: &Router, call: Call) -> (StatusCode, HeaderMap, Value) {
+    let mut request = Request::builder().method(call.method).uri(call.uri);
+    if let Some(token) = call.token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    if let Some(key) = call.idempotency_key {
+        request = request.header("Idempotency-Key", key);
+    }
+    let request = match call.body {
+        Some(body) => request.header(header::CONTENT_TYPE, "application/json").body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    };
+    // A router is always ready, so no `poll_ready` first.
+    let response = app.clone().call(request.unwrap()).await.unwrap();
+    let (parts, body) = response.into_parts();
+    let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+    let body = if bytes.is_empty() { Value::Null } else { serde_json::from_slice(&bytes).unwrap_or(Value::Null) };
+    (parts.status, parts.headers, body)
+}
+
+/// Posts a raw body, e.g. an uploaded document, and returns the raw response.
+async fn post_bytes(app: &Router, uri: &str, body: Vec<u8>) -> (StatusCode, HeaderMap, Vec<u8>) {
+    let request = Request::post(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", TOKEN))
+        .header(header::CONTENT_TYPE, "application/pdf")
+        .body(Body::from(body))
+        .unwrap();
+    let response = app.clone().call(request).await.unwrap();
+    let (parts, body) = response.into_parts();
+    let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+    (parts.status, parts.headers, bytes.to_vec())
+}
+
+/// A PDF of `pages` blank Letter pages.
+fn blank_pdf(pages: usize) -> Vec<u8> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let kids: Vec<Object> = (0..pages)
+        .map(|_| {
+            let content = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+            doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content }).into()
+        })
+        .collect();
+    let media_box: Vec<Object> = vec![0.into(), 0.into(), 612.into(), 792.into()];
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => pages as i64, "MediaBox" => media_box }),
+    );
+    let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog);
+    let mut pdf = Vec::new();
+    doc.save_to(&mut pdf).unwrap();
+    pdf
+}
+
+fn customer(email: &str) -> Value {
+    json!({
+        "email": email,
+        "login_email": email,
+        "first_name": "Ann",
+        "last_name": "Lee",
+        "dob": "1990-04-12",
+        "sex": "FEMALE",
+        "marital_status": "SINGLE",
+        "phone": "(212) 555 0100",
+        "emergency_contact_name": "Bo Lee",
+        "emergency_contact_phone": "+1 212 555 0101",
+        "employment_start_date": "2023-10-01",
+        "street_name": "1 penn plaza",
+        "city": "nyc",
+        "state": "new york",
+        "zip": "10001",
+        "client_name": "Acme",
+        "client_street_name": "350 5th Ave",
+        "client_city": "New York",
+        "client_state": "NY",
+        "client_zip": "10001",
+        "lca_title": "Software Developers",
+        "lca_salary": "150000.00",
+        "lca_code": "15-1252",
+        "h1b_start_date": "2023-10-01",
+        "h1b_end_date": "2026-09-30",
+        "h1b_status": "Draft"
+    })
+}
+
+async fn create(app: &Router, body: Value) -> String {
+    let (status, created) = send(app, post("/h1b_customer/create", body)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", created);
+    created["customer_id"].as_str().unwrap().to_string()
+}
+
+fn transition(to_status: &str, receipt_number: Option<&str>) -> Value {
+    json!({ "to_status": to_status, "reason": "test", "effective_date": "2024-01-02", "receipt_number": receipt_number })
+}
+
+#[tokio::test]
+async fn readyz_fails_while_the_breaker_is_open() {
+    let (app, breaker) = app_with_breaker();
+    let (status, body) = send(&app, get("/readyz")).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["checks"]["store"]["status"], "up");
+    assert_eq!(body["checks"]["circuit_breaker"]["status"], "closed");
+
+    for _ in 0..Config::default().database.circuit_breaker.failure_threshold {
+        breaker.record_failure();
+    }
+    let (status, body) = send(&app, get("/readyz")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", body);
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["checks"]["circuit_breaker"]["status"], "open");
+
+    // Liveness doesn't depend on the database.
+    let (status, _) = send(&app, get("/livez")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn create_normalizes_and_reads_back() {
+    let app = app();
+    let id = create(&app, customer("ann.lee@example.com")).await;
+
+    let (status, c) = send(&app, get(format!("/customers/{}", id))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(c["phone"], "+12125550100");
+    assert_eq!(c["phone_display"], "(212) 555-0100");
+    assert_eq!((c["street_name"].as_str(), c["city"].as_str(), c["state"].as_str()), (Some("1 Penn Plz"), Some("New York"), Some("NY")));
+    assert_eq!(c["msa"], "35620");
+    assert_eq!(c["receipt_number"], Value::Null);
+
+    let (status, _) = send(&app, get(format!("/customers/{}", uuid::Uuid::new_v4()))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn invalid_fields_are_rejected() {
+    let app = app();
+    let mut bad_phone = customer("ann.lee@example.com");
+    bad_phone["phone"] = json!("555-1234");
+    assert_eq!(send(&app, post("/h1b_customer/create", bad_phone)).await.0, StatusCode::BAD_REQUEST);
+
+    let mut bad_zip = customer("ann.lee@example.com");
+    bad_zip["zip"] = json!("1001");
+    assert_eq!(send(&app, post("/h1b_customer/create", bad_zip)).await.0, StatusCode::BAD_REQUEST);
+
+    let mut bad_receipt = customer("ann.lee@example.com");
+    bad_receipt["receipt_number"] = json!("XYZ123");
+    assert_eq!(send(&app, post("/h1b_customer/create", bad_receipt)).await.0, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn new_customers_start_as_draft() {
+    let app = app();
+    let mut approved = customer("ann.lee@example.com");
+    approved["h1b_status"] = json!("Approved");
+    assert_eq!(send(&app, post("/h1b_customer/create", approved)).await.0, StatusCode::BAD_REQUEST);
+
+    let mut unset = customer("ann.lee@example.com");
+    unset.as_object_mut().unwrap().remove("h1b_status");
+    let id = create(&app, unset).await;
+    let (_, c) = send(&app, get(format!("/customers/{}", id))).await;
+    assert_eq!(c["h1b_status"], "Draft");
+}
+
+#[tokio::test]
+async fn filing_requires_a_receipt_number() {
+    let app = app();
+    let id = create(&app, customer("ann.lee@example.com")).await;
+    let uri = format!("/customers/{}/transitions", id);
+    let update = format!("/update_customer_by_id/{}", id);
+
+    // A Draft customer has no receipt yet, whether on create or update.
+    let mut with_receipt = customer("bo.lee@example.com");
+    with_receipt["receipt_number"] = json!("EAC2412345670");
+    let (status, _) = send(&app, post("/h1b_customer/create", with_receipt)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(&app, put(&update, json!({ "receipt_number": "EAC2412345670" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(&app, post(&uri, transition("Filed", None))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(&app, post(&uri, transition("Approved", None))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(&app, post(&uri, transition("Filed", Some("eac-24-123-45678")))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["customer"]["receipt_number"], "EAC2412345678");
+    assert_eq!(body["transition"]["from_status"], "Draft");
+
+    let (status, found) = send(&app, get("/customers/by_receipt/eac-24-123-45678")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["customer_id"], id.as_str());
+
+    // A receipt only goes with the move to Filed.
+    let (status, _) = send(&app, post(&uri, transition("Approved", Some("EAC2412345679")))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, transitions) = send(&app, get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(transitions["h1b_status"], "Filed");
+    assert_eq!(transitions["history"].as_array().map(Vec::len), Some(1));
+
+    // Once filed, a mistyped receipt can be corrected.
+    let (status, body) = send(&app, put(&update, json!({ "receipt_number": "EAC2412345679" }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (_, c) = send(&app, get(format!("/customers/{}", id))).await;
+    assert_eq!(c["receipt_number"], "EAC2412345679");
+}
+
+#[tokio::test]
+async fn upsert_creates_then_updates_by_email() {
+    let app = app();
+    let upsert = "/h1b_customer/create?upsert=true";
+
+    let (status, headers, created) = send_with_headers(&app, post(upsert, customer("ann.lee@example.com"))).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", created);
+    let id = created["customer_id"].as_str().unwrap().to_string();
+    assert_eq!(headers[header::LOCATION], format!("/customers/{}", id));
+
+    // Same email in another case: the same customer, with its details replaced.
+    let mut changed = customer("Ann.Lee@Example.com");
+    changed["login_email"] = json!("ann.lee@example.com");
+    changed["city"] = json!("Brooklyn");
+    let (status, headers, updated) = send_with_headers(&app, post(upsert, changed)).await;
+    assert_eq!(status, StatusCode::OK, "{}", updated);
+    assert_eq!(updated["customer_id"], id.as_str());
+    assert_eq!(updated["city"], "Brooklyn");
+    assert_eq!(headers[header::LOCATION], format!("/customers/{}", id));
+    let (_, all) = send(&app, get("/h1b_customer/all")).await;
+    assert_eq!(all.as_array().map(Vec::len), Some(1));
+
+    // The email matches one customer and the login_email another.
+    create(&app, customer("bo.lee@example.com")).await;
+    let mut ambiguous = customer("ann.lee@example.com");
+    ambiguous["login_email"] = json!("bo.lee@example.com");
+    let (status, _) = send(&app, post(upsert, ambiguous)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, c) = send(&app, get(format!("/customers/{}", id))).await;
+    assert_eq!(c["login_email"], "ann.lee@example.com");
+}
+
+#[tokio::test]
+async fn soft_deleted_customers_are_hidden_until_restored() {
+    let app = app();
+    let patch = |uri: String, body| Call { method: Method::PATCH, uri, body, token: Some(TOKEN), idempotency_key: None };
+    let kept = create(&app, customer("ann.lee@example.com")).await;
+    let deleted = create(&app, customer("bo.lee@example.com")).await;
+
+    let (_, outcome) = send(&app, patch(format!("/customers/{}/restore", kept), None)).await;
+    assert_eq!(outcome["message"], "This customer is not deleted.");
+
+    let reason = json!({ "reason": "entered twice" });
+    let (status, outcome) = send(&app, patch(format!("/soft_delete_customer_via_id/{}", deleted), Some(reason))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(outcome["message"], "Customer soft deleted successfully");
+
+    let (_, all) = send(&app, get("/h1b_customer/all")).await;
+    let ids: Vec<&str> = all.as_array().unwrap().iter().filter_map(|c| c["customer_id"].as_str()).collect();
+    assert_eq!(ids, [kept.as_str()]);
+    let (status, _) = send(&app, get(format!("/customers/{}", deleted))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Only admins may look past a deletion.
+    let with_deleted = format!("/customers/{}?include_deleted=true", deleted);
+    let (status, _) = send(&app, Call { token: Some(CLIENT_KEY), ..get(&with_deleted) }).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, Call { token: None, ..get("/h1b_customer/all?include_deleted=true") }).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, c) = send(&app, get(&with_deleted)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(c["customer_id"], deleted.as_str());
+    let (_, all) = send(&app, get("/h1b_customer/all?include_deleted=true")).await;
+    assert_eq!(all.as_array().map(Vec::len), Some(2));
+
+    let (_, outcome) = send(&app, patch(format!("/customers/{}/restore", deleted), None)).await;
+    assert_eq!(outcome["message"], "Customer restored successfully");
+    let (_, all) = send(&app, get("/h1b_customer/all")).await;
+    assert_eq!(all.as_array().map(Vec::len), Some(2));
+}
+
+#[tokio::test]
+async fn idempotency_key_replays_per_identified_caller() {
+    let app = app();
+    let call = |token, email| Call {
+        method: Method::POST,
+        uri: "/h1b_customer/create".to_string(),
+        body: Some(customer(email)),
+        token,
+        idempotency_key: Some("create-ann"),
+    };
+
+    let (status, _) = send(&app, call(None, "ann.lee@example.com")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A client retrying after a timeout gets the first response back.
+    let (first_status, first) = send(&app, call(Some(CLIENT_KEY), "ann.lee@example.com")).await;
+    assert_eq!(first_status, StatusCode::CREATED);
+    let (replay_status, replay) = send(&app, call(Some(CLIENT_KEY), "ann.lee@example.com")).await;
+    assert_eq!(replay_status, StatusCode::CREATED);
+    assert_eq!(replay["customer_id"], first["customer_id"]);
+
+    let (status, _) = send(&app, call(Some(CLIENT_KEY), "bo.lee@example.com")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Another caller's key of the same name is a different key.
+    let (status, _) = send(&app, call(Some(TOKEN), "bo.lee@example.com")).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (_, all) = send(&app, get("/h1b_customer/all")).await;
+    assert_eq!(all.as_array().map(Vec::len), Some(2));
+}
+
+#[tokio::test]
+async fn duplicates_are_found_and_merged() {
+    let app = app();
+    let kept = create(&app, customer("ann.lee@example.com")).await;
+    let duplicate = create(&app, customer("Ann.Lee+h1b@example.com")).await;
+    let (status, _) = send(&app, post(format!("/customers/{}/petitions", duplicate), json!({
+        "employer": "Old Co", "valid_from": "2019-01-01", "valid_to": "2021-12-31"
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(&app, post(format!("/customers/{}/transitions", duplicate), transition("Filed", Some("EAC2412345678")))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, pairs) = send(&app, get("/customers/duplicates")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(pairs.as_array().map(Vec::len), Some(1));
+
+    let merge = json!({ "duplicate_id": duplicate, "reason": "entered twice" });
+    let mut anonymous = post(format!("/customers/{}/merge", kept), merge.clone());
+    anonymous.token = None;
+    assert_eq!(send(&app, anonymous).await.0, StatusCode::FORBIDDEN);
+    let (status, merged) = send(&app, post(format!("/customers/{}/merge", kept), merge)).await;
+    assert_eq!(status, StatusCode::OK, "{}", merged);
+    assert_eq!((merged["transitions_moved"].as_u64(), merged["petitions_moved"].as_u64()), (Some(1), Some(1)));
+    assert_eq!(merged["public_access_files_moved"], 0);
+
+    assert_eq!(send(&app, get(format!("/customers/{}", duplicate))).await.0, StatusCode::NOT_FOUND);
+    let (_, petitions) = send(&app, get(format!("/customers/{}/petitions", kept))).await;
+    assert_eq!(petitions.as_array().map(Vec::len), Some(1));
+
+    // The duplicate's history stays its own: the kept customer is still Draft.
+    let (_, transitions) = send(&app, get(format!("/customers/{}/transitions", kept))).await;
+    assert_eq!(transitions["h1b_status"], "Draft");
+    assert_eq!(transitions["history"][0]["merged_from"], duplicate.as_str());
+}
+
+#[tokio::test]
+async fn max_stay_report_matches_each_customer() {
+    let app = app();
+    let mut ids = Vec::new();
+    for (email, valid_to) in [("a@example.com", "2021-12-31"), ("b@example.com", "2019-06-30")] {
+        let mut body = customer(email);
+        body["first_name"] = json!(email);
+        let id = create(&app, body).await;
+        let (status, _) = send(&app, post(format!("/customers/{}/petitions", id), json!({
+            "valid_from": "2019-01-01", "valid_to": valid_to
+        })))
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(&app, post(format!("/customers/{}/trips", id), json!({
+            "departed_on": "2019-03-01", "returned_on": "2019-03-11"
+        })))
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        ids.push(id);
+    }
+
+    let (status, report) = send(&app, get("/compliance/max_stay")).await;
+    assert_eq!(status, StatusCode::OK);
+    let rows = report["customers"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    for id in ids {
+        let (_, single) = send(&app, get(format!("/customers/{}/max_stay", id))).await;
+        let row = rows.iter().find(|row| row["customer_id"] == id.as_str()).unwrap();
+        assert_eq!(row["days_used"], single["days_used"]);
+        assert_eq!(row["recapturable_days"], 9);
+    }
+}
+
+#[tokio::test]
+async fn public_access_file_includes_uploads_and_is_recorded() {
+    let app = app();
+    let id = create(&app, customer("ann.lee@example.com")).await;
+    let generate = format!("/customers/{}/public_access_file", id);
+
+    let (status, _, without_uploads) = post_bytes(&app, &generate, Vec::new()).await;
+    assert_eq!(status, StatusCode::OK);
+    let base_pages = visa_api::pdf::pdf_page_count(&without_uploads).unwrap();
+
+    let mut document_ids = Vec::new();
+    for (category, pages) in [("certified_lca", 1), ("notice_posting", 2)] {
+        let uri = format!("/customers/{}/lca_documents?category={}&filename={}.pdf", id, category, category);
+        let (status, _, body) = post_bytes(&app, &uri, blank_pdf(pages)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let document: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(document["page_count"], pages);
+        document_ids.push(document["document_id"].clone());
+    }
+
+    let (status, headers, pdf) = post_bytes(&app, &generate, Vec::new()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/pdf");
+    let page_count = visa_api::pdf::pdf_page_count(&pdf).unwrap();
+    assert_eq!(page_count, base_pages + 3);
+
+    let (status, generations) = send(&app, get(format!("/customers/{}/public_access_files", id))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(generations.as_array().map(Vec::len), Some(2));
+    let latest = &generations[0];
+    assert_eq!(latest["file_id"], headers["x-public-access-file-id"].to_str().unwrap());
+    assert_eq!(latest["page_count"], page_count);
+    assert_eq!(latest["document_ids"], Value::Array(document_ids));
+    assert_eq!(latest["sha256"], hex::encode(Sha256::digest(&pdf)));
+    assert_eq!(latest["lca_code"], "15-1252");
+}